@@ -0,0 +1,52 @@
+//! Local file hashing: memory-mapped and chunked against reading the whole
+//! file first. Run with `cargo bench --bench hash`.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use baul_lib::util::{hash_file, hash_reader, MMAP_THRESHOLD};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use md5::{Digest, Md5};
+
+fn sample_file(size: u64) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("baul-hash-bench-{}", size));
+    if fs::metadata(&path).is_ok_and(|m| m.len() == size) {
+        return path;
+    }
+
+    let mut file = File::create(&path).expect("create sample file");
+    let block: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let mut written = 0;
+    while written < size {
+        let len = block.len().min((size - written) as usize);
+        file.write_all(&block[..len]).expect("write sample file");
+        written += len as u64;
+    }
+    path
+}
+
+fn bench_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("md5_file");
+    group.sample_size(10);
+
+    for size in [1024 * 1024, MMAP_THRESHOLD, 256 * 1024 * 1024] {
+        let path = sample_file(size);
+        group.throughput(Throughput::Bytes(size));
+
+        group.bench_with_input(BenchmarkId::new("hash_file", size), &path, |b, path| {
+            b.iter(|| hash_file::<Md5>(path).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("chunked", size), &path, |b, path| {
+            b.iter(|| hash_reader::<Md5>(File::open(path).unwrap()).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("read_all", size), &path, |b, path| {
+            b.iter(|| format!("{:x}", Md5::digest(fs::read(path).unwrap())))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_hash);
+criterion_main!(benches);
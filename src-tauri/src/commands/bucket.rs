@@ -1,30 +1,49 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use chrono::Utc;
 use log::{debug, error, info, warn};
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
+use tokio::fs;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{BucketInfo, BucketStats};
-use crate::services::S3Service;
+use crate::models::{
+    AccessControlList, BatchResult, Bookmark, BucketAuditReport, BucketEncryptionConfig,
+    BucketInfo, BucketStats, CannedAcl, FeatureResult, IntelligentTieringConfig, InventorySnapshot,
+    KeyTemplate, NoncurrentPrefix, NoncurrentVersionReport, ObjectVersion, ProviderCapabilities,
+    ProviderFeature, PublicScanSummary, S3ConnectionWithSecret, S3Provider, ScopedCredentials,
+    ScopedCredentialsOptions,
+};
+use crate::services::{
+    AuditService, BookmarkService, CdnService, ConfigService, InventoryService, KeyTemplates,
+    LocalService, S3Service, StsService,
+};
 use crate::state::AppState;
 
 #[tauri::command]
 pub async fn list_buckets(
     state: State<'_, AppState>,
     connection_id: String,
+    force_refresh: Option<bool>,
 ) -> AppResult<Vec<BucketInfo>> {
     debug!("Listing buckets for connection: {}", connection_id);
 
-    let connections = state.connections.lock().await;
+    if !force_refresh.unwrap_or(false) {
+        if let Some(buckets) = state.cached_buckets(&connection_id).await {
+            debug!("Using cached bucket list ({} buckets)", buckets.len());
+            return Ok(buckets);
+        }
+    }
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| {
-            warn!("Connection not found when listing buckets: {}", connection_id);
-            AppError::ConnectionNotFound(connection_id)
-        })?;
+    let connection = state
+        .connection(&connection_id)
+        .await
+        .inspect_err(|_| warn!("Connection not found when listing buckets: {}", connection_id))?;
 
-    match S3Service::list_buckets(connection).await {
+    match S3Service::list_buckets(&connection).await {
         Ok(buckets) => {
             info!("Found {} buckets", buckets.len());
+            state.cache_buckets(&connection_id, buckets.clone()).await;
             Ok(buckets)
         }
         Err(e) => {
@@ -47,20 +66,14 @@ pub async fn create_bucket(
         region.as_deref().unwrap_or("default")
     );
 
-    let connections = state.connections.lock().await;
-
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| {
-            warn!("Connection not found when creating bucket: {}", connection_id);
-            AppError::ConnectionNotFound(connection_id)
-        })?
-        .clone();
-
-    drop(connections);
+    let connection = state
+        .connection(&connection_id)
+        .await
+        .inspect_err(|_| warn!("Connection not found when creating bucket: {}", connection_id))?;
 
     match S3Service::create_bucket(&connection, &bucket_name, region.as_deref()).await {
         Ok(()) => {
+            state.invalidate_buckets(&connection.id).await;
             info!("Successfully created bucket '{}'", bucket_name);
             Ok(())
         }
@@ -79,20 +92,15 @@ pub async fn delete_bucket(
 ) -> AppResult<()> {
     warn!("Deleting bucket '{}'", bucket_name);
 
-    let connections = state.connections.lock().await;
-
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| {
-            warn!("Connection not found when deleting bucket: {}", connection_id);
-            AppError::ConnectionNotFound(connection_id)
-        })?
-        .clone();
-
-    drop(connections);
+    let connection = state
+        .connection(&connection_id)
+        .await
+        .inspect_err(|_| warn!("Connection not found when deleting bucket: {}", connection_id))?;
 
     match S3Service::delete_bucket(&connection, &bucket_name).await {
         Ok(()) => {
+            state.forget_bucket_region(&connection.id, &bucket_name).await;
+            state.invalidate_buckets(&connection.id).await;
             info!("Successfully deleted bucket '{}'", bucket_name);
             Ok(())
         }
@@ -108,19 +116,24 @@ pub async fn get_bucket_location(
     state: State<'_, AppState>,
     connection_id: String,
     bucket_name: String,
-) -> AppResult<Option<String>> {
+) -> AppResult<FeatureResult<Option<String>>> {
     debug!("Getting location for bucket '{}'", bucket_name);
 
-    let connections = state.connections.lock().await;
-
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
+    let connection = state.connection(&connection_id).await?;
 
-    drop(connections);
+    if !connection.provider.supports(ProviderFeature::BucketLocation) {
+        debug!("{:?} does not support GetBucketLocation", connection.provider);
+        return Ok(FeatureResult::NotSupported);
+    }
 
-    S3Service::get_bucket_location(&connection, &bucket_name).await
+    match S3Service::get_bucket_location(&connection, &bucket_name).await {
+        Ok(location) => Ok(FeatureResult::Supported(location)),
+        Err(AppError::NotSupported(msg)) => {
+            debug!("GetBucketLocation not supported for '{}': {}", bucket_name, msg);
+            Ok(FeatureResult::NotSupported)
+        }
+        Err(e) => Err(e),
+    }
 }
 
 #[tauri::command]
@@ -131,14 +144,7 @@ pub async fn head_bucket(
 ) -> AppResult<bool> {
     debug!("Checking if bucket '{}' exists", bucket_name);
 
-    let connections = state.connections.lock().await;
-
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
-
-    drop(connections);
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
 
     S3Service::head_bucket(&connection, &bucket_name).await
 }
@@ -148,19 +154,24 @@ pub async fn get_bucket_versioning(
     state: State<'_, AppState>,
     connection_id: String,
     bucket_name: String,
-) -> AppResult<Option<String>> {
+) -> AppResult<FeatureResult<Option<String>>> {
     debug!("Getting versioning status for bucket '{}'", bucket_name);
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
-
-    drop(connections);
+    if !connection.provider.supports(ProviderFeature::BucketVersioning) {
+        debug!("{:?} does not support bucket versioning", connection.provider);
+        return Ok(FeatureResult::NotSupported);
+    }
 
-    S3Service::get_bucket_versioning(&connection, &bucket_name).await
+    match S3Service::get_bucket_versioning(&connection, &bucket_name).await {
+        Ok(status) => Ok(FeatureResult::Supported(status)),
+        Err(AppError::NotSupported(msg)) => {
+            debug!("GetBucketVersioning not supported for '{}': {}", bucket_name, msg);
+            Ok(FeatureResult::NotSupported)
+        }
+        Err(e) => Err(e),
+    }
 }
 
 #[tauri::command]
@@ -171,20 +182,21 @@ pub async fn get_bucket_stats(
 ) -> AppResult<BucketStats> {
     debug!("Calculating stats for bucket '{}'", bucket_name);
 
-    let connections = state.connections.lock().await;
-
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+    let limits = state.settings.lock().await.scan_limits;
 
-    drop(connections);
-
-    match S3Service::get_bucket_stats(&connection, &bucket_name).await {
+    match S3Service::get_bucket_stats(&connection, &bucket_name, limits).await {
         Ok(stats) => {
             info!(
-                "Bucket '{}' stats: {} objects, {} bytes",
-                bucket_name, stats.object_count, stats.total_size
+                "Bucket '{}' stats: {} objects, {} bytes{}",
+                bucket_name,
+                stats.object_count,
+                stats.total_size,
+                if stats.stopped_early.is_some() {
+                    " (partial)"
+                } else {
+                    ""
+                }
             );
             Ok(stats)
         }
@@ -194,3 +206,749 @@ pub async fn get_bucket_stats(
         }
     }
 }
+
+/// Probe (once per bucket) which optional S3 features the backend supports.
+#[tauri::command]
+pub async fn get_provider_capabilities(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    force_refresh: Option<bool>,
+) -> AppResult<ProviderCapabilities> {
+    let cache_key = (connection_id.clone(), bucket_name.clone());
+
+    if !force_refresh.unwrap_or(false) {
+        if let Some(capabilities) = state.capabilities.lock().await.get(&cache_key) {
+            return Ok(capabilities.clone());
+        }
+    }
+
+    debug!("Probing capabilities for bucket '{}'", bucket_name);
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+
+    match S3Service::probe_capabilities(&connection, &bucket_name).await {
+        Ok(capabilities) => {
+            info!(
+                "Capabilities for '{}': {:?}",
+                bucket_name, capabilities
+            );
+            if capabilities.is_complete() {
+                state
+                    .capabilities
+                    .lock()
+                    .await
+                    .insert(cache_key, capabilities.clone());
+            }
+            Ok(capabilities)
+        }
+        Err(e) => {
+            error!("Failed to probe capabilities for '{}': {}", bucket_name, e);
+            Err(e)
+        }
+    }
+}
+
+/// Associate a CloudFront distribution with a bucket, or clear it with `None`.
+#[tauri::command]
+pub async fn set_bucket_distribution(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    distribution_id: Option<String>,
+) -> AppResult<()> {
+    let entry = state.connection_entry(&connection_id).await?;
+    let mut connection = entry.write().await;
+
+    match distribution_id.filter(|id| !id.is_empty()) {
+        Some(id) => {
+            info!("Associating bucket '{}' with distribution {}", bucket_name, id);
+            connection.cdn_distributions.insert(bucket_name, id);
+        }
+        None => {
+            info!("Clearing distribution for bucket '{}'", bucket_name);
+            connection.cdn_distributions.remove(&bucket_name);
+        }
+    }
+
+    let updated = connection.clone();
+    drop(connection);
+
+    ConfigService::save_connection(&updated)
+}
+
+/// Set or clear (`template: None`) the key template for uploads into
+/// `prefix` of `bucket`.
+#[tauri::command]
+pub async fn set_key_template(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    prefix: String,
+    template: Option<String>,
+) -> AppResult<Vec<KeyTemplate>> {
+    let template = template.filter(|t| !t.trim().is_empty());
+    if let Some(template) = &template {
+        KeyTemplates::validate(template)?;
+    }
+
+    let entry = state.connection_entry(&connection_id).await?;
+    let mut connection = entry.write().await;
+
+    let existing = KeyTemplates::find(&connection.key_templates, &bucket_name, &prefix).cloned();
+    connection
+        .key_templates
+        .retain(|t| Some(t) != existing.as_ref());
+
+    match template {
+        Some(template) => {
+            info!(
+                "Setting key template for '{}/{}' to '{}'",
+                bucket_name, prefix, template
+            );
+            connection.key_templates.push(KeyTemplate {
+                bucket: bucket_name,
+                prefix,
+                template,
+            });
+        }
+        None => info!("Clearing key template for '{}/{}'", bucket_name, prefix),
+    }
+
+    let updated = connection.clone();
+    drop(connection);
+
+    ConfigService::save_connection(&updated)?;
+    Ok(updated.key_templates)
+}
+
+#[tauri::command]
+pub async fn invalidate_paths(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    paths: Vec<String>,
+) -> AppResult<String> {
+    let connection = state.connection(&connection_id).await?;
+
+    if connection.provider != S3Provider::Aws {
+        return Err(AppError::NotSupported(
+            "CDN invalidation is only available for AWS connections".into(),
+        ));
+    }
+
+    let distribution_id = connection
+        .cdn_distributions
+        .get(&bucket_name)
+        .cloned()
+        .ok_or_else(|| {
+            AppError::NotFound(format!("no distribution configured for '{}'", bucket_name))
+        })?;
+
+    info!(
+        "Invalidating {} paths for bucket '{}' on distribution {}",
+        paths.len(),
+        bucket_name,
+        distribution_id
+    );
+
+    match CdnService::invalidate_paths(&connection, &distribution_id, paths).await {
+        Ok(invalidation_id) => {
+            info!("Created invalidation {}", invalidation_id);
+            Ok(invalidation_id)
+        }
+        Err(e) => {
+            error!("Failed to invalidate distribution {}: {}", distribution_id, e);
+            Err(e)
+        }
+    }
+}
+
+/// Gather the bucket's security settings into one report, optionally
+/// writing it to `output_path` as JSON.
+#[tauri::command]
+pub async fn audit_bucket(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    output_path: Option<String>,
+) -> AppResult<BucketAuditReport> {
+    info!("Auditing bucket '{}'", bucket_name);
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+
+    let report = match AuditService::audit_bucket(&connection, &bucket_name).await {
+        Ok(report) => report,
+        Err(e) => {
+            error!("Failed to audit bucket '{}': {}", bucket_name, e);
+            return Err(e);
+        }
+    };
+
+    if let Some(path) = output_path {
+        let path = PathBuf::from(path);
+        let json = serde_json::to_string_pretty(&report)?;
+        fs::write(&path, json)
+            .await
+            .map_err(|e| LocalService::destination_error(&path, e))?;
+        debug!("Wrote audit report to {:?}", path);
+    }
+
+    info!(
+        "Audit of '{}': public={}, encrypted={}, {} findings",
+        bucket_name,
+        report.is_public,
+        report.is_encrypted,
+        report.findings.len()
+    );
+    Ok(report)
+}
+
+/// Look for objects anyone can read under `prefix`. Each hit is emitted as a
+/// `public-object` event while the scan runs; the summary comes back at the end.
+#[tauri::command]
+pub async fn scan_public_objects(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    prefix: Option<String>,
+    sample_size: Option<usize>,
+) -> AppResult<PublicScanSummary> {
+    let prefix = prefix.unwrap_or_default();
+    info!("Scanning '{}/{}' for public objects", bucket_name, prefix);
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+    let mut budget = state.settings.lock().await.scan_limits.start();
+
+    let result = AuditService::scan_public_objects(
+        &connection,
+        &bucket_name,
+        &prefix,
+        sample_size.unwrap_or(1_000),
+        &mut budget,
+        |object| {
+            let _ = app.emit("public-object", object);
+        },
+    )
+    .await;
+
+    match result {
+        Ok(summary) => {
+            info!(
+                "Public scan of '{}/{}': {} of {} objects public ({} ACLs sampled){}",
+                bucket_name,
+                prefix,
+                summary.public_count,
+                summary.scanned_count,
+                summary.sampled_count,
+                if summary.stopped_early.is_some() {
+                    ", stopped at the scan limit"
+                } else {
+                    ""
+                }
+            );
+            Ok(summary)
+        }
+        Err(e) => {
+            error!("Public scan of '{}/{}' failed: {}", bucket_name, prefix, e);
+            Err(e)
+        }
+    }
+}
+
+/// Bytes held by noncurrent versions and delete markers under `prefix`,
+/// grouped by the first `depth` path segments below it. With `purge`, the
+/// noncurrent versions are deleted, then the delete markers of keys left
+/// with no versions at all; `dry_run` only lists them.
+#[tauri::command]
+pub async fn report_noncurrent_versions(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    prefix: Option<String>,
+    depth: Option<usize>,
+    purge: Option<bool>,
+    dry_run: Option<bool>,
+) -> AppResult<NoncurrentVersionReport> {
+    let prefix = prefix.unwrap_or_default();
+    let depth = depth.unwrap_or(1).max(1);
+    let purge = purge.unwrap_or(false);
+    let dry_run = dry_run.unwrap_or(false);
+    info!(
+        "Reporting noncurrent versions in '{}/{}' (purge: {}, dry run: {})",
+        bucket_name, prefix, purge, dry_run
+    );
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    let versions = S3Service::list_versions(&connection, &bucket_name, &prefix).await?;
+
+    let group_of = |key: &str| {
+        let rest = key.strip_prefix(prefix.as_str()).unwrap_or(key);
+        let segments: Vec<&str> = rest.split('/').collect();
+        if segments.len() <= depth {
+            // Objects directly at this level group under the prefix itself
+            prefix.clone()
+        } else {
+            format!("{}{}/", prefix, segments[..depth].join("/"))
+        }
+    };
+
+    let mut groups: HashMap<String, NoncurrentPrefix> = HashMap::new();
+    for version in &versions {
+        if version.is_latest && !version.is_delete_marker {
+            continue;
+        }
+        let group = group_of(&version.key);
+        let stats = groups
+            .entry(group.clone())
+            .or_insert_with(|| NoncurrentPrefix {
+                prefix: group,
+                ..Default::default()
+            });
+        if version.is_delete_marker {
+            stats.delete_marker_count += 1;
+        } else {
+            stats.noncurrent_count += 1;
+            stats.noncurrent_bytes += version.size;
+        }
+    }
+
+    let mut prefixes: Vec<NoncurrentPrefix> = groups.into_values().collect();
+    prefixes.sort_by_key(|p| std::cmp::Reverse(p.noncurrent_bytes));
+
+    let purged = if purge {
+        Some(purge_noncurrent_versions(&connection, &bucket_name, &versions, dry_run).await)
+    } else {
+        None
+    };
+
+    let report = NoncurrentVersionReport {
+        bucket: bucket_name,
+        prefix,
+        noncurrent_count: prefixes.iter().map(|p| p.noncurrent_count).sum(),
+        noncurrent_bytes: prefixes.iter().map(|p| p.noncurrent_bytes).sum(),
+        delete_marker_count: prefixes.iter().map(|p| p.delete_marker_count).sum(),
+        prefixes,
+        purged,
+        dry_run,
+    };
+
+    info!(
+        "'{}/{}' holds {} noncurrent versions ({} bytes) and {} delete markers",
+        report.bucket,
+        report.prefix,
+        report.noncurrent_count,
+        report.noncurrent_bytes,
+        report.delete_marker_count
+    );
+    Ok(report)
+}
+
+/// Delete the noncurrent versions in `versions` in batches, then the delete
+/// markers of keys left with nothing else. With `dry_run` nothing is
+/// deleted and the result lists what would be.
+async fn purge_noncurrent_versions(
+    connection: &S3ConnectionWithSecret,
+    bucket: &str,
+    versions: &[ObjectVersion],
+    dry_run: bool,
+) -> BatchResult {
+    let id = |v: &ObjectVersion| {
+        (
+            v.key.clone(),
+            v.version_id.clone().unwrap_or_else(|| "null".to_string()),
+        )
+    };
+    let label = |(key, version_id): &(String, String)| format!("{}?versionId={}", key, version_id);
+
+    let noncurrent: Vec<(String, String)> = versions
+        .iter()
+        .filter(|v| !v.is_latest && !v.is_delete_marker)
+        .map(id)
+        .collect();
+    let markers = versions.iter().filter(|v| v.is_delete_marker);
+
+    if dry_run {
+        let mut result = BatchResult::default();
+        for version in &noncurrent {
+            result.succeed(label(version));
+        }
+        for marker in markers {
+            result.succeed(label(&id(marker)));
+        }
+        info!(
+            "Would purge {} old versions and delete markers from '{}'",
+            result.succeeded.len(),
+            bucket
+        );
+        return result;
+    }
+
+    let mut result = S3Service::delete_versions(connection, bucket, &noncurrent).await;
+
+    // Keys whose old versions couldn't all be deleted keep their current
+    // delete marker, since removing it would resurrect an old version
+    let failed_keys: HashSet<String> = {
+        let failed: HashSet<&str> = result.failed.iter().map(|f| f.key.as_str()).collect();
+        noncurrent
+            .iter()
+            .filter(|version| failed.contains(label(version).as_str()))
+            .map(|(key, _)| key.clone())
+            .collect()
+    };
+
+    let mut removable = Vec::new();
+    for marker in markers {
+        if marker.is_latest && failed_keys.contains(&marker.key) {
+            result.skip(label(&id(marker)));
+        } else {
+            removable.push(id(marker));
+        }
+    }
+    result.merge(S3Service::delete_versions(connection, bucket, &removable).await);
+
+    for failure in &result.failed {
+        warn!("Failed to delete {}: {}", failure.key, failure.message);
+    }
+    info!(
+        "Purged {} old versions and delete markers from '{}', {} failed",
+        result.succeeded.len(),
+        bucket,
+        result.failed.len()
+    );
+    result
+}
+
+#[tauri::command]
+pub async fn get_intelligent_tiering_configs(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<Vec<IntelligentTieringConfig>> {
+    debug!("Getting Intelligent-Tiering configuration for '{}'", bucket_name);
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+
+    if !connection.provider.supports(ProviderFeature::IntelligentTiering) {
+        debug!("{:?} does not support Intelligent-Tiering", connection.provider);
+        return Err(AppError::NotSupported(format!(
+            "Intelligent-Tiering is not available on {:?}",
+            connection.provider
+        )));
+    }
+
+    S3Service::list_intelligent_tiering_configs(&connection, &bucket_name).await
+}
+
+/// Create or replace the Intelligent-Tiering configuration with `config.id`.
+#[tauri::command]
+pub async fn put_intelligent_tiering_config(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    config: IntelligentTieringConfig,
+) -> AppResult<()> {
+    info!(
+        "Saving Intelligent-Tiering configuration '{}' for '{}'",
+        config.id, bucket_name
+    );
+
+    if config.tierings.is_empty() {
+        return Err(AppError::ConfigError(
+            "An Intelligent-Tiering configuration needs at least one tier".into(),
+        ));
+    }
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+
+    S3Service::put_intelligent_tiering_config(&connection, &bucket_name, &config)
+        .await
+        .inspect_err(|e| error!("Failed to save tiering configuration: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_intelligent_tiering_config(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    id: String,
+) -> AppResult<()> {
+    info!(
+        "Deleting Intelligent-Tiering configuration '{}' from '{}'",
+        id, bucket_name
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+
+    S3Service::delete_intelligent_tiering_config(&connection, &bucket_name, &id).await
+}
+
+/// The newest S3 Inventory report for the bucket, which deploys can plan
+/// from instead of listing. `None` when no CSV inventory has been delivered.
+#[tauri::command]
+pub async fn get_inventory_snapshot(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<Option<InventorySnapshot>> {
+    debug!("Looking for an inventory report for '{}'", bucket_name);
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+    let client = S3Service::create_s3_client(&connection).await;
+
+    InventoryService::latest_snapshot(&client, &connection, &bucket_name).await
+}
+
+/// Short-lived AWS credentials confined to a prefix of the bucket, for
+/// handing to a colleague or CI job instead of the connection's own keys.
+#[tauri::command]
+pub async fn generate_scoped_credentials(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    prefix: Option<String>,
+    options: Option<ScopedCredentialsOptions>,
+) -> AppResult<ScopedCredentials> {
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    if connection.provider != S3Provider::Aws {
+        return Err(AppError::NotSupported(
+            "Scoped credentials are only available for AWS connections".into(),
+        ));
+    }
+
+    let prefix = prefix.unwrap_or_default();
+    let prefix = S3Service::scoped_key(&connection, prefix.trim_start_matches('/'))?;
+
+    match StsService::scoped_credentials(
+        &connection,
+        &bucket_name,
+        &prefix,
+        &options.unwrap_or_default(),
+    )
+    .await
+    {
+        Ok(credentials) => Ok(credentials),
+        Err(e) => {
+            error!(
+                "Failed to issue scoped credentials for '{}/{}': {}",
+                bucket_name, prefix, e
+            );
+            Err(e)
+        }
+    }
+}
+
+/// The bucket's policy, pretty-printed, or `None` when it has none.
+#[tauri::command]
+pub async fn get_bucket_policy(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<Option<String>> {
+    debug!("Getting bucket policy for '{}'", bucket_name);
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    match S3Service::get_bucket_policy(&connection, &bucket_name).await? {
+        // Providers return the policy minified; fall back to it as-is
+        Some(policy) => Ok(Some(
+            S3Service::validate_bucket_policy(&policy).unwrap_or(policy),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Syntax-check a policy document without sending it. Returns it
+/// pretty-printed.
+#[tauri::command]
+pub async fn validate_bucket_policy(policy: String) -> AppResult<String> {
+    S3Service::validate_bucket_policy(&policy)
+}
+
+/// Validate and replace the bucket's policy. Returns the document as sent.
+#[tauri::command]
+pub async fn put_bucket_policy(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    policy: String,
+) -> AppResult<String> {
+    info!("Saving bucket policy for '{}'", bucket_name);
+
+    let policy = S3Service::validate_bucket_policy(&policy)
+        .inspect_err(|e| warn!("Rejected policy for '{}': {}", bucket_name, e))?;
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    S3Service::put_bucket_policy(&connection, &bucket_name, &policy)
+        .await
+        .inspect_err(|e| error!("Failed to save policy for '{}': {}", bucket_name, e))?;
+    Ok(policy)
+}
+
+#[tauri::command]
+pub async fn delete_bucket_policy(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<()> {
+    warn!("Deleting bucket policy for '{}'", bucket_name);
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    S3Service::delete_bucket_policy(&connection, &bucket_name)
+        .await
+        .inspect_err(|e| error!("Failed to delete policy for '{}': {}", bucket_name, e))
+}
+
+/// The bucket's default encryption, or `None` when it has none.
+#[tauri::command]
+pub async fn get_bucket_encryption(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<Option<BucketEncryptionConfig>> {
+    debug!("Getting default encryption for '{}'", bucket_name);
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    S3Service::get_bucket_encryption(&connection, &bucket_name).await
+}
+
+#[tauri::command]
+pub async fn put_bucket_encryption(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    config: BucketEncryptionConfig,
+) -> AppResult<()> {
+    info!(
+        "Setting default encryption {:?} on '{}'",
+        config.algorithm, bucket_name
+    );
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    S3Service::put_bucket_encryption(&connection, &bucket_name, &config)
+        .await
+        .inspect_err(|e| error!("Failed to set encryption on '{}': {}", bucket_name, e))
+}
+
+#[tauri::command]
+pub async fn get_bucket_acl(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<AccessControlList> {
+    debug!("Getting bucket ACL for '{}'", bucket_name);
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    S3Service::get_bucket_acl(&connection, &bucket_name).await
+}
+
+/// Apply a canned ACL to the bucket and return the grants it ends up with.
+#[tauri::command]
+pub async fn put_bucket_acl(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    acl: CannedAcl,
+) -> AppResult<AccessControlList> {
+    info!("Setting ACL '{}' on bucket '{}'", acl.as_str(), bucket_name);
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    S3Service::put_bucket_acl(&connection, &bucket_name, acl)
+        .await
+        .inspect_err(|e| error!("Failed to set ACL on bucket '{}': {}", bucket_name, e))?;
+    S3Service::get_bucket_acl(&connection, &bucket_name).await
+}
+
+/// Bookmarks for a bucket, merged with the copy shared in the bucket unless
+/// sharing is turned off in settings.
+#[tauri::command]
+pub async fn get_bookmarks(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<Vec<Bookmark>> {
+    debug!("Getting bookmarks for '{}'", bucket_name);
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+    let shared = state.settings.lock().await.share_bookmarks;
+
+    BookmarkService::sync(&connection, &bucket_name, shared, None).await
+}
+
+/// Add or update the bookmark for `prefix`.
+#[tauri::command]
+pub async fn save_bookmark(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    prefix: String,
+    label: Option<String>,
+    note: Option<String>,
+) -> AppResult<Vec<Bookmark>> {
+    info!("Bookmarking '{}/{}'", bucket_name, prefix);
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+    let shared = state.settings.lock().await.share_bookmarks;
+
+    let bookmark = Bookmark {
+        prefix,
+        label: label.filter(|l| !l.is_empty()),
+        note: note.filter(|n| !n.is_empty()),
+        updated_at: Utc::now().timestamp(),
+        deleted: false,
+    };
+
+    BookmarkService::sync(&connection, &bucket_name, shared, Some(bookmark)).await
+}
+
+#[tauri::command]
+pub async fn delete_bookmark(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+    prefix: String,
+) -> AppResult<Vec<Bookmark>> {
+    info!("Removing bookmark '{}/{}'", bucket_name, prefix);
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+    let shared = state.settings.lock().await.share_bookmarks;
+
+    let tombstone = Bookmark {
+        prefix,
+        label: None,
+        note: None,
+        updated_at: Utc::now().timestamp(),
+        deleted: true,
+    };
+
+    BookmarkService::sync(&connection, &bucket_name, shared, Some(tombstone)).await
+}
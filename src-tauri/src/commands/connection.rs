@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use chrono::Utc;
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
@@ -5,8 +7,15 @@ use tauri::State;
 use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{S3Connection, S3ConnectionWithSecret, S3Provider};
-use crate::services::{ConfigService, CredentialService, S3Service};
+use crate::models::{
+    BatchResult, ClockSkewReport, CompatReport, CompatibilityOptions, OrphanedSecret, RateLimits,
+    S3Connection, S3ConnectionWithSecret, S3Provider, SignatureVersion, WriteProbeReport,
+    DELETE_BATCH_SIZES,
+};
+use crate::services::{
+    ClockSkew, CompatCheckService, ConfigService, CredentialService, IndexService, RateLimiter,
+    S3Service,
+};
 use crate::state::AppState;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,6 +28,18 @@ pub struct ExportedConnection {
     pub access_key: String,
     pub use_ssl: bool,
     pub use_path_style: bool,
+    #[serde(default)]
+    pub endpoint_template: Option<String>,
+    #[serde(default)]
+    pub signature_version: SignatureVersion,
+    #[serde(default)]
+    pub compatibility: CompatibilityOptions,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    #[serde(default)]
+    pub root_prefix: Option<String>,
+    #[serde(default)]
+    pub delete_batch_size: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,17 +60,50 @@ pub async fn create_connection(
     secret_key: String,
     use_ssl: bool,
     use_path_style: bool,
+    endpoint_template: Option<String>,
+    signature_version: Option<SignatureVersion>,
+    compatibility: Option<CompatibilityOptions>,
+    rate_limits: Option<RateLimits>,
+    root_prefix: Option<String>,
+    preset_id: Option<String>,
 ) -> AppResult<S3Connection> {
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().timestamp();
 
+    let preset = match preset_id.as_deref() {
+        Some(preset_id) => {
+            let settings = state.settings.lock().await;
+            let preset = settings
+                .provider_presets
+                .iter()
+                .find(|p| p.id == preset_id)
+                .cloned()
+                .ok_or_else(|| AppError::NotFound(format!("Provider preset {}", preset_id)))?;
+            debug!("Creating connection from preset '{}'", preset.name);
+            Some(preset)
+        }
+        None => None,
+    };
+    let endpoint = match &preset {
+        Some(preset) if endpoint.is_empty() => preset.endpoint.clone().unwrap_or_default(),
+        _ => endpoint,
+    };
+    let region = match &preset {
+        Some(preset) if region.is_empty() => preset.default_region.clone().unwrap_or_default(),
+        _ => region,
+    };
+    let endpoint_template =
+        endpoint_template.or_else(|| preset.as_ref()?.endpoint_template.clone());
+    let signature_version = signature_version.or(preset.as_ref().map(|p| p.signature_version));
+    let compatibility = compatibility.or(preset.as_ref().map(|p| p.compatibility));
+
     info!("Creating new connection '{}' for provider {:?}", name, provider);
     debug!(
         "Connection details - endpoint: {}, region: {}, path_style: {}",
         endpoint, region, use_path_style
     );
 
-    let connection = S3ConnectionWithSecret {
+    let mut connection = S3ConnectionWithSecret {
         id: id.clone(),
         name: name.clone(),
         provider,
@@ -59,10 +113,24 @@ pub async fn create_connection(
         secret_key: secret_key.clone(),
         use_ssl,
         use_path_style,
+        endpoint_template: endpoint_template.filter(|t| !t.is_empty()),
+        signature_version: signature_version.unwrap_or_default(),
+        compatibility: compatibility.unwrap_or_default(),
+        rate_limits: rate_limits.unwrap_or_default(),
+        root_prefix: S3Service::normalize_root_prefix(root_prefix),
+        cdn_distributions: HashMap::new(),
+        key_templates: Vec::new(),
+        delete_batch_size: preset.as_ref().and_then(|p| p.delete_batch_size),
+        preset_id: preset.map(|p| p.id),
         created_at: now,
         updated_at: now,
     };
 
+    if connection.endpoint_template.is_some() {
+        connection.endpoint = connection.endpoint_for_region(&connection.region);
+        debug!("Resolved endpoint template to: {}", connection.endpoint);
+    }
+
     // Store secret in keychain
     if let Err(e) = CredentialService::store_secret(&id, &secret_key) {
         error!("Failed to store credentials in keychain for '{}': {}", name, e);
@@ -71,8 +139,7 @@ pub async fn create_connection(
     debug!("Stored credentials in keychain for connection '{}'", name);
 
     // Store connection in state
-    let mut connections = state.connections.lock().await;
-    connections.insert(id.clone(), connection.clone());
+    state.insert_connection(connection.clone()).await;
 
     // Persist to config file
     if let Err(e) = ConfigService::save_connection(&connection) {
@@ -86,9 +153,86 @@ pub async fn create_connection(
 
 #[tauri::command]
 pub async fn list_connections(state: State<'_, AppState>) -> AppResult<Vec<S3Connection>> {
-    let connections = state.connections.lock().await;
+    let connections = state.all_connections().await;
     debug!("Listing {} connections", connections.len());
-    Ok(connections.values().cloned().map(|c| c.into()).collect())
+    Ok(connections.into_iter().map(|c| c.into()).collect())
+}
+
+/// Connections whose secret couldn't be read from the keychain at startup.
+#[tauri::command]
+pub async fn get_credential_errors(
+    state: State<'_, AppState>,
+) -> AppResult<HashMap<String, String>> {
+    Ok(state.credential_errors.lock().await.clone())
+}
+
+/// Ask the keychain for a connection's secret again, typically after its
+/// access prompt was denied at startup and the connection was left locked.
+#[tauri::command]
+pub async fn retry_unlock_connection(
+    state: State<'_, AppState>,
+    connection_id: String,
+) -> AppResult<S3Connection> {
+    info!("Retrying keychain access for connection: {}", connection_id);
+
+    match state.unlock_connection(&connection_id).await {
+        Ok(()) => {
+            info!("Unlocked connection: {}", connection_id);
+            Ok(state.connection(&connection_id).await?.into())
+        }
+        Err(e) => {
+            warn!("Failed to unlock connection '{}': {}", connection_id, e);
+            Err(e)
+        }
+    }
+}
+
+/// Keychain entries left behind by deleted connections or other installs.
+#[tauri::command]
+pub async fn list_orphaned_secrets(state: State<'_, AppState>) -> AppResult<Vec<OrphanedSecret>> {
+    let known_ids: HashSet<String> = state.connections.read().await.keys().cloned().collect();
+
+    let orphans = CredentialService::list_orphaned_secrets(&known_ids)?;
+    debug!("Found {} orphaned keychain entries", orphans.len());
+    Ok(orphans)
+}
+
+/// Delete the given orphaned keychain accounts. Accounts still owned by a
+/// live connection are skipped.
+#[tauri::command]
+pub async fn cleanup_secrets(
+    state: State<'_, AppState>,
+    accounts: Vec<String>,
+) -> AppResult<BatchResult> {
+    info!("Cleaning up {} keychain entries", accounts.len());
+
+    let known_ids: HashSet<String> = state.connections.read().await.keys().cloned().collect();
+    let orphans: HashSet<String> = CredentialService::list_orphaned_secrets(&known_ids)?
+        .into_iter()
+        .map(|o| o.account)
+        .collect();
+
+    let mut result = BatchResult::default();
+    for account in accounts {
+        if !orphans.contains(&account) {
+            warn!("Refusing to delete keychain entry in use: {}", account);
+            result.skip(account);
+            continue;
+        }
+
+        match CredentialService::delete_account(&account) {
+            Ok(()) => result.succeed(account),
+            Err(e) => result.fail(account, &e),
+        }
+    }
+
+    info!(
+        "Keychain cleanup: {} removed, {} failed, {} skipped",
+        result.succeeded.len(),
+        result.failed.len(),
+        result.skipped.len()
+    );
+    Ok(result)
 }
 
 #[tauri::command]
@@ -97,15 +241,11 @@ pub async fn get_connection(
     connection_id: String,
 ) -> AppResult<S3Connection> {
     debug!("Getting connection: {}", connection_id);
-    let connections = state.connections.lock().await;
-    connections
-        .get(&connection_id)
-        .cloned()
+    state
+        .connection(&connection_id)
+        .await
         .map(|c| c.into())
-        .ok_or_else(|| {
-            warn!("Connection not found: {}", connection_id);
-            crate::error::AppError::ConnectionNotFound(connection_id)
-        })
+        .inspect_err(|_| warn!("Connection not found: {}", connection_id))
 }
 
 #[tauri::command]
@@ -120,17 +260,20 @@ pub async fn update_connection(
     secret_key: Option<String>,
     use_ssl: Option<bool>,
     use_path_style: Option<bool>,
+    endpoint_template: Option<String>,
+    signature_version: Option<SignatureVersion>,
+    compatibility: Option<CompatibilityOptions>,
+    rate_limits: Option<RateLimits>,
+    root_prefix: Option<String>,
 ) -> AppResult<S3Connection> {
     info!("Updating connection: {}", connection_id);
 
-    let mut connections = state.connections.lock().await;
+    let entry = state
+        .connection_entry(&connection_id)
+        .await
+        .inspect_err(|_| warn!("Cannot update - connection not found: {}", connection_id))?;
 
-    let connection = connections
-        .get_mut(&connection_id)
-        .ok_or_else(|| {
-            warn!("Cannot update - connection not found: {}", connection_id);
-            crate::error::AppError::ConnectionNotFound(connection_id.clone())
-        })?;
+    let mut connection = entry.write().await;
 
     if let Some(ref name) = name {
         debug!("Updating name to: {}", name);
@@ -156,6 +299,8 @@ pub async fn update_connection(
         debug!("Updating secret key and storing in keychain");
         connection.secret_key = secret_key.clone();
         CredentialService::store_secret(&connection_id, secret_key)?;
+        state.credential_errors.lock().await.remove(&connection_id);
+        state.locked_connections.lock().await.remove(&connection_id);
     }
     if let Some(use_ssl) = use_ssl {
         debug!("Updating use_ssl to: {}", use_ssl);
@@ -165,10 +310,39 @@ pub async fn update_connection(
         debug!("Updating use_path_style to: {}", use_path_style);
         connection.use_path_style = use_path_style;
     }
+    if let Some(endpoint_template) = endpoint_template {
+        // An empty template clears it and falls back to the plain endpoint
+        debug!("Updating endpoint template to: {}", endpoint_template);
+        connection.endpoint_template = Some(endpoint_template).filter(|t| !t.is_empty());
+    }
+    if connection.endpoint_template.is_some() {
+        connection.endpoint = connection.endpoint_for_region(&connection.region);
+    }
+    if let Some(signature_version) = signature_version {
+        debug!("Updating signature_version to: {:?}", signature_version);
+        connection.signature_version = signature_version;
+    }
+    if let Some(compatibility) = compatibility {
+        debug!("Updating compatibility options to: {:?}", compatibility);
+        connection.compatibility = compatibility;
+    }
+    if let Some(rate_limits) = rate_limits {
+        debug!("Updating rate limits to: {:?}", rate_limits);
+        connection.rate_limits = rate_limits;
+    }
+    if let Some(root_prefix) = root_prefix {
+        // An empty prefix lifts the restriction
+        debug!("Updating root prefix to: {}", root_prefix);
+        connection.root_prefix = S3Service::normalize_root_prefix(Some(root_prefix));
+    }
 
     connection.updated_at = Utc::now().timestamp();
 
     let updated = connection.clone();
+    drop(connection);
+
+    // Endpoint or region changes can invalidate previously resolved regions
+    state.clear_connection_caches(&connection_id).await;
 
     // Persist to config file
     ConfigService::save_connection(&updated)?;
@@ -184,8 +358,7 @@ pub async fn delete_connection(
 ) -> AppResult<()> {
     info!("Deleting connection: {}", connection_id);
 
-    let mut connections = state.connections.lock().await;
-    let removed = connections.remove(&connection_id);
+    let removed = state.remove_connection(&connection_id).await;
 
     if removed.is_none() {
         warn!("Connection to delete was not found in state: {}", connection_id);
@@ -196,6 +369,15 @@ pub async fn delete_connection(
         warn!("Failed to delete credentials from keychain: {}", e);
     }
 
+    state.clear_connection_caches(&connection_id).await;
+    state.credential_errors.lock().await.remove(&connection_id);
+    state.locked_connections.lock().await.remove(&connection_id);
+    RateLimiter::reset(&connection_id);
+    ClockSkew::reset(&connection_id);
+    if let Err(e) = IndexService::remove_all(&connection_id) {
+        warn!("Failed to delete bucket indexes: {}", e);
+    }
+
     // Delete from config file
     ConfigService::delete_connection(&connection_id)?;
 
@@ -212,6 +394,9 @@ pub async fn test_connection(
     use_ssl: bool,
     use_path_style: bool,
     provider: S3Provider,
+    endpoint_template: Option<String>,
+    signature_version: Option<SignatureVersion>,
+    compatibility: Option<CompatibilityOptions>,
 ) -> AppResult<bool> {
     info!("Testing connection to {:?} endpoint: {}", provider, endpoint);
     debug!(
@@ -229,6 +414,15 @@ pub async fn test_connection(
         secret_key,
         use_ssl,
         use_path_style,
+        endpoint_template: endpoint_template.filter(|t| !t.is_empty()),
+        signature_version: signature_version.unwrap_or_default(),
+        compatibility: compatibility.unwrap_or_default(),
+        rate_limits: RateLimits::default(),
+        root_prefix: None,
+        cdn_distributions: HashMap::new(),
+        key_templates: Vec::new(),
+        preset_id: None,
+        delete_batch_size: None,
         created_at: 0,
         updated_at: 0,
     };
@@ -250,14 +444,85 @@ pub async fn test_connection(
     }
 }
 
+/// Check that a bucket accepts writes, not just listings, by putting a tiny
+/// object under the probe prefix from settings, reading it back and
+/// deleting it.
+#[tauri::command]
+pub async fn test_write_access(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<WriteProbeReport> {
+    let prefix = state.settings.lock().await.probe_prefix.clone();
+    let key = format!("{}{}", prefix, Uuid::new_v4());
+    info!("Running write probe on '{}/{}'", bucket_name, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket_name).await?;
+    let operator = S3Service::create_operator(&connection, &bucket_name)?;
+
+    let steps = S3Service::write_probe(&operator, &key).await;
+    let success = steps.len() == 3 && steps.iter().all(|s| s.ok);
+
+    for step in steps.iter().filter(|s| !s.ok) {
+        warn!(
+            "Write probe {:?} failed on '{}': {}",
+            step.step,
+            bucket_name,
+            step.error.as_deref().unwrap_or_default()
+        );
+    }
+    info!(
+        "Write probe on '{}' {}",
+        bucket_name,
+        if success { "passed" } else { "failed" }
+    );
+
+    Ok(WriteProbeReport {
+        bucket: bucket_name,
+        key,
+        steps,
+        success,
+    })
+}
+
+/// How far the connection's server clock was from this one at the last
+/// response, or `None` when they agree.
+#[tauri::command]
+pub async fn get_clock_skew(connection_id: String) -> AppResult<Option<ClockSkewReport>> {
+    Ok(ClockSkew::report(&connection_id))
+}
+
+/// Run Baul's compatibility checks against a bucket, typically on a
+/// self-hosted endpoint, and report which features work there.
+#[tauri::command]
+pub async fn run_compat_check(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket_name: String,
+) -> AppResult<CompatReport> {
+    let prefix = state.settings.lock().await.probe_prefix.clone();
+    info!("Running compatibility check on '{}'", bucket_name);
+
+    let connection = state
+        .bucket_connection(&connection_id, &bucket_name)
+        .await?;
+
+    let report = CompatCheckService::run(&connection, &bucket_name, &prefix).await?;
+    info!(
+        "Compatibility check on '{}': {} passed, {} failed",
+        bucket_name, report.passed, report.failed
+    );
+    Ok(report)
+}
+
 #[tauri::command]
 pub async fn export_connections(state: State<'_, AppState>) -> AppResult<String> {
     info!("Exporting connections");
 
-    let connections = state.connections.lock().await;
+    let connections = state.all_connections().await;
 
     let exported: Vec<ExportedConnection> = connections
-        .values()
+        .iter()
         .map(|c| ExportedConnection {
             name: c.name.clone(),
             provider: c.provider.clone(),
@@ -266,6 +531,12 @@ pub async fn export_connections(state: State<'_, AppState>) -> AppResult<String>
             access_key: c.access_key.clone(),
             use_ssl: c.use_ssl,
             use_path_style: c.use_path_style,
+            endpoint_template: c.endpoint_template.clone(),
+            signature_version: c.signature_version,
+            compatibility: c.compatibility,
+            rate_limits: c.rate_limits,
+            root_prefix: c.root_prefix.clone(),
+            delete_batch_size: c.delete_batch_size,
         })
         .collect();
 
@@ -298,8 +569,17 @@ pub async fn import_connections(
         )));
     }
 
+    if let Some(exported) = import.connections.iter().find(|c| {
+        c.delete_batch_size
+            .is_some_and(|s| !DELETE_BATCH_SIZES.contains(&s))
+    }) {
+        return Err(AppError::ConfigError(format!(
+            "Delete batch size for '{}' must be between 1 and 1000",
+            exported.name
+        )));
+    }
+
     let mut imported_connections = Vec::new();
-    let mut connections = state.connections.lock().await;
 
     for exported in import.connections {
         let id = Uuid::new_v4().to_string();
@@ -320,12 +600,21 @@ pub async fn import_connections(
             secret_key: String::new(), // Will need to be set by user
             use_ssl: exported.use_ssl,
             use_path_style: exported.use_path_style,
+            endpoint_template: exported.endpoint_template,
+            signature_version: exported.signature_version,
+            compatibility: exported.compatibility,
+            rate_limits: exported.rate_limits,
+            root_prefix: exported.root_prefix,
+            cdn_distributions: HashMap::new(),
+            key_templates: Vec::new(),
+            preset_id: None,
+            delete_batch_size: exported.delete_batch_size,
             created_at: now,
             updated_at: now,
         };
 
         // Store connection in state
-        connections.insert(id.clone(), connection.clone());
+        state.insert_connection(connection.clone()).await;
 
         // Persist to config file (without secret key stored)
         if let Err(e) = ConfigService::save_connection(&connection) {
@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use log::{debug, error, info};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{IndexProgress, IndexQuery, IndexQueryResult, IndexStats, IndexSummary};
+use crate::services::{IndexService, S3Service};
+use crate::state::AppState;
+
+async fn crawl_bucket(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    rebuild: bool,
+) -> AppResult<IndexSummary> {
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let client = S3Service::create_s3_client(&connection).await;
+
+    let kind = if rebuild {
+        "build_index"
+    } else {
+        "refresh_index"
+    };
+    let job = state.start_job(kind, &bucket).await;
+    let job_id = job.id.clone();
+    let indexed_so_far = AtomicU64::new(0);
+
+    let result = IndexService::crawl(
+        &client,
+        &connection,
+        &bucket,
+        rebuild,
+        &job.id,
+        job.cancelled(),
+        |indexed| {
+            indexed_so_far.store(indexed, Ordering::Relaxed);
+            let _ = app.emit(
+                "index-progress",
+                IndexProgress {
+                    job_id: job_id.clone(),
+                    bucket: bucket.clone(),
+                    indexed,
+                },
+            );
+        },
+    )
+    .await;
+
+    match result {
+        Ok(summary) => {
+            info!(
+                "Indexed {} objects ({} bytes) in '{}'",
+                summary.object_count, summary.total_size, bucket
+            );
+            job.finish(summary.object_count, 0);
+            Ok(summary)
+        }
+        Err(e) => {
+            error!("Failed to index '{}': {}", bucket, e);
+            // Pages stored before the failure stay in the index
+            job.finish(indexed_so_far.load(Ordering::Relaxed), 1);
+            Err(e)
+        }
+    }
+}
+
+/// Crawl the whole bucket into a fresh local index, reporting
+/// `index-progress` as pages arrive. Can be cancelled.
+#[tauri::command]
+pub async fn build_index(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+) -> AppResult<IndexSummary> {
+    crawl_bucket(app, state, connection_id, bucket, true).await
+}
+
+/// Bring an existing index up to date, dropping objects that are gone.
+#[tauri::command]
+pub async fn refresh_index(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+) -> AppResult<IndexSummary> {
+    crawl_bucket(app, state, connection_id, bucket, false).await
+}
+
+/// Search a bucket's local index without touching the bucket.
+#[tauri::command]
+pub async fn query_index(
+    connection_id: String,
+    bucket: String,
+    query: IndexQuery,
+) -> AppResult<IndexQueryResult> {
+    debug!("Querying index of '{}': {:?}", bucket, query);
+
+    tokio::task::spawn_blocking(move || IndexService::query(&connection_id, &bucket, &query))
+        .await
+        .map_err(|e| AppError::IoError(std::io::Error::other(e)))?
+}
+
+/// Sizes under `prefix` by sub-folder and extension, from the local index.
+#[tauri::command]
+pub async fn get_index_stats(
+    connection_id: String,
+    bucket: String,
+    prefix: Option<String>,
+) -> AppResult<IndexStats> {
+    let prefix = prefix.unwrap_or_default();
+    debug!("Getting index stats for '{}/{}'", bucket, prefix);
+
+    tokio::task::spawn_blocking(move || IndexService::stats(&connection_id, &bucket, &prefix))
+        .await
+        .map_err(|e| AppError::IoError(std::io::Error::other(e)))?
+}
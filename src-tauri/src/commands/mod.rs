@@ -1,7 +1,15 @@
 pub mod bucket;
 pub mod connection;
+pub mod index;
 pub mod object;
+pub mod profile;
+pub mod settings;
+pub mod sync;
 
 pub use bucket::*;
 pub use connection::*;
+pub use index::*;
 pub use object::*;
+pub use profile::*;
+pub use settings::*;
+pub use sync::*;
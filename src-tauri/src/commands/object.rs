@@ -1,12 +1,47 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use aws_sdk_s3::Client as S3Client;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::{stream, FutureExt, StreamExt, TryStreamExt};
 use log::{debug, error, info, warn};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
 use tokio::fs;
+use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{ListObjectsResult, ObjectMetadata, S3Object, UploadProgress};
-use crate::services::S3Service;
-use crate::state::AppState;
+use crate::models::{
+    AccessControlList, BatchProgress, BatchResult, CannedAcl, ChecksumAlgorithm, ClipboardFormat,
+    CollisionStrategy, ConfigFormat, ConfigPreview, ContentEncoding, CopyPrefixSummary,
+    CopyProgress, DeleteProgress, DeleteProtectionReport, DeleteTarget, DeployOptions,
+    DeploySummary, DocumentPreview, DownloadManifest, DownloadManifestEntry, DownloadOutcome,
+    DownloadProgress, ExternalEdit, FolderUploadSummary, IndexMode, JournalAction, JournalStart,
+    KeyListDownloadProgress, ListObjectVersionsResult, ListObjectsResult, ListingQuery, LocalCopy,
+    ManifestStatus, MetadataUpdate, ObjectAttributes, ObjectMetadata, ObjectVersion,
+    OverwritePolicy, PlannedCopy, PrefixActivity, PrefixDownloadProgress, PrefixEstimate,
+    PresignedLink, PresignedUpload, PresignedUrlList, PreviewPlan, RecentActivity, RequestKind,
+    S3ConnectionWithSecret, S3Object, ScanCutoff, SearchOptions, SearchProgress, SearchSummary,
+    SelectionSummary, ShareFormat, ShareManifest, ShareResult, SharedLink, SqlitePreview,
+    StagedObject, StreamingSource, TagFilter, TagSearchResult, UploadDuplicate,
+    UploadDuplicateReport, UploadOptions, UploadProgress, UrlListFormat,
+};
+use crate::services::{
+    DestinationKeys, DownloadHistory, HookService, IgnoreRules, Incoming, InventoryService,
+    JobJournal, JournalService, KeyMatcher, KeyTemplates, LocalService, Placement, PreviewService,
+    S3Service, SiteService, TransferTuner, COPY_CONCURRENCY, DELETE_CONCURRENCY, INDEX_FILE,
+    MAX_LIST_PAGE_SIZE, SNIFF_BYTES,
+};
+use crate::state::{AppState, PrefetchedPage};
+use crate::util::{hash_file_async, HashAlgorithm};
 
+/// List one page under `prefix`. `max_keys` overrides the page size from
+/// settings; `continuation_token` fetches the page after a truncated one and
+/// `start_after` jumps straight to a point in a large flat prefix. `query`
+/// sorts and filters the entries before they're returned.
 #[tauri::command]
 pub async fn list_objects(
     state: State<'_, AppState>,
@@ -14,30 +49,79 @@ pub async fn list_objects(
     bucket: String,
     prefix: String,
     max_keys: Option<u32>,
+    continuation_token: Option<String>,
+    start_after: Option<String>,
+    query: Option<ListingQuery>,
 ) -> AppResult<ListObjectsResult> {
     debug!(
-        "Listing objects in bucket '{}' with prefix '{}' (max_keys: {:?})",
-        bucket, prefix, max_keys
+        "Listing objects in bucket '{}' with prefix '{}' (max_keys: {:?}, start_after: {:?})",
+        bucket, prefix, max_keys, start_after
     );
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?;
+    let (show_folder_markers, limit) = listing_options(&state, max_keys).await;
 
-    let operator = S3Service::create_operator(connection, &bucket)?;
+    let query = query.filter(|q| *q != ListingQuery::default());
+    let prefetched = match (&continuation_token, &query) {
+        (Some(token), None) => {
+            take_prefetched_page(&state, &connection_id, &bucket, token, limit).await
+        }
+        _ => None,
+    };
+    let result = match (prefetched, &query) {
+        (Some(page), _) => Ok(page),
+        (None, Some(query)) => {
+            S3Service::list_objects_matching(
+                &connection,
+                &bucket,
+                &prefix,
+                limit,
+                continuation_token,
+                start_after.as_deref(),
+                show_folder_markers,
+                query,
+            )
+            .await
+        }
+        (None, None) => {
+            S3Service::throttle(&connection, RequestKind::List).await;
+            S3Service::list_objects(
+                &connection,
+                &bucket,
+                &prefix,
+                limit,
+                continuation_token,
+                start_after.as_deref(),
+                show_folder_markers,
+            )
+            .await
+        }
+    };
 
-    match S3Service::list_objects(&operator, &prefix, max_keys).await {
+    match result {
         Ok(result) => {
             debug!(
-                "Found {} objects and {} prefixes in '{}/{}' (truncated: {})",
+                "Found {} objects and {} folders in '{}/{}' (truncated: {})",
                 result.objects.len(),
-                result.prefixes.len(),
+                result.folders.len(),
                 bucket,
                 prefix,
                 result.is_truncated
             );
+            save_listing_cursor(&state, &connection_id, &bucket, &prefix, &result).await;
+            if query.is_none() {
+                prefetch_next_page(
+                    &state,
+                    connection,
+                    &bucket,
+                    &prefix,
+                    limit,
+                    show_folder_markers,
+                    &result,
+                )
+                .await;
+            }
             Ok(result)
         }
         Err(e) => {
@@ -47,6 +131,233 @@ pub async fn list_objects(
     }
 }
 
+/// Continue the last truncated `list_objects` call for this prefix, with the
+/// same `query` it was made with.
+#[tauri::command]
+pub async fn list_more_objects(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    max_keys: Option<u32>,
+    query: Option<ListingQuery>,
+) -> AppResult<ListObjectsResult> {
+    let cursor_key = (connection_id.clone(), bucket.clone(), prefix.clone());
+    let Some(cursor) = state.listing_cursors.lock().await.get(&cursor_key).cloned() else {
+        debug!("No listing to continue for '{}/{}'", bucket, prefix);
+        return Ok(ListObjectsResult {
+            objects: Vec::new(),
+            folders: Vec::new(),
+            prefixes: Vec::new(),
+            continuation_token: None,
+            is_truncated: false,
+        });
+    };
+
+    debug!("Continuing listing of '{}/{}'", bucket, prefix);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let (show_folder_markers, limit) = listing_options(&state, max_keys).await;
+
+    let query = query.filter(|q| *q != ListingQuery::default());
+    let prefetched = match &query {
+        Some(_) => None,
+        None => take_prefetched_page(&state, &connection_id, &bucket, &cursor, limit).await,
+    };
+    let result = match (prefetched, &query) {
+        (Some(page), _) => Ok(page),
+        (None, Some(query)) => {
+            S3Service::list_objects_matching(
+                &connection,
+                &bucket,
+                &prefix,
+                limit,
+                Some(cursor),
+                None,
+                show_folder_markers,
+                query,
+            )
+            .await
+        }
+        (None, None) => {
+            S3Service::throttle(&connection, RequestKind::List).await;
+            S3Service::list_objects(
+                &connection,
+                &bucket,
+                &prefix,
+                limit,
+                Some(cursor),
+                None,
+                show_folder_markers,
+            )
+            .await
+        }
+    };
+
+    match result {
+        Ok(result) => {
+            debug!(
+                "Found {} more objects and {} folders in '{}/{}' (truncated: {})",
+                result.objects.len(),
+                result.folders.len(),
+                bucket,
+                prefix,
+                result.is_truncated
+            );
+            save_listing_cursor(&state, &connection_id, &bucket, &prefix, &result).await;
+            if query.is_none() {
+                prefetch_next_page(
+                    &state,
+                    connection,
+                    &bucket,
+                    &prefix,
+                    limit,
+                    show_folder_markers,
+                    &result,
+                )
+                .await;
+            }
+            Ok(result)
+        }
+        Err(e) => {
+            error!("Failed to continue listing '{}/{}': {}", bucket, prefix, e);
+            Err(e)
+        }
+    }
+}
+
+/// Largest listing page in low-memory mode.
+const LOW_MEMORY_PAGE_SIZE: u32 = 1000;
+
+/// Folder marker visibility and the effective page size for a listing.
+async fn listing_options(state: &AppState, max_keys: Option<u32>) -> (bool, usize) {
+    let settings = state.settings.lock().await;
+    let max_page_size = if settings.low_memory {
+        LOW_MEMORY_PAGE_SIZE
+    } else {
+        MAX_LIST_PAGE_SIZE
+    };
+    let page_size = max_keys
+        .unwrap_or(settings.listing_page_size)
+        .clamp(1, max_page_size);
+
+    (settings.show_folder_markers, page_size as usize)
+}
+
+async fn save_listing_cursor(
+    state: &AppState,
+    connection_id: &str,
+    bucket: &str,
+    prefix: &str,
+    result: &ListObjectsResult,
+) {
+    let cursor_key = (
+        connection_id.to_string(),
+        bucket.to_string(),
+        prefix.to_string(),
+    );
+    let mut cursors = state.listing_cursors.lock().await;
+
+    match result.continuation_token.clone() {
+        Some(token) => {
+            cursors.insert(cursor_key, token);
+        }
+        None => {
+            cursors.remove(&cursor_key);
+        }
+    }
+}
+
+/// How long a prefetched page is served before it counts as stale.
+const PREFETCH_TTL: Duration = Duration::from_secs(60);
+
+/// Start fetching the page after a truncated `result` in the background,
+/// when prefetching is turned on.
+async fn prefetch_next_page(
+    state: &AppState,
+    connection: S3ConnectionWithSecret,
+    bucket: &str,
+    prefix: &str,
+    limit: usize,
+    show_folder_markers: bool,
+    result: &ListObjectsResult,
+) {
+    let Some(token) = result.continuation_token.clone() else {
+        return;
+    };
+    let enabled = {
+        let settings = state.settings.lock().await;
+        settings.prefetch_listings && !settings.low_memory
+    };
+    if !enabled {
+        return;
+    }
+
+    let cache_key = (connection.id.clone(), bucket.to_string(), token.clone());
+    let (bucket, prefix) = (bucket.to_string(), prefix.to_string());
+    let page = async move {
+        S3Service::throttle(&connection, RequestKind::List).await;
+        S3Service::list_objects(
+            &connection,
+            &bucket,
+            &prefix,
+            limit,
+            Some(token),
+            None,
+            show_folder_markers,
+        )
+        .await
+        .inspect_err(|e| {
+            debug!(
+                "Prefetching next page of '{}/{}' failed: {}",
+                bucket, prefix, e
+            )
+        })
+        .ok()
+    }
+    .boxed()
+    .shared();
+    tauri::async_runtime::spawn(page.clone());
+
+    let mut pages = state.prefetched_pages.lock().await;
+    pages.retain(|_, p| p.started_at.elapsed() < PREFETCH_TTL);
+    pages.insert(
+        cache_key,
+        PrefetchedPage {
+            started_at: Instant::now(),
+            limit,
+            page,
+        },
+    );
+}
+
+/// The prefetched page after `token`, waiting for it if the fetch is still
+/// running. Stale pages and ones fetched with another page size are dropped.
+async fn take_prefetched_page(
+    state: &AppState,
+    connection_id: &str,
+    bucket: &str,
+    token: &str,
+    limit: usize,
+) -> Option<ListObjectsResult> {
+    let cache_key = (
+        connection_id.to_string(),
+        bucket.to_string(),
+        token.to_string(),
+    );
+    let prefetched = state.prefetched_pages.lock().await.remove(&cache_key)?;
+    if prefetched.limit != limit || prefetched.started_at.elapsed() >= PREFETCH_TTL {
+        return None;
+    }
+
+    let page = prefetched.page.await;
+    if page.is_some() {
+        debug!("Serving prefetched listing page for '{}'", bucket);
+    }
+    page
+}
+
 #[tauri::command]
 pub async fn get_object_details(
     state: State<'_, AppState>,
@@ -56,17 +367,16 @@ pub async fn get_object_details(
 ) -> AppResult<S3Object> {
     debug!("Getting details for object '{}/{}'", bucket, key);
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?;
-
-    let operator = S3Service::create_operator(connection, &bucket)?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
 
     S3Service::get_object_details(&operator, &key).await
 }
 
+/// Upload a local file to `key`, returning the key it was written to.
+/// `collision` decides what happens when the key is taken; when it says to
+/// keep the existing object, nothing is uploaded and its key is returned.
 #[tauri::command]
 pub async fn upload_file(
     app: AppHandle,
@@ -75,66 +385,149 @@ pub async fn upload_file(
     bucket: String,
     key: String,
     file_path: String,
-) -> AppResult<()> {
+    options: Option<UploadOptions>,
+    collision: Option<CollisionStrategy>,
+) -> AppResult<String> {
     info!("Uploading file '{}' to '{}/{}'", file_path, bucket, key);
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
-
-    drop(connections);
+    let (parent, name) = key.rsplit_once('/').unwrap_or(("", &key));
+    let key = match KeyTemplates::apply(&connection.key_templates, &bucket, parent, name) {
+        Some(templated) => {
+            debug!("Key template maps '{}' to '{}'", key, templated);
+            templated
+        }
+        None => key,
+    };
 
     let operator = S3Service::create_operator(&connection, &bucket)?;
 
-    let data = match fs::read(&file_path).await {
-        Ok(data) => data,
+    let metadata = match fs::metadata(&file_path).await {
+        Ok(metadata) => metadata,
         Err(e) => {
             error!("Failed to read file '{}': {}", file_path, e);
             return Err(e.into());
         }
     };
+    let total_bytes = metadata.len();
 
-    let total_bytes = data.len() as u64;
+    let collision = collision.unwrap_or_default();
+    let key = if collision == CollisionStrategy::Overwrite {
+        key
+    } else {
+        let digest = match collision {
+            CollisionStrategy::SuffixHash => {
+                Some(hash_file_async(PathBuf::from(&file_path), HashAlgorithm::Md5).await?)
+            }
+            _ => None,
+        };
+        let incoming = Incoming {
+            size: total_bytes,
+            modified: LocalService::modified_millis(&metadata) / 1000,
+            digest: digest.as_deref(),
+        };
+        let placement = collision_candidates(&connection, &bucket, &key)
+            .await?
+            .place(collision, &key, incoming)
+            .inspect_err(|e| error!("Failed to upload '{}': {}", file_path, e))?;
+        match placement {
+            Placement::Write(key) => key,
+            Placement::Skip => {
+                info!(
+                    "Not uploading '{}', '{}/{}' already exists",
+                    file_path, bucket, key
+                );
+                return Ok(key);
+            }
+        }
+    };
     let file_name = key.clone();
 
+    let plan = TransferTuner::plan(&connection, total_bytes)?;
     debug!(
-        "Read {} bytes from '{}', starting upload",
-        total_bytes, file_path
+        "Uploading {} bytes from '{}' in {} parts of {} bytes, {} at a time",
+        total_bytes, file_path, plan.part_count, plan.part_size, plan.concurrency
     );
 
-    // Emit start progress
+    let started = Instant::now();
     let _ = app.emit(
         "upload-progress",
-        UploadProgress {
-            file_name: file_name.clone(),
-            bytes_uploaded: 0,
-            total_bytes,
-            percentage: 0.0,
-        },
+        UploadProgress::new(file_name.clone(), 0, total_bytes, Duration::ZERO).with_plan(plan),
     );
 
-    match S3Service::upload_object(&operator, &key, data).await {
-        Ok(()) => {
+    // Explicit options win over the content-type rules
+    let options = options.unwrap_or_default();
+    let rules = state.settings.lock().await.content_type_rules.clone();
+    let (rule_content_type, rule_cache_control) = SiteService::content_headers(&rules, &key);
+    let content_type = options.content_type.clone().unwrap_or(rule_content_type);
+    let cache_control = options.cache_control.clone().or(rule_cache_control);
+
+    let on_progress = |uploaded| {
+        let _ = app.emit(
+            "upload-progress",
+            UploadProgress::new(file_name.clone(), uploaded, total_bytes, started.elapsed())
+                .with_plan(plan),
+        );
+    };
+    let result = if options.needs_sdk() {
+        let options = UploadOptions {
+            content_type: Some(content_type),
+            cache_control,
+            ..options
+        };
+        S3Service::upload_file_with_options(
+            &connection,
+            &bucket,
+            &key,
+            Path::new(&file_path),
+            &plan,
+            &options,
+            on_progress,
+        )
+        .await
+    } else {
+        S3Service::upload_file_chunked(
+            &operator,
+            &key,
+            Path::new(&file_path),
+            &plan,
+            Some(&content_type),
+            cache_control.as_deref(),
+            on_progress,
+        )
+        .await
+    };
+
+    match result {
+        Ok(uploaded) => {
             info!(
-                "Successfully uploaded {} bytes to '{}/{}'",
-                total_bytes, bucket, key
+                "Successfully uploaded {} bytes to '{}/{}' in {:.1}s",
+                uploaded,
+                bucket,
+                key,
+                started.elapsed().as_secs_f64()
             );
+            TransferTuner::record(&connection.id, uploaded, started.elapsed());
 
-            // Emit completion
-            let _ = app.emit(
-                "upload-progress",
-                UploadProgress {
-                    file_name,
-                    bytes_uploaded: total_bytes,
-                    total_bytes,
-                    percentage: 100.0,
-                },
-            );
+            // An empty file has no parts, so report completion explicitly
+            if uploaded == 0 {
+                let _ = app.emit(
+                    "upload-progress",
+                    UploadProgress::new(file_name, 0, 0, started.elapsed()),
+                );
+            }
 
-            Ok(())
+            spawn_post_upload_hooks(
+                &app,
+                &state,
+                &connection,
+                &bucket,
+                vec![(key.clone(), uploaded)],
+            )
+            .await;
+
+            Ok(key)
         }
         Err(e) => {
             error!("Failed to upload '{}' to '{}/{}': {}", file_path, bucket, key, e);
@@ -143,314 +536,3783 @@ pub async fn upload_file(
     }
 }
 
+/// Run the configured post-upload hooks for `uploads` (key and size) in the
+/// background, copy any URLs they produce to the clipboard and report the
+/// outcomes in a `post-upload-hooks` event.
+pub(crate) async fn spawn_post_upload_hooks(
+    app: &AppHandle,
+    state: &AppState,
+    connection: &S3ConnectionWithSecret,
+    bucket: &str,
+    uploads: Vec<(String, u64)>,
+) {
+    let hooks = state.settings.lock().await.post_upload_hooks.clone();
+    if hooks.is_empty() || uploads.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    let connection = connection.clone();
+    let bucket = bucket.to_string();
+    tauri::async_runtime::spawn(async move {
+        let mut results = Vec::new();
+        for (key, size) in uploads {
+            results.extend(
+                HookService::run_post_upload(&connection, &bucket, &key, size, &hooks).await,
+            );
+        }
+        if results.is_empty() {
+            return;
+        }
+
+        let copied: Vec<&str> = results.iter().filter_map(|r| r.output.as_deref()).collect();
+        if !copied.is_empty() {
+            if let Err(e) = app.clipboard().write_text(copied.join("\n")) {
+                warn!("Failed to copy post-upload URLs to the clipboard: {}", e);
+            }
+        }
+
+        let _ = app.emit("post-upload-hooks", results);
+    });
+}
+
+/// Bytes between `download-progress` events.
+const DOWNLOAD_PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// Stream an object to `destination`, emitting `download-progress` events.
+/// The object is written to a `.part` file next to the destination and only
+/// renamed into place once verified.
 #[tauri::command]
 pub async fn download_file(
+    app: AppHandle,
     state: State<'_, AppState>,
     connection_id: String,
     bucket: String,
     key: String,
     destination: String,
-) -> AppResult<()> {
+    overwrite: Option<OverwritePolicy>,
+    create_dirs: Option<bool>,
+) -> AppResult<DownloadOutcome> {
+    let overwrite = overwrite.unwrap_or_default();
     info!(
-        "Downloading '{}/{}' to '{}'",
-        bucket, key, destination
+        "Downloading '{}/{}' to '{}' (overwrite: {:?})",
+        bucket, key, destination, overwrite
     );
 
-    let connections = state.connections.lock().await;
+    let destination_path = PathBuf::from(&destination);
+    LocalService::prepare_destination(&destination_path, create_dirs.unwrap_or(true)).await?;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
-
-    drop(connections);
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
 
     let operator = S3Service::create_operator(&connection, &bucket)?;
 
-    let data = match S3Service::download_object(&operator, &key).await {
-        Ok(data) => {
-            debug!("Downloaded {} bytes from '{}/{}'", data.len(), bucket, key);
-            data
+    let expected = S3Service::get_object_details(&operator, &key).await?;
+
+    let copies =
+        DownloadHistory::copies_of(&connection_id, &bucket, &key, expected.etag.as_deref())
+            .unwrap_or_default();
+    let unchanged_copy = copies.into_iter().find(|c| c.up_to_date);
+    if let Some(copy) = &unchanged_copy {
+        debug!(
+            "'{}/{}' is unchanged since it was downloaded to '{}'",
+            bucket, key, copy.path
+        );
+    }
+
+    if fs::try_exists(&destination).await? {
+        match overwrite {
+            OverwritePolicy::Overwrite => {}
+            OverwritePolicy::Skip => {
+                info!("Skipping download, '{}' already exists", destination);
+                return Ok(DownloadOutcome {
+                    size: None,
+                    unchanged_copy,
+                });
+            }
+            OverwritePolicy::Fail => {
+                warn!("Refusing to overwrite existing file '{}'", destination);
+                return Err(AppError::AlreadyExists(destination));
+            }
+        }
+    }
+
+    let size = save_object(
+        &app,
+        &operator,
+        &connection,
+        &bucket,
+        &expected,
+        &destination_path,
+    )
+    .await?;
+    Ok(DownloadOutcome {
+        size: Some(size),
+        unchanged_copy,
+    })
+}
+
+/// Stream `expected` to `destination`, emitting `download-progress` events,
+/// verify it, move it into place and record it in the download history.
+/// Returns the bytes written.
+pub(crate) async fn save_object(
+    app: &AppHandle,
+    operator: &opendal::Operator,
+    connection: &S3ConnectionWithSecret,
+    bucket: &str,
+    expected: &S3Object,
+    destination: &Path,
+) -> AppResult<u64> {
+    let key = &expected.key;
+    let plan = TransferTuner::plan(connection, expected.size)?;
+    debug!(
+        "Downloading '{}/{}' in {} parts of {} bytes, {} at a time",
+        bucket, key, plan.part_count, plan.part_size, plan.concurrency
+    );
+
+    // Write next to the destination and rename into place so an interrupted
+    // download never leaves a truncated file under the final name
+    let mut part_path = destination.as_os_str().to_owned();
+    part_path.push(".part");
+    let part_path = PathBuf::from(part_path);
+
+    let total = expected.size;
+    let mut last_emitted = 0;
+    let emit_progress = |downloaded: u64| {
+        if downloaded - last_emitted < DOWNLOAD_PROGRESS_INTERVAL && downloaded < total {
+            return;
+        }
+        last_emitted = downloaded;
+        let _ = app.emit(
+            "download-progress",
+            DownloadProgress {
+                key: key.clone(),
+                bytes_downloaded: downloaded,
+                total_bytes: total,
+                percentage: if total > 0 {
+                    (downloaded as f32 / total as f32) * 100.0
+                } else {
+                    100.0
+                },
+                plan: Some(plan),
+            },
+        );
+    };
+
+    let md5 = S3Service::content_md5(connection, bucket, expected).await?;
+    let started = Instant::now();
+    let downloaded =
+        S3Service::download_object_to_file(operator, key, &part_path, &plan, emit_progress)
+            .await
+            .and_then(|(size, digest)| {
+                S3Service::verify_digest(expected, size, md5.as_deref(), || digest)?;
+                Ok(size)
+            });
+
+    let size = match downloaded {
+        Ok(size) => {
+            debug!("Downloaded {} bytes from '{}/{}'", size, bucket, key);
+            TransferTuner::record(&connection.id, size, started.elapsed());
+            size
         }
         Err(e) => {
             error!("Failed to download '{}/{}': {}", bucket, key, e);
-            return Err(e);
+            let _ = fs::remove_file(&part_path).await;
+            return Err(match e {
+                AppError::IoError(e) => LocalService::destination_error(destination, e),
+                e => e,
+            });
         }
     };
 
-    match fs::write(&destination, &data).await {
+    match fs::rename(&part_path, destination).await {
         Ok(()) => {
-            info!(
-                "Successfully saved {} bytes to '{}'",
-                data.len(),
-                destination
-            );
-            Ok(())
+            info!("Successfully saved {} bytes to {:?}", size, destination);
+            if let Err(e) = DownloadHistory::record(
+                &connection.id,
+                bucket,
+                key,
+                destination,
+                expected.etag.clone(),
+                size,
+            ) {
+                warn!("Failed to record download of '{}/{}': {}", bucket, key, e);
+            }
+            Ok(size)
         }
         Err(e) => {
-            error!("Failed to write file '{}': {}", destination, e);
-            Err(e.into())
+            error!("Failed to move {:?} into place: {}", part_path, e);
+            let _ = fs::remove_file(&part_path).await;
+            Err(LocalService::destination_error(destination, e))
         }
     }
 }
 
+/// Objects downloaded at once by `download_prefix` unless overridden.
+const PREFIX_DOWNLOAD_CONCURRENCY: usize = 4;
+
+/// Download everything under `prefix` into `destination`, recreating the
+/// folder hierarchy. Each file reports `download-progress`; the run as a
+/// whole reports `prefix-download-progress` as files finish.
 #[tauri::command]
-pub async fn delete_objects(
+pub async fn download_prefix(
+    app: AppHandle,
     state: State<'_, AppState>,
     connection_id: String,
     bucket: String,
-    keys: Vec<String>,
-) -> AppResult<()> {
-    warn!("Deleting {} objects from bucket '{}'", keys.len(), bucket);
-    debug!("Objects to delete: {:?}", keys);
+    prefix: String,
+    destination: String,
+    overwrite: Option<OverwritePolicy>,
+    concurrency: Option<usize>,
+) -> AppResult<BatchResult> {
+    let overwrite = overwrite.unwrap_or_default();
+    let concurrency =
+        TransferTuner::concurrency(concurrency.unwrap_or(PREFIX_DOWNLOAD_CONCURRENCY));
+    info!(
+        "Downloading prefix '{}/{}' to '{}' (overwrite: {:?}, concurrency: {})",
+        bucket, prefix, destination, overwrite, concurrency
+    );
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+    let mut budget = state.settings.lock().await.scan_limits.start();
+    let job = state.start_job("download_prefix", &bucket).await;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
+    let key_prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.clone()
+    } else {
+        format!("{}/", prefix)
+    };
+    let (folders, objects): (Vec<S3Object>, Vec<S3Object>) =
+        S3Service::list_objects_within(&connection, &bucket, &key_prefix, &mut budget)
+            .await?
+            .into_iter()
+            .filter(|o| o.key != key_prefix)
+            .partition(|o| o.is_directory);
 
-    drop(connections);
+    // Half a tree on disk looks complete, so a cut-short listing downloads nothing
+    if let Some(cutoff) = budget.cutoff() {
+        error!(
+            "Not downloading '{}/{}': listing stopped after {} objects ({:?})",
+            bucket,
+            key_prefix,
+            budget.scanned(),
+            cutoff
+        );
+        return Err(AppError::ConfigError(format!(
+            "'{}' holds more objects than the scan limits allow; \
+             download a smaller prefix or raise the limits",
+            key_prefix
+        )));
+    }
 
-    let operator = S3Service::create_operator(&connection, &bucket)?;
+    let root = PathBuf::from(&destination);
+    fs::create_dir_all(&root)
+        .await
+        .map_err(|e| LocalService::destination_error(&root, e))?;
 
-    let mut deleted_count = 0;
-    for key in &keys {
-        match S3Service::delete_object(&operator, key).await {
-            Ok(()) => {
-                debug!("Deleted '{}/{}'", bucket, key);
-                deleted_count += 1;
+    // Recreate empty folders too, so the local tree matches the listing
+    for folder in &folders {
+        let relative = folder.key.strip_prefix(&key_prefix).unwrap_or(&folder.key);
+        match LocalService::local_path_for_key(&root, relative) {
+            Ok(dir) => {
+                if let Err(e) = fs::create_dir_all(&dir).await {
+                    warn!("Failed to create folder {:?}: {}", dir, e);
+                }
+            }
+            Err(e) => warn!("Skipping folder '{}': {}", folder.key, e),
+        }
+    }
+
+    let files_total = objects.len() as u64;
+    let total_bytes: u64 = objects.iter().map(|o| o.size).sum();
+    debug!(
+        "Found {} objects ({} bytes) under '{}/{}'",
+        files_total, total_bytes, bucket, key_prefix
+    );
+
+    let mut result = BatchResult::default();
+    let mut files_completed: u64 = 0;
+    let mut bytes_downloaded: u64 = 0;
+
+    let mut downloads = stream::iter(objects)
+        .map(|object| {
+            let app = &app;
+            let operator = &operator;
+            let connection = &connection;
+            let bucket = &bucket;
+            let root = &root;
+            let key_prefix = &key_prefix;
+            async move {
+                let relative = object
+                    .key
+                    .strip_prefix(key_prefix.as_str())
+                    .unwrap_or(&object.key);
+                let outcome: AppResult<Option<u64>> = async {
+                    let path = LocalService::local_path_for_key(root, relative)?;
+                    if fs::try_exists(&path).await? {
+                        match overwrite {
+                            OverwritePolicy::Overwrite => {}
+                            OverwritePolicy::Skip => return Ok(None),
+                            OverwritePolicy::Fail => {
+                                return Err(AppError::AlreadyExists(
+                                    path.to_string_lossy().to_string(),
+                                ))
+                            }
+                        }
+                    }
+                    LocalService::prepare_destination(&path, true).await?;
+                    save_object(app, operator, connection, bucket, &object, &path)
+                        .await
+                        .map(Some)
+                }
+                .await;
+                (object, outcome)
+            }
+        })
+        .buffer_unordered(concurrency);
+
+    while let Some((object, outcome)) = downloads.next().await {
+        files_completed += 1;
+        bytes_downloaded += object.size;
+        match outcome {
+            Ok(Some(_)) => result.succeed(object.key),
+            Ok(None) => {
+                debug!("Skipping '{}', it already exists locally", object.key);
+                result.skip(object.key);
             }
             Err(e) => {
-                error!("Failed to delete '{}/{}': {}", bucket, key, e);
-                return Err(e);
+                error!("Failed to download '{}/{}': {}", bucket, object.key, e);
+                result.fail(object.key, &e);
             }
         }
+
+        let _ = app.emit(
+            "prefix-download-progress",
+            PrefixDownloadProgress {
+                prefix: key_prefix.clone(),
+                files_completed,
+                files_total,
+                bytes_downloaded,
+                total_bytes,
+                percentage: if total_bytes > 0 {
+                    (bytes_downloaded as f32 / total_bytes as f32) * 100.0
+                } else {
+                    (files_completed as f32 / files_total as f32) * 100.0
+                },
+            },
+        );
     }
 
     info!(
-        "Successfully deleted {} objects from bucket '{}'",
-        deleted_count, bucket
+        "Downloaded '{}/{}' to '{}': {} succeeded, {} skipped, {} failed",
+        bucket,
+        key_prefix,
+        destination,
+        result.succeeded.len(),
+        result.skipped.len(),
+        result.failed.len()
     );
-    Ok(())
+    job.finish(result.succeeded.len() as u64, result.failed.len() as u64);
+    Ok(result)
 }
 
+/// File `download_objects` writes its manifest to unless told otherwise.
+const DOWNLOAD_MANIFEST_FILE: &str = ".baul-download-manifest.json";
+
+/// Download an explicit list of keys into `destination`, below it at the
+/// path each key has relative to `base_prefix`, and write a manifest of
+/// what succeeded, was skipped or failed and why. Passing a previous
+/// manifest's failed keys back in retries just those.
 #[tauri::command]
-pub async fn create_folder(
+pub async fn download_objects(
+    app: AppHandle,
     state: State<'_, AppState>,
     connection_id: String,
     bucket: String,
-    path: String,
-) -> AppResult<()> {
-    info!("Creating folder '{}/{}/'", bucket, path);
+    keys: Vec<String>,
+    destination: String,
+    base_prefix: Option<String>,
+    overwrite: Option<OverwritePolicy>,
+    concurrency: Option<usize>,
+    manifest_path: Option<String>,
+) -> AppResult<DownloadManifest> {
+    let overwrite = overwrite.unwrap_or_default();
+    let concurrency =
+        TransferTuner::concurrency(concurrency.unwrap_or(PREFIX_DOWNLOAD_CONCURRENCY));
+    let base_prefix = base_prefix.unwrap_or_default();
+    info!(
+        "Downloading {} keys from '{}' to '{}' (overwrite: {:?}, concurrency: {})",
+        keys.len(),
+        bucket,
+        destination,
+        overwrite,
+        concurrency
+    );
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
+    let root = PathBuf::from(&destination);
+    fs::create_dir_all(&root)
+        .await
+        .map_err(|e| LocalService::destination_error(&root, e))?;
+    let manifest_path = manifest_path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| root.join(DOWNLOAD_MANIFEST_FILE));
 
-    drop(connections);
+    let job = state.start_job("download_objects", &bucket).await;
+    let files_total = keys.len() as u64;
+    let mut files_completed: u64 = 0;
+    let mut bytes_downloaded: u64 = 0;
+    let mut entries = Vec::with_capacity(keys.len());
 
-    let operator = S3Service::create_operator(&connection, &bucket)?;
+    let mut downloads = stream::iter(keys)
+        .map(|key| {
+            let app = &app;
+            let operator = &operator;
+            let connection = &connection;
+            let bucket = &bucket;
+            let root = &root;
+            let base_prefix = &base_prefix;
+            async move {
+                let relative = key.strip_prefix(base_prefix.as_str()).unwrap_or(&key);
+                let path = match LocalService::local_path_for_key(root, relative) {
+                    Ok(path) => path,
+                    Err(e) => return (key, None, Err(e)),
+                };
+                let outcome: AppResult<Option<u64>> = async {
+                    if fs::try_exists(&path).await? {
+                        match overwrite {
+                            OverwritePolicy::Overwrite => {}
+                            OverwritePolicy::Skip => return Ok(None),
+                            OverwritePolicy::Fail => {
+                                return Err(AppError::AlreadyExists(
+                                    path.to_string_lossy().to_string(),
+                                ))
+                            }
+                        }
+                    }
+                    let object = S3Service::get_object_details(operator, &key).await?;
+                    LocalService::prepare_destination(&path, true).await?;
+                    save_object(app, operator, connection, bucket, &object, &path)
+                        .await
+                        .map(Some)
+                }
+                .await;
+                let path = Some(path.to_string_lossy().to_string());
+                (key, path, outcome)
+            }
+        })
+        .buffer_unordered(concurrency);
 
-    match S3Service::create_folder(&operator, &path).await {
-        Ok(()) => {
-            info!("Successfully created folder '{}/{}/'", bucket, path);
-            Ok(())
-        }
-        Err(e) => {
-            error!("Failed to create folder '{}/{}': {}", bucket, path, e);
-            Err(e)
-        }
+    while let Some((key, path, outcome)) = downloads.next().await {
+        files_completed += 1;
+        let entry = match outcome {
+            Ok(Some(size)) => {
+                bytes_downloaded += size;
+                DownloadManifestEntry {
+                    key,
+                    path,
+                    status: ManifestStatus::Succeeded,
+                    size: Some(size),
+                    reason: None,
+                    code: None,
+                }
+            }
+            Ok(None) => {
+                debug!("Skipping '{}', it already exists locally", key);
+                DownloadManifestEntry {
+                    key,
+                    path,
+                    status: ManifestStatus::Skipped,
+                    size: None,
+                    reason: Some("already exists locally".to_string()),
+                    code: None,
+                }
+            }
+            Err(e) => {
+                error!("Failed to download '{}/{}': {}", bucket, key, e);
+                DownloadManifestEntry {
+                    key,
+                    path,
+                    status: ManifestStatus::Failed,
+                    size: None,
+                    reason: Some(e.to_string()),
+                    code: Some(e.code().to_string()),
+                }
+            }
+        };
+        entries.push(entry);
+
+        let _ = app.emit(
+            "download-objects-progress",
+            KeyListDownloadProgress {
+                files_completed,
+                files_total,
+                bytes_downloaded,
+                percentage: (files_completed as f32 / files_total as f32) * 100.0,
+            },
+        );
+    }
+
+    let manifest = DownloadManifest {
+        bucket: bucket.clone(),
+        destination: destination.clone(),
+        created_at: chrono::Utc::now().timestamp(),
+        entries,
+        manifest_path: manifest_path.to_string_lossy().to_string(),
+    };
+    if let Err(e) = fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?).await {
+        // The results still go back to the caller
+        warn!(
+            "Failed to write download manifest {:?}: {}",
+            manifest_path, e
+        );
     }
+
+    let count = |status| {
+        manifest
+            .entries
+            .iter()
+            .filter(|e| e.status == status)
+            .count()
+    };
+    let (succeeded, failed) = (
+        count(ManifestStatus::Succeeded),
+        count(ManifestStatus::Failed),
+    );
+    info!(
+        "Downloaded keys from '{}' to '{}': {} succeeded, {} skipped, {} failed",
+        bucket,
+        destination,
+        succeeded,
+        count(ManifestStatus::Skipped),
+        failed
+    );
+    job.finish(succeeded as u64, failed as u64);
+    Ok(manifest)
 }
 
+/// Where an object has been downloaded to, with each copy marked as still
+/// on disk and up to date with the remote object or not, so the frontend can
+/// warn before a pointless re-download.
 #[tauri::command]
-pub async fn get_presigned_url(
+pub async fn get_local_copies(
     state: State<'_, AppState>,
     connection_id: String,
     bucket: String,
     key: String,
-    expires_in_secs: Option<u64>,
-) -> AppResult<String> {
-    let expires = expires_in_secs.unwrap_or(3600);
-    debug!(
-        "Generating presigned URL for '{}/{}' (expires in {}s)",
-        bucket, key, expires
-    );
+) -> AppResult<Vec<LocalCopy>> {
+    debug!("Getting local copies of '{}/{}'", bucket, key);
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
+    let remote_etag = match S3Service::get_object_details(&operator, &key).await {
+        Ok(object) => object.etag,
+        Err(e) => {
+            debug!("Couldn't stat '{}/{}': {}", bucket, key, e);
+            None
+        }
+    };
 
-    drop(connections);
+    DownloadHistory::copies_of(&connection_id, &bucket, &key, remote_etag.as_deref())
+}
 
-    S3Service::get_presigned_url(&connection, &bucket, &key, expires).await
+/// Emit `delete-progress` for a bulk delete after each batch.
+fn emit_delete_progress(
+    app: &AppHandle,
+    job_id: &str,
+    result: &BatchResult,
+    progress: BatchProgress,
+) {
+    let _ = app.emit(
+        "delete-progress",
+        DeleteProgress {
+            job_id: job_id.to_string(),
+            deleted: result.succeeded.len() as u64,
+            failed: result.failed.len() as u64,
+            progress,
+        },
+    );
 }
 
+/// Delete keys in DeleteObjects batches, several at once, reporting
+/// `delete-progress` as batches finish. Cancelling the job skips the
+/// batches that haven't started.
 #[tauri::command]
-pub async fn get_object_text(
+pub async fn delete_objects(
+    app: AppHandle,
     state: State<'_, AppState>,
     connection_id: String,
     bucket: String,
-    key: String,
-    max_size: Option<u64>,
-) -> AppResult<String> {
-    let max = max_size.unwrap_or(1024 * 1024);
-    debug!(
-        "Reading text content from '{}/{}' (max {})",
-        bucket, key, max
-    );
-
-    let connections = state.connections.lock().await;
-
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
-
-    drop(connections);
+    keys: Vec<String>,
+    concurrency: Option<usize>,
+) -> AppResult<BatchResult> {
+    warn!("Deleting {} objects from bucket '{}'", keys.len(), bucket);
+    debug!("Objects to delete: {:?}", keys);
 
-    let operator = S3Service::create_operator(&connection, &bucket)?;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let job = state.start_job("delete_objects", &bucket).await;
 
-    match S3Service::get_object_content_as_text(&operator, &key, max).await {
-        Ok(text) => {
-            debug!(
-                "Read {} characters of text from '{}/{}'",
-                text.len(),
-                bucket,
-                key
-            );
-            Ok(text)
-        }
-        Err(e) => {
-            warn!("Failed to read text from '{}/{}': {}", bucket, key, e);
-            Err(e)
-        }
+    // DeleteObjects takes up to a provider-specific number of keys per call
+    let result = S3Service::delete_keys_with(
+        &connection,
+        &bucket,
+        keys,
+        concurrency.unwrap_or(DELETE_CONCURRENCY),
+        job.cancelled(),
+        |result, progress| emit_delete_progress(&app, &job.id, result, progress),
+    )
+    .await?;
+    for failure in &result.failed {
+        error!(
+            "Failed to delete '{}/{}': {}",
+            bucket, failure.key, failure.message
+        );
     }
+
+    info!(
+        "Deleted {} objects from bucket '{}', {} failed, {} cancelled",
+        result.succeeded.len(),
+        bucket,
+        result.failed.len(),
+        result.skipped.len()
+    );
+    job.finish(result.succeeded.len() as u64, result.failed.len() as u64);
+    Ok(result)
 }
 
+/// Delete a folder and everything under it, not just its placeholder.
+/// Reports `delete-progress` and can be cancelled like `delete_objects`.
 #[tauri::command]
-pub async fn copy_object(
+pub async fn delete_prefix(
+    app: AppHandle,
     state: State<'_, AppState>,
     connection_id: String,
-    source_bucket: String,
-    source_key: String,
-    dest_bucket: String,
-    dest_key: String,
-) -> AppResult<()> {
+    bucket: String,
+    prefix: String,
+    concurrency: Option<usize>,
+) -> AppResult<BatchResult> {
+    warn!("Deleting everything under '{}/{}'", bucket, prefix);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let job = state.start_job("delete_prefix", &bucket).await;
+
+    let result = S3Service::delete_prefix(
+        &connection,
+        &bucket,
+        &prefix,
+        concurrency.unwrap_or(DELETE_CONCURRENCY),
+        job.cancelled(),
+        |result, progress| emit_delete_progress(&app, &job.id, result, progress),
+    )
+    .await?;
+
     info!(
-        "Copying '{}/{}' to '{}/{}'",
-        source_bucket, source_key, dest_bucket, dest_key
+        "Deleted {} objects under '{}/{}', {} failed, {} cancelled",
+        result.succeeded.len(),
+        bucket,
+        prefix,
+        result.failed.len(),
+        result.skipped.len()
     );
+    job.finish(result.succeeded.len() as u64, result.failed.len() as u64);
+    Ok(result)
+}
 
-    let connections = state.connections.lock().await;
+/// Check what deleting `targets` will really do before doing it, so
+/// deletes in Object Lock buckets don't half-succeed unexpectedly.
+#[tauri::command]
+pub async fn check_delete_protection(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    targets: Vec<DeleteTarget>,
+) -> AppResult<DeleteProtectionReport> {
+    debug!(
+        "Checking delete protection of {} objects in '{}'",
+        targets.len(),
+        bucket
+    );
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let report = S3Service::check_delete_protection(&connection, &bucket, targets).await?;
 
-    drop(connections);
+    if report.blocked > 0 || report.delete_markers > 0 {
+        info!(
+            "Deleting in '{}': {} blocked by Object Lock, {} would leave delete markers",
+            bucket, report.blocked, report.delete_markers
+        );
+    }
+    Ok(report)
+}
 
-    match S3Service::copy_object(
-        &connection,
-        &source_bucket,
-        &source_key,
-        &dest_bucket,
-        &dest_key,
-    )
-    .await
-    {
+#[tauri::command]
+pub async fn create_folder(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    path: String,
+) -> AppResult<()> {
+    info!("Creating folder '{}/{}/'", bucket, path);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    match S3Service::create_folder(&operator, &path).await {
         Ok(()) => {
-            info!(
-                "Successfully copied '{}/{}' to '{}/{}'",
-                source_bucket, source_key, dest_bucket, dest_key
-            );
+            info!("Successfully created folder '{}/{}/'", bucket, path);
             Ok(())
         }
+        Err(
+            e @ (AppError::AlreadyExists(_) | AppError::Conflict(_) | AppError::ConfigError(_)),
+        ) => {
+            warn!("Not creating folder '{}/{}': {}", bucket, path, e);
+            Err(e)
+        }
         Err(e) => {
-            error!(
-                "Failed to copy '{}/{}' to '{}/{}': {}",
-                source_bucket, source_key, dest_bucket, dest_key, e
-            );
+            error!("Failed to create folder '{}/{}': {}", bucket, path, e);
             Err(e)
         }
     }
 }
 
 #[tauri::command]
-pub async fn rename_object(
+pub async fn get_presigned_url(
     state: State<'_, AppState>,
     connection_id: String,
     bucket: String,
-    old_key: String,
-    new_key: String,
-) -> AppResult<()> {
+    key: String,
+    expires_in_secs: Option<u64>,
+) -> AppResult<String> {
+    let expires = expires_in_secs.unwrap_or(3600);
+    debug!(
+        "Generating presigned URL for '{}/{}' (expires in {}s)",
+        bucket, key, expires
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    S3Service::get_presigned_url(&connection, &bucket, &key, expires, None).await
+}
+
+/// Presign an upload of `key` to hand to someone without credentials. With
+/// `content_type` or `content_length` the upload must match them.
+#[tauri::command]
+pub async fn get_presigned_upload_url(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    expires_in_secs: Option<u64>,
+    content_type: Option<String>,
+    content_length: Option<u64>,
+) -> AppResult<PresignedUpload> {
+    let expires = expires_in_secs.unwrap_or(3600);
     info!(
-        "Renaming '{}/{}' to '{}/{}'",
-        bucket, old_key, bucket, new_key
+        "Generating presigned upload URL for '{}/{}' (expires in {}s)",
+        bucket, key, expires
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    if let Some(reason) = S3Service::presign_unavailable(&connection) {
+        return Err(AppError::NotSupported(format!(
+            "Cannot presign an upload to '{}': {}",
+            key, reason
+        )));
+    }
+
+    S3Service::get_presigned_upload_url(
+        &connection,
+        &bucket,
+        &key,
+        expires,
+        content_type.as_deref(),
+        content_length,
+    )
+    .await
+}
+
+/// Presign GET URLs for many selected objects in one call, optionally
+/// rendered as CSV or a plain list ready to paste.
+#[tauri::command]
+pub async fn get_presigned_urls(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    keys: Vec<String>,
+    expires_in_secs: Option<u64>,
+    format: Option<UrlListFormat>,
+) -> AppResult<PresignedUrlList> {
+    let expires = expires_in_secs.unwrap_or(3600);
+    debug!(
+        "Generating {} presigned URLs in '{}' (expires in {}s)",
+        keys.len(),
+        bucket,
+        expires
     );
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    if let Some(reason) = S3Service::presign_unavailable(&connection) {
+        return Err(AppError::NotSupported(format!(
+            "Cannot presign URLs in '{}': {}",
+            bucket, reason
+        )));
+    }
+
+    let urls = S3Service::get_presigned_urls(&connection, &bucket, &keys, expires).await?;
+    let mut list = PresignedUrlList {
+        links: keys
+            .into_iter()
+            .zip(urls)
+            .map(|(key, url)| PresignedLink { key, url })
+            .collect(),
+        expires_at: chrono::Utc::now().timestamp() + expires as i64,
+        text: None,
+    };
+    list.text = format.map(|format| list.render(format));
+    Ok(list)
+}
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
+/// Presign every object under `prefix` and package the links into one HTML
+/// or JSON manifest, so a whole folder can be shared with someone who has
+/// no credentials. With `upload_key` the manifest is also stored in the
+/// bucket and presigned itself, leaving a single link to hand out.
+#[tauri::command]
+pub async fn share_prefix(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    expires_in_secs: Option<u64>,
+    format: Option<ShareFormat>,
+    upload_key: Option<String>,
+) -> AppResult<ShareResult> {
+    let expires = expires_in_secs.unwrap_or(3600);
+    let format = format.unwrap_or_default();
+    info!(
+        "Sharing '{}/{}' as {:?} (expires in {}s)",
+        bucket, prefix, format, expires
+    );
 
-    drop(connections);
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    if let Some(reason) = S3Service::presign_unavailable(&connection) {
+        return Err(AppError::NotSupported(format!(
+            "Cannot share '{}': {}",
+            prefix, reason
+        )));
+    }
+    let operator = S3Service::create_operator(&connection, &bucket)?;
 
-    match S3Service::rename_object(&connection, &bucket, &old_key, &new_key).await {
-        Ok(()) => {
-            info!(
-                "Successfully renamed '{}/{}' to '{}/{}'",
-                bucket, old_key, bucket, new_key
-            );
-            Ok(())
-        }
-        Err(e) => {
+    let key_prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.clone()
+    } else {
+        format!("{}/", prefix)
+    };
+    let objects: Vec<S3Object> = S3Service::list_all_objects_recursive(&operator, &key_prefix)
+        .await?
+        .objects
+        .into_iter()
+        .filter(|o| upload_key.as_deref() != Some(o.key.as_str()))
+        .collect();
+    let keys: Vec<String> = objects.iter().map(|o| o.key.clone()).collect();
+    let urls = S3Service::get_presigned_urls(&connection, &bucket, &keys, expires).await?;
+
+    let created_at = chrono::Utc::now().timestamp();
+    let manifest = ShareManifest {
+        bucket: bucket.clone(),
+        prefix: key_prefix.clone(),
+        created_at,
+        expires_at: created_at + expires as i64,
+        links: objects
+            .into_iter()
+            .zip(urls)
+            .map(|(object, url)| SharedLink {
+                name: object
+                    .key
+                    .strip_prefix(&key_prefix)
+                    .unwrap_or(&object.key)
+                    .to_string(),
+                url,
+                size: object.size,
+            })
+            .collect(),
+    };
+
+    let (content, content_type) = match format {
+        ShareFormat::Html => (
+            SiteService::render_share_page(&manifest),
+            "text/html; charset=utf-8",
+        ),
+        ShareFormat::Json => (serde_json::to_string_pretty(&manifest)?, "application/json"),
+    };
+
+    let mut manifest_url = None;
+    if let Some(key) = upload_key.as_deref() {
+        // The links inside expire, so caches must not outlive them
+        S3Service::upload_object_with(
+            &operator,
+            key,
+            content.clone().into_bytes(),
+            Some(content_type),
+            Some("no-cache"),
+            None,
+        )
+        .await
+        .inspect_err(|e| {
             error!(
-                "Failed to rename '{}/{}' to '{}': {}",
-                bucket, old_key, new_key, e
+                "Failed to upload share manifest to '{}/{}': {}",
+                bucket, key, e
+            )
+        })?;
+        manifest_url =
+            Some(S3Service::get_presigned_url(&connection, &bucket, key, expires, None).await?);
+    }
+
+    info!(
+        "Shared {} objects under '{}/{}'",
+        manifest.links.len(),
+        bucket,
+        key_prefix
+    );
+    Ok(ShareResult {
+        manifest,
+        content,
+        manifest_key: upload_key,
+        manifest_url,
+    })
+}
+
+/// Copy keys to the system clipboard in the chosen format, one per line,
+/// presigning URLs on demand. Returns the copied text.
+#[tauri::command]
+pub async fn copy_to_clipboard(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    keys: Vec<String>,
+    format: ClipboardFormat,
+    expires_in_secs: Option<u64>,
+) -> AppResult<String> {
+    debug!(
+        "Copying {} keys from '{}' to the clipboard as {:?}",
+        keys.len(),
+        bucket,
+        format
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let mut lines = Vec::with_capacity(keys.len());
+    for key in &keys {
+        let line = match format {
+            ClipboardFormat::Key => key.clone(),
+            ClipboardFormat::S3Uri => {
+                let real_key = S3Service::scoped_key(&connection, key)?;
+                format!("s3://{}/{}", bucket, real_key)
+            }
+            ClipboardFormat::PublicUrl => S3Service::public_url(&connection, &bucket, key)?,
+            ClipboardFormat::PresignedUrl => {
+                let expires = expires_in_secs.unwrap_or(3600);
+                S3Service::get_presigned_url(&connection, &bucket, key, expires, None).await?
+            }
+        };
+        lines.push(line);
+    }
+
+    let text = lines.join("\n");
+    app.clipboard()
+        .write_text(text.as_str())
+        .map_err(|e| AppError::NotSupported(format!("clipboard unavailable: {}", e)))?;
+
+    Ok(text)
+}
+
+/// Give the frontend something its media player can load directly. Prefers
+/// a presigned URL with a playable content type; falls back to downloading
+/// the head of the object into a temp file.
+#[tauri::command]
+pub async fn get_streaming_url(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    expires_in_secs: Option<u64>,
+) -> AppResult<StreamingSource> {
+    debug!("Preparing streaming source for '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let object = S3Service::get_object_details(&operator, &key).await?;
+
+    // Buckets often hold media as application/octet-stream, which players
+    // refuse, so fall back to the extension
+    let content_type = object
+        .content_type
+        .clone()
+        .filter(|t| !t.is_empty() && !t.starts_with("application/octet-stream"))
+        .unwrap_or_else(|| {
+            mime_guess::from_path(&key)
+                .first_or_octet_stream()
+                .essence_str()
+                .to_string()
+        });
+
+    let presigned = match S3Service::presign_unavailable(&connection) {
+        Some(reason) => {
+            debug!("Not presigning '{}/{}': {}", bucket, key, reason);
+            None
+        }
+        None => {
+            let expires = expires_in_secs.unwrap_or(3600);
+            match S3Service::get_presigned_url(
+                &connection,
+                &bucket,
+                &key,
+                expires,
+                Some(&content_type),
+            )
+            .await
+            {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    warn!("Failed to presign '{}/{}': {}", bucket, key, e);
+                    None
+                }
+            }
+        }
+    };
+
+    if let Some(url) = presigned {
+        let supports_range = if object.size > 0 {
+            S3Service::supports_range(&connection, &bucket, &key).await?
+        } else {
+            false
+        };
+        if !supports_range {
+            warn!(
+                "'{}/{}' doesn't support range requests; seeking won't work",
+                bucket, key
             );
-            Err(e)
         }
+
+        return Ok(StreamingSource {
+            key,
+            url: Some(url),
+            local_path: None,
+            content_type,
+            size: object.size,
+            supports_range,
+            partial: false,
+        });
+    }
+
+    let limit = state.settings.lock().await.effective_preview_limits().media;
+    let length = object.size.min(limit);
+
+    let dir = LocalService::temp_dir("stream").join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&dir).await?;
+    state.track_temp_dir(dir.clone());
+    let local_path = dir.join(LocalService::temp_file_name(&key));
+    if length > 0 {
+        S3Service::download_head_to_file(&operator, &key, length, &local_path).await?;
+    } else {
+        fs::write(&local_path, Vec::<u8>::new()).await?;
     }
+
+    info!(
+        "Downloaded {} of {} bytes of '{}/{}' for local playback",
+        length, object.size, bucket, key
+    );
+
+    Ok(StreamingSource {
+        key,
+        url: None,
+        local_path: Some(local_path.to_string_lossy().to_string()),
+        content_type,
+        size: object.size,
+        supports_range: true,
+        partial: length < object.size,
+    })
 }
 
+/// Tell the frontend which preview mode suits an object and how much of it
+/// to fetch, based on size, content type and its first bytes.
 #[tauri::command]
-pub async fn get_object_metadata(
+pub async fn get_preview_plan(
     state: State<'_, AppState>,
     connection_id: String,
     bucket: String,
     key: String,
-) -> AppResult<ObjectMetadata> {
-    debug!("Getting metadata for '{}/{}'", bucket, key);
+) -> AppResult<PreviewPlan> {
+    debug!("Planning preview for '{}/{}'", bucket, key);
 
-    let connections = state.connections.lock().await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
 
-    let connection = connections
-        .get(&connection_id)
-        .ok_or_else(|| AppError::ConnectionNotFound(connection_id))?
-        .clone();
+    let object = S3Service::get_object_details(&operator, &key).await?;
+    let head = if object.size > 0 {
+        S3Service::read_range(&operator, &key, 0..object.size.min(SNIFF_BYTES)).await?
+    } else {
+        Vec::new()
+    };
 
-    drop(connections);
+    let limits = state.settings.lock().await.effective_preview_limits();
+    let plan = PreviewService::plan(&object, &head, &limits);
 
-    match S3Service::get_object_metadata(&connection, &bucket, &key).await {
-        Ok(metadata) => {
-            debug!("Retrieved metadata for '{}/{}'", bucket, key);
-            Ok(metadata)
+    debug!(
+        "Preview plan for '{}/{}': {:?} ({} bytes)",
+        bucket, key, plan.mode, plan.max_bytes
+    );
+    Ok(plan)
+}
+
+/// Render the first page of a PDF or office document to an image. Linearized
+/// PDFs are rendered from just their first-page section; anything else is
+/// downloaded in full, up to the document preview limit.
+#[tauri::command]
+pub async fn preview_document(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    width: Option<u32>,
+) -> AppResult<DocumentPreview> {
+    debug!("Rendering document preview for '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let object = S3Service::get_object_details(&operator, &key).await?;
+    let limit = state
+        .settings
+        .lock()
+        .await
+        .effective_preview_limits()
+        .document;
+    let is_office = PreviewService::is_office_document(&key);
+
+    let head = S3Service::read_range(&operator, &key, 0..object.size.min(1024)).await?;
+    if !is_office && PreviewService::sniff(&head) != Some("application/pdf") {
+        return Err(AppError::NotSupported(format!(
+            "'{}' is not a PDF or office document",
+            key
+        )));
+    }
+
+    let first_page_end = if is_office {
+        None
+    } else {
+        PreviewService::linearized_first_page_end(&head).filter(|end| *end < object.size)
+    };
+    let length = match first_page_end {
+        Some(end) => end,
+        None if object.size <= limit => object.size,
+        None => {
+            return Err(AppError::NotSupported(format!(
+                "'{}' is {} bytes, over the {} byte document preview limit",
+                key, object.size, limit
+            )))
         }
-        Err(e) => {
-            error!("Failed to get metadata for '{}/{}': {}", bucket, key, e);
-            Err(e)
+    };
+
+    let data = S3Service::read_range(&operator, &key, 0..length).await?;
+
+    let file_name = LocalService::temp_file_name(&key);
+    let dir = LocalService::temp_dir("documents").join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&dir).await?;
+
+    let width = width.unwrap_or(800);
+    let rendered = async {
+        let mut path = dir.join(file_name);
+        fs::write(&path, &data).await?;
+        if is_office {
+            path = PreviewService::convert_to_pdf(&path, &dir).await?;
         }
+        PreviewService::render_first_page(&path, width).await
+    }
+    .await;
+
+    if let Err(e) = fs::remove_dir_all(&dir).await {
+        warn!("Failed to clean up document preview {:?}: {}", dir, e);
+    }
+
+    let image = rendered?;
+    debug!(
+        "Rendered '{}/{}' from {} of {} bytes",
+        bucket, key, length, object.size
+    );
+
+    Ok(DocumentPreview {
+        key,
+        image: BASE64.encode(image),
+        width,
+        bytes_read: length,
+        size: object.size,
+    })
+}
+
+/// Rows sampled from each table by `preview_sqlite`.
+const SQLITE_SAMPLE_ROWS: usize = 20;
+
+/// Download a SQLite database to a temp file and list its tables with a few
+/// sample rows each.
+#[tauri::command]
+pub async fn preview_sqlite(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+) -> AppResult<SqlitePreview> {
+    debug!("Previewing SQLite database '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let object = S3Service::get_object_details(&operator, &key).await?;
+    let limit = state
+        .settings
+        .lock()
+        .await
+        .effective_preview_limits()
+        .sqlite;
+    if object.size > limit {
+        return Err(AppError::NotSupported(format!(
+            "'{}' is {} bytes, over the {} byte database preview limit",
+            key, object.size, limit
+        )));
+    }
+
+    let data = S3Service::download_object(&operator, &key).await?;
+
+    let dir = LocalService::temp_dir("sqlite").join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&dir).await?;
+    let path = dir.join("preview.db");
+    fs::write(&path, &data).await?;
+
+    let inspect_path = path.clone();
+    let tables = tokio::task::spawn_blocking(move || {
+        PreviewService::inspect_sqlite(&inspect_path, SQLITE_SAMPLE_ROWS)
+    })
+    .await
+    .map_err(|e| AppError::PreviewError(e.to_string()))?;
+
+    if let Err(e) = fs::remove_dir_all(&dir).await {
+        warn!("Failed to clean up database preview {:?}: {}", dir, e);
     }
+
+    let tables = tables?;
+    debug!("'{}/{}' has {} tables and views", bucket, key, tables.len());
+
+    Ok(SqlitePreview {
+        key,
+        size: object.size,
+        tables,
+    })
+}
+
+/// Parse a JSON, YAML or TOML object and return a normalized pretty form
+/// with basic structure info, or the line where parsing failed. `format`
+/// overrides detection from the key and content type.
+#[tauri::command]
+pub async fn preview_config_object(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    format: Option<ConfigFormat>,
+) -> AppResult<ConfigPreview> {
+    debug!("Previewing config object '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let object = S3Service::get_object_details(&operator, &key).await?;
+    let format = format
+        .or_else(|| PreviewService::config_format(&key, object.content_type.as_deref()))
+        .ok_or_else(|| {
+            AppError::NotSupported(format!("can't tell the config format of '{}'", key))
+        })?;
+
+    let limit = state
+        .settings
+        .lock()
+        .await
+        .effective_preview_limits()
+        .structured;
+    let text = S3Service::get_object_content_as_text(&operator, &key, limit).await?;
+
+    let preview = PreviewService::parse_config(key, format, &text);
+    if let Some(error) = &preview.error {
+        debug!(
+            "'{}/{}' failed to parse at line {:?}: {}",
+            bucket, preview.key, error.line, error.message
+        );
+    }
+    Ok(preview)
+}
+
+#[tauri::command]
+pub async fn get_object_text(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    max_size: Option<u64>,
+) -> AppResult<String> {
+    let max = max_size.unwrap_or(1024 * 1024);
+    debug!(
+        "Reading text content from '{}/{}' (max {})",
+        bucket, key, max
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    match S3Service::get_object_content_as_text(&operator, &key, max).await {
+        Ok(text) => {
+            debug!(
+                "Read {} characters of text from '{}/{}'",
+                text.len(),
+                bucket,
+                key
+            );
+            Ok(text)
+        }
+        Err(e) => {
+            warn!("Failed to read text from '{}/{}': {}", bucket, key, e);
+            Err(e)
+        }
+    }
+}
+
+/// Copy one object, returning the key it was written to, or `None` when
+/// `collision` left an existing object in place.
+#[tauri::command]
+pub async fn copy_object(
+    state: State<'_, AppState>,
+    connection_id: String,
+    source_bucket: String,
+    source_key: String,
+    dest_bucket: String,
+    dest_key: String,
+    collision: Option<CollisionStrategy>,
+) -> AppResult<Option<String>> {
+    let collision = collision.unwrap_or_default();
+    info!(
+        "Copying '{}/{}' to '{}/{}'",
+        source_bucket, source_key, dest_bucket, dest_key
+    );
+
+    let connection = state.bucket_connection(&connection_id, &dest_bucket).await?;
+
+    let (dest_key, size) = if collision == CollisionStrategy::Overwrite {
+        (dest_key, None)
+    } else {
+        let source_connection = state
+            .bucket_connection(&connection_id, &source_bucket)
+            .await?;
+        let source =
+            S3Service::get_object_metadata(&source_connection, &source_bucket, &source_key).await?;
+        let incoming = Incoming {
+            size: source.size,
+            modified: source.last_modified.unwrap_or(0),
+            digest: source.etag.as_deref(),
+        };
+        let placement = collision_candidates(&connection, &dest_bucket, &dest_key)
+            .await?
+            .place(collision, &dest_key, incoming)
+            .inspect_err(|e| error!("Failed to copy '{}/{}': {}", source_bucket, source_key, e))?;
+        match placement {
+            Placement::Write(key) => (key, Some(source.size)),
+            Placement::Skip => {
+                info!(
+                    "Not copying '{}/{}', '{}/{}' already exists",
+                    source_bucket, source_key, dest_bucket, dest_key
+                );
+                return Ok(None);
+            }
+        }
+    };
+
+    match S3Service::copy_object(
+        &connection,
+        &source_bucket,
+        &source_key,
+        size,
+        &dest_bucket,
+        &dest_key,
+    )
+    .await
+    {
+        Ok(()) => {
+            info!(
+                "Successfully copied '{}/{}' to '{}/{}'",
+                source_bucket, source_key, dest_bucket, dest_key
+            );
+            Ok(Some(dest_key))
+        }
+        Err(e) => {
+            error!(
+                "Failed to copy '{}/{}' to '{}/{}': {}",
+                source_bucket, source_key, dest_bucket, dest_key, e
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Copy (or move, with `remove_source`) a selection of objects into a
+/// destination prefix, keeping each object's file name unless `collision`
+/// picks another. Objects it leaves in place are reported as skipped.
+#[tauri::command]
+pub async fn copy_objects(
+    state: State<'_, AppState>,
+    connection_id: String,
+    source_bucket: String,
+    keys: Vec<String>,
+    dest_bucket: String,
+    dest_prefix: String,
+    remove_source: Option<bool>,
+    collision: Option<CollisionStrategy>,
+) -> AppResult<BatchResult> {
+    let remove_source = remove_source.unwrap_or(false);
+    let collision = collision.unwrap_or_default();
+    info!(
+        "{} {} objects from '{}' to '{}/{}'",
+        if remove_source { "Moving" } else { "Copying" },
+        keys.len(),
+        source_bucket,
+        dest_bucket,
+        dest_prefix
+    );
+
+    let connection = state.bucket_connection(&connection_id, &dest_bucket).await?;
+    let source_connection = state.bucket_connection(&connection_id, &source_bucket).await?;
+    let source_operator = if remove_source {
+        Some(S3Service::create_operator(&source_connection, &source_bucket)?)
+    } else {
+        None
+    };
+
+    let dest_prefix = if dest_prefix.is_empty() || dest_prefix.ends_with('/') {
+        dest_prefix
+    } else {
+        format!("{}/", dest_prefix)
+    };
+
+    // Every destination is directly under `dest_prefix`, so one listing
+    // of that level covers them all
+    let mut destinations = DestinationKeys::default();
+    if collision != CollisionStrategy::Overwrite {
+        destinations
+            .extend(S3Service::list_siblings(&connection, &dest_bucket, &dest_prefix).await?);
+    }
+
+    let mut result = BatchResult::default();
+    for key in keys {
+        let name = key.trim_end_matches('/').rsplit('/').next().unwrap_or(&key);
+        let dest_key = format!("{}{}", dest_prefix, name);
+
+        if source_bucket == dest_bucket && key == dest_key {
+            debug!("Skipping '{}', source and destination are the same", key);
+            result.skip(key);
+            continue;
+        }
+
+        let (dest_key, size) =
+            if collision == CollisionStrategy::Overwrite {
+                (dest_key, None)
+            } else {
+                let placed =
+                    match S3Service::get_object_metadata(&source_connection, &source_bucket, &key)
+                        .await
+                    {
+                        Ok(source) => {
+                            let incoming = Incoming {
+                                size: source.size,
+                                modified: source.last_modified.unwrap_or(0),
+                                digest: source.etag.as_deref(),
+                            };
+                            destinations
+                                .place(collision, &dest_key, incoming)
+                                .map(|placement| (placement, source.size))
+                        }
+                        Err(e) => Err(e),
+                    };
+                match placed {
+                    Ok((Placement::Write(dest_key), size)) => (dest_key, Some(size)),
+                    Ok((Placement::Skip, _)) => {
+                        debug!("Skipping '{}', '{}' already exists", key, dest_key);
+                        result.skip(key);
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Failed to copy '{}/{}': {}", source_bucket, key, e);
+                        result.fail(key, &e);
+                        continue;
+                    }
+                }
+            };
+
+        let copied =
+            S3Service::copy_object(&connection, &source_bucket, &key, size, &dest_bucket, &dest_key)
+                .await;
+        let outcome = match (copied, &source_operator) {
+            (Ok(()), Some(operator)) => {
+                S3Service::throttle(&connection, RequestKind::Delete).await;
+                S3Service::delete_object(operator, &key).await
+            }
+            (copied, _) => copied,
+        };
+
+        match outcome {
+            Ok(()) => {
+                debug!("Copied '{}/{}' to '{}/{}'", source_bucket, key, dest_bucket, dest_key);
+                result.succeed(key);
+            }
+            Err(e) => {
+                error!("Failed to copy '{}/{}': {}", source_bucket, key, e);
+                result.fail(key, &e);
+            }
+        }
+    }
+
+    info!(
+        "Copied {} objects, {} failed, {} skipped",
+        result.succeeded.len(),
+        result.failed.len(),
+        result.skipped.len()
+    );
+    Ok(result)
+}
+
+/// Keys already under `prefix` in `bucket`, added a listing page at a time.
+async fn destination_keys(
+    client: &S3Client,
+    connection: &S3ConnectionWithSecret,
+    bucket: &str,
+    prefix: &str,
+) -> AppResult<DestinationKeys> {
+    let mut pages = std::pin::pin!(S3Service::list_object_pages(
+        client, connection, bucket, prefix
+    )?);
+    let mut keys = DestinationKeys::default();
+    while let Some(page) = pages.try_next().await? {
+        keys.extend(page);
+    }
+    Ok(keys)
+}
+
+/// Objects beside `key` sharing its stem, which is all a collision strategy
+/// needs to place a single object at `key`.
+async fn collision_candidates(
+    connection: &S3ConnectionWithSecret,
+    bucket: &str,
+    key: &str,
+) -> AppResult<DestinationKeys> {
+    let mut keys = DestinationKeys::default();
+    keys.extend(S3Service::list_siblings(connection, bucket, DestinationKeys::stem(key)).await?);
+    Ok(keys)
+}
+
+/// Recursively copy everything under `source_prefix` to `dest_prefix`,
+/// within or across buckets, keeping each key's path relative to the
+/// prefix. Folder markers are copied too so empty folders survive. With
+/// `dry_run` nothing is copied and the summary lists what would be.
+/// Reports `copy-progress` and can be cancelled.
+#[tauri::command]
+pub async fn copy_prefix(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    source_bucket: String,
+    source_prefix: String,
+    dest_bucket: String,
+    dest_prefix: String,
+    overwrite: Option<CollisionStrategy>,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+) -> AppResult<CopyPrefixSummary> {
+    transfer_prefix(
+        app,
+        state,
+        connection_id,
+        (source_bucket, source_prefix),
+        (dest_bucket, dest_prefix),
+        overwrite,
+        dry_run,
+        concurrency,
+        false,
+    )
+    .await
+}
+
+/// `copy_prefix`, deleting each source object once its copy has landed.
+/// Sources whose copy failed or was skipped stay where they are.
+#[tauri::command]
+pub async fn move_prefix(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    source_bucket: String,
+    source_prefix: String,
+    dest_bucket: String,
+    dest_prefix: String,
+    overwrite: Option<CollisionStrategy>,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+) -> AppResult<CopyPrefixSummary> {
+    transfer_prefix(
+        app,
+        state,
+        connection_id,
+        (source_bucket, source_prefix),
+        (dest_bucket, dest_prefix),
+        overwrite,
+        dry_run,
+        concurrency,
+        true,
+    )
+    .await
+}
+
+async fn transfer_prefix(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    (source_bucket, source_prefix): (String, String),
+    (dest_bucket, dest_prefix): (String, String),
+    overwrite: Option<CollisionStrategy>,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+    delete_sources: bool,
+) -> AppResult<CopyPrefixSummary> {
+    let verb = if delete_sources { "Moving" } else { "Copying" };
+    let overwrite = overwrite.unwrap_or_default();
+    let dry_run = dry_run.unwrap_or(false);
+    let folder = |prefix: String| {
+        if prefix.is_empty() || prefix.ends_with('/') {
+            prefix
+        } else {
+            format!("{}/", prefix)
+        }
+    };
+    let source_prefix = folder(source_prefix);
+    let dest_prefix = folder(dest_prefix);
+    info!(
+        "{} '{}/{}' to '{}/{}' ({:?} on collision{})",
+        verb,
+        source_bucket,
+        source_prefix,
+        dest_bucket,
+        dest_prefix,
+        overwrite,
+        if dry_run { ", dry run" } else { "" }
+    );
+
+    if source_bucket == dest_bucket && source_prefix == dest_prefix {
+        return Err(AppError::ConfigError(
+            "Source and destination prefixes are the same".into(),
+        ));
+    }
+
+    let source_connection = state
+        .bucket_connection(&connection_id, &source_bucket)
+        .await?;
+    let connection = state
+        .bucket_connection(&connection_id, &dest_bucket)
+        .await?;
+
+    // Copying into a subfolder of the source mustn't pick up what an
+    // earlier copy put there
+    let nested = source_bucket == dest_bucket && dest_prefix.starts_with(&source_prefix);
+    let source_client = S3Service::create_s3_client(&source_connection).await;
+    let client = S3Service::create_s3_client(&connection).await;
+
+    let mut destinations = if overwrite == CollisionStrategy::Overwrite {
+        DestinationKeys::default()
+    } else {
+        destination_keys(&client, &connection, &dest_bucket, &dest_prefix).await?
+    };
+
+    // Plan each page of sources as it arrives rather than listing them all
+    let mut result = BatchResult::default();
+    let mut copies = Vec::new();
+    let mut sources = std::pin::pin!(S3Service::list_object_pages(
+        &source_client,
+        &source_connection,
+        &source_bucket,
+        &source_prefix
+    )?
+    .map_ok(|page| stream::iter(page.into_iter().map(Ok::<_, AppError>)))
+    .try_flatten());
+    while let Some(object) = sources.try_next().await? {
+        if nested && object.key.starts_with(&dest_prefix) {
+            continue;
+        }
+        let relative = object
+            .key
+            .strip_prefix(&source_prefix)
+            .unwrap_or(&object.key);
+        let dest_key = format!("{}{}", dest_prefix, relative);
+
+        let incoming = Incoming {
+            size: object.size,
+            modified: object.last_modified,
+            digest: object.etag.as_deref(),
+        };
+        let dest_key = match destinations.place(overwrite, &dest_key, incoming) {
+            Ok(Placement::Write(key)) => key,
+            Ok(Placement::Skip) => {
+                debug!("Skipping '{}', '{}' already exists", object.key, dest_key);
+                result.skip(object.key);
+                continue;
+            }
+            Err(e) => {
+                warn!("Refusing to overwrite '{}/{}'", dest_bucket, dest_key);
+                return Err(e);
+            }
+        };
+
+        copies.push(PlannedCopy {
+            source_key: object.key,
+            dest_key,
+            size: object.size,
+        });
+    }
+    let total_bytes = copies.iter().map(|c| c.size).sum();
+
+    if dry_run {
+        info!(
+            "Would {} {} objects ({} bytes), {} skipped",
+            if delete_sources { "move" } else { "copy" },
+            copies.len(),
+            total_bytes,
+            result.skipped.len()
+        );
+        return Ok(CopyPrefixSummary {
+            copies,
+            result,
+            total_bytes,
+            dry_run,
+            moved: delete_sources,
+        });
+    }
+
+    let source_operator = S3Service::create_operator(&source_connection, &source_bucket)?;
+    let job = state
+        .start_job(
+            if delete_sources {
+                "move_prefix"
+            } else {
+                "copy_prefix"
+            },
+            &dest_bucket,
+        )
+        .await;
+    let total = copies.len() as u64;
+    let mut bytes_copied = 0;
+
+    let mut outcomes = stream::iter(&copies)
+        .map(|copy| {
+            let connection = &connection;
+            let source_connection = &source_connection;
+            let source_operator = &source_operator;
+            let source_bucket = &source_bucket;
+            let dest_bucket = &dest_bucket;
+            let cancelled = job.cancelled();
+            async move {
+                if cancelled.load(Ordering::Relaxed) {
+                    return (copy, None);
+                }
+                let copied = S3Service::copy_object(
+                    connection,
+                    source_bucket,
+                    &copy.source_key,
+                    Some(copy.size),
+                    dest_bucket,
+                    &copy.dest_key,
+                )
+                .await;
+                let outcome = match copied {
+                    Ok(()) if delete_sources => {
+                        S3Service::throttle(source_connection, RequestKind::Delete).await;
+                        S3Service::delete_object(source_operator, &copy.source_key).await
+                    }
+                    copied => copied,
+                };
+                (copy, Some(outcome))
+            }
+        })
+        .buffer_unordered(TransferTuner::concurrency(
+            concurrency.unwrap_or(COPY_CONCURRENCY),
+        ));
+
+    while let Some((copy, outcome)) = outcomes.next().await {
+        match outcome {
+            None => result.skip(copy.source_key.clone()),
+            Some(Ok(())) => {
+                debug!(
+                    "{} '{}/{}' to '{}/{}'",
+                    if delete_sources { "Moved" } else { "Copied" },
+                    source_bucket,
+                    copy.source_key,
+                    dest_bucket,
+                    copy.dest_key
+                );
+                bytes_copied += copy.size;
+                result.succeed(copy.source_key.clone());
+            }
+            Some(Err(e)) => {
+                error!(
+                    "Failed to {} '{}/{}': {}",
+                    if delete_sources { "move" } else { "copy" },
+                    source_bucket,
+                    copy.source_key,
+                    e
+                );
+                result.fail(copy.source_key.clone(), &e);
+            }
+        }
+
+        let _ = app.emit(
+            "copy-progress",
+            CopyProgress {
+                job_id: job.id.clone(),
+                copied: result.succeeded.len() as u64,
+                failed: result.failed.len() as u64,
+                total,
+                bytes_copied,
+                total_bytes,
+            },
+        );
+    }
+    drop(outcomes);
+
+    info!(
+        "{} {} objects to '{}/{}', {} failed, {} skipped",
+        if delete_sources { "Moved" } else { "Copied" },
+        result.succeeded.len(),
+        dest_bucket,
+        dest_prefix,
+        result.failed.len(),
+        result.skipped.len()
+    );
+    job.finish(result.succeeded.len() as u64, result.failed.len() as u64);
+    Ok(CopyPrefixSummary {
+        copies,
+        result,
+        total_bytes,
+        dry_run,
+        moved: delete_sources,
+    })
+}
+
+/// Rename an object by copying it to `new_key` and deleting the original.
+/// `collision` decides what happens when `new_key` is taken. Returns the key
+/// the object ended up under, or `None` when it was left where it was.
+#[tauri::command]
+pub async fn rename_object(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    old_key: String,
+    new_key: String,
+    collision: Option<CollisionStrategy>,
+) -> AppResult<Option<String>> {
+    let collision = collision.unwrap_or_default();
+    info!(
+        "Renaming '{}/{}' to '{}/{}'",
+        bucket, old_key, bucket, new_key
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let new_key = if collision == CollisionStrategy::Overwrite {
+        new_key
+    } else {
+        let source = S3Service::get_object_metadata(&connection, &bucket, &old_key).await?;
+        let incoming = Incoming {
+            size: source.size,
+            modified: source.last_modified.unwrap_or(0),
+            digest: source.etag.as_deref(),
+        };
+        let placement = collision_candidates(&connection, &bucket, &new_key)
+            .await?
+            .place(collision, &new_key, incoming)
+            .inspect_err(|e| error!("Failed to rename '{}/{}': {}", bucket, old_key, e))?;
+        match placement {
+            Placement::Write(key) => key,
+            Placement::Skip => {
+                info!(
+                    "Leaving '{}/{}' in place, '{}' already exists",
+                    bucket, old_key, new_key
+                );
+                return Ok(None);
+            }
+        }
+    };
+
+    match S3Service::rename_object(&connection, &bucket, &old_key, &new_key).await {
+        Ok(()) => {
+            info!(
+                "Successfully renamed '{}/{}' to '{}/{}'",
+                bucket, old_key, bucket, new_key
+            );
+            Ok(Some(new_key))
+        }
+        Err(e) => {
+            error!(
+                "Failed to rename '{}/{}' to '{}': {}",
+                bucket, old_key, new_key, e
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Rename a folder by moving every key under `old_prefix` to `new_prefix`
+/// in the same bucket. Refuses to overwrite existing keys unless told
+/// otherwise. Reports `copy-progress` and supports `dry_run`.
+#[tauri::command]
+pub async fn rename_prefix(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    old_prefix: String,
+    new_prefix: String,
+    overwrite: Option<CollisionStrategy>,
+    dry_run: Option<bool>,
+    concurrency: Option<usize>,
+) -> AppResult<CopyPrefixSummary> {
+    if old_prefix.trim_matches('/').is_empty() || new_prefix.trim_matches('/').is_empty() {
+        return Err(AppError::ConfigError(
+            "Cannot rename to or from the bucket root".into(),
+        ));
+    }
+
+    transfer_prefix(
+        app,
+        state,
+        connection_id,
+        (bucket.clone(), old_prefix),
+        (bucket, new_prefix),
+        Some(overwrite.unwrap_or(CollisionStrategy::Fail)),
+        dry_run,
+        concurrency,
+        true,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn get_object_metadata(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+) -> AppResult<ObjectMetadata> {
+    debug!("Getting metadata for '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    match S3Service::get_object_metadata(&connection, &bucket, &key).await {
+        Ok(metadata) => {
+            debug!("Retrieved metadata for '{}/{}'", bucket, key);
+            Ok(metadata)
+        }
+        Err(e) => {
+            error!("Failed to get metadata for '{}/{}': {}", bucket, key, e);
+            Err(e)
+        }
+    }
+}
+
+/// Lighter details for the details pane: size, checksum, part count and
+/// storage class, via HEAD when the provider lacks GetObjectAttributes.
+#[tauri::command]
+pub async fn get_object_attributes(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+) -> AppResult<ObjectAttributes> {
+    debug!("Getting attributes of '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    match S3Service::get_object_attributes(&connection, &bucket, &key).await {
+        Ok(attributes) => {
+            debug!(
+                "Retrieved attributes of '{}/{}' (via HEAD: {})",
+                bucket, key, attributes.from_head
+            );
+            Ok(attributes)
+        }
+        Err(e) => {
+            error!("Failed to get attributes of '{}/{}': {}", bucket, key, e);
+            Err(e)
+        }
+    }
+}
+
+/// Change the Content-Type, Cache-Control, Content-Disposition or custom
+/// metadata of an existing object, returning its metadata afterwards.
+#[tauri::command]
+pub async fn update_object_metadata(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    update: MetadataUpdate,
+) -> AppResult<ObjectMetadata> {
+    info!("Updating metadata of '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    match S3Service::update_object_metadata(&connection, &bucket, &key, &update).await {
+        Ok(metadata) => {
+            info!("Updated metadata of '{}/{}'", bucket, key);
+            Ok(metadata)
+        }
+        Err(e) => {
+            error!("Failed to update metadata of '{}/{}': {}", bucket, key, e);
+            Err(e)
+        }
+    }
+}
+
+/// HEAD a page of keys in the background and emit an `object-details` event
+/// for each one as it arrives, so the listing can fill in lazily.
+#[tauri::command]
+pub async fn prefetch_object_details(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    keys: Vec<String>,
+    concurrency: Option<usize>,
+) -> AppResult<()> {
+    let concurrency = concurrency.unwrap_or(8).max(1);
+    debug!(
+        "Prefetching details for {} objects in '{}' (concurrency: {})",
+        keys.len(),
+        bucket,
+        concurrency
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    tauri::async_runtime::spawn(async move {
+        stream::iter(keys)
+            .map(|key| {
+                let connection = &connection;
+                let bucket = &bucket;
+                async move {
+                    let result = S3Service::get_object_metadata(connection, bucket, &key).await;
+                    (key, result)
+                }
+            })
+            .buffer_unordered(concurrency)
+            .for_each(|(key, result)| {
+                match result {
+                    Ok(metadata) => {
+                        let _ = app.emit("object-details", metadata);
+                    }
+                    Err(e) => {
+                        // Usually the object was removed since the listing; not worth surfacing
+                        debug!("Failed to prefetch details for '{}/{}': {}", bucket, key, e);
+                    }
+                }
+                async {}
+            })
+            .await;
+    });
+
+    Ok(())
+}
+
+/// Bounded size check for a prefix, for bulk-operation confirmations.
+#[tauri::command]
+pub async fn estimate_prefix(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    max_objects: Option<u64>,
+) -> AppResult<PrefixEstimate> {
+    let max_objects = max_objects.unwrap_or(10_000);
+    debug!(
+        "Estimating size of '{}/{}' (up to {} objects)",
+        bucket, prefix, max_objects
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    match S3Service::estimate_prefix(&connection, &bucket, &prefix, max_objects).await {
+        Ok(estimate) => {
+            debug!(
+                "'{}/{}': {}{} objects, {} bytes",
+                bucket,
+                prefix,
+                if estimate.is_complete { "" } else { ">" },
+                estimate.object_count,
+                estimate.total_size
+            );
+            Ok(estimate)
+        }
+        Err(e) => {
+            error!("Failed to estimate '{}/{}': {}", bucket, prefix, e);
+            Err(e)
+        }
+    }
+}
+
+/// Aggregate stats for the current selection. Entries ending in `/` are
+/// prefixes and count every object beneath them; an object selected both
+/// directly and through its prefix is counted once.
+#[tauri::command]
+pub async fn summarize_selection(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    keys: Vec<String>,
+) -> AppResult<SelectionSummary> {
+    debug!(
+        "Summarizing selection of {} entries in '{}'",
+        keys.len(),
+        bucket
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+    let mut budget = state.settings.lock().await.scan_limits.start();
+
+    let (prefixes, objects): (Vec<String>, Vec<String>) =
+        keys.into_iter().partition(|key| key.ends_with('/'));
+
+    let mut seen = HashSet::new();
+    let mut summary = SelectionSummary::default();
+
+    // One budget covers every prefix, so later ones are skipped once it runs out
+    for prefix in &prefixes {
+        if budget.exhausted().is_some() {
+            break;
+        }
+        let listing =
+            S3Service::list_objects_within(&connection, &bucket, prefix, &mut budget).await?;
+        for object in listing.iter().filter(|o| !o.is_directory) {
+            if seen.insert(object.key.clone()) {
+                summary.add(object);
+            }
+        }
+    }
+    summary.stopped_early = budget.cutoff();
+
+    let objects: Vec<String> = objects.into_iter().filter(|key| !seen.contains(key)).collect();
+    let details: Vec<AppResult<S3Object>> = stream::iter(objects)
+        .map(|key| {
+            let operator = operator.clone();
+            async move { S3Service::get_object_details(&operator, &key).await }
+        })
+        .buffer_unordered(8)
+        .collect()
+        .await;
+
+    for object in details {
+        let object = object?;
+        if seen.insert(object.key.clone()) {
+            summary.add(&object);
+        }
+    }
+
+    debug!(
+        "Selection in '{}': {} objects, {} bytes",
+        bucket, summary.object_count, summary.total_size
+    );
+    Ok(summary)
+}
+
+/// Activity feed for the connection home screen: what changed under each of
+/// `prefixes` in the last `hours` (default 24), capped at `limit` entries.
+#[tauri::command]
+pub async fn get_recent_activity(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefixes: Vec<String>,
+    hours: Option<u64>,
+    limit: Option<usize>,
+) -> AppResult<RecentActivity> {
+    let hours = hours.unwrap_or(24);
+    let limit = limit.unwrap_or(50);
+    debug!(
+        "Collecting activity in '{}' for {} prefixes over {}h",
+        bucket,
+        prefixes.len(),
+        hours
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let limits = state.settings.lock().await.scan_limits;
+    let since = chrono::Utc::now().timestamp() - (hours as i64) * 3600;
+
+    let results: Vec<_> = stream::iter(prefixes)
+        .map(|prefix| {
+            let connection = &connection;
+            let bucket = &bucket;
+            async move {
+                let mut budget = limits.start();
+                let changed =
+                    S3Service::list_modified_since(connection, bucket, &prefix, since, &mut budget)
+                        .await;
+                (prefix, changed, budget.cutoff())
+            }
+        })
+        .buffered(4)
+        .collect()
+        .await;
+
+    let mut activity = RecentActivity {
+        since,
+        prefixes: Vec::with_capacity(results.len()),
+        feed: Vec::new(),
+    };
+
+    for (prefix, changed, stopped_early) in results {
+        let changed = match changed {
+            Ok(changed) => changed,
+            Err(e) => {
+                error!("Failed to list activity under '{}/{}': {}", bucket, prefix, e);
+                return Err(e);
+            }
+        };
+
+        activity.prefixes.push(PrefixActivity {
+            prefix,
+            changed_count: changed.len() as u64,
+            changed_bytes: changed.iter().map(|o| o.size).sum(),
+            latest_modified: changed.first().map(|o| o.last_modified),
+            stopped_early,
+        });
+        activity.feed.extend(changed);
+    }
+
+    // Overlapping prefixes would list the same object twice
+    activity
+        .feed
+        .sort_by(|a, b| b.last_modified.cmp(&a.last_modified).then(a.key.cmp(&b.key)));
+    activity.feed.dedup_by(|a, b| a.key == b.key);
+    activity.feed.truncate(limit);
+
+    debug!("Found {} recent changes in '{}'", activity.feed.len(), bucket);
+    Ok(activity)
+}
+
+#[tauri::command]
+pub async fn get_object_history(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+) -> AppResult<Vec<ObjectVersion>> {
+    debug!("Getting version history for '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    match S3Service::get_object_history(&connection, &bucket, &key).await {
+        Ok(history) => {
+            debug!("Found {} versions of '{}/{}'", history.len(), bucket, key);
+            Ok(history)
+        }
+        Err(e) => {
+            error!("Failed to get version history for '{}/{}': {}", bucket, key, e);
+            Err(e)
+        }
+    }
+}
+
+/// Browse versions in a versioned bucket one page at a time, folder by
+/// folder. Pass the returned markers back to get the next page.
+#[tauri::command]
+pub async fn list_object_versions(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    key_marker: Option<String>,
+    version_id_marker: Option<String>,
+    max_keys: Option<u32>,
+) -> AppResult<ListObjectVersionsResult> {
+    debug!("Listing object versions under '{}/{}'", bucket, prefix);
+
+    let (_, page_size) = listing_options(&state, max_keys).await;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    match S3Service::list_object_versions(
+        &connection,
+        &bucket,
+        &prefix,
+        key_marker,
+        version_id_marker,
+        page_size.min(1000) as i32,
+    )
+    .await
+    {
+        Ok(result) => {
+            debug!(
+                "Found {} versions and {} folders under '{}/{}'",
+                result.versions.len(),
+                result.folders.len(),
+                bucket,
+                prefix
+            );
+            Ok(result)
+        }
+        Err(e) => {
+            error!(
+                "Failed to list versions under '{}/{}': {}",
+                bucket, prefix, e
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Save one version of an object to `destination`, leaving the latest
+/// version untouched. Reports `download-progress` like `download_file`.
+#[tauri::command]
+pub async fn download_object_version(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    version_id: String,
+    destination: String,
+    overwrite: Option<OverwritePolicy>,
+) -> AppResult<()> {
+    let overwrite = overwrite.unwrap_or_default();
+    info!(
+        "Downloading '{}/{}' version '{}' to '{}'",
+        bucket, key, version_id, destination
+    );
+
+    let destination_path = PathBuf::from(&destination);
+    LocalService::prepare_destination(&destination_path, true).await?;
+
+    if fs::try_exists(&destination).await? {
+        match overwrite {
+            OverwritePolicy::Overwrite => {}
+            OverwritePolicy::Skip => {
+                info!("Skipping download, '{}' already exists", destination);
+                return Ok(());
+            }
+            OverwritePolicy::Fail => {
+                warn!("Refusing to overwrite existing file '{}'", destination);
+                return Err(AppError::AlreadyExists(destination));
+            }
+        }
+    }
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let mut part_path = destination_path.as_os_str().to_owned();
+    part_path.push(".part");
+    let part_path = PathBuf::from(part_path);
+
+    let mut last_emitted = 0;
+    let emit_progress = |downloaded: u64, total: u64| {
+        if downloaded - last_emitted < DOWNLOAD_PROGRESS_INTERVAL && downloaded < total {
+            return;
+        }
+        last_emitted = downloaded;
+        let _ = app.emit(
+            "download-progress",
+            DownloadProgress {
+                key: key.clone(),
+                bytes_downloaded: downloaded,
+                total_bytes: total,
+                percentage: if total > 0 {
+                    (downloaded as f32 / total as f32) * 100.0
+                } else {
+                    100.0
+                },
+                plan: None,
+            },
+        );
+    };
+
+    let size = match S3Service::download_object_version_to_file(
+        &connection,
+        &bucket,
+        &key,
+        &version_id,
+        &part_path,
+        emit_progress,
+    )
+    .await
+    {
+        Ok(size) => size,
+        Err(e) => {
+            error!(
+                "Failed to download '{}/{}' version '{}': {}",
+                bucket, key, version_id, e
+            );
+            let _ = fs::remove_file(&part_path).await;
+            return Err(match e {
+                AppError::IoError(e) => LocalService::destination_error(&destination_path, e),
+                e => e,
+            });
+        }
+    };
+
+    if let Err(e) = fs::rename(&part_path, &destination_path).await {
+        error!("Failed to move {:?} into place: {}", part_path, e);
+        let _ = fs::remove_file(&part_path).await;
+        return Err(LocalService::destination_error(&destination_path, e));
+    }
+
+    info!("Successfully saved {} bytes to '{}'", size, destination);
+    Ok(())
+}
+
+/// Copy a previous version over the latest one, so it becomes current
+/// again without losing the history after it.
+#[tauri::command]
+pub async fn restore_object_version(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    version_id: String,
+) -> AppResult<()> {
+    info!("Restoring '{}/{}' to version '{}'", bucket, key, version_id);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    match S3Service::restore_object_version(&connection, &bucket, &key, &version_id).await {
+        Ok(()) => {
+            info!(
+                "Successfully restored '{}/{}' to version '{}'",
+                bucket, key, version_id
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!(
+                "Failed to restore '{}/{}' to version '{}': {}",
+                bucket, key, version_id, e
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Permanently remove one version or delete marker. Removing the delete
+/// marker that hides an object brings the object back.
+#[tauri::command]
+pub async fn delete_object_version(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    version_id: String,
+) -> AppResult<()> {
+    info!("Deleting version '{}' of '{}/{}'", version_id, bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    match S3Service::delete_object_version(&connection, &bucket, &key, &version_id).await {
+        Ok(()) => {
+            info!(
+                "Successfully deleted version '{}' of '{}/{}'",
+                version_id, bucket, key
+            );
+            Ok(())
+        }
+        Err(e) => {
+            error!(
+                "Failed to delete version '{}' of '{}/{}': {}",
+                version_id, bucket, key, e
+            );
+            Err(e)
+        }
+    }
+}
+
+/// Render an index.html for a prefix and upload it next to the objects it
+/// lists. Returns the key of the uploaded page.
+#[tauri::command]
+pub async fn generate_index(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    mode: Option<IndexMode>,
+    title: Option<String>,
+) -> AppResult<String> {
+    let mode = mode.unwrap_or_default();
+    info!("Generating {:?} index for '{}/{}'", mode, bucket, prefix);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let key_prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.clone()
+    } else {
+        format!("{}/", prefix)
+    };
+
+    let listing = S3Service::list_all_objects_recursive(&operator, &key_prefix).await?;
+    let title = title.unwrap_or_else(|| {
+        if key_prefix.is_empty() {
+            bucket.clone()
+        } else {
+            format!("{}/{}", bucket, key_prefix)
+        }
+    });
+
+    let html = SiteService::render_index(&title, &key_prefix, &listing.objects, mode);
+    let key = format!("{}{}", key_prefix, INDEX_FILE);
+
+    // The index changes whenever the folder does, so don't let caches pin it
+    match S3Service::upload_object_with(
+        &operator,
+        &key,
+        html.into_bytes(),
+        Some("text/html; charset=utf-8"),
+        Some("no-cache"),
+        None,
+    )
+    .await
+    {
+        Ok(()) => {
+            info!("Uploaded index to '{}/{}'", bucket, key);
+            Ok(key)
+        }
+        Err(e) => {
+            error!("Failed to upload index to '{}/{}': {}", bucket, key, e);
+            Err(e)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn search_by_tag(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    filters: Vec<TagFilter>,
+    concurrency: Option<usize>,
+) -> AppResult<TagSearchResult> {
+    let concurrency = concurrency.unwrap_or(16);
+    info!(
+        "Searching '{}/{}' for {} tag filters (concurrency: {})",
+        bucket,
+        prefix,
+        filters.len(),
+        concurrency
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let mut budget = state.settings.lock().await.scan_limits.start();
+
+    let result = S3Service::search_by_tag(
+        &connection,
+        &bucket,
+        &prefix,
+        &filters,
+        concurrency,
+        &mut budget,
+    )
+    .await;
+    match result {
+        Ok(objects) => {
+            info!(
+                "Found {} objects matching tag filters in '{}/{}'",
+                objects.len(),
+                bucket,
+                prefix
+            );
+            Ok(TagSearchResult {
+                objects,
+                stopped_early: budget.cutoff(),
+            })
+        }
+        Err(e) => {
+            error!("Tag search failed in '{}/{}': {}", bucket, prefix, e);
+            Err(e)
+        }
+    }
+}
+
+/// Walk every key under `prefix` without a delimiter and report the ones
+/// matching `query` as `search-objects-progress` events, a listing page at a
+/// time. Can be cancelled.
+#[tauri::command]
+pub async fn search_objects(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    query: String,
+    prefix: Option<String>,
+    options: Option<SearchOptions>,
+) -> AppResult<SearchSummary> {
+    let prefix = prefix.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    info!(
+        "Searching '{}/{}' for '{}' ({:?})",
+        bucket, prefix, query, options.mode
+    );
+
+    let matcher = KeyMatcher::new(&query, &options)?;
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let mut budget = state.settings.lock().await.scan_limits.start();
+    let client = S3Service::create_s3_client(&connection).await;
+    let mut pages = std::pin::pin!(S3Service::list_object_pages(
+        &client,
+        &connection,
+        &bucket,
+        &prefix
+    )?);
+
+    let job = state.start_job("search_objects", &bucket).await;
+    let mut matched: u64 = 0;
+    let mut truncated = false;
+
+    loop {
+        let page = match pages.try_next().await {
+            Ok(Some(page)) => page,
+            Ok(None) => break,
+            Err(e) => {
+                error!("Search failed in '{}/{}': {}", bucket, prefix, e);
+                // Matches already reported stand; the page that failed doesn't
+                job.finish(matched, 1);
+                return Err(e);
+            }
+        };
+        if budget.exhausted().is_some() {
+            truncated = true;
+            break;
+        }
+        budget.charge(page.len());
+        let scanned = budget.scanned();
+        let mut matches: Vec<S3Object> = page
+            .into_iter()
+            .filter(|o| matcher.matches(&o.key))
+            .collect();
+        if let Some(max) = options.max_results {
+            let room = max.saturating_sub(matched) as usize;
+            if matches.len() >= room {
+                matches.truncate(room);
+                truncated = true;
+            }
+        }
+        matched += matches.len() as u64;
+
+        if !matches.is_empty() {
+            let _ = app.emit(
+                "search-objects-progress",
+                SearchProgress {
+                    job_id: job.id.clone(),
+                    matches,
+                    scanned,
+                    matched,
+                },
+            );
+        }
+        if truncated || job.cancelled().load(Ordering::Relaxed) {
+            truncated = true;
+            break;
+        }
+    }
+
+    info!(
+        "Search of '{}/{}' matched {} of {} keys{}",
+        bucket,
+        prefix,
+        matched,
+        budget.scanned(),
+        match budget.cutoff() {
+            Some(ScanCutoff::MaxObjects) => " before hitting the object limit",
+            Some(ScanCutoff::MaxDuration) => " before hitting the time limit",
+            None if truncated => " before stopping",
+            None => "",
+        }
+    );
+    let summary = SearchSummary {
+        job_id: job.id.clone(),
+        scanned: budget.scanned(),
+        matched,
+        truncated,
+        stopped_early: budget.cutoff(),
+    };
+    job.finish(matched, 0);
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn get_object_acl(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+) -> AppResult<AccessControlList> {
+    debug!("Getting ACL for '{}/{}'", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    S3Service::get_object_acl(&connection, &bucket, &key).await
+}
+
+/// Apply a canned ACL to an object and return the grants it ends up with.
+#[tauri::command]
+pub async fn put_object_acl(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+    acl: CannedAcl,
+) -> AppResult<AccessControlList> {
+    info!("Setting ACL '{}' on '{}/{}'", acl.as_str(), bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    S3Service::put_object_acl(&connection, &bucket, &key, acl)
+        .await
+        .inspect_err(|e| error!("Failed to set ACL on '{}/{}': {}", bucket, key, e))?;
+    S3Service::get_object_acl(&connection, &bucket, &key).await
+}
+
+#[tauri::command]
+pub async fn upload_folder(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    folder_path: String,
+    ignore_patterns: Option<Vec<String>>,
+    dedupe_prefix: Option<String>,
+    collision: Option<CollisionStrategy>,
+    checksum_algorithm: Option<ChecksumAlgorithm>,
+) -> AppResult<FolderUploadSummary> {
+    let collision = collision.unwrap_or_default();
+    info!("Uploading folder '{}' to '{}/{}'", folder_path, bucket, prefix);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    // Per-job patterns come last so they can override the global ones with `!`
+    let mut patterns = state.settings.lock().await.ignore_patterns.clone();
+    patterns.extend(ignore_patterns.unwrap_or_default());
+    let rules = IgnoreRules::new(&patterns);
+
+    let root = PathBuf::from(&folder_path);
+    let walk = tokio::task::spawn_blocking(move || LocalService::collect_files(&root, &rules))
+        .await
+        .map_err(|e| AppError::IoError(std::io::Error::other(e)))??;
+
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let key_prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.clone()
+    } else {
+        format!("{}/", prefix)
+    };
+
+    let content_type_rules = state.settings.lock().await.content_type_rules.clone();
+
+    let mut summary = FolderUploadSummary {
+        uploaded_count: 0,
+        ignored_count: walk.ignored.len() as u64,
+        total_bytes: 0,
+        deduplicated_count: 0,
+        bytes_saved: 0,
+        skipped_count: 0,
+        failed: Vec::new(),
+    };
+
+    let mut destinations = if collision == CollisionStrategy::Overwrite {
+        DestinationKeys::default()
+    } else {
+        let client = S3Service::create_s3_client(&connection).await;
+        destination_keys(&client, &connection, &bucket, &key_prefix).await?
+    };
+
+    let content_index = match dedupe_prefix.as_deref() {
+        Some(search_prefix) => {
+            let mut budget = state.settings.lock().await.scan_limits.start();
+            S3Service::content_index(&connection, &bucket, search_prefix, &mut budget).await?
+        }
+        None => HashMap::new(),
+    };
+
+    let mut files = Vec::with_capacity(walk.files.len());
+    for file in walk.files {
+        match fs::metadata(&file.path).await {
+            Ok(meta) => files.push((file, meta)),
+            Err(e) => {
+                error!("Failed to read file '{:?}': {}", file.path, e);
+                summary.fail(file.relative_path, &e.into());
+            }
+        }
+    }
+
+    // Only files sharing a size with another one can repeat within the batch
+    let mut size_counts: HashMap<u64, usize> = HashMap::new();
+    for (_, meta) in &files {
+        *size_counts.entry(meta.len()).or_default() += 1;
+    }
+
+    // Resolve every key before writing anything, so a `Fail` collision
+    // refuses the whole folder instead of stopping partway through
+    let mut planned = Vec::with_capacity(files.len());
+    for (file, meta) in files {
+        let total_bytes = meta.len();
+        let key = KeyTemplates::apply(
+            &connection.key_templates,
+            &bucket,
+            &key_prefix,
+            &file.relative_path,
+        )
+        .unwrap_or_else(|| format!("{}{}", key_prefix, file.relative_path));
+
+        // Hash from disk first so duplicates are never read into memory
+        let repeats_in_batch = total_bytes > 0 && size_counts[&total_bytes] > 1;
+        let digest = if !content_index.is_empty()
+            || repeats_in_batch
+            || collision == CollisionStrategy::SuffixHash
+        {
+            match hash_file_async(file.path.clone(), HashAlgorithm::Md5).await {
+                Ok(digest) => Some(digest),
+                Err(e) => {
+                    error!("Failed to hash file '{:?}': {}", file.path, e);
+                    summary.fail(file.relative_path, &e);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+
+        let key = if collision == CollisionStrategy::Overwrite {
+            key
+        } else {
+            let incoming = Incoming {
+                size: total_bytes,
+                modified: LocalService::modified_millis(&meta) / 1000,
+                digest: digest.as_deref(),
+            };
+            let placement = destinations
+                .place(collision, &key, incoming)
+                .inspect_err(|e| error!("Not uploading '{}': {}", folder_path, e))?;
+            match placement {
+                Placement::Write(key) => key,
+                Placement::Skip => {
+                    debug!("Skipping '{:?}', '{}' already exists", file.path, key);
+                    summary.skipped_count += 1;
+                    continue;
+                }
+            }
+        };
+        planned.push((file, key, total_bytes, digest, repeats_in_batch));
+    }
+
+    let job = state.start_job("upload_folder", &bucket).await;
+    // Content uploaded so far in this batch, with the headers it got, so a
+    // copy never carries another file's content type
+    let mut batch_index: HashMap<(u64, String, String, Option<String>), String> = HashMap::new();
+
+    let mut uploaded = Vec::new();
+    for (file, key, total_bytes, digest, repeats_in_batch) in planned {
+        let (content_type, cache_control) = SiteService::content_headers(&content_type_rules, &key);
+
+        if let Some(digest) = &digest {
+            let batch_entry = (
+                total_bytes,
+                digest.clone(),
+                content_type.clone(),
+                cache_control.clone(),
+            );
+            let existing_key = content_index
+                .get(&(total_bytes, digest.clone()))
+                .or_else(|| batch_index.get(&batch_entry));
+            if let Some(existing_key) = existing_key {
+                let started = Instant::now();
+                if *existing_key != key {
+                    debug!("Copying '{}' to '{}' instead of uploading", existing_key, key);
+                    if let Err(e) = S3Service::copy_object_as(
+                        &connection,
+                        &bucket,
+                        existing_key,
+                        total_bytes,
+                        &key,
+                        &content_type,
+                        cache_control.as_deref(),
+                    )
+                    .await
+                    {
+                        error!("Failed to copy '{}' to '{}': {}", existing_key, key, e);
+                        summary.fail(file.relative_path, &e);
+                        continue;
+                    }
+                    uploaded.push((key.clone(), total_bytes));
+                }
+                let _ = app.emit(
+                    "upload-progress",
+                    UploadProgress::new(key, total_bytes, total_bytes, started.elapsed()),
+                );
+                summary.deduplicated_count += 1;
+                summary.bytes_saved += total_bytes;
+                continue;
+            }
+        }
+
+        // Stream from disk in tuned parts so large files never sit in memory
+        let plan = match TransferTuner::plan(&connection, total_bytes) {
+            Ok(plan) => plan,
+            Err(e) => {
+                error!("Not uploading '{:?}': {}", file.path, e);
+                summary.fail(file.relative_path, &e);
+                continue;
+            }
+        };
+
+        S3Service::throttle(&connection, RequestKind::Put).await;
+        let started = Instant::now();
+        let on_progress = |uploaded| {
+            let _ = app.emit(
+                "upload-progress",
+                UploadProgress::new(key.clone(), uploaded, total_bytes, started.elapsed())
+                    .with_plan(plan),
+            );
+        };
+        let result = match checksum_algorithm {
+            Some(algorithm) => {
+                let options = UploadOptions {
+                    content_type: Some(content_type.clone()),
+                    cache_control: cache_control.clone(),
+                    checksum_algorithm: Some(algorithm),
+                    ..Default::default()
+                };
+                S3Service::upload_file_with_options(
+                    &connection,
+                    &bucket,
+                    &key,
+                    &file.path,
+                    &plan,
+                    &options,
+                    on_progress,
+                )
+                .await
+            }
+            None => {
+                S3Service::upload_file_chunked(
+                    &operator,
+                    &key,
+                    &file.path,
+                    &plan,
+                    Some(&content_type),
+                    cache_control.as_deref(),
+                    on_progress,
+                )
+                .await
+            }
+        };
+        if let Err(e) = result {
+            error!(
+                "Failed to upload '{:?}' to '{}/{}': {}",
+                file.path, bucket, key, e
+            );
+            summary.fail(file.relative_path, &e);
+            continue;
+        }
+        TransferTuner::record(&connection.id, total_bytes, started.elapsed());
+
+        // An empty file has no parts, so report completion explicitly
+        if total_bytes == 0 {
+            let _ = app.emit(
+                "upload-progress",
+                UploadProgress::new(key.clone(), 0, 0, started.elapsed()),
+            );
+        }
+
+        if let Some(digest) = digest.filter(|_| repeats_in_batch) {
+            batch_index.insert(
+                (total_bytes, digest, content_type, cache_control),
+                key.clone(),
+            );
+        }
+
+        summary.uploaded_count += 1;
+        summary.total_bytes += total_bytes;
+        uploaded.push((key, total_bytes));
+    }
+
+    info!(
+        "Uploaded {} files ({} bytes) from '{}' to '{}/{}', {} deduplicated, {} ignored",
+        summary.uploaded_count,
+        summary.total_bytes,
+        folder_path,
+        bucket,
+        prefix,
+        summary.deduplicated_count,
+        summary.ignored_count
+    );
+    if summary.skipped_count > 0 {
+        info!(
+            "Kept {} existing objects whose keys were taken",
+            summary.skipped_count
+        );
+    }
+    if summary.bytes_saved > 0 {
+        info!(
+            "Server-side copies saved uploading {} bytes",
+            summary.bytes_saved
+        );
+    }
+    if !summary.failed.is_empty() {
+        warn!(
+            "{} files from '{}' failed to upload",
+            summary.failed.len(),
+            folder_path
+        );
+    }
+
+    spawn_post_upload_hooks(&app, &state, &connection, &bucket, uploaded).await;
+    job.finish(
+        summary.uploaded_count + summary.deduplicated_count,
+        summary.failed.len() as u64,
+    );
+    Ok(summary)
+}
+
+/// Find local files whose content already exists under `search_prefix`, so
+/// the user can choose to copy them server-side rather than re-upload. The
+/// remote walk is bounded by the scan limits in settings.
+#[tauri::command]
+pub async fn find_upload_duplicates(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    folder_path: String,
+    search_prefix: String,
+    ignore_patterns: Option<Vec<String>>,
+) -> AppResult<UploadDuplicateReport> {
+    debug!(
+        "Looking for duplicates of '{}' under '{}/{}'",
+        folder_path, bucket, search_prefix
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let (mut patterns, limits) = {
+        let settings = state.settings.lock().await;
+        (settings.ignore_patterns.clone(), settings.scan_limits)
+    };
+    patterns.extend(ignore_patterns.unwrap_or_default());
+    let rules = IgnoreRules::new(&patterns);
+
+    let root = PathBuf::from(&folder_path);
+    let walk = tokio::task::spawn_blocking(move || LocalService::collect_files(&root, &rules))
+        .await
+        .map_err(|e| AppError::IoError(std::io::Error::other(e)))??;
+
+    let mut budget = limits.start();
+    let content_index =
+        S3Service::content_index(&connection, &bucket, &search_prefix, &mut budget).await?;
+    let indexed_sizes: HashSet<u64> = content_index.keys().map(|(size, _)| *size).collect();
+
+    let key_prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.clone()
+    } else {
+        format!("{}/", prefix)
+    };
+
+    let mut duplicates = Vec::new();
+
+    for file in walk.files {
+        // Only hash files whose size could possibly match
+        let size = fs::metadata(&file.path).await?.len();
+        if !indexed_sizes.contains(&size) {
+            continue;
+        }
+
+        let digest = hash_file_async(file.path.clone(), HashAlgorithm::Md5).await?;
+        let key = format!("{}{}", key_prefix, file.relative_path);
+
+        if let Some(existing_key) = content_index.get(&(size, digest)) {
+            if *existing_key != key {
+                duplicates.push(UploadDuplicate {
+                    local_path: file.path.to_string_lossy().to_string(),
+                    key,
+                    existing_key: existing_key.clone(),
+                    size,
+                });
+            }
+        }
+    }
+
+    info!(
+        "Found {} duplicate files in '{}' under '{}/{}'{}",
+        duplicates.len(),
+        folder_path,
+        bucket,
+        search_prefix,
+        if budget.cutoff().is_some() {
+            " (partial index)"
+        } else {
+            ""
+        }
+    );
+    Ok(UploadDuplicateReport {
+        duplicates,
+        scanned: budget.scanned(),
+        stopped_early: budget.cutoff(),
+    })
+}
+
+/// Publish a local build directory to a prefix: upload every file with its
+/// content type, cache rule and optional compression, then with
+/// `delete_removed` remove remote objects that no longer exist locally.
+/// `dry_run` reports without writing.
+#[tauri::command]
+pub async fn deploy_site(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    folder_path: String,
+    options: Option<DeployOptions>,
+) -> AppResult<DeploySummary> {
+    let options = options.unwrap_or_default();
+    info!(
+        "Deploying '{}' to '{}/{}' (dry run: {})",
+        folder_path, bucket, prefix, options.dry_run
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let (patterns, content_type_rules) = {
+        let settings = state.settings.lock().await;
+        (
+            settings.ignore_patterns.clone(),
+            settings.content_type_rules.clone(),
+        )
+    };
+    let rules = IgnoreRules::new(&patterns);
+
+    let root = PathBuf::from(&folder_path);
+    let walk_rules = rules.clone();
+    let walk = tokio::task::spawn_blocking(move || LocalService::collect_files(&root, &walk_rules))
+        .await
+        .map_err(|e| AppError::IoError(std::io::Error::other(e)))??;
+
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let key_prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.clone()
+    } else {
+        format!("{}/", prefix)
+    };
+
+    let mut summary = DeploySummary {
+        uploads: BatchResult::default(),
+        deletions: BatchResult::default(),
+        ignored_count: walk.ignored.len() as u64,
+        total_bytes: 0,
+        dry_run: options.dry_run,
+        journal_path: None,
+    };
+
+    let job = state.start_job("deploy", &bucket).await;
+    let mut journal = JobJournal::create(JournalStart {
+        job_id: job.id.clone(),
+        job: "deploy".to_string(),
+        bucket: bucket.clone(),
+        prefix: key_prefix.clone(),
+        source: folder_path.clone(),
+        dry_run: options.dry_run,
+        started_at: chrono::Utc::now().timestamp(),
+    })?;
+    for relative in &walk.ignored {
+        let local_path = Path::new(&folder_path).join(relative);
+        journal.skip(&format!("{}{}", key_prefix, relative), Some(&local_path));
+    }
+
+    let local_keys: HashSet<String> = walk
+        .files
+        .iter()
+        .map(|f| format!("{}{}", key_prefix, f.relative_path))
+        .collect();
+
+    for file in walk.files {
+        let key = format!("{}{}", key_prefix, file.relative_path);
+        // The deploy's own cache rules are more specific than the global ones
+        let (content_type, rule_cache_control) =
+            SiteService::content_headers(&content_type_rules, &key);
+        let cache_control = options
+            .cache_control_for(&key)
+            .or(rule_cache_control.as_deref());
+
+        let sent = async {
+            let file_size = fs::metadata(&file.path).await?.len();
+            let plan = TransferTuner::plan(&connection, file_size)?;
+            // Compressing needs the whole file in memory, so in low-memory
+            // mode anything over a part is sent as it is
+            let encoding = options
+                .compression
+                .filter(|_| SiteService::is_compressible(&content_type))
+                .filter(|_| !TransferTuner::low_memory() || file_size <= plan.part_size);
+
+            let Some(encoding) = encoding else {
+                debug!(
+                    "Deploying '{}' ({}, cache: {:?}) in {} parts",
+                    key, content_type, cache_control, plan.part_count
+                );
+                let checksum = hash_file_async(file.path.clone(), HashAlgorithm::Md5).await?;
+                if !options.dry_run {
+                    S3Service::throttle(&connection, RequestKind::Put).await;
+                    let started = Instant::now();
+                    match options.checksum_algorithm {
+                        Some(algorithm) => {
+                            let upload = UploadOptions {
+                                content_type: Some(content_type.clone()),
+                                cache_control: cache_control.map(str::to_string),
+                                checksum_algorithm: Some(algorithm),
+                                ..Default::default()
+                            };
+                            S3Service::upload_file_with_options(
+                                &connection,
+                                &bucket,
+                                &key,
+                                &file.path,
+                                &plan,
+                                &upload,
+                                |_| {},
+                            )
+                            .await?
+                        }
+                        None => {
+                            S3Service::upload_file_chunked(
+                                &operator,
+                                &key,
+                                &file.path,
+                                &plan,
+                                Some(&content_type),
+                                cache_control,
+                                |_| {},
+                            )
+                            .await?
+                        }
+                    };
+                    TransferTuner::record(&connection.id, file_size, started.elapsed());
+                }
+                return Ok::<_, AppError>((file_size, checksum));
+            };
+
+            let data = SiteService::compress(&fs::read(&file.path).await?, encoding)?;
+            let content_encoding = match encoding {
+                ContentEncoding::Gzip => "gzip",
+                ContentEncoding::Brotli => "br",
+            };
+            debug!(
+                "Deploying '{}' ({}, cache: {:?}, encoding: {})",
+                key, content_type, cache_control, content_encoding
+            );
+
+            let size = data.len() as u64;
+            let checksum = S3Service::content_digest(&data);
+            if !options.dry_run {
+                S3Service::throttle(&connection, RequestKind::Put).await;
+                match options.checksum_algorithm {
+                    Some(algorithm) => {
+                        let upload = UploadOptions {
+                            content_type: Some(content_type.clone()),
+                            cache_control: cache_control.map(str::to_string),
+                            content_encoding: Some(content_encoding.to_string()),
+                            checksum_algorithm: Some(algorithm),
+                            ..Default::default()
+                        };
+                        S3Service::put_object_with_options(
+                            &connection,
+                            &bucket,
+                            &key,
+                            data,
+                            &upload,
+                        )
+                        .await?;
+                    }
+                    None => {
+                        S3Service::upload_object_with(
+                            &operator,
+                            &key,
+                            data,
+                            Some(&content_type),
+                            cache_control,
+                            Some(content_encoding),
+                        )
+                        .await?
+                    }
+                }
+            }
+            Ok((size, checksum))
+        }
+        .await;
+
+        let (size, checksum) = match sent {
+            Ok(sent) => sent,
+            Err(e) => {
+                error!(
+                    "Failed to deploy '{}' to '{}/{}': {}",
+                    file.relative_path, bucket, key, e
+                );
+                journal.record(
+                    JournalAction::Upload,
+                    &key,
+                    Some(&file.path),
+                    0,
+                    None,
+                    Some(&e),
+                );
+                summary.uploads.fail(key, &e);
+                continue;
+            }
+        };
+
+        journal.record(JournalAction::Upload, &key, Some(&file.path), size, Some(checksum), None);
+        summary.total_bytes += size;
+        summary.uploads.succeed(key);
+    }
+
+    if options.delete_removed {
+        // Walk the remote side a page at a time rather than listing it whole
+        let client = S3Service::create_s3_client(&connection).await;
+        let pages = match InventoryService::planning_pages(
+            &client,
+            &connection,
+            &bucket,
+            &key_prefix,
+            options.use_inventory,
+        )
+        .await
+        {
+            Ok(pages) => pages,
+            Err(e) => {
+                error!(
+                    "Failed to plan stale objects under '{}/{}': {}",
+                    bucket, prefix, e
+                );
+                journal.fail(&e);
+                return Err(e);
+            }
+        };
+        let mut remote = std::pin::pin!(pages
+            .map_ok(|page| stream::iter(page.into_iter().map(Ok::<_, AppError>)))
+            .try_flatten());
+
+        loop {
+            let object = match remote.try_next().await {
+                Ok(Some(object)) => object,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Failed to list '{}/{}': {}", bucket, prefix, e);
+                    journal.fail(&e);
+                    return Err(e);
+                }
+            };
+            if object.is_directory || local_keys.contains(&object.key) {
+                continue;
+            }
+            // Ignored files were never uploaded, so their absence locally
+            // says nothing about whether the remote copy is stale
+            let relative = object.key.strip_prefix(&key_prefix).unwrap_or(&object.key);
+            if rules.is_path_ignored(relative) {
+                journal.skip(&object.key, None);
+                continue;
+            }
+
+            if !options.dry_run {
+                S3Service::throttle(&connection, RequestKind::Delete).await;
+                if let Err(e) = S3Service::delete_object(&operator, &object.key).await {
+                    error!("Failed to remove stale '{}/{}': {}", bucket, object.key, e);
+                    journal.record(
+                        JournalAction::Delete,
+                        &object.key,
+                        None,
+                        object.size,
+                        None,
+                        Some(&e),
+                    );
+                    summary.deletions.fail(object.key, &e);
+                    continue;
+                }
+            }
+
+            debug!("Removed stale object '{}'", object.key);
+            journal.record(JournalAction::Delete, &object.key, None, object.size, None, None);
+            summary.deletions.succeed(object.key);
+        }
+    }
+
+    info!(
+        "Deployed {} files ({} bytes) to '{}/{}', removed {} stale objects, {} failed{}",
+        summary.uploads.succeeded.len(),
+        summary.total_bytes,
+        bucket,
+        prefix,
+        summary.deletions.succeeded.len(),
+        summary.uploads.failed.len() + summary.deletions.failed.len(),
+        if options.dry_run { " (dry run)" } else { "" }
+    );
+
+    let journal_path = journal.finish()?;
+    if let Some(journal_key) = options.journal_key.as_deref() {
+        if let Err(e) = JournalService::upload(&operator, &journal_path, journal_key).await {
+            warn!("Failed to store journal at '{}/{}': {}", bucket, journal_key, e);
+        }
+    }
+    summary.journal_path = Some(journal_path.to_string_lossy().to_string());
+
+    job.finish(
+        (summary.uploads.succeeded.len() + summary.deletions.succeeded.len()) as u64,
+        (summary.uploads.failed.len() + summary.deletions.failed.len()) as u64,
+    );
+    Ok(summary)
+}
+
+/// Check a job journal against the bucket it describes: every upload it
+/// recorded must still be present with the same size and checksum.
+#[tauri::command]
+pub async fn verify_journal(
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    journal_path: String,
+) -> AppResult<BatchResult> {
+    info!("Verifying journal '{}' against '{}'", journal_path, bucket);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let journal = PathBuf::from(&journal_path);
+    match JournalService::verify(&connection, &bucket, &operator, &journal).await {
+        Ok(result) => {
+            info!(
+                "Journal '{}': {} verified, {} failed",
+                journal_path,
+                result.succeeded.len(),
+                result.failed.len()
+            );
+            Ok(result)
+        }
+        Err(e) => {
+            error!("Failed to verify journal '{}': {}", journal_path, e);
+            Err(e)
+        }
+    }
+}
+
+/// Poll a temp copy opened externally and emit `external-edit-changed` when
+/// it's saved. Stops once the edit is closed or the file disappears.
+fn watch_external_edit(app: AppHandle, edit_id: String) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(2));
+
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<AppState>();
+            let mut edits = state.external_edits.lock().await;
+
+            let Some(edit) = edits.get_mut(&edit_id) else {
+                debug!("Stopped watching external edit {}", edit_id);
+                break;
+            };
+
+            let meta = match fs::metadata(&edit.local_path).await {
+                Ok(meta) => meta,
+                Err(e) => {
+                    warn!("External edit file '{}' is gone: {}", edit.local_path, e);
+                    edits.remove(&edit_id);
+                    break;
+                }
+            };
+
+            let modified_at = LocalService::modified_millis(&meta);
+            if modified_at != edit.local_modified_at || meta.len() != edit.local_size {
+                debug!("Detected changes to '{}'", edit.local_path);
+                edit.local_modified_at = modified_at;
+                edit.local_size = meta.len();
+                edit.has_changes = true;
+                let _ = app.emit("external-edit-changed", edit.clone());
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn open_object_externally(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    key: String,
+) -> AppResult<ExternalEdit> {
+    info!("Opening '{}/{}' in external application", bucket, key);
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    let details = S3Service::get_object_details(&operator, &key).await?;
+    let data = S3Service::download_object(&operator, &key).await?;
+
+    let id = Uuid::new_v4().to_string();
+    let dir = LocalService::temp_dir("edits").join(&id);
+    fs::create_dir_all(&dir).await?;
+
+    let local_path = dir.join(LocalService::temp_file_name(&key));
+    fs::write(&local_path, &data).await?;
+
+    let meta = fs::metadata(&local_path).await?;
+
+    let edit = ExternalEdit {
+        id: id.clone(),
+        connection_id,
+        bucket,
+        key,
+        local_path: local_path.to_string_lossy().to_string(),
+        remote_etag: details.etag,
+        local_modified_at: LocalService::modified_millis(&meta),
+        local_size: meta.len(),
+        has_changes: false,
+    };
+
+    state
+        .external_edits
+        .lock()
+        .await
+        .insert(id.clone(), edit.clone());
+
+    if let Err(e) = LocalService::open_with_default_app(&local_path) {
+        error!("Failed to open '{}': {}", edit.local_path, e);
+        state.external_edits.lock().await.remove(&id);
+        let _ = fs::remove_dir_all(&dir).await;
+        return Err(e);
+    }
+
+    watch_external_edit(app, id);
+
+    info!(
+        "Opened '{}/{}' from temp copy '{}'",
+        edit.bucket, edit.key, edit.local_path
+    );
+    Ok(edit)
+}
+
+#[tauri::command]
+pub async fn upload_external_edit(
+    state: State<'_, AppState>,
+    edit_id: String,
+    force: Option<bool>,
+) -> AppResult<ExternalEdit> {
+    info!("Uploading changes for external edit {}", edit_id);
+
+    let edit = state
+        .external_edits
+        .lock()
+        .await
+        .get(&edit_id)
+        .cloned()
+        .ok_or_else(|| AppError::NotFound(format!("external edit {}", edit_id)))?;
+
+    let connection = state
+        .bucket_connection(&edit.connection_id, &edit.bucket)
+        .await?;
+
+    let operator = S3Service::create_operator(&connection, &edit.bucket)?;
+
+    // Don't silently clobber changes someone else made while we were editing
+    if !force.unwrap_or(false) {
+        let current = S3Service::get_object_details(&operator, &edit.key).await?;
+        if current.etag != edit.remote_etag {
+            warn!(
+                "'{}/{}' changed remotely since it was opened",
+                edit.bucket, edit.key
+            );
+            return Err(AppError::S3Error(format!(
+                "'{}' was modified remotely since it was opened",
+                edit.key
+            )));
+        }
+    }
+
+    let data = fs::read(&edit.local_path).await?;
+    let rules = state.settings.lock().await.content_type_rules.clone();
+    let (content_type, cache_control) = SiteService::content_headers(&rules, &edit.key);
+    S3Service::upload_object_with(
+        &operator,
+        &edit.key,
+        data,
+        Some(&content_type),
+        cache_control.as_deref(),
+        None,
+    )
+    .await?;
+
+    let updated = S3Service::get_object_details(&operator, &edit.key).await?;
+
+    let mut edits = state.external_edits.lock().await;
+    let edit = edits
+        .get_mut(&edit_id)
+        .ok_or_else(|| AppError::NotFound(format!("external edit {}", edit_id)))?;
+    edit.remote_etag = updated.etag;
+    edit.has_changes = false;
+
+    info!("Uploaded external edit of '{}/{}'", edit.bucket, edit.key);
+    Ok(edit.clone())
+}
+
+#[tauri::command]
+pub async fn close_external_edit(state: State<'_, AppState>, edit_id: String) -> AppResult<()> {
+    debug!("Closing external edit {}", edit_id);
+
+    let removed = state.external_edits.lock().await.remove(&edit_id);
+
+    if removed.is_some() {
+        let dir = LocalService::temp_dir("edits").join(&edit_id);
+        if let Err(e) = fs::remove_dir_all(&dir).await {
+            warn!("Failed to clean up temp copy {:?}: {}", dir, e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn remove_drag_staging(state: &AppState) {
+    let Some(dir) = state.drag_staging.lock().await.take() else {
+        return;
+    };
+    match fs::remove_dir_all(&dir).await {
+        Ok(()) => debug!("Removed drag staging {:?}", dir),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to remove drag staging {:?}: {}", dir, e),
+    }
+}
+
+/// Remove the files staged by `stage_objects_for_drag` once the drop has
+/// finished with them.
+#[tauri::command]
+pub async fn release_drag_staging(state: State<'_, AppState>) -> AppResult<()> {
+    remove_drag_staging(&state).await;
+    Ok(())
+}
+
+/// Objects up to this size are staged synchronously; larger ones get a
+/// placeholder that is filled in by a background download.
+const DRAG_STAGE_INLINE_LIMIT: u64 = 16 * 1024 * 1024;
+
+#[tauri::command]
+pub async fn stage_objects_for_drag(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    keys: Vec<String>,
+) -> AppResult<Vec<StagedObject>> {
+    info!(
+        "Staging {} objects from bucket '{}' for drag-out",
+        keys.len(),
+        bucket
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+
+    // Only one drag runs at a time, so the previous one's files are done with
+    remove_drag_staging(&state).await;
+
+    let dir = LocalService::temp_dir("drag").join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&dir).await?;
+    *state.drag_staging.lock().await = Some(dir.clone());
+    state.track_temp_dir(dir.clone());
+
+    let mut staged = Vec::new();
+    let mut deferred = Vec::new();
+    let mut used_names = HashSet::new();
+
+    for key in keys {
+        let base_name = LocalService::temp_file_name(&key);
+
+        // Keys from different prefixes can share a file name
+        let mut file_name = base_name.clone();
+        let mut counter = 1;
+        while !used_names.insert(file_name.clone()) {
+            file_name = format!("{} ({})", base_name, counter);
+            counter += 1;
+        }
+
+        let local_path = dir.join(&file_name);
+        let details = S3Service::get_object_details(&operator, &key).await?;
+
+        let ready = if details.size <= DRAG_STAGE_INLINE_LIMIT {
+            let data = S3Service::download_object(&operator, &key).await?;
+            fs::write(&local_path, &data).await?;
+            true
+        } else {
+            fs::write(&local_path, Vec::<u8>::new()).await?;
+            deferred.push((key.clone(), local_path.clone()));
+            false
+        };
+
+        staged.push(StagedObject {
+            key,
+            local_path: local_path.to_string_lossy().to_string(),
+            ready,
+        });
+    }
+
+    if !deferred.is_empty() {
+        debug!("Downloading {} large objects in the background", deferred.len());
+
+        tauri::async_runtime::spawn(async move {
+            for (key, local_path) in deferred {
+                let result = match S3Service::download_object(&operator, &key).await {
+                    Ok(data) => fs::write(&local_path, &data).await.map_err(AppError::from),
+                    Err(e) => Err(e),
+                };
+
+                match result {
+                    Ok(()) => {
+                        let _ = app.emit(
+                            "drag-object-staged",
+                            StagedObject {
+                                key,
+                                local_path: local_path.to_string_lossy().to_string(),
+                                ready: true,
+                            },
+                        );
+                    }
+                    Err(e) => {
+                        error!("Failed to stage '{}' for drag-out: {}", key, e);
+                    }
+                }
+            }
+        });
+    }
+
+    info!("Staged {} objects in {:?}", staged.len(), dir);
+    Ok(staged)
 }
@@ -0,0 +1,54 @@
+use log::{debug, error, info, warn};
+use tauri::State;
+
+use crate::error::AppResult;
+use crate::models::{ProfileList, S3Connection};
+use crate::services::ConfigService;
+use crate::state::AppState;
+
+#[tauri::command]
+pub async fn list_profiles() -> AppResult<ProfileList> {
+    let profiles = ConfigService::list_profiles()?;
+    debug!("Listing {} profiles", profiles.len());
+
+    Ok(ProfileList {
+        active: ConfigService::active_profile(),
+        profiles,
+    })
+}
+
+/// Switch to another profile, creating it if it doesn't exist, and reload
+/// connections from it.
+#[tauri::command]
+pub async fn switch_profile(
+    state: State<'_, AppState>,
+    profile: String,
+) -> AppResult<Vec<S3Connection>> {
+    info!("Switching to profile '{}'", profile);
+
+    // Only persist the switch once the profile's connections have loaded,
+    // so a broken profile isn't what the next launch opens
+    let previous = ConfigService::active_profile();
+    let loaded = match ConfigService::select_profile(&profile) {
+        Ok(()) => state.load_connections().await,
+        Err(e) => Err(e),
+    };
+
+    match loaded {
+        Ok(count) => {
+            info!("Loaded {} connections from profile '{}'", count, profile);
+            if let Err(e) = ConfigService::save_active_profile() {
+                warn!("Using profile '{}' for this session only: {}", profile, e);
+            }
+            let connections = state.all_connections().await;
+            Ok(connections.into_iter().map(|c| c.into()).collect())
+        }
+        Err(e) => {
+            error!("Failed to switch to profile '{}': {}", profile, e);
+            if let Err(e) = ConfigService::select_profile(&previous) {
+                warn!("Failed to go back to profile '{}': {}", previous, e);
+            }
+            Err(e)
+        }
+    }
+}
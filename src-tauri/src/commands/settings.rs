@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use log::{debug, info, warn};
+use ring::aead::LessSafeKey;
+use tauri::State;
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    AppConfigBundle, AppSettings, AppStatus, BundledConnection, ConfigImportSummary, FeatureFlags,
+    ProviderPreset, S3Connection, S3ConnectionWithSecret, DELETE_BATCH_SIZES,
+};
+use crate::services::{
+    BookmarkService, ClockSkew, ConfigBundleService, ConfigService, CredentialService,
+    KeyTemplates, PreviewService, RateLimiter, S3Service, TransferTuner, CONFIG_BUNDLE_VERSION,
+};
+use crate::state::AppState;
+
+#[tauri::command]
+pub async fn get_settings(state: State<'_, AppState>) -> AppResult<AppSettings> {
+    debug!("Getting application settings");
+    Ok(state.settings.lock().await.clone())
+}
+
+fn validate_preset(preset: &ProviderPreset) -> AppResult<()> {
+    if preset.name.trim().is_empty() {
+        return Err(AppError::ConfigError("Provider preset needs a name".into()));
+    }
+    if let Some(size) = preset
+        .delete_batch_size
+        .filter(|s| !DELETE_BATCH_SIZES.contains(s))
+    {
+        return Err(AppError::ConfigError(format!(
+            "Delete batch size for '{}' must be between 1 and 1000, got {}",
+            preset.name, size
+        )));
+    }
+    Ok(())
+}
+
+/// Reject a connection from a bundle that this install couldn't use as-is.
+fn validate_connection(connection: &S3Connection) -> AppResult<()> {
+    if connection.name.trim().is_empty() {
+        return Err(AppError::ConfigError(
+            "Bundled connection needs a name".into(),
+        ));
+    }
+    if let Some(size) = connection
+        .delete_batch_size
+        .filter(|s| !DELETE_BATCH_SIZES.contains(s))
+    {
+        return Err(AppError::ConfigError(format!(
+            "Delete batch size for '{}' must be between 1 and 1000, got {}",
+            connection.name, size
+        )));
+    }
+    for template in &connection.key_templates {
+        KeyTemplates::validate(&template.template)?;
+    }
+    Ok(())
+}
+
+fn validate_settings(settings: &AppSettings) -> AppResult<()> {
+    if let Some(window) = settings.bandwidth.windows.iter().find(|w| !w.is_valid()) {
+        return Err(AppError::ConfigError(format!(
+            "Invalid bandwidth window {}-{}: times must be HH:MM",
+            window.start, window.end
+        )));
+    }
+    for preset in &settings.provider_presets {
+        validate_preset(preset)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    state: State<'_, AppState>,
+    settings: AppSettings,
+) -> AppResult<AppSettings> {
+    info!("Updating application settings");
+
+    validate_settings(&settings)?;
+
+    let mut current = state.settings.lock().await;
+
+    ConfigService::save_settings(&settings)?;
+    RateLimiter::set_bandwidth_schedule(settings.bandwidth.clone());
+    ClockSkew::set_compensation(settings.compensate_clock_skew);
+    TransferTuner::set_low_memory(settings.low_memory);
+    if settings.low_memory {
+        state.prefetched_pages.lock().await.clear();
+    }
+    *current = settings;
+
+    Ok(current.clone())
+}
+
+/// Add a provider preset, or replace the one with the same id. Presets
+/// without an id get a new one.
+#[tauri::command]
+pub async fn save_provider_preset(
+    state: State<'_, AppState>,
+    mut preset: ProviderPreset,
+) -> AppResult<ProviderPreset> {
+    validate_preset(&preset)?;
+    if preset.id.is_empty() {
+        preset.id = Uuid::new_v4().to_string();
+    }
+    info!("Saving provider preset '{}' ({})", preset.name, preset.id);
+
+    let mut settings = state.settings.lock().await;
+    let mut updated = settings.clone();
+    match updated
+        .provider_presets
+        .iter_mut()
+        .find(|p| p.id == preset.id)
+    {
+        Some(existing) => *existing = preset.clone(),
+        None => updated.provider_presets.push(preset.clone()),
+    }
+
+    ConfigService::save_settings(&updated)?;
+    *settings = updated;
+    Ok(preset)
+}
+
+/// Remove a provider preset. Connections created from it keep their
+/// settings.
+#[tauri::command]
+pub async fn delete_provider_preset(
+    state: State<'_, AppState>,
+    preset_id: String,
+) -> AppResult<()> {
+    info!("Deleting provider preset: {}", preset_id);
+
+    let mut settings = state.settings.lock().await;
+    let mut updated = settings.clone();
+    updated.provider_presets.retain(|p| p.id != preset_id);
+    if updated.provider_presets.len() == settings.provider_presets.len() {
+        return Err(AppError::NotFound(format!("Provider preset {}", preset_id)));
+    }
+
+    ConfigService::save_settings(&updated)?;
+    *settings = updated;
+    Ok(())
+}
+
+/// Derive the bundle key off the async runtime; PBKDF2 is slow on purpose.
+async fn bundle_key(passphrase: String, salt: String) -> AppResult<LessSafeKey> {
+    tokio::task::spawn_blocking(move || ConfigBundleService::derive_key(&passphrase, &salt))
+        .await
+        .map_err(|e| AppError::IoError(std::io::Error::other(e)))?
+}
+
+/// Settings, provider presets, connections and local bookmarks as one JSON
+/// bundle. Secret keys and webhook secrets are included, encrypted, only
+/// when a passphrase is given.
+#[tauri::command]
+pub async fn export_app_config(
+    state: State<'_, AppState>,
+    passphrase: Option<String>,
+) -> AppResult<String> {
+    let passphrase = passphrase.filter(|p| !p.is_empty());
+    info!(
+        "Exporting app configuration ({})",
+        if passphrase.is_some() {
+            "with secrets"
+        } else {
+            "without secrets"
+        }
+    );
+
+    let (secret_salt, key) = match passphrase {
+        Some(passphrase) => {
+            let salt = ConfigBundleService::new_salt()?;
+            let key = bundle_key(passphrase, salt.clone()).await?;
+            (Some(salt), Some(key))
+        }
+        None => (None, None),
+    };
+
+    let mut connections = Vec::new();
+    for connection in state.all_connections().await {
+        // Locked and freshly imported connections have no secret to share
+        let secret = match &key {
+            Some(key) if !connection.secret_key.is_empty() => {
+                Some(ConfigBundleService::seal(key, &connection.secret_key)?)
+            }
+            _ => None,
+        };
+        connections.push(BundledConnection {
+            connection: connection.into(),
+            secret,
+        });
+    }
+
+    // Webhook secrets travel sealed next to the settings, never in them
+    let mut settings = state.settings.lock().await.clone();
+    let mut webhook_secrets = Vec::with_capacity(settings.webhooks.len());
+    for webhook in &mut settings.webhooks {
+        webhook_secrets.push(match (&key, webhook.secret.take()) {
+            (Some(key), Some(secret)) => Some(ConfigBundleService::seal(key, &secret)?),
+            _ => None,
+        });
+    }
+
+    let bundle = AppConfigBundle {
+        version: CONFIG_BUNDLE_VERSION,
+        exported_at: Utc::now().timestamp(),
+        settings,
+        connections,
+        bookmarks: BookmarkService::export_local()?,
+        secret_salt,
+        webhook_secrets,
+    };
+
+    info!(
+        "Exported {} connections and bookmarks for {} buckets",
+        bundle.connections.len(),
+        bundle.bookmarks.len()
+    );
+    Ok(serde_json::to_string_pretty(&bundle)?)
+}
+
+/// Set this install up from an `export_app_config` bundle. Settings are
+/// replaced; connections are added alongside existing ones under new ids,
+/// with their bookmarks. Without the passphrase, secrets are left for the
+/// user to enter.
+#[tauri::command]
+pub async fn import_app_config(
+    state: State<'_, AppState>,
+    json_data: String,
+    passphrase: Option<String>,
+) -> AppResult<ConfigImportSummary> {
+    info!("Importing app configuration");
+
+    let mut bundle: AppConfigBundle = serde_json::from_str(&json_data)
+        .map_err(|e| AppError::ConfigError(format!("Invalid configuration bundle: {}", e)))?;
+    if bundle.version > CONFIG_BUNDLE_VERSION {
+        warn!("Unknown bundle version: {}", bundle.version);
+        return Err(AppError::ConfigError(format!(
+            "Unsupported bundle version: {}",
+            bundle.version
+        )));
+    }
+    validate_settings(&bundle.settings)?;
+    for bundled in &bundle.connections {
+        validate_connection(&bundled.connection)?;
+    }
+
+    let has_secrets = bundle.connections.iter().any(|c| c.secret.is_some())
+        || bundle.webhook_secrets.iter().any(Option::is_some);
+    let key = match (
+        passphrase.filter(|p| !p.is_empty()),
+        bundle.secret_salt.clone(),
+    ) {
+        (Some(passphrase), Some(salt)) if has_secrets => Some(bundle_key(passphrase, salt).await?),
+        _ => {
+            if has_secrets {
+                warn!("Bundle contains secrets but no passphrase was given");
+            }
+            None
+        }
+    };
+
+    // Decrypt everything before writing anything, so a wrong passphrase
+    // leaves this install untouched
+    let mut secrets = Vec::with_capacity(bundle.connections.len());
+    for bundled in &bundle.connections {
+        secrets.push(match (&key, &bundled.secret) {
+            (Some(key), Some(sealed)) => Some(ConfigBundleService::open(key, sealed)?),
+            _ => None,
+        });
+    }
+    let webhook_secrets = std::mem::take(&mut bundle.webhook_secrets);
+    for (webhook, sealed) in bundle.settings.webhooks.iter_mut().zip(webhook_secrets) {
+        webhook.secret = match (&key, sealed) {
+            (Some(key), Some(sealed)) => Some(ConfigBundleService::open(key, &sealed)?),
+            _ => None,
+        };
+    }
+
+    let now = Utc::now().timestamp();
+    let mut ids = HashMap::new();
+    let mut connections = Vec::new();
+    let mut secrets_restored = 0;
+
+    for (bundled, secret) in bundle.connections.into_iter().zip(secrets) {
+        let mut connection = bundled.connection;
+        connection.root_prefix = S3Service::normalize_root_prefix(connection.root_prefix);
+        let id = Uuid::new_v4().to_string();
+        ids.insert(connection.id.clone(), id.clone());
+        connection.id = id.clone();
+        connection.created_at = now;
+        connection.updated_at = now;
+
+        let secret = match secret {
+            Some(secret) => match CredentialService::store_secret(&id, &secret) {
+                Ok(()) => {
+                    secrets_restored += 1;
+                    secret
+                }
+                Err(e) => {
+                    warn!("Failed to store secret for '{}': {}", connection.name, e);
+                    String::new()
+                }
+            },
+            None => String::new(),
+        };
+
+        let connection = S3ConnectionWithSecret::from_connection(connection, secret);
+        state.insert_connection(connection.clone()).await;
+        ConfigService::save_connection(&connection)?;
+        debug!("Imported connection '{}' as {}", connection.name, id);
+        connections.push(connection.into());
+    }
+
+    let mut bookmarks_restored = 0;
+    for entry in bundle.bookmarks {
+        let Some(id) = ids.get(&entry.connection_id) else {
+            debug!(
+                "Skipping bookmarks of unknown connection {}",
+                entry.connection_id
+            );
+            continue;
+        };
+        BookmarkService::import_local(id, &entry.bucket, entry.bookmarks)?;
+        bookmarks_restored += 1;
+    }
+
+    {
+        let mut current = state.settings.lock().await;
+        ConfigService::save_settings(&bundle.settings)?;
+        RateLimiter::set_bandwidth_schedule(bundle.settings.bandwidth.clone());
+        ClockSkew::set_compensation(bundle.settings.compensate_clock_skew);
+        TransferTuner::set_low_memory(bundle.settings.low_memory);
+        *current = bundle.settings;
+    }
+
+    info!(
+        "Imported {} connections ({} with secrets) and bookmarks for {} buckets",
+        connections.len(),
+        secrets_restored,
+        bookmarks_restored
+    );
+    Ok(ConfigImportSummary {
+        connections,
+        secrets_restored,
+        bookmarks_restored,
+    })
+}
+
+/// Ask a running job to stop after its current step.
+#[tauri::command]
+pub async fn cancel_job(state: State<'_, AppState>, job_id: String) -> AppResult<()> {
+    info!("Cancelling job: {}", job_id);
+
+    if state.cancel_job(&job_id) {
+        Ok(())
+    } else {
+        Err(AppError::NotFound(format!("No running job {}", job_id)))
+    }
+}
+
+/// Backend readiness and capabilities, so the frontend can render startup
+/// and feature states without probing each command.
+#[tauri::command]
+pub async fn get_app_status(state: State<'_, AppState>) -> AppResult<AppStatus> {
+    debug!("Getting application status");
+
+    let (shared_bookmarks, bandwidth_limits) = {
+        let settings = state.settings.lock().await;
+        (
+            settings.share_bookmarks,
+            settings.bandwidth.default_limit.is_some() || !settings.bandwidth.windows.is_empty(),
+        )
+    };
+
+    // Keychain and PATH lookups block
+    let (store_check, document_preview, office_preview) = tokio::task::spawn_blocking(|| {
+        (
+            CredentialService::check_store(),
+            PreviewService::tool_available("pdftoppm"),
+            PreviewService::tool_available("soffice"),
+        )
+    })
+    .await
+    .map_err(|e| AppError::IoError(std::io::Error::other(e)))?;
+
+    Ok(AppStatus {
+        ready: state.is_ready(),
+        profile: ConfigService::active_profile(),
+        connection_count: state.connections.read().await.len(),
+        credential_error_count: state.credential_errors.lock().await.len(),
+        locked_connections: state
+            .locked_connections
+            .lock()
+            .await
+            .iter()
+            .cloned()
+            .collect(),
+        credential_store_available: store_check.is_ok(),
+        credential_store_error: store_check.err().map(|e| e.to_string()),
+        active_jobs: state.active_jobs(),
+        features: FeatureFlags {
+            shared_bookmarks,
+            bandwidth_limits,
+            document_preview,
+            office_preview,
+        },
+    })
+}
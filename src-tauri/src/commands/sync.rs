@@ -0,0 +1,313 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use futures::{stream, StreamExt};
+use log::{debug, error, info, warn};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::{save_object, spawn_post_upload_hooks};
+use crate::error::AppResult;
+use crate::models::{
+    BatchResult, ContentTypeRule, RequestKind, S3ConnectionWithSecret, S3Object, SyncAction,
+    SyncDirection, SyncItem, SyncOptions, SyncProgress, SyncSummary,
+};
+use crate::services::{
+    IgnoreRules, LocalService, S3Service, SiteService, SyncService, TransferTuner,
+};
+use crate::state::AppState;
+
+/// Files transferred at once by `sync` unless overridden.
+const SYNC_CONCURRENCY: usize = 4;
+
+async fn upload_item(
+    connection: &S3ConnectionWithSecret,
+    operator: &opendal::Operator,
+    content_type_rules: &[ContentTypeRule],
+    item: &SyncItem,
+) -> AppResult<u64> {
+    let plan = TransferTuner::plan(connection, item.size)?;
+    let (content_type, cache_control) = SiteService::content_headers(content_type_rules, &item.key);
+
+    S3Service::throttle(connection, RequestKind::Put).await;
+    let started = Instant::now();
+    let uploaded = S3Service::upload_file_chunked(
+        operator,
+        &item.key,
+        Path::new(&item.local_path),
+        &plan,
+        Some(&content_type),
+        cache_control.as_deref(),
+        |_| {},
+    )
+    .await?;
+    TransferTuner::record(&connection.id, uploaded, started.elapsed());
+    Ok(uploaded)
+}
+
+async fn download_item(
+    app: &AppHandle,
+    operator: &opendal::Operator,
+    connection: &S3ConnectionWithSecret,
+    bucket: &str,
+    item: &SyncItem,
+) -> AppResult<u64> {
+    let path = PathBuf::from(&item.local_path);
+    LocalService::prepare_destination(&path, true).await?;
+
+    let expected = S3Object {
+        key: item.key.clone(),
+        size: item.size,
+        last_modified: item.modified,
+        etag: item.etag.clone(),
+        is_directory: false,
+        content_type: None,
+        child_count: None,
+    };
+    let size = save_object(app, operator, connection, bucket, &expected, &path).await?;
+
+    // Carry the object's timestamp over so the next sync sees both sides
+    // in step rather than the download as a newer local edit
+    let modified = UNIX_EPOCH + Duration::from_secs(item.modified.max(0) as u64);
+    let stamped = std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .and_then(|file| file.set_modified(modified));
+    if let Err(e) = stamped {
+        warn!("Failed to set the modification time of {:?}: {}", path, e);
+    }
+    Ok(size)
+}
+
+fn emit_progress(
+    app: &AppHandle,
+    job_id: &str,
+    item: &SyncItem,
+    result: &BatchResult,
+    total: u64,
+    bytes_transferred: u64,
+    total_bytes: u64,
+) {
+    let _ = app.emit(
+        "sync-progress",
+        SyncProgress {
+            job_id: job_id.to_string(),
+            key: item.key.clone(),
+            action: item.action,
+            completed: (result.succeeded.len() + result.skipped.len()) as u64,
+            failed: result.failed.len() as u64,
+            total,
+            bytes_transferred,
+            total_bytes,
+        },
+    );
+}
+
+/// One-way sync between a local folder and a bucket prefix: compare both
+/// sides, push or pull whatever changed and, with `delete`, remove what the
+/// source side no longer has. A dry run returns the plan without touching
+/// either side. Reports `sync-progress` and can be cancelled.
+#[tauri::command]
+pub async fn sync(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+    bucket: String,
+    prefix: String,
+    folder_path: String,
+    options: Option<SyncOptions>,
+) -> AppResult<SyncSummary> {
+    let options = options.unwrap_or_default();
+    info!(
+        "Syncing '{}' {} '{}/{}' ({:?}{}{})",
+        folder_path,
+        match options.direction {
+            SyncDirection::Push => "to",
+            SyncDirection::Pull => "from",
+        },
+        bucket,
+        prefix,
+        options.compare,
+        if options.delete {
+            ", deleting extras"
+        } else {
+            ""
+        },
+        if options.dry_run { ", dry run" } else { "" }
+    );
+
+    let connection = state.bucket_connection(&connection_id, &bucket).await?;
+    let (mut patterns, content_type_rules) = {
+        let settings = state.settings.lock().await;
+        (
+            settings.ignore_patterns.clone(),
+            settings.content_type_rules.clone(),
+        )
+    };
+    patterns.extend(options.ignore_patterns.iter().cloned());
+    let rules = IgnoreRules::new(&patterns);
+
+    let key_prefix = if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.clone()
+    } else {
+        format!("{}/", prefix)
+    };
+    let root = PathBuf::from(&folder_path);
+
+    let plan = SyncService::plan(&connection, &bucket, &key_prefix, &root, &rules, &options)
+        .await
+        .inspect_err(|e| {
+            error!(
+                "Failed to plan sync of '{}' with '{}/{}': {}",
+                folder_path, bucket, key_prefix, e
+            )
+        })?;
+    info!(
+        "Sync plan: {} uploads, {} downloads, {} deletions, {} unchanged ({} bytes)",
+        plan.uploads.len(),
+        plan.downloads.len(),
+        plan.deletions.len(),
+        plan.unchanged_count,
+        plan.total_bytes
+    );
+
+    if options.dry_run || plan.is_empty() {
+        return Ok(SyncSummary {
+            plan,
+            result: BatchResult::default(),
+            bytes_transferred: 0,
+            dry_run: options.dry_run,
+        });
+    }
+
+    let operator = S3Service::create_operator(&connection, &bucket)?;
+    let concurrency = TransferTuner::concurrency(options.concurrency.unwrap_or(SYNC_CONCURRENCY));
+    let job = state.start_job("sync", &bucket).await;
+    let total = plan.len() as u64;
+
+    let mut result = BatchResult::default();
+    let mut bytes_transferred: u64 = 0;
+    let mut uploaded = Vec::new();
+
+    let mut transfers = stream::iter(plan.uploads.iter().chain(&plan.downloads))
+        .map(|item| {
+            let app = &app;
+            let connection = &connection;
+            let operator = &operator;
+            let bucket = &bucket;
+            let content_type_rules = &content_type_rules;
+            let cancelled = job.cancelled();
+            async move {
+                if cancelled.load(Ordering::Relaxed) {
+                    return (item, None);
+                }
+                let outcome = match item.action {
+                    SyncAction::Upload => {
+                        upload_item(connection, operator, content_type_rules, item).await
+                    }
+                    _ => download_item(app, operator, connection, bucket, item).await,
+                };
+                (item, Some(outcome))
+            }
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    while let Some((item, outcome)) = transfers.next().await {
+        match outcome {
+            None => result.skip(item.key.clone()),
+            Some(Ok(bytes)) => {
+                debug!(
+                    "Synced '{}' ({:?}, {:?})",
+                    item.key, item.action, item.reason
+                );
+                bytes_transferred += bytes;
+                result.succeed(item.key.clone());
+                if item.action == SyncAction::Upload {
+                    uploaded.push((item.key.clone(), bytes));
+                }
+            }
+            Some(Err(e)) => {
+                error!("Failed to sync '{}' ({:?}): {}", item.key, item.action, e);
+                result.fail(item.key.clone(), &e);
+            }
+        }
+        emit_progress(
+            &app,
+            &job.id,
+            item,
+            &result,
+            total,
+            bytes_transferred,
+            plan.total_bytes,
+        );
+    }
+    drop(transfers);
+
+    // Only mirror deletions once the source side made it across whole
+    if !result.failed.is_empty() || job.cancelled().load(Ordering::Relaxed) {
+        if !plan.deletions.is_empty() {
+            warn!(
+                "Not deleting {} files after failed or cancelled transfers",
+                plan.deletions.len()
+            );
+        }
+        for item in &plan.deletions {
+            result.skip(item.key.clone());
+        }
+    } else if options.direction == SyncDirection::Push {
+        if let Some(last) = plan.deletions.last() {
+            let keys = plan.deletions.iter().map(|i| i.key.clone()).collect();
+            result.merge(S3Service::delete_keys(&connection, &bucket, keys).await?);
+            emit_progress(
+                &app,
+                &job.id,
+                last,
+                &result,
+                total,
+                bytes_transferred,
+                plan.total_bytes,
+            );
+        }
+    } else {
+        for item in &plan.deletions {
+            match tokio::fs::remove_file(&item.local_path).await {
+                Ok(()) => {
+                    debug!("Removed '{}'", item.local_path);
+                    result.succeed(item.key.clone());
+                }
+                Err(e) => {
+                    error!("Failed to remove '{}': {}", item.local_path, e);
+                    result.fail(item.key.clone(), &e.into());
+                }
+            }
+            emit_progress(
+                &app,
+                &job.id,
+                item,
+                &result,
+                total,
+                bytes_transferred,
+                plan.total_bytes,
+            );
+        }
+    }
+    info!(
+        "Synced '{}' with '{}/{}': {} succeeded, {} skipped, {} failed, {} bytes transferred",
+        folder_path,
+        bucket,
+        key_prefix,
+        result.succeeded.len(),
+        result.skipped.len(),
+        result.failed.len(),
+        bytes_transferred
+    );
+
+    spawn_post_upload_hooks(&app, &state, &connection, &bucket, uploaded).await;
+    job.finish(result.succeeded.len() as u64, result.failed.len() as u64);
+    Ok(SyncSummary {
+        plan,
+        result,
+        bytes_transferred,
+        dry_run: false,
+    })
+}
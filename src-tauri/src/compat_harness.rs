@@ -0,0 +1,67 @@
+//! Entry point for the self-hosted compatibility tests. Reads the endpoint
+//! from the environment:
+//!
+//! - `BAUL_COMPAT_ENDPOINT`, e.g. `http://localhost:9000`
+//! - `BAUL_COMPAT_BUCKET`, which must already exist
+//! - `BAUL_COMPAT_ACCESS_KEY` and `BAUL_COMPAT_SECRET_KEY`
+//! - `BAUL_COMPAT_REGION`, defaulting to `us-east-1`
+
+use std::collections::HashMap;
+use std::env;
+
+use chrono::Utc;
+
+use crate::models::{S3ConnectionWithSecret, S3Provider};
+use crate::services::CompatCheckService;
+
+pub use crate::models::{CompatCheckResult, CompatFeature, CompatReport};
+
+/// Prefix the checks write under, kept apart from the app's probe prefix.
+const SCRATCH_PREFIX: &str = ".baul-compat-tests/";
+
+fn var(name: &str) -> Result<String, String> {
+    env::var(name).map_err(|_| format!("{} is not set", name))
+}
+
+fn connection_from_env(endpoint: String) -> Result<S3ConnectionWithSecret, String> {
+    let now = Utc::now().timestamp();
+    Ok(S3ConnectionWithSecret {
+        id: "compat-tests".to_string(),
+        name: "Compatibility tests".to_string(),
+        provider: S3Provider::Custom,
+        use_ssl: !endpoint.starts_with("http://"),
+        endpoint,
+        region: env::var("BAUL_COMPAT_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+        access_key: var("BAUL_COMPAT_ACCESS_KEY")?,
+        secret_key: var("BAUL_COMPAT_SECRET_KEY")?,
+        use_path_style: true,
+        endpoint_template: None,
+        signature_version: Default::default(),
+        compatibility: Default::default(),
+        rate_limits: Default::default(),
+        root_prefix: None,
+        cdn_distributions: HashMap::new(),
+        key_templates: Vec::new(),
+        preset_id: None,
+        delete_batch_size: None,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+/// Run the compatibility checks against the configured endpoint, or `None`
+/// when `BAUL_COMPAT_ENDPOINT` isn't set.
+pub async fn run_from_env() -> Option<Result<CompatReport, String>> {
+    let endpoint = env::var("BAUL_COMPAT_ENDPOINT")
+        .ok()
+        .filter(|e| !e.is_empty())?;
+
+    let run = async {
+        let connection = connection_from_env(endpoint)?;
+        let bucket = var("BAUL_COMPAT_BUCKET")?;
+        CompatCheckService::run(&connection, &bucket, SCRATCH_PREFIX)
+            .await
+            .map_err(|e| e.to_string())
+    };
+    Some(run.await)
+}
@@ -18,11 +18,148 @@ pub enum AppError {
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
+    #[error("Not supported by this provider: {0}")]
+    NotSupported(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Cannot write to destination: {0}")]
+    DestinationNotWritable(String),
+
+    #[error("Already exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("Conflicts with an existing object: {0}")]
+    Conflict(String),
+
+    #[error("Integrity check failed: {0}")]
+    IntegrityError(String),
+
     #[error("Keyring error: {0}")]
     KeyringError(String),
 
+    #[error("Keychain access denied: {0}")]
+    KeychainLocked(String),
+
+    #[error("Preview failed: {0}")]
+    PreviewError(String),
+
+    #[error("Webhook failed: {0}")]
+    WebhookError(String),
+
+    #[error("Bucket index error: {0}")]
+    IndexError(String),
+
+    /// A provider error users commonly trip over, with what to do about it.
+    #[error("{message}. {hint}")]
+    ProviderError {
+        code: &'static str,
+        message: String,
+        hint: &'static str,
+    },
+
     #[error("OpenDAL error: {0}")]
-    OpendalError(#[from] opendal::Error),
+    OpendalError(opendal::Error),
+}
+
+impl AppError {
+    /// Stable identifier for the error kind, for the frontend to match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::S3Error(_) => "s3_error",
+            AppError::ConfigError(_) => "config_error",
+            AppError::ConnectionNotFound(_) => "connection_not_found",
+            AppError::IoError(_) => "io_error",
+            AppError::SerializationError(_) => "serialization_error",
+            AppError::NotSupported(_) => "not_supported",
+            AppError::NotFound(_) => "not_found",
+            AppError::DestinationNotWritable(_) => "destination_not_writable",
+            AppError::AlreadyExists(_) => "already_exists",
+            AppError::Conflict(_) => "conflict",
+            AppError::IntegrityError(_) => "integrity_error",
+            AppError::KeyringError(_) => "keyring_error",
+            AppError::KeychainLocked(_) => "keychain_locked",
+            AppError::PreviewError(_) => "preview_error",
+            AppError::WebhookError(_) => "webhook_error",
+            AppError::IndexError(_) => "index_error",
+            AppError::ProviderError { code, .. } => code,
+            AppError::OpendalError(_) => "opendal_error",
+        }
+    }
+
+    /// Translate an S3 error code into a `ProviderError` with a suggested
+    /// fix, or `None` for codes without one.
+    pub fn from_provider(code: &str, message: &str) -> Option<AppError> {
+        let (kind, hint) = match code {
+            "SignatureDoesNotMatch" => (
+                "signature_mismatch",
+                "Check the secret key. If it is right, a proxy may be rewriting \
+                 requests; for self-hosted endpoints try path-style addressing",
+            ),
+            "InvalidAccessKeyId" => (
+                "invalid_access_key",
+                "Check the access key and that the endpoint belongs to the \
+                 provider that issued it",
+            ),
+            "RequestTimeTooSkewed" => (
+                "clock_skew",
+                "This computer's clock is too far from the server's; sync it \
+                 and try again",
+            ),
+            "BucketAlreadyOwnedByYou" => (
+                "bucket_already_owned",
+                "You already own a bucket with this name, so there is nothing \
+                 to create",
+            ),
+            "BucketAlreadyExists" => (
+                "bucket_name_taken",
+                "Bucket names are shared by every account; choose another name",
+            ),
+            "PermanentRedirect"
+            | "AuthorizationHeaderMalformed"
+            | "IllegalLocationConstraintException"
+            | "IncorrectEndpoint" => (
+                "wrong_region",
+                "The bucket is in another region; set the connection's region \
+                 to the bucket's, or use path-style addressing for endpoints \
+                 that don't route by region",
+            ),
+            _ => return None,
+        };
+
+        let message = message.trim().trim_end_matches('.');
+        Some(AppError::ProviderError {
+            code: kind,
+            message: if message.is_empty() {
+                code.to_string()
+            } else {
+                message.to_string()
+            },
+            hint,
+        })
+    }
+
+    /// Pull `<Code>` and `<Message>` out of an S3 error body quoted in an
+    /// error message.
+    fn provider_code_in(text: &str) -> Option<(&str, &str)> {
+        let tag = |name: &str| {
+            let open = format!("<{}>", name);
+            let start = text.find(&open)? + open.len();
+            let end = text[start..].find(&format!("</{}>", name))?;
+            Some(&text[start..start + end])
+        };
+        Some((tag("Code")?, tag("Message").unwrap_or_default()))
+    }
+}
+
+impl From<opendal::Error> for AppError {
+    fn from(e: opendal::Error) -> Self {
+        let text = e.to_string();
+        AppError::provider_code_in(&text)
+            .and_then(|(code, message)| AppError::from_provider(code, message))
+            .unwrap_or(AppError::OpendalError(e))
+    }
 }
 
 impl Serialize for AppError {
@@ -1,18 +1,17 @@
 mod commands;
+#[cfg(feature = "compat-tests")]
+pub mod compat_harness;
 mod error;
 mod models;
 mod services;
 mod state;
-
-use std::collections::HashMap;
+pub mod util;
 
 use log::{debug, info, warn};
 use tauri::Manager;
 use tauri_plugin_log::{Target, TargetKind};
 
-use models::S3ConnectionWithSecret;
-use services::ConfigService;
-use services::CredentialService;
+use services::{ClockSkew, ConfigService, RateLimiter, TransferTuner};
 use state::AppState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -30,6 +29,7 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .manage(AppState::default())
         .setup(|app| {
             info!("Baul S3 Client starting up");
@@ -37,70 +37,36 @@ pub fn run() {
             // Load saved connections from config file
             let state = app.state::<AppState>();
 
-            match ConfigService::load_connections() {
-                Ok(connections) => {
-                    let connection_count = connections.len();
-                    debug!("Found {} saved connections", connection_count);
-
-                    let rt = tokio::runtime::Runtime::new().unwrap();
-                    rt.block_on(async {
-                        let mut state_connections: HashMap<String, S3ConnectionWithSecret> =
-                            HashMap::new();
-
-                        for (id, conn) in connections {
-                            // Try to get secret from keychain
-                            match CredentialService::get_secret(&id) {
-                                Ok(secret_key) => {
-                                    debug!("Loaded credentials for connection: {}", conn.name);
-                                    let full_conn = S3ConnectionWithSecret {
-                                        id: conn.id,
-                                        name: conn.name,
-                                        provider: conn.provider,
-                                        endpoint: conn.endpoint,
-                                        region: conn.region,
-                                        access_key: conn.access_key,
-                                        secret_key,
-                                        use_ssl: conn.use_ssl,
-                                        use_path_style: conn.use_path_style,
-                                        created_at: conn.created_at,
-                                        updated_at: conn.updated_at,
-                                    };
-                                    state_connections.insert(id, full_conn);
-                                }
-                                Err(e) => {
-                                    warn!(
-                                        "Failed to load credentials for connection '{}': {}",
-                                        conn.name, e
-                                    );
-                                    // Still add the connection but with empty secret
-                                    let full_conn = S3ConnectionWithSecret {
-                                        id: conn.id,
-                                        name: conn.name,
-                                        provider: conn.provider,
-                                        endpoint: conn.endpoint,
-                                        region: conn.region,
-                                        access_key: conn.access_key,
-                                        secret_key: String::new(),
-                                        use_ssl: conn.use_ssl,
-                                        use_path_style: conn.use_path_style,
-                                        created_at: conn.created_at,
-                                        updated_at: conn.updated_at,
-                                    };
-                                    state_connections.insert(id, full_conn);
-                                }
-                            }
-                        }
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            match rt.block_on(state.load_connections()) {
+                Ok(connection_count) => {
+                    info!(
+                        "Loaded {} connections from profile '{}'",
+                        connection_count,
+                        ConfigService::active_profile()
+                    );
+                }
+                Err(e) => {
+                    warn!("No saved connections found or failed to load: {}", e);
+                }
+            }
 
-                        *state.connections.lock().await = state_connections;
+            match ConfigService::load_settings() {
+                Ok(settings) => {
+                    RateLimiter::set_bandwidth_schedule(settings.bandwidth.clone());
+                    ClockSkew::set_compensation(settings.compensate_clock_skew);
+                    TransferTuner::set_low_memory(settings.low_memory);
+                    tauri::async_runtime::block_on(async {
+                        *state.settings.lock().await = settings;
                     });
-
-                    info!("Loaded {} connections from config", connection_count);
+                    debug!("Loaded application settings");
                 }
                 Err(e) => {
-                    warn!("No saved connections found or failed to load: {}", e);
+                    warn!("Failed to load settings, using defaults: {}", e);
                 }
             }
 
+            state.mark_ready();
             info!("Baul initialization complete");
             Ok(())
         })
@@ -112,8 +78,18 @@ pub fn run() {
             commands::update_connection,
             commands::delete_connection,
             commands::test_connection,
+            commands::test_write_access,
+            commands::run_compat_check,
+            commands::get_clock_skew,
             commands::export_connections,
             commands::import_connections,
+            commands::get_credential_errors,
+            commands::retry_unlock_connection,
+            commands::list_orphaned_secrets,
+            commands::cleanup_secrets,
+            // Profile commands
+            commands::list_profiles,
+            commands::switch_profile,
             // Bucket commands
             commands::list_buckets,
             commands::create_bucket,
@@ -122,19 +98,106 @@ pub fn run() {
             commands::head_bucket,
             commands::get_bucket_versioning,
             commands::get_bucket_stats,
+            commands::get_provider_capabilities,
+            commands::set_bucket_distribution,
+            commands::set_key_template,
+            commands::invalidate_paths,
+            commands::audit_bucket,
+            commands::scan_public_objects,
+            commands::report_noncurrent_versions,
+            commands::get_intelligent_tiering_configs,
+            commands::put_intelligent_tiering_config,
+            commands::delete_intelligent_tiering_config,
+            commands::get_inventory_snapshot,
+            commands::generate_scoped_credentials,
+            commands::get_bucket_policy,
+            commands::validate_bucket_policy,
+            commands::put_bucket_policy,
+            commands::delete_bucket_policy,
+            commands::get_bucket_encryption,
+            commands::put_bucket_encryption,
+            commands::get_bucket_acl,
+            commands::put_bucket_acl,
+            commands::get_bookmarks,
+            commands::save_bookmark,
+            commands::delete_bookmark,
             // Object commands
             commands::list_objects,
+            commands::list_more_objects,
             commands::get_object_details,
             commands::get_object_metadata,
+            commands::get_object_attributes,
+            commands::update_object_metadata,
+            commands::get_object_history,
+            commands::list_object_versions,
+            commands::download_object_version,
+            commands::restore_object_version,
+            commands::delete_object_version,
+            commands::prefetch_object_details,
+            commands::estimate_prefix,
+            commands::summarize_selection,
+            commands::get_recent_activity,
             commands::upload_file,
             commands::download_file,
+            commands::download_prefix,
+            commands::download_objects,
+            commands::get_local_copies,
             commands::delete_objects,
+            commands::delete_prefix,
+            commands::check_delete_protection,
             commands::create_folder,
             commands::get_presigned_url,
+            commands::get_presigned_urls,
+            commands::get_presigned_upload_url,
+            commands::share_prefix,
+            commands::get_streaming_url,
+            commands::copy_to_clipboard,
+            commands::get_preview_plan,
+            commands::preview_document,
+            commands::preview_sqlite,
+            commands::preview_config_object,
             commands::get_object_text,
             commands::copy_object,
+            commands::copy_objects,
+            commands::copy_prefix,
+            commands::move_prefix,
             commands::rename_object,
+            commands::rename_prefix,
+            commands::search_by_tag,
+            commands::search_objects,
+            commands::build_index,
+            commands::refresh_index,
+            commands::query_index,
+            commands::get_index_stats,
+            commands::get_object_acl,
+            commands::put_object_acl,
+            commands::upload_folder,
+            commands::find_upload_duplicates,
+            commands::generate_index,
+            commands::deploy_site,
+            commands::sync,
+            commands::verify_journal,
+            commands::open_object_externally,
+            commands::upload_external_edit,
+            commands::close_external_edit,
+            commands::stage_objects_for_drag,
+            commands::release_drag_staging,
+            // Settings commands
+            commands::get_settings,
+            commands::update_settings,
+            commands::save_provider_preset,
+            commands::delete_provider_preset,
+            commands::export_app_config,
+            commands::import_app_config,
+            commands::get_app_status,
+            commands::cancel_job,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Playback copies and drag staging only live as long as the app
+            if let tauri::RunEvent::Exit = event {
+                app.state::<AppState>().remove_temp_dirs();
+            }
+        });
 }
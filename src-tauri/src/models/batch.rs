@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFailure {
+    pub key: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// How far a bulk operation run in batches has got.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    /// Items in the whole operation.
+    pub total: u64,
+    pub batches_completed: usize,
+    pub batch_count: usize,
+}
+
+/// Per-item outcome of a bulk operation, so one failure doesn't hide the
+/// state of everything else.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<BatchFailure>,
+    pub skipped: Vec<String>,
+}
+
+impl BatchResult {
+    pub fn succeed(&mut self, key: impl Into<String>) {
+        self.succeeded.push(key.into());
+    }
+
+    pub fn fail(&mut self, key: impl Into<String>, error: &AppError) {
+        self.failed.push(BatchFailure {
+            key: key.into(),
+            code: error.code().to_string(),
+            message: error.to_string(),
+        });
+    }
+
+    pub fn skip(&mut self, key: impl Into<String>) {
+        self.skipped.push(key.into());
+    }
+
+    pub fn merge(&mut self, other: BatchResult) {
+        self.succeeded.extend(other.succeeded);
+        self.failed.extend(other.failed);
+        self.skipped.extend(other.skipped);
+    }
+}
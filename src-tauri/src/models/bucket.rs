@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::models::{BatchResult, ListingQuery, S3Object, ScanCutoff, SearchOptions};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -14,4 +17,352 @@ pub struct BucketStats {
     pub name: String,
     pub object_count: u64,
     pub total_size: u64,
+    /// Set when a scan limit stopped the count; the totals are partial.
+    #[serde(default)]
+    pub stopped_early: Option<ScanCutoff>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingSeverity {
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditFinding {
+    pub severity: FindingSeverity,
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GranteeType {
+    CanonicalUser,
+    Email,
+    Group,
+}
+
+/// Serialized the way S3 spells it, e.g. `FULL_CONTROL`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AclPermission {
+    FullControl,
+    Read,
+    Write,
+    ReadAcp,
+    WriteAcp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AclGrant {
+    /// Canonical user id, email or group URI, whichever the grant names.
+    pub grantee: String,
+    pub grantee_type: GranteeType,
+    pub display_name: Option<String>,
+    pub permission: AclPermission,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessControlList {
+    pub owner: Option<String>,
+    pub grants: Vec<AclGrant>,
+    /// Whether any grant goes to everyone or to any AWS account.
+    pub is_public: bool,
+}
+
+/// Canned ACLs, named as in the `x-amz-acl` header. The `bucket-owner-*`
+/// and `aws-exec-read` ones only apply to objects.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CannedAcl {
+    Private,
+    PublicRead,
+    PublicReadWrite,
+    AuthenticatedRead,
+    AwsExecRead,
+    BucketOwnerRead,
+    BucketOwnerFullControl,
+}
+
+impl CannedAcl {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CannedAcl::Private => "private",
+            CannedAcl::PublicRead => "public-read",
+            CannedAcl::PublicReadWrite => "public-read-write",
+            CannedAcl::AuthenticatedRead => "authenticated-read",
+            CannedAcl::AwsExecRead => "aws-exec-read",
+            CannedAcl::BucketOwnerRead => "bucket-owner-read",
+            CannedAcl::BucketOwnerFullControl => "bucket-owner-full-control",
+        }
+    }
+
+    pub fn applies_to_buckets(&self) -> bool {
+        matches!(
+            self,
+            CannedAcl::Private
+                | CannedAcl::PublicRead
+                | CannedAcl::PublicReadWrite
+                | CannedAcl::AuthenticatedRead
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicAccessBlock {
+    pub block_public_acls: bool,
+    pub ignore_public_acls: bool,
+    pub block_public_policy: bool,
+    pub restrict_public_buckets: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExposureSource {
+    BucketPolicy,
+    ObjectAcl,
+}
+
+/// An object anyone can read, emitted as `public-object` during a scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicObject {
+    pub key: String,
+    pub source: ExposureSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicScanSummary {
+    pub bucket: String,
+    pub prefix: String,
+    pub policy_is_public: bool,
+    /// Anyone can list the bucket; object reads still depend on the rest.
+    pub bucket_acl_is_public: bool,
+    pub scanned_count: u64,
+    /// Objects whose ACL was actually fetched.
+    pub sampled_count: u64,
+    pub public_count: u64,
+    /// Set when a scan limit stopped the listing; later keys weren't
+    /// checked.
+    pub stopped_early: Option<ScanCutoff>,
+}
+
+/// Security-relevant bucket settings in one place. Sections the provider
+/// couldn't return are listed in `unavailable` with the reason.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketAuditReport {
+    pub bucket: String,
+    pub generated_at: i64,
+    pub policy: Option<String>,
+    pub policy_is_public: bool,
+    pub acl_grants: Vec<AclGrant>,
+    pub acl_is_public: bool,
+    pub public_access_block: Option<PublicAccessBlock>,
+    /// Default encryption algorithm, e.g. `AES256` or `aws:kms`.
+    pub encryption: Option<String>,
+    pub versioning: Option<String>,
+    pub mfa_delete: Option<String>,
+    pub logging_target: Option<String>,
+    pub object_lock: Option<String>,
+    pub is_public: bool,
+    pub is_encrypted: bool,
+    pub findings: Vec<AuditFinding>,
+    pub unavailable: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoncurrentPrefix {
+    pub prefix: String,
+    pub noncurrent_count: u64,
+    pub noncurrent_bytes: u64,
+    pub delete_marker_count: u64,
+}
+
+/// Storage held by old versions and delete markers, grouped by prefix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoncurrentVersionReport {
+    pub bucket: String,
+    pub prefix: String,
+    pub noncurrent_count: u64,
+    pub noncurrent_bytes: u64,
+    pub delete_marker_count: u64,
+    /// Largest first.
+    pub prefixes: Vec<NoncurrentPrefix>,
+    /// Outcome of the purge, when one was requested. Keys are
+    /// `key?versionId=...`.
+    pub purged: Option<BatchResult>,
+    /// Set when the purge only listed what it would delete.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TieringRule {
+    /// `ARCHIVE_ACCESS` or `DEEP_ARCHIVE_ACCESS`.
+    pub access_tier: String,
+    /// Days without access before objects move to the tier.
+    pub days: i32,
+}
+
+/// An S3 Intelligent-Tiering archive configuration for a bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntelligentTieringConfig {
+    pub id: String,
+    pub enabled: bool,
+    pub prefix: Option<String>,
+    pub tierings: Vec<TieringRule>,
+}
+
+/// The newest S3 Inventory report delivered for a bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InventorySnapshot {
+    pub configuration_id: String,
+    pub destination_bucket: String,
+    pub manifest_key: String,
+    /// When the report was generated; objects changed since aren't in it.
+    pub created_at: i64,
+    /// Set when the configuration only inventories part of the bucket.
+    pub filter_prefix: Option<String>,
+    /// Column names, in the order the data files use.
+    pub schema: Vec<String>,
+    /// Keys of the gzipped CSV data files in the destination bucket.
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SseAlgorithm {
+    /// S3-managed keys (`AES256`).
+    SseS3,
+    /// KMS keys (`aws:kms`).
+    SseKms,
+}
+
+/// A bucket's default server-side encryption.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketEncryptionConfig {
+    pub algorithm: SseAlgorithm,
+    /// KMS key ARN or id; the AWS-managed `aws/s3` key when absent.
+    pub kms_key_id: Option<String>,
+    /// Use S3 Bucket Keys to cut KMS request costs.
+    #[serde(default)]
+    pub bucket_key_enabled: bool,
+}
+
+/// A saved prefix with an optional label and note. Deleted bookmarks are
+/// kept as tombstones for a while so the deletion wins a later merge.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub prefix: String,
+    pub label: Option<String>,
+    pub note: Option<String>,
+    pub updated_at: i64,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Contents of the shared metadata object Baul keeps in a bucket.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BucketMeta {
+    pub version: u32,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// Progress of `build_index` / `refresh_index`, emitted per listing page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexProgress {
+    pub job_id: String,
+    pub bucket: String,
+    pub indexed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSummary {
+    pub job_id: String,
+    pub object_count: u64,
+    pub total_size: u64,
+    /// Rows dropped because their objects are gone from the bucket.
+    pub removed: u64,
+    /// Unset when the crawl was cancelled before it finished.
+    pub indexed_at: Option<i64>,
+}
+
+/// A search over a bucket's local index. Filters and sort order come from
+/// `filter`; `search` matches keys like `search_objects`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IndexQuery {
+    pub search: Option<String>,
+    pub search_options: SearchOptions,
+    pub prefix: String,
+    pub filter: ListingQuery,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for IndexQuery {
+    fn default() -> Self {
+        Self {
+            search: None,
+            search_options: SearchOptions::default(),
+            prefix: String::new(),
+            filter: ListingQuery::default(),
+            offset: 0,
+            limit: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexQueryResult {
+    pub objects: Vec<S3Object>,
+    pub has_more: bool,
+    /// When the index last finished a crawl; results may be older than the
+    /// bucket.
+    pub indexed_at: Option<i64>,
+}
+
+/// Object count and bytes for one group in `IndexStats`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeBreakdown {
+    pub name: String,
+    pub object_count: u64,
+    pub total_size: u64,
+}
+
+/// Size analytics for a prefix, from the local index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    pub prefix: String,
+    pub object_count: u64,
+    pub total_size: u64,
+    /// Direct sub-folders, largest first; objects right under the prefix
+    /// are grouped under an empty name.
+    pub folders: Vec<SizeBreakdown>,
+    /// By lowercase extension, largest first.
+    pub extensions: Vec<SizeBreakdown>,
+    pub largest: Vec<S3Object>,
+    pub indexed_at: Option<i64>,
 }
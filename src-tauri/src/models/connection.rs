@@ -1,4 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// DeleteObjects batch sizes a connection or preset may override with.
+pub const DELETE_BATCH_SIZES: RangeInclusive<usize> = 1..=1000;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -13,6 +18,298 @@ pub enum S3Provider {
     Custom,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureVersion {
+    #[default]
+    V4,
+    /// For legacy gateways. Only requests made through the AWS SDK can be
+    /// signed this way; operations built on OpenDAL fail with `NotSupported`.
+    V2,
+}
+
+/// Optional S3 APIs that some providers don't implement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProviderFeature {
+    BucketLocation,
+    BucketVersioning,
+    IntelligentTiering,
+}
+
+impl S3Provider {
+    pub fn supports(&self, feature: ProviderFeature) -> bool {
+        !matches!(
+            (self, feature),
+            (S3Provider::CloudflareR2, ProviderFeature::BucketVersioning)
+                | (
+                    S3Provider::Minio
+                        | S3Provider::CloudflareR2
+                        | S3Provider::Digitalocean
+                        | S3Provider::Backblaze
+                        | S3Provider::Wasabi,
+                    ProviderFeature::IntelligentTiering
+                )
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipartLimits {
+    pub max_parts: u32,
+    pub min_part_size: u64,
+    pub max_object_size: u64,
+}
+
+impl S3Provider {
+    /// Most keys a single DeleteObjects request may carry.
+    pub fn max_delete_batch(&self) -> usize {
+        match self {
+            S3Provider::CloudflareR2 => 700,
+            _ => 1000,
+        }
+    }
+
+    /// Documented multipart limits; unknown gateways get the AWS values.
+    pub fn multipart_limits(&self) -> MultipartLimits {
+        const MIB: u64 = 1024 * 1024;
+        const TIB: u64 = 1024 * 1024 * MIB;
+
+        let max_object_size = match self {
+            S3Provider::Backblaze => 10 * TIB,
+            S3Provider::Minio => 50 * TIB,
+            _ => 5 * TIB,
+        };
+
+        MultipartLimits {
+            max_parts: 10_000,
+            min_part_size: 5 * MIB,
+            max_object_size,
+        }
+    }
+}
+
+/// What a connection's backend supports for a given bucket. Probed
+/// features are `None` when the probe couldn't tell, e.g. access was denied
+/// or the server failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCapabilities {
+    pub versioning: Option<bool>,
+    pub tagging: Option<bool>,
+    pub lifecycle: Option<bool>,
+    pub object_lock: Option<bool>,
+    pub select: bool,
+    pub acceleration: Option<bool>,
+    pub multipart: MultipartLimits,
+}
+
+impl ProviderCapabilities {
+    /// Whether every probe gave an answer. Inconclusive results are worth
+    /// probing again rather than caching.
+    pub fn is_complete(&self) -> bool {
+        [
+            self.versioning,
+            self.tagging,
+            self.lifecycle,
+            self.object_lock,
+            self.acceleration,
+        ]
+        .iter()
+        .all(Option::is_some)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStep {
+    Put,
+    Read,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeStepResult {
+    pub step: ProbeStep,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Outcome of writing, reading back and deleting a throwaway object.
+/// Steps after a failed put are not attempted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteProbeReport {
+    pub bucket: String,
+    pub key: String,
+    pub steps: Vec<ProbeStepResult>,
+    pub success: bool,
+}
+
+/// A piece of the S3 API Baul relies on, exercised by the compatibility check.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompatFeature {
+    PutObject,
+    HeadObject,
+    GetObject,
+    RangeRead,
+    ListObjects,
+    CopyObject,
+    MultipartUpload,
+    ObjectTagging,
+    PresignedUrl,
+    Versioning,
+    BatchDelete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatCheckResult {
+    pub feature: CompatFeature,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Which Baul features work against an endpoint. Nothing runs after a
+/// failed put, since every other check needs the scratch object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatReport {
+    pub bucket: String,
+    pub endpoint: String,
+    pub checks: Vec<CompatCheckResult>,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanReason {
+    /// The connection it belonged to no longer exists.
+    UnknownConnection,
+    /// Written under another install's namespace (synced or copied keychain).
+    ForeignNamespace,
+}
+
+/// A keychain entry that no connection on this install will ever read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedSecret {
+    pub account: String,
+    pub connection_id: String,
+    pub reason: OrphanReason,
+}
+
+/// Outcome of a provider-dependent query, so the UI can hide features a
+/// provider lacks instead of surfacing an error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", content = "value", rename_all = "snake_case")]
+pub enum FeatureResult<T> {
+    Supported(T),
+    NotSupported,
+}
+
+/// How far a connection's server clock is from this machine's.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockSkewReport {
+    /// Server time minus local time; positive when the local clock is behind.
+    pub offset_seconds: i64,
+    pub measured_at: i64,
+    /// Whether SDK requests are signed with the server's time instead.
+    pub compensated: bool,
+}
+
+/// Request tweaks for S3-compatible providers that choke on newer SDK defaults.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompatibilityOptions {
+    pub disable_request_checksums: bool,
+    pub unsigned_payload: bool,
+    pub disable_expect_continue: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestKind {
+    List,
+    Get,
+    Put,
+    Delete,
+}
+
+/// Requests per second allowed for each kind of call; unset means unlimited.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RateLimits {
+    pub list: Option<f64>,
+    pub get: Option<f64>,
+    pub put: Option<f64>,
+    pub delete: Option<f64>,
+}
+
+impl RateLimits {
+    pub fn for_kind(&self, kind: RequestKind) -> Option<f64> {
+        match kind {
+            RequestKind::List => self.list,
+            RequestKind::Get => self.get,
+            RequestKind::Put => self.put,
+            RequestKind::Delete => self.delete,
+        }
+    }
+}
+
+/// Naming rule for uploads into `prefix` of `bucket`, e.g.
+/// `{yyyy}/{mm}/{uuid}-{filename}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyTemplate {
+    pub bucket: String,
+    pub prefix: String,
+    pub template: String,
+}
+
+/// A user-defined provider, e.g. an internal S3 gateway, saved in settings
+/// and offered when creating connections. A connection created from it
+/// takes the endpoint, region, signature and compatibility options the
+/// caller leaves unset, and always its delete batch size; provider, SSL
+/// and path style are for the connection form to prefill.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderPreset {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Built-in provider whose behaviour the gateway follows.
+    #[serde(default)]
+    pub provider: S3Provider,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Endpoint with a `{region}` placeholder.
+    #[serde(default)]
+    pub endpoint_template: Option<String>,
+    #[serde(default)]
+    pub default_region: Option<String>,
+    #[serde(default = "default_true")]
+    pub use_ssl: bool,
+    #[serde(default)]
+    pub use_path_style: bool,
+    #[serde(default)]
+    pub signature_version: SignatureVersion,
+    #[serde(default)]
+    pub compatibility: CompatibilityOptions,
+    /// Keys per DeleteObjects request, for gateways with a lower limit.
+    #[serde(default)]
+    pub delete_batch_size: Option<usize>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct S3Connection {
@@ -24,6 +321,29 @@ pub struct S3Connection {
     pub access_key: String,
     pub use_ssl: bool,
     pub use_path_style: bool,
+    #[serde(default)]
+    pub endpoint_template: Option<String>,
+    #[serde(default)]
+    pub signature_version: SignatureVersion,
+    #[serde(default)]
+    pub compatibility: CompatibilityOptions,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    /// Confine every object operation to keys under this prefix.
+    #[serde(default)]
+    pub root_prefix: Option<String>,
+    /// CloudFront distribution ids keyed by bucket name.
+    #[serde(default)]
+    pub cdn_distributions: HashMap<String, String>,
+    /// Key templates applied to uploads into specific prefixes.
+    #[serde(default)]
+    pub key_templates: Vec<KeyTemplate>,
+    /// The provider preset the connection was created from, if any.
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// Overrides the provider's DeleteObjects batch size.
+    #[serde(default)]
+    pub delete_batch_size: Option<usize>,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -40,6 +360,29 @@ pub struct S3ConnectionWithSecret {
     pub secret_key: String,
     pub use_ssl: bool,
     pub use_path_style: bool,
+    #[serde(default)]
+    pub endpoint_template: Option<String>,
+    #[serde(default)]
+    pub signature_version: SignatureVersion,
+    #[serde(default)]
+    pub compatibility: CompatibilityOptions,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    /// Confine every object operation to keys under this prefix.
+    #[serde(default)]
+    pub root_prefix: Option<String>,
+    /// CloudFront distribution ids keyed by bucket name.
+    #[serde(default)]
+    pub cdn_distributions: HashMap<String, String>,
+    /// Key templates applied to uploads into specific prefixes.
+    #[serde(default)]
+    pub key_templates: Vec<KeyTemplate>,
+    /// The provider preset the connection was created from, if any.
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// Overrides the provider's DeleteObjects batch size.
+    #[serde(default)]
+    pub delete_batch_size: Option<usize>,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -55,8 +398,100 @@ impl From<S3ConnectionWithSecret> for S3Connection {
             access_key: conn.access_key,
             use_ssl: conn.use_ssl,
             use_path_style: conn.use_path_style,
+            endpoint_template: conn.endpoint_template,
+            signature_version: conn.signature_version,
+            compatibility: conn.compatibility,
+            rate_limits: conn.rate_limits,
+            root_prefix: conn.root_prefix,
+            cdn_distributions: conn.cdn_distributions,
+            key_templates: conn.key_templates,
+            preset_id: conn.preset_id,
+            delete_batch_size: conn.delete_batch_size,
             created_at: conn.created_at,
             updated_at: conn.updated_at,
         }
     }
 }
+
+impl S3ConnectionWithSecret {
+    pub fn from_connection(conn: S3Connection, secret_key: String) -> Self {
+        Self {
+            id: conn.id,
+            name: conn.name,
+            provider: conn.provider,
+            endpoint: conn.endpoint,
+            region: conn.region,
+            access_key: conn.access_key,
+            secret_key,
+            use_ssl: conn.use_ssl,
+            use_path_style: conn.use_path_style,
+            endpoint_template: conn.endpoint_template,
+            signature_version: conn.signature_version,
+            compatibility: conn.compatibility,
+            rate_limits: conn.rate_limits,
+            root_prefix: conn.root_prefix,
+            cdn_distributions: conn.cdn_distributions,
+            key_templates: conn.key_templates,
+            preset_id: conn.preset_id,
+            delete_batch_size: conn.delete_batch_size,
+            created_at: conn.created_at,
+            updated_at: conn.updated_at,
+        }
+    }
+
+    /// Most keys a single DeleteObjects request may carry for this
+    /// connection.
+    pub fn max_delete_batch(&self) -> usize {
+        self.delete_batch_size
+            .unwrap_or_else(|| self.provider.max_delete_batch())
+            .clamp(*DELETE_BATCH_SIZES.start(), *DELETE_BATCH_SIZES.end())
+    }
+
+    /// Resolve the endpoint for a region, expanding `{region}` in the
+    /// endpoint template when one is configured.
+    pub fn endpoint_for_region(&self, region: &str) -> String {
+        match self.endpoint_template.as_deref() {
+            Some(template) if !template.is_empty() => template.replace("{region}", region),
+            _ => self.endpoint.clone(),
+        }
+    }
+}
+
+/// What scoped credentials may do under their prefix.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScopedAccess {
+    #[default]
+    ReadOnly,
+    ReadWrite,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopedCredentialsOptions {
+    #[serde(default)]
+    pub access: ScopedAccess,
+    /// Lifetime in seconds, an hour when unset.
+    pub duration_seconds: Option<i32>,
+    /// Assume this role instead of requesting a federation token, which
+    /// needs the connection to hold an IAM user's long-term keys.
+    pub role_arn: Option<String>,
+    /// Federated user or session name shown in CloudTrail.
+    pub session_name: Option<String>,
+}
+
+/// Temporary credentials limited to one bucket prefix by a session policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopedCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: String,
+    pub expires_at: i64,
+    pub region: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub access: ScopedAccess,
+    /// The session policy the credentials were issued with.
+    pub policy: String,
+}
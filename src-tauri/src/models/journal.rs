@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalAction {
+    Upload,
+    Delete,
+    /// Left alone on purpose, e.g. matched by an ignore pattern.
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalStart {
+    pub job_id: String,
+    pub job: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub source: String,
+    pub dry_run: bool,
+    pub started_at: i64,
+}
+
+/// One file considered by a job and what happened to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub key: String,
+    pub local_path: Option<String>,
+    pub action: JournalAction,
+    pub size: u64,
+    /// Hex MD5 of the bytes sent, comparable with single-part ETags.
+    pub checksum: Option<String>,
+    /// `None` when the action succeeded.
+    pub error: Option<String>,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalFinish {
+    pub finished_at: i64,
+    pub succeeded: u64,
+    pub failed: u64,
+    /// Why the job stopped early; `None` when it ran to completion.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// A line of a job journal (JSON Lines, one record per line).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalRecord {
+    Start(JournalStart),
+    Entry(JournalEntry),
+    Finish(JournalFinish),
+}
@@ -1,7 +1,15 @@
+pub mod batch;
 pub mod bucket;
 pub mod connection;
+pub mod journal;
 pub mod object;
+pub mod settings;
+pub mod sync;
 
+pub use batch::*;
 pub use bucket::*;
 pub use connection::*;
+pub use journal::*;
 pub use object::*;
+pub use settings::*;
+pub use sync::*;
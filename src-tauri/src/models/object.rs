@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::error::AppError;
+use crate::models::{BatchFailure, BatchProgress, BatchResult, ScanCutoff};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct S3Object {
@@ -10,6 +13,9 @@ pub struct S3Object {
     pub etag: Option<String>,
     pub content_type: Option<String>,
     pub is_directory: bool,
+    /// For folders: number of objects beneath it, when the listing saw them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub child_count: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,19 +31,539 @@ pub struct ObjectMetadata {
     pub content_language: Option<String>,
     pub cache_control: Option<String>,
     pub storage_class: Option<String>,
+    /// `ARCHIVE_ACCESS` or `DEEP_ARCHIVE_ACCESS` when Intelligent-Tiering has
+    /// moved the object to an archive tier and it must be restored first.
+    pub archive_status: Option<String>,
+    /// Raw `x-amz-restore` header, e.g. `ongoing-request="true"`.
+    pub restore: Option<String>,
     pub version_id: Option<String>,
     pub custom_metadata: HashMap<String, String>,
+    /// Unset when the object was uploaded without one or the provider
+    /// doesn't support GetObjectAttributes.
+    pub checksum: Option<ObjectChecksum>,
+}
+
+/// What the details pane shows, from GetObjectAttributes where the
+/// provider has it and HEAD otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectAttributes {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<i64>,
+    pub etag: Option<String>,
+    pub storage_class: Option<String>,
+    pub checksum: Option<ObjectChecksum>,
+    /// Parts of a multipart upload; HEAD can't tell, so unset after a
+    /// fallback.
+    pub parts_count: Option<u32>,
+    pub version_id: Option<String>,
+    /// Whether HEAD stood in for GetObjectAttributes.
+    pub from_head: bool,
+}
+
+/// Checksum algorithms S3 can compute and store alongside an object,
+/// serialized the way S3 spells them, e.g. `CRC32C`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChecksumAlgorithm {
+    Crc32,
+    Crc32c,
+    Sha1,
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Crc32 => "CRC32",
+            ChecksumAlgorithm::Crc32c => "CRC32C",
+            ChecksumAlgorithm::Sha1 => "SHA1",
+            ChecksumAlgorithm::Sha256 => "SHA256",
+        }
+    }
+}
+
+/// A checksum the provider computed, base64 as S3 returns it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectChecksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub value: String,
+    /// `FULL_OBJECT`, or `COMPOSITE` for a checksum of the part checksums,
+    /// which only matches a local file hashed with the same part size.
+    pub checksum_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ListObjectsResult {
     pub objects: Vec<S3Object>,
+    /// Sub-folders as directory entries; `size` is only filled in when the
+    /// listing covered the folder's contents.
+    pub folders: Vec<S3Object>,
+    /// Keys of `folders`, for callers that only need the names.
     pub prefixes: Vec<String>,
     pub continuation_token: Option<String>,
     pub is_truncated: bool,
 }
 
+/// Order of a filtered or sorted listing. S3 returns keys by name, so only
+/// `Name` ascending can be paged straight from the server.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingSort {
+    #[default]
+    Name,
+    Size,
+    LastModified,
+}
+
+/// Sort and filter options for `list_objects`. Filters apply to objects;
+/// folders are always kept.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ListingQuery {
+    pub sort: ListingSort,
+    pub descending: bool,
+    /// Extensions to keep, with or without the dot; empty keeps all.
+    pub extensions: Vec<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<i64>,
+    pub modified_before: Option<i64>,
+}
+
+impl ListingQuery {
+    /// Whether every page has to be fetched before the first one can be
+    /// returned.
+    pub fn needs_full_listing(&self) -> bool {
+        self.sort != ListingSort::Name || self.descending
+    }
+
+    pub fn matches(&self, object: &S3Object) -> bool {
+        if !self.extensions.is_empty() {
+            let name = object.key.rsplit('/').next().unwrap_or_default();
+            let Some((_, extension)) = name.rsplit_once('.') else {
+                return false;
+            };
+            let wanted = self
+                .extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension));
+            if !wanted {
+                return false;
+            }
+        }
+
+        self.min_size.is_none_or(|min| object.size >= min)
+            && self.max_size.is_none_or(|max| object.size <= max)
+            && self
+                .modified_after
+                .is_none_or(|after| object.last_modified >= after)
+            && self
+                .modified_before
+                .is_none_or(|before| object.last_modified < before)
+    }
+
+    pub fn sort(&self, entries: &mut [S3Object]) {
+        match self.sort {
+            ListingSort::Name => entries.sort_by(|a, b| a.key.cmp(&b.key)),
+            ListingSort::Size => {
+                entries.sort_by(|a, b| a.size.cmp(&b.size).then_with(|| a.key.cmp(&b.key)))
+            }
+            ListingSort::LastModified => entries.sort_by(|a, b| {
+                a.last_modified
+                    .cmp(&b.last_modified)
+                    .then_with(|| a.key.cmp(&b.key))
+            }),
+        }
+        if self.descending {
+            entries.reverse();
+        }
+    }
+}
+
+/// One entry in a key's version history; delete markers carry no size or ETag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectVersion {
+    pub key: String,
+    pub version_id: Option<String>,
+    pub size: u64,
+    pub last_modified: Option<i64>,
+    pub etag: Option<String>,
+    pub storage_class: Option<String>,
+    pub owner: Option<String>,
+    pub is_latest: bool,
+    pub is_delete_marker: bool,
+}
+
+/// One page of versions under a prefix. Versions of a key are newest
+/// first; sub-folders are listed separately as in `ListObjectsResult`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListObjectVersionsResult {
+    pub versions: Vec<ObjectVersion>,
+    pub folders: Vec<String>,
+    pub next_key_marker: Option<String>,
+    pub next_version_id_marker: Option<String>,
+    pub is_truncated: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexMode {
+    #[default]
+    Listing,
+    Gallery,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentEncoding {
+    Gzip,
+    Brotli,
+}
+
+/// Cache-Control header applied to files with any of the listed extensions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheRule {
+    pub extensions: Vec<String>,
+    pub cache_control: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DeployOptions {
+    /// First matching rule wins; unmatched files get `default_cache_control`.
+    pub cache_rules: Vec<CacheRule>,
+    pub default_cache_control: Option<String>,
+    /// Pre-compress text assets and upload them with a Content-Encoding.
+    pub compression: Option<ContentEncoding>,
+    /// Remove remote objects under the prefix that no longer exist locally.
+    /// Objects matching the ignore patterns are kept.
+    pub delete_removed: bool,
+    /// Find removed objects from the bucket's newest S3 Inventory report
+    /// instead of listing the prefix. Objects added since the report stay.
+    pub use_inventory: bool,
+    pub dry_run: bool,
+    /// Also store the job journal in the bucket under this key.
+    pub journal_key: Option<String>,
+    /// Have the provider compute and store this checksum for every file.
+    pub checksum_algorithm: Option<ChecksumAlgorithm>,
+}
+
+impl Default for DeployOptions {
+    fn default() -> Self {
+        Self {
+            cache_rules: vec![CacheRule {
+                extensions: vec!["html".into(), "htm".into(), "json".into(), "xml".into()],
+                cache_control: "no-cache".into(),
+            }],
+            default_cache_control: Some("public, max-age=31536000, immutable".into()),
+            compression: Some(ContentEncoding::Gzip),
+            delete_removed: false,
+            use_inventory: false,
+            dry_run: false,
+            journal_key: None,
+            checksum_algorithm: None,
+        }
+    }
+}
+
+impl DeployOptions {
+    pub fn cache_control_for(&self, key: &str) -> Option<&str> {
+        let extension = key.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+
+        self.cache_rules
+            .iter()
+            .find(|rule| {
+                extension
+                    .as_deref()
+                    .is_some_and(|ext| rule.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+            })
+            .map(|rule| rule.cache_control.as_str())
+            .or(self.default_cache_control.as_deref())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploySummary {
+    pub uploads: BatchResult,
+    pub deletions: BatchResult,
+    pub ignored_count: u64,
+    pub total_bytes: u64,
+    pub dry_run: bool,
+    /// Local file recording every action this run took.
+    pub journal_path: Option<String>,
+}
+
+/// Size of a prefix from a bounded listing. When `is_complete` is false the
+/// counts are lower bounds ("more than N objects").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefixEstimate {
+    pub prefix: String,
+    pub object_count: u64,
+    pub total_size: u64,
+    pub is_complete: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionTotal {
+    pub count: u64,
+    pub size: u64,
+}
+
+/// Totals over every object in a selection, with selected prefixes expanded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionSummary {
+    pub object_count: u64,
+    pub total_size: u64,
+    /// Keyed by lowercase extension; `""` for names without one.
+    pub size_by_extension: HashMap<String, ExtensionTotal>,
+    pub newest_modified: Option<i64>,
+    pub oldest_modified: Option<i64>,
+    /// Set when a scan limit stopped expanding the prefixes; the totals
+    /// are partial.
+    pub stopped_early: Option<ScanCutoff>,
+}
+
+impl SelectionSummary {
+    pub fn add(&mut self, object: &S3Object) {
+        self.object_count += 1;
+        self.total_size += object.size;
+
+        let name = object.key.rsplit('/').next().unwrap_or_default();
+        let extension = name
+            .rsplit_once('.')
+            .filter(|(stem, _)| !stem.is_empty())
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        let total = self.size_by_extension.entry(extension).or_default();
+        total.count += 1;
+        total.size += object.size;
+
+        self.newest_modified = self.newest_modified.max(Some(object.last_modified));
+        self.oldest_modified = Some(
+            self.oldest_modified
+                .map_or(object.last_modified, |t| t.min(object.last_modified)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefixActivity {
+    pub prefix: String,
+    pub changed_count: u64,
+    pub changed_bytes: u64,
+    pub latest_modified: Option<i64>,
+    /// Set when a scan limit stopped the walk of this prefix.
+    pub stopped_early: Option<ScanCutoff>,
+}
+
+/// Objects changed since `since` across the watched prefixes, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentActivity {
+    pub since: i64,
+    pub prefixes: Vec<PrefixActivity>,
+    pub feed: Vec<S3Object>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewMode {
+    Text,
+    Hex,
+    Image,
+    /// JSON, YAML, CSV and similar, rendered as a tree or table.
+    Structured,
+    /// Too large or not previewable; open in another application.
+    External,
+}
+
+/// How the frontend should preview an object, decided from its size,
+/// declared content type and leading bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewPlan {
+    pub key: String,
+    pub mode: PreviewMode,
+    pub size: u64,
+    pub content_type: Option<String>,
+    /// Type recognized from magic bytes, when it differs from the declared one.
+    pub detected_type: Option<String>,
+    /// How many bytes the preview should fetch.
+    pub max_bytes: u64,
+    pub reason: Option<String>,
+}
+
+/// First page of a document rendered to a PNG.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentPreview {
+    pub key: String,
+    /// Base64-encoded PNG.
+    pub image: String,
+    pub width: u32,
+    /// Bytes of the document fetched to render the page.
+    pub bytes_read: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqliteTable {
+    pub name: String,
+    /// `table` or `view`.
+    pub kind: String,
+    /// Row count, for tables only.
+    pub row_count: Option<u64>,
+    pub columns: Vec<String>,
+    pub sample_rows: Vec<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlitePreview {
+    pub key: String,
+    pub size: u64,
+    pub tables: Vec<SqliteTable>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+/// Where a config file failed to parse. Lines and columns are 1-based.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigParseError {
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigPreview {
+    pub key: String,
+    pub format: ConfigFormat,
+    /// Re-serialized from the parsed document; `None` when parsing failed.
+    pub pretty: Option<String>,
+    /// Keys across all nested objects.
+    pub key_count: usize,
+    /// Deepest nesting of objects and arrays; a bare scalar is 0.
+    pub depth: usize,
+    pub error: Option<ConfigParseError>,
+}
+
+/// Where the frontend should load media from: a presigned URL, or a local
+/// temp copy of the object's head when presigning isn't possible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamingSource {
+    pub key: String,
+    pub url: Option<String>,
+    pub local_path: Option<String>,
+    pub content_type: String,
+    pub size: u64,
+    /// Whether the server honours range requests, needed for seeking.
+    pub supports_range: bool,
+    /// The local copy stops short of the full object.
+    pub partial: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    Fail,
+}
+
+/// What a copy, rename or upload does when its destination key is taken.
+/// The first three behave like the matching `OverwritePolicy`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionStrategy {
+    #[default]
+    Overwrite,
+    Skip,
+    Fail,
+    /// Write to `name-1.ext`, `name-2.ext`, ... whichever is free first.
+    SuffixCounter,
+    /// Write to `name-<hash>.ext` with the first 8 characters of the content
+    /// hash, skipping content that is already there under either key.
+    SuffixHash,
+    /// Overwrite only when the incoming object was modified more recently.
+    KeepNewer,
+}
+
+/// Headers and storage settings for an upload. Unset fields fall back to
+/// the content-type rules in settings and the bucket's defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UploadOptions {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    /// For content that is already compressed, e.g. `gzip`.
+    pub content_encoding: Option<String>,
+    /// Sent as `x-amz-meta-<name>`; a leading `x-amz-meta-` is dropped.
+    pub metadata: HashMap<String, String>,
+    /// e.g. `STANDARD_IA` or `GLACIER_IR`.
+    pub storage_class: Option<String>,
+    /// Have the provider compute and store this checksum.
+    pub checksum_algorithm: Option<ChecksumAlgorithm>,
+}
+
+impl UploadOptions {
+    /// Whether the upload needs more than the content type and cache
+    /// control every upload path can set.
+    pub fn needs_sdk(&self) -> bool {
+        !self.metadata.is_empty()
+            || self.content_encoding.is_some()
+            || self.storage_class.is_some()
+            || self.checksum_algorithm.is_some()
+    }
+}
+
+/// Changes for `update_object_metadata`. Unset fields keep their current
+/// value and an empty string clears one; `metadata`, when given, replaces
+/// all custom metadata.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MetadataUpdate {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// How a transfer is split up, chosen per file by `TransferTuner`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferPlan {
+    pub part_size: u64,
+    pub part_count: u64,
+    /// Parts in flight at once.
+    pub concurrency: usize,
+    /// Smoothed throughput the plan was based on, if any was measured.
+    pub measured_bytes_per_second: Option<f64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UploadProgress {
@@ -45,4 +571,530 @@ pub struct UploadProgress {
     pub bytes_uploaded: u64,
     pub total_bytes: u64,
     pub percentage: f32,
+    pub bytes_per_second: f64,
+    /// `None` until there's a measurable rate to extrapolate from.
+    pub eta_seconds: Option<f64>,
+    pub plan: Option<TransferPlan>,
+}
+
+impl UploadProgress {
+    pub fn new(
+        file_name: String,
+        bytes_uploaded: u64,
+        total_bytes: u64,
+        elapsed: std::time::Duration,
+    ) -> Self {
+        let percentage = if total_bytes == 0 {
+            100.0
+        } else {
+            (bytes_uploaded as f64 / total_bytes as f64 * 100.0) as f32
+        };
+        let secs = elapsed.as_secs_f64();
+        let bytes_per_second = if secs > 0.0 {
+            bytes_uploaded as f64 / secs
+        } else {
+            0.0
+        };
+        let eta_seconds = (bytes_per_second > 0.0)
+            .then(|| total_bytes.saturating_sub(bytes_uploaded) as f64 / bytes_per_second);
+
+        Self {
+            file_name,
+            bytes_uploaded,
+            total_bytes,
+            percentage,
+            bytes_per_second,
+            eta_seconds,
+            plan: None,
+        }
+    }
+
+    pub fn with_plan(self, plan: TransferPlan) -> Self {
+        Self {
+            plan: Some(plan),
+            ..self
+        }
+    }
+}
+
+/// Outcome of one post-upload action. `output` holds the URL for the
+/// copy actions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostUploadResult {
+    pub key: String,
+    pub action: String,
+    pub ok: bool,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Body of the webhook a `webhook` post-upload action sends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadNotification {
+    pub event: String,
+    pub bucket: String,
+    pub key: String,
+    pub size: u64,
+    pub uploaded_at: i64,
+}
+
+/// Overall progress of a `download_prefix` run, emitted as each file ends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefixDownloadProgress {
+    pub prefix: String,
+    pub files_completed: u64,
+    pub files_total: u64,
+    /// Bytes of the files finished so far, skipped and failed ones included.
+    pub bytes_downloaded: u64,
+    pub total_bytes: u64,
+    pub percentage: f32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyMatchMode {
+    #[default]
+    Substring,
+    /// Gitignore-style; patterns without a `/` match the file name only.
+    Glob,
+    Regex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchOptions {
+    pub mode: KeyMatchMode,
+    pub case_sensitive: bool,
+    /// Stop walking the bucket once this many keys have matched.
+    pub max_results: Option<u64>,
+}
+
+/// Matches from one listing page of `search_objects`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchProgress {
+    pub job_id: String,
+    pub matches: Vec<S3Object>,
+    pub scanned: u64,
+    pub matched: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSummary {
+    pub job_id: String,
+    pub scanned: u64,
+    pub matched: u64,
+    /// Set when the walk stopped early, at `max_results`, a scan limit or
+    /// on cancel.
+    pub truncated: bool,
+    /// The scan limit that stopped the walk, if one did.
+    pub stopped_early: Option<ScanCutoff>,
+}
+
+/// Progress of `download_objects`, emitted as each key finishes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyListDownloadProgress {
+    pub files_completed: u64,
+    pub files_total: u64,
+    pub bytes_downloaded: u64,
+    pub percentage: f32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestStatus {
+    Succeeded,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadManifestEntry {
+    pub key: String,
+    /// Local file the key maps to, when the mapping succeeded.
+    pub path: Option<String>,
+    pub status: ManifestStatus,
+    pub size: Option<u64>,
+    /// Why the key was skipped or failed.
+    pub reason: Option<String>,
+    /// Error code for failures, as in `BatchFailure`.
+    pub code: Option<String>,
+}
+
+/// Per-key results of `download_objects`, also written to disk so a rerun
+/// can pick out the failures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadManifest {
+    pub bucket: String,
+    pub destination: String,
+    pub created_at: i64,
+    pub entries: Vec<DownloadManifestEntry>,
+    /// Where the manifest was written.
+    pub manifest_path: String,
+}
+
+/// Progress of a bulk delete, emitted as each DeleteObjects batch ends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteProgress {
+    /// Pass to `cancel_job` to stop the remaining batches.
+    pub job_id: String,
+    pub deleted: u64,
+    pub failed: u64,
+    #[serde(flatten)]
+    pub progress: BatchProgress,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteTarget {
+    pub key: String,
+    /// Delete this version permanently rather than the current object.
+    #[serde(default)]
+    pub version_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteOutcome {
+    /// The data is removed.
+    Deleted,
+    /// The bucket is versioned, so a delete marker hides the object and
+    /// its data stays.
+    DeleteMarker,
+    /// Object Lock refuses the delete.
+    Blocked,
+    /// The object's lock state couldn't be read.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteCheck {
+    pub key: String,
+    pub version_id: Option<String>,
+    pub outcome: DeleteOutcome,
+    /// `GOVERNANCE` or `COMPLIANCE`.
+    pub retention_mode: Option<String>,
+    pub retain_until: Option<i64>,
+    pub legal_hold: bool,
+    pub reason: Option<String>,
+}
+
+/// What deleting a set of objects will actually do, checked before
+/// deleting in buckets that may have Object Lock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteProtectionReport {
+    pub bucket: String,
+    pub object_lock_enabled: bool,
+    pub versioning: Option<String>,
+    pub checks: Vec<DeleteCheck>,
+    pub blocked: usize,
+    pub delete_markers: usize,
+}
+
+/// One object `copy_prefix` copies, or would copy in a dry run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedCopy {
+    pub source_key: String,
+    pub dest_key: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyPrefixSummary {
+    pub copies: Vec<PlannedCopy>,
+    /// Keyed by source key. Collisions skipped by the overwrite policy and
+    /// copies cancelled before they started land in `skipped`.
+    pub result: BatchResult,
+    pub total_bytes: u64,
+    pub dry_run: bool,
+    /// From `move_prefix`: succeeded sources were deleted after copying,
+    /// and a failed delete counts the object as failed.
+    pub moved: bool,
+}
+
+/// Progress of a `copy_prefix` or `move_prefix` run, emitted as each
+/// object ends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyProgress {
+    /// Pass to `cancel_job` to stop the remaining copies.
+    pub job_id: String,
+    pub copied: u64,
+    pub failed: u64,
+    pub total: u64,
+    pub bytes_copied: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub key: String,
+    pub bytes_downloaded: u64,
+    pub total_bytes: u64,
+    pub percentage: f32,
+    pub plan: Option<TransferPlan>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagFilter {
+    pub key: String,
+    /// When unset, any object carrying the tag key matches.
+    pub value: Option<String>,
+}
+
+impl TagFilter {
+    pub fn matches(&self, tags: &HashMap<String, String>) -> bool {
+        match (tags.get(&self.key), &self.value) {
+            (Some(actual), Some(expected)) => actual == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaggedObject {
+    pub key: String,
+    pub tags: HashMap<String, String>,
+}
+
+/// Objects whose tags matched a `search_by_tag` query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagSearchResult {
+    pub objects: Vec<TaggedObject>,
+    /// Set when a scan limit stopped the walk before every key was checked.
+    pub stopped_early: Option<ScanCutoff>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderUploadSummary {
+    pub uploaded_count: u64,
+    pub ignored_count: u64,
+    pub total_bytes: u64,
+    /// Files satisfied by a server-side copy of identical content, either
+    /// already in the bucket or uploaded earlier in the same batch.
+    #[serde(default)]
+    pub deduplicated_count: u64,
+    /// Bytes those copies kept from being uploaded.
+    #[serde(default)]
+    pub bytes_saved: u64,
+    /// Files left out because their key was taken and the collision
+    /// strategy said to keep what was there.
+    #[serde(default)]
+    pub skipped_count: u64,
+    /// Files that couldn't be read or uploaded, keyed by relative path.
+    #[serde(default)]
+    pub failed: Vec<BatchFailure>,
+}
+
+impl FolderUploadSummary {
+    pub fn fail(&mut self, path: impl Into<String>, error: &AppError) {
+        self.failed.push(BatchFailure {
+            key: path.into(),
+            code: error.code().to_string(),
+            message: error.to_string(),
+        });
+    }
+}
+
+/// A local file whose content already exists in the bucket under another key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadDuplicate {
+    pub local_path: String,
+    pub key: String,
+    pub existing_key: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadDuplicateReport {
+    pub duplicates: Vec<UploadDuplicate>,
+    /// Remote objects indexed for comparison.
+    pub scanned: u64,
+    /// Set when a scan limit cut the remote index short, so some
+    /// duplicates may have gone unnoticed.
+    pub stopped_early: Option<ScanCutoff>,
+}
+
+/// How `copy_to_clipboard` formats each object.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardFormat {
+    Key,
+    S3Uri,
+    PublicUrl,
+    PresignedUrl,
+}
+
+/// How `get_presigned_urls` renders its links as text.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlListFormat {
+    /// `key,url` rows under a header.
+    Csv,
+    /// One URL per line.
+    Plain,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresignedLink {
+    pub key: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresignedUrlList {
+    pub links: Vec<PresignedLink>,
+    pub expires_at: i64,
+    /// The links rendered in the requested format, if one was asked for.
+    pub text: Option<String>,
+}
+
+impl PresignedUrlList {
+    pub fn render(&self, format: UrlListFormat) -> String {
+        match format {
+            UrlListFormat::Plain => self
+                .links
+                .iter()
+                .map(|l| l.url.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            UrlListFormat::Csv => {
+                // Keys may hold commas and quotes; URLs are already escaped
+                let quote = |field: &str| format!("\"{}\"", field.replace('"', "\"\""));
+                let mut csv = String::from("key,url\n");
+                for link in &self.links {
+                    csv.push_str(&format!("{},{}\n", quote(&link.key), quote(&link.url)));
+                }
+                csv
+            }
+        }
+    }
+}
+
+/// How `share_prefix` packages its links.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareFormat {
+    #[default]
+    Html,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedLink {
+    /// Key relative to the shared prefix.
+    pub name: String,
+    pub url: String,
+    pub size: u64,
+}
+
+/// Presigned links to everything under a prefix, valid until `expires_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareManifest {
+    pub bucket: String,
+    pub prefix: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub links: Vec<SharedLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareResult {
+    pub manifest: ShareManifest,
+    /// The rendered manifest, HTML or JSON.
+    pub content: String,
+    /// Where the manifest was uploaded, when requested.
+    pub manifest_key: Option<String>,
+    /// Presigned link to the uploaded manifest: the one link to send.
+    pub manifest_url: Option<String>,
+}
+
+/// A presigned PutObject request. Whoever uploads with it must send
+/// `headers` exactly as given, since they were signed along with the URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresignedUpload {
+    pub url: String,
+    pub method: String,
+    pub headers: HashMap<String, String>,
+    pub expires_at: i64,
+}
+
+/// A file an object was downloaded to, as recorded at download time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalCopy {
+    pub connection_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub path: String,
+    pub downloaded_at: i64,
+    pub etag: Option<String>,
+    pub size: u64,
+    /// The file is still at `path` with the downloaded size.
+    #[serde(default)]
+    pub exists: bool,
+    /// The file exists and the remote ETag hasn't changed since.
+    #[serde(default)]
+    pub up_to_date: bool,
+}
+
+/// What `download_file` did.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadOutcome {
+    /// Bytes written; `None` when an existing file was kept.
+    pub size: Option<u64>,
+    /// An earlier download of the same, unchanged object that is still on
+    /// disk, so the UI can tell the user this one may have been unnecessary.
+    pub unchanged_copy: Option<LocalCopy>,
+}
+
+/// An object opened in an external application from a managed temp copy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalEdit {
+    pub id: String,
+    pub connection_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub local_path: String,
+    pub remote_etag: Option<String>,
+    pub local_modified_at: i64,
+    pub local_size: u64,
+    pub has_changes: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedObject {
+    pub key: String,
+    pub local_path: String,
+    /// False while a large object is still downloading into its placeholder.
+    pub ready: bool,
 }
@@ -0,0 +1,493 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::models::{Bookmark, ProviderPreset, S3Connection};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppSettings {
+    /// Gitignore-style patterns excluded from every folder upload.
+    pub ignore_patterns: Vec<String>,
+    /// Show the zero-byte placeholder of the folder being listed instead of
+    /// folding it into the folder itself.
+    pub show_folder_markers: bool,
+    /// Entries per listing page when the caller doesn't ask for a size.
+    pub listing_page_size: u32,
+    /// Content type / cache-control overrides applied on upload; the first
+    /// matching rule that sets a field wins.
+    pub content_type_rules: Vec<ContentTypeRule>,
+    /// Transfer speed limits by time of day, shared by all connections.
+    pub bandwidth: BandwidthSchedule,
+    pub preview_limits: PreviewLimits,
+    /// Where write probes and compatibility checks put throwaway objects.
+    pub probe_prefix: String,
+    /// Keep bookmarks in the bucket's `.baul/meta.json` so everyone using
+    /// Baul on it shares them; otherwise they stay on this machine.
+    pub share_bookmarks: bool,
+    /// Actions run after each successful upload, in order.
+    pub post_upload_hooks: Vec<PostUploadHook>,
+    /// Endpoints told when background jobs finish or fail.
+    pub webhooks: Vec<WebhookConfig>,
+    /// User-defined providers offered alongside the built-in ones.
+    pub provider_presets: Vec<ProviderPreset>,
+    /// Sign requests with the server's time when this computer's clock is
+    /// measurably off, instead of letting them fail as skewed.
+    pub compensate_clock_skew: bool,
+    /// Fetch the next listing page in the background as soon as a truncated
+    /// page comes back, so "load more" doesn't wait on the network.
+    pub prefetch_listings: bool,
+    /// Smaller transfer buffers, fewer parallel requests, shorter listing
+    /// pages, no prefetching and lower preview limits, for tight machines.
+    pub low_memory: bool,
+    pub scan_limits: ScanLimits,
+}
+
+/// Hard caps on walks over every key under a prefix, so a mistaken
+/// full-bucket scan stops with partial results instead of running for hours
+/// and billing for every page. `None` disables a cap.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScanLimits {
+    pub max_objects: Option<u64>,
+    pub max_duration_secs: Option<u64>,
+}
+
+impl Default for ScanLimits {
+    fn default() -> Self {
+        Self {
+            max_objects: Some(5_000_000),
+            max_duration_secs: Some(30 * 60),
+        }
+    }
+}
+
+impl ScanLimits {
+    pub fn start(self) -> ScanBudget {
+        ScanBudget {
+            limits: self,
+            started: Instant::now(),
+            scanned: 0,
+            cutoff: None,
+        }
+    }
+}
+
+/// Which `ScanLimits` cap ended a walk early.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanCutoff {
+    MaxObjects,
+    MaxDuration,
+}
+
+/// A walk in progress, measured against its `ScanLimits`.
+#[derive(Debug)]
+pub struct ScanBudget {
+    limits: ScanLimits,
+    started: Instant,
+    scanned: u64,
+    cutoff: Option<ScanCutoff>,
+}
+
+impl ScanBudget {
+    /// Count another page of `objects`.
+    pub fn charge(&mut self, objects: usize) {
+        self.scanned += objects as u64;
+    }
+
+    /// The cap reached so far, if any. Ask only once another page is known
+    /// to exist, so a walk that ends right at a cap isn't reported as
+    /// partial; the answer is then kept for `cutoff`.
+    pub fn exhausted(&mut self) -> Option<ScanCutoff> {
+        if self.cutoff.is_none() {
+            if self
+                .limits
+                .max_objects
+                .is_some_and(|max| self.scanned >= max)
+            {
+                self.cutoff = Some(ScanCutoff::MaxObjects);
+            } else if self
+                .limits
+                .max_duration_secs
+                .is_some_and(|max| self.started.elapsed() >= Duration::from_secs(max))
+            {
+                self.cutoff = Some(ScanCutoff::MaxDuration);
+            }
+        }
+        self.cutoff
+    }
+
+    pub fn scanned(&self) -> u64 {
+        self.scanned
+    }
+
+    pub fn cutoff(&self) -> Option<ScanCutoff> {
+        self.cutoff
+    }
+}
+
+/// Largest object, in bytes, previewed inline in each mode.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreviewLimits {
+    pub text: u64,
+    pub structured: u64,
+    pub image: u64,
+    /// Bytes shown by the hex viewer; larger objects show their head only.
+    pub hex: u64,
+    /// Bytes downloaded for media playback when a URL can't be presigned.
+    pub media: u64,
+    /// Largest document downloaded in full to render its first page.
+    pub document: u64,
+    /// Largest SQLite database downloaded to list its tables.
+    pub sqlite: u64,
+}
+
+impl Default for PreviewLimits {
+    fn default() -> Self {
+        Self {
+            text: 1024 * 1024,
+            structured: 5 * 1024 * 1024,
+            image: 20 * 1024 * 1024,
+            hex: 64 * 1024,
+            media: 64 * 1024 * 1024,
+            document: 50 * 1024 * 1024,
+            sqlite: 100 * 1024 * 1024,
+        }
+    }
+}
+
+impl PreviewLimits {
+    /// Ceilings applied on top of the configured limits in low-memory mode.
+    const LOW_MEMORY: Self = Self {
+        text: 256 * 1024,
+        structured: 1024 * 1024,
+        image: 5 * 1024 * 1024,
+        hex: 16 * 1024,
+        media: 8 * 1024 * 1024,
+        document: 10 * 1024 * 1024,
+        sqlite: 20 * 1024 * 1024,
+    };
+
+    fn min(self, other: Self) -> Self {
+        Self {
+            text: self.text.min(other.text),
+            structured: self.structured.min(other.structured),
+            image: self.image.min(other.image),
+            hex: self.hex.min(other.hex),
+            media: self.media.min(other.media),
+            document: self.document.min(other.document),
+            sqlite: self.sqlite.min(other.sqlite),
+        }
+    }
+}
+
+/// A daily time window (local time, `HH:MM`) with its own speed limit.
+/// Windows where `end` is before `start` wrap past midnight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthWindow {
+    pub start: String,
+    pub end: String,
+    /// Bytes per second; `None` means unlimited.
+    pub limit: Option<u64>,
+}
+
+impl BandwidthWindow {
+    fn parse(time: &str) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(time, "%H:%M").ok()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        Self::parse(&self.start).is_some() && Self::parse(&self.end).is_some()
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let (Some(start), Some(end)) = (Self::parse(&self.start), Self::parse(&self.end)) else {
+            return false;
+        };
+
+        if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BandwidthSchedule {
+    /// Bytes per second outside every window; `None` means unlimited.
+    pub default_limit: Option<u64>,
+    /// First window containing the current time wins.
+    pub windows: Vec<BandwidthWindow>,
+}
+
+impl BandwidthSchedule {
+    pub fn limit_at(&self, time: NaiveTime) -> Option<u64> {
+        self.windows
+            .iter()
+            .find(|w| w.contains(time))
+            .map_or(self.default_limit, |w| w.limit)
+    }
+}
+
+/// Upload header override. `pattern` is a bare extension (`wasm`, `.wasm`)
+/// or a glob; globs without `/` match the file name, others the whole key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentTypeRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub cache_control: Option<String>,
+}
+
+impl ContentTypeRule {
+    fn new(pattern: &str, content_type: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            content_type: Some(content_type.to_string()),
+            cache_control: None,
+        }
+    }
+}
+
+/// Something to do with an object once it has been uploaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostUploadAction {
+    CopyPublicUrl,
+    #[serde(rename_all = "camelCase")]
+    CopyPresignedUrl {
+        expires_in_secs: u64,
+    },
+    SetTags {
+        tags: HashMap<String, String>,
+    },
+    /// POST the upload's details as JSON to `url`.
+    Webhook {
+        url: String,
+    },
+}
+
+impl PostUploadAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostUploadAction::CopyPublicUrl => "copy_public_url",
+            PostUploadAction::CopyPresignedUrl { .. } => "copy_presigned_url",
+            PostUploadAction::SetTags { .. } => "set_tags",
+            PostUploadAction::Webhook { .. } => "webhook",
+        }
+    }
+}
+
+/// A post-upload action limited to uploads into `bucket` (any bucket when
+/// unset) under `prefix`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostUploadHook {
+    #[serde(default)]
+    pub bucket: Option<String>,
+    #[serde(default)]
+    pub prefix: String,
+    pub action: PostUploadAction,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobEventKind {
+    JobCompleted,
+    JobFailed,
+}
+
+/// An endpoint for job notifications. With a `secret`, each request is
+/// signed in an `X-Baul-Signature: sha256=<hex HMAC of the body>` header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Events to send; all of them when empty.
+    #[serde(default)]
+    pub events: Vec<JobEventKind>,
+}
+
+/// Body of a job notification webhook.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobEvent {
+    pub event: JobEventKind,
+    pub job_id: String,
+    pub job: String,
+    pub bucket: String,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+/// Which profile is active; persisted outside any profile directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProfileConfig {
+    pub active_profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<String>,
+}
+
+impl AppSettings {
+    /// Preview limits in effect, lowered in low-memory mode.
+    pub fn effective_preview_limits(&self) -> PreviewLimits {
+        if self.low_memory {
+            self.preview_limits.min(PreviewLimits::LOW_MEMORY)
+        } else {
+            self.preview_limits
+        }
+    }
+}
+
+/// Keychain accounts this install has written, so stale ones can be found
+/// later (the OS keychain can't be enumerated).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KeyringRegistry {
+    /// Per-install prefix for keychain account names.
+    pub namespace: String,
+    pub accounts: Vec<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            ignore_patterns: vec![".DS_Store".to_string(), "Thumbs.db".to_string()],
+            show_folder_markers: false,
+            listing_page_size: 500,
+            // Several providers fall back to octet-stream for these
+            content_type_rules: vec![
+                ContentTypeRule::new("wasm", "application/wasm"),
+                ContentTypeRule::new("mjs", "text/javascript; charset=utf-8"),
+                ContentTypeRule::new("avif", "image/avif"),
+            ],
+            bandwidth: BandwidthSchedule::default(),
+            preview_limits: PreviewLimits::default(),
+            probe_prefix: ".baul-probe/".to_string(),
+            share_bookmarks: true,
+            post_upload_hooks: Vec::new(),
+            webhooks: Vec::new(),
+            provider_presets: Vec::new(),
+            compensate_clock_skew: false,
+            prefetch_listings: false,
+            low_memory: false,
+            scan_limits: ScanLimits::default(),
+        }
+    }
+}
+
+/// A long-running job the backend is working on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundJob {
+    pub id: String,
+    /// What the job does, e.g. `deploy` or `upload_folder`.
+    pub kind: String,
+    pub bucket: String,
+    pub started_at: i64,
+}
+
+/// Optional behaviour that is switched on by settings or by tools being
+/// installed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlags {
+    pub shared_bookmarks: bool,
+    pub bandwidth_limits: bool,
+    /// `pdftoppm` is installed, so PDFs can be rendered.
+    pub document_preview: bool,
+    /// `soffice` is installed, so office documents can be converted.
+    pub office_preview: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppStatus {
+    /// Startup has finished loading connections and settings.
+    pub ready: bool,
+    pub profile: String,
+    pub connection_count: usize,
+    /// Connections whose secret couldn't be read from the keychain.
+    pub credential_error_count: usize,
+    /// Connections waiting on a denied keychain prompt to be retried.
+    pub locked_connections: Vec<String>,
+    pub credential_store_available: bool,
+    pub credential_store_error: Option<String>,
+    pub active_jobs: Vec<BackgroundJob>,
+    pub features: FeatureFlags,
+}
+
+/// A connection's secret key sealed with the bundle passphrase
+/// (AES-256-GCM, base64).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedSecret {
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledConnection {
+    #[serde(flatten)]
+    pub connection: S3Connection,
+    /// Only present when the bundle was exported with a passphrase.
+    #[serde(default)]
+    pub secret: Option<EncryptedSecret>,
+}
+
+/// Local bookmarks of one bucket, keyed by the exporting install's
+/// connection id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketBookmarks {
+    pub connection_id: String,
+    pub bucket: String,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// Everything needed to set Baul up on another machine in one step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppConfigBundle {
+    pub version: u32,
+    pub exported_at: i64,
+    pub settings: AppSettings,
+    pub connections: Vec<BundledConnection>,
+    #[serde(default)]
+    pub bookmarks: Vec<BucketBookmarks>,
+    /// PBKDF2 salt for the secrets, base64; unset when none are included.
+    #[serde(default)]
+    pub secret_salt: Option<String>,
+    /// Signing secrets of `settings.webhooks`, by position, which are
+    /// stripped from the settings themselves. Only sealed with a passphrase.
+    #[serde(default)]
+    pub webhook_secrets: Vec<Option<EncryptedSecret>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigImportSummary {
+    pub connections: Vec<S3Connection>,
+    /// Connections whose secret was restored; the rest need one entered.
+    pub secrets_restored: usize,
+    pub bookmarks_restored: usize,
+}
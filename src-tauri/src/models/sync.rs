@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::BatchResult;
+
+/// Which side of a sync is the source of truth.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    /// Local folder to bucket.
+    #[default]
+    Push,
+    /// Bucket to local folder.
+    Pull,
+}
+
+/// How a file present on both sides is judged out of date.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncCompare {
+    /// Different size, or the source side modified more recently.
+    #[default]
+    SizeAndMtime,
+    /// Different size only.
+    Size,
+    /// Different size or MD5 against the ETag, hashing local files whose
+    /// size matches. Multipart uploads and SSE-KMS or SSE-C objects, whose
+    /// ETags aren't an MD5, fall back to modification times.
+    Checksum,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncOptions {
+    pub direction: SyncDirection,
+    pub compare: SyncCompare,
+    /// Remove files from the destination that the source no longer has.
+    pub delete: bool,
+    pub dry_run: bool,
+    /// Added to the ignore patterns from settings; ignored paths are never
+    /// transferred or deleted on either side.
+    pub ignore_patterns: Vec<String>,
+    pub concurrency: Option<usize>,
+    /// Plan from the bucket's newest S3 Inventory report instead of listing
+    /// the prefix. The report is a day or more behind, so objects changed
+    /// since then are compared as they were.
+    pub use_inventory: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncAction {
+    Upload,
+    Download,
+    DeleteRemote,
+    DeleteLocal,
+}
+
+/// Why a file is in the plan.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncReason {
+    Missing,
+    SizeChanged,
+    Newer,
+    ChecksumChanged,
+    /// Gone from the source side.
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncItem {
+    pub action: SyncAction,
+    pub reason: SyncReason,
+    pub key: String,
+    pub local_path: String,
+    /// Bytes to transfer, or held by the file being deleted.
+    pub size: u64,
+    /// Unix seconds the source side was last modified.
+    pub modified: i64,
+    /// The remote object's ETag, which downloads are verified against.
+    pub etag: Option<String>,
+}
+
+/// What a sync will do, split by action.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlan {
+    pub uploads: Vec<SyncItem>,
+    pub downloads: Vec<SyncItem>,
+    pub deletions: Vec<SyncItem>,
+    /// Files already in step on both sides.
+    pub unchanged_count: u64,
+    /// Bytes the uploads and downloads will move.
+    pub total_bytes: u64,
+}
+
+impl SyncPlan {
+    pub fn len(&self) -> usize {
+        self.uploads.len() + self.downloads.len() + self.deletions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Progress of a `sync` run, emitted as each file finishes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncProgress {
+    pub job_id: String,
+    pub key: String,
+    pub action: SyncAction,
+    pub completed: u64,
+    pub failed: u64,
+    pub total: u64,
+    pub bytes_transferred: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSummary {
+    pub plan: SyncPlan,
+    /// Outcome per key; empty for a dry run.
+    pub result: BatchResult,
+    pub bytes_transferred: u64,
+    pub dry_run: bool,
+}
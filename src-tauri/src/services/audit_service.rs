@@ -0,0 +1,579 @@
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{Permission, Type};
+use aws_sdk_s3::Client as S3Client;
+use chrono::Utc;
+use futures::{stream, StreamExt};
+use log::{debug, trace};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::error::AppResult;
+use crate::models::{
+    AccessControlList, AclGrant, AclPermission, AuditFinding, BucketAuditReport, ExposureSource,
+    FindingSeverity, GranteeType, PublicAccessBlock, PublicObject, PublicScanSummary, RequestKind,
+    S3ConnectionWithSecret, S3Object, ScanBudget,
+};
+use crate::services::S3Service;
+
+/// Group URIs that make an ACL grant public.
+const PUBLIC_GRANTEES: [&str; 2] = [
+    "http://acs.amazonaws.com/groups/global/AllUsers",
+    "http://acs.amazonaws.com/groups/global/AuthenticatedUsers",
+];
+
+pub struct AuditService;
+
+impl AuditService {
+    /// Unwrap one section of the audit. Error codes in `not_configured` mean
+    /// the setting simply isn't there; anything else is reported as
+    /// unavailable under `section`.
+    fn section<T, E>(
+        result: Result<T, E>,
+        section: &str,
+        not_configured: &[&str],
+        unavailable: &mut HashMap<String, String>,
+    ) -> Option<T>
+    where
+        E: ProvideErrorMetadata + std::fmt::Display,
+    {
+        match result {
+            Ok(output) => Some(output),
+            Err(e) if e.code().is_some_and(|c| not_configured.contains(&c)) => None,
+            Err(e) => {
+                trace!("Audit section '{}' unavailable: {}", section, e);
+                let reason = e.message().map(str::to_string).unwrap_or(e.to_string());
+                unavailable.insert(section.to_string(), reason);
+                None
+            }
+        }
+    }
+
+    async fn public_access_block(
+        client: &S3Client,
+        bucket: &str,
+        unavailable: &mut HashMap<String, String>,
+    ) -> Option<PublicAccessBlock> {
+        Self::section(
+            client.get_public_access_block().bucket(bucket).send().await,
+            "publicAccessBlock",
+            &["NoSuchPublicAccessBlockConfiguration"],
+            unavailable,
+        )
+        .and_then(|o| {
+            o.public_access_block_configuration()
+                .map(|c| PublicAccessBlock {
+                    block_public_acls: c.block_public_acls().unwrap_or(false),
+                    ignore_public_acls: c.ignore_public_acls().unwrap_or(false),
+                    block_public_policy: c.block_public_policy().unwrap_or(false),
+                    restrict_public_buckets: c.restrict_public_buckets().unwrap_or(false),
+                })
+        })
+    }
+
+    /// Statements that allow something to everyone without conditions.
+    fn public_statements(policy: &str) -> Vec<Value> {
+        let Ok(document) = serde_json::from_str::<Value>(policy) else {
+            return Vec::new();
+        };
+
+        let statements = match &document["Statement"] {
+            Value::Array(statements) => statements.clone(),
+            statement @ Value::Object(_) => vec![statement.clone()],
+            _ => return Vec::new(),
+        };
+
+        let is_wildcard = |value: &Value| match value {
+            Value::String(s) => s == "*",
+            Value::Array(items) => items.iter().any(|i| i == "*"),
+            _ => false,
+        };
+
+        statements
+            .into_iter()
+            .filter(|statement| {
+                statement["Effect"] == "Allow"
+                    && statement.get("Condition").is_none()
+                    && (is_wildcard(&statement["Principal"])
+                        || is_wildcard(&statement["Principal"]["AWS"]))
+            })
+            .collect()
+    }
+
+    /// Whether a bucket policy grants anything to everyone without conditions.
+    pub fn policy_is_public(policy: &str) -> bool {
+        !Self::public_statements(policy).is_empty()
+    }
+
+    /// Key patterns (IAM wildcards) that a policy lets everyone read.
+    fn public_read_patterns(policy: &str, bucket: &str) -> Vec<String> {
+        let object_arn = format!("arn:aws:s3:::{}/", bucket);
+
+        let as_list = |value: &Value| -> Vec<String> {
+            match value {
+                Value::String(s) => vec![s.clone()],
+                Value::Array(items) => items
+                    .iter()
+                    .filter_map(|i| i.as_str().map(str::to_string))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        Self::public_statements(policy)
+            .iter()
+            .filter(|statement| {
+                as_list(&statement["Action"])
+                    .iter()
+                    .any(|a| a == "*" || a == "s3:*" || a == "s3:GetObject")
+            })
+            .flat_map(|statement| as_list(&statement["Resource"]))
+            .filter_map(|resource| resource.strip_prefix(&object_arn).map(str::to_string))
+            .collect()
+    }
+
+    /// IAM-style wildcard match: `*` spans any run of characters, `?` one.
+    /// Linear backtracking to the last `*`, so patterns full of stars can't
+    /// blow up on long keys.
+    fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+        let (mut p, mut t) = (0, 0);
+        // Position after the last `*` seen, and where in `text` it resumed
+        let mut star: Option<(usize, usize)> = None;
+
+        while t < text.len() {
+            match pattern.get(p) {
+                Some(b'*') => {
+                    p += 1;
+                    star = Some((p, t));
+                }
+                Some(&c) if c == b'?' || c == text[t] => {
+                    p += 1;
+                    t += 1;
+                }
+                _ => match star {
+                    // Let the last star swallow one more character
+                    Some((star_p, star_t)) => {
+                        p = star_p;
+                        t = star_t + 1;
+                        star = Some((star_p, star_t + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        pattern[p..].iter().all(|&c| c == b'*')
+    }
+
+    pub fn grant_is_public(grant: &AclGrant) -> bool {
+        PUBLIC_GRANTEES.contains(&grant.grantee.as_str())
+    }
+
+    /// Grants S3 returned, minus any naming a grantee type or permission
+    /// this build doesn't know.
+    pub fn acl_grants(grants: &[aws_sdk_s3::types::Grant]) -> Vec<AclGrant> {
+        grants
+            .iter()
+            .filter_map(|grant| {
+                let grantee = grant.grantee()?;
+                let name = grantee
+                    .uri()
+                    .or(grantee.id())
+                    .or(grantee.email_address())?;
+
+                let grantee_type = match grantee.r#type() {
+                    Type::CanonicalUser => GranteeType::CanonicalUser,
+                    Type::AmazonCustomerByEmail => GranteeType::Email,
+                    Type::Group => GranteeType::Group,
+                    other => {
+                        trace!("Skipping grant to unknown grantee type {}", other.as_str());
+                        return None;
+                    }
+                };
+                let permission = match grant.permission()? {
+                    Permission::FullControl => AclPermission::FullControl,
+                    Permission::Read => AclPermission::Read,
+                    Permission::Write => AclPermission::Write,
+                    Permission::ReadAcp => AclPermission::ReadAcp,
+                    Permission::WriteAcp => AclPermission::WriteAcp,
+                    other => {
+                        trace!("Skipping grant of unknown permission {}", other.as_str());
+                        return None;
+                    }
+                };
+
+                Some(AclGrant {
+                    grantee: name.to_string(),
+                    grantee_type,
+                    display_name: grantee.display_name().map(str::to_string),
+                    permission,
+                })
+            })
+            .collect()
+    }
+
+    pub fn access_control_list(
+        owner: Option<&aws_sdk_s3::types::Owner>,
+        grants: &[aws_sdk_s3::types::Grant],
+    ) -> AccessControlList {
+        let grants = Self::acl_grants(grants);
+        AccessControlList {
+            owner: owner.and_then(|o| o.display_name().or(o.id()).map(str::to_string)),
+            is_public: grants.iter().any(Self::grant_is_public),
+            grants,
+        }
+    }
+
+    pub async fn audit_bucket(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+    ) -> AppResult<BucketAuditReport> {
+        debug!("Auditing bucket '{}'", bucket);
+
+        let client = S3Service::create_s3_client(connection).await;
+        let mut unavailable = HashMap::new();
+
+        let policy = Self::section(
+            client.get_bucket_policy().bucket(bucket).send().await,
+            "policy",
+            &["NoSuchBucketPolicy"],
+            &mut unavailable,
+        )
+        .and_then(|o| o.policy().map(str::to_string));
+
+        let acl_grants = Self::section(
+            client.get_bucket_acl().bucket(bucket).send().await,
+            "acl",
+            &[],
+            &mut unavailable,
+        )
+        .map(|o| Self::acl_grants(o.grants()))
+        .unwrap_or_default();
+
+        let public_access_block =
+            Self::public_access_block(&client, bucket, &mut unavailable).await;
+
+        let encryption = Self::section(
+            client.get_bucket_encryption().bucket(bucket).send().await,
+            "encryption",
+            &["ServerSideEncryptionConfigurationNotFoundError"],
+            &mut unavailable,
+        )
+        .and_then(|o| {
+            o.server_side_encryption_configuration()?
+                .rules()
+                .iter()
+                .find_map(|r| r.apply_server_side_encryption_by_default())
+                .map(|d| d.sse_algorithm().as_str().to_string())
+        });
+
+        let (versioning, mfa_delete) = Self::section(
+            client.get_bucket_versioning().bucket(bucket).send().await,
+            "versioning",
+            &[],
+            &mut unavailable,
+        )
+        .map(|o| {
+            (
+                o.status().map(|s| s.as_str().to_string()),
+                o.mfa_delete().map(|s| s.as_str().to_string()),
+            )
+        })
+        .unwrap_or_default();
+
+        let logging_target = Self::section(
+            client.get_bucket_logging().bucket(bucket).send().await,
+            "logging",
+            &[],
+            &mut unavailable,
+        )
+        .and_then(|o| {
+            o.logging_enabled()
+                .map(|l| format!("{}/{}", l.target_bucket(), l.target_prefix()))
+        });
+
+        let object_lock = Self::section(
+            client
+                .get_object_lock_configuration()
+                .bucket(bucket)
+                .send()
+                .await,
+            "objectLock",
+            &["ObjectLockConfigurationNotFoundError"],
+            &mut unavailable,
+        )
+        .and_then(|o| {
+            let config = o.object_lock_configuration()?;
+            config.object_lock_enabled()?;
+
+            let retention = config.rule().and_then(|r| r.default_retention());
+            Some(match retention {
+                Some(r) => {
+                    let mode = r.mode().map(|m| m.as_str()).unwrap_or("Enabled");
+                    match (r.days(), r.years()) {
+                        (Some(days), _) => format!("{} ({} days)", mode, days),
+                        (_, Some(years)) => format!("{} ({} years)", mode, years),
+                        _ => mode.to_string(),
+                    }
+                }
+                None => "Enabled".to_string(),
+            })
+        });
+
+        let mut report = BucketAuditReport {
+            bucket: bucket.to_string(),
+            generated_at: Utc::now().timestamp(),
+            policy_is_public: policy.as_deref().is_some_and(Self::policy_is_public),
+            policy,
+            acl_is_public: acl_grants.iter().any(Self::grant_is_public),
+            acl_grants,
+            public_access_block,
+            is_encrypted: encryption.is_some(),
+            encryption,
+            versioning,
+            mfa_delete,
+            logging_target,
+            object_lock,
+            is_public: false,
+            findings: Vec::new(),
+            unavailable,
+        };
+
+        report.is_public = Self::effectively_public(&report);
+        report.findings = Self::findings(&report);
+
+        debug!(
+            "Audit of '{}' produced {} findings",
+            bucket,
+            report.findings.len()
+        );
+        Ok(report)
+    }
+
+    /// List objects under `prefix` that anyone can read, calling `on_found`
+    /// for each as it is discovered. Keys covered by a public policy are
+    /// reported without further requests; object ACLs are fetched for an
+    /// evenly spread sample of at most `sample_size` of the rest. The
+    /// listing stops early once `budget` runs out.
+    pub async fn scan_public_objects(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        sample_size: usize,
+        budget: &mut ScanBudget,
+        mut on_found: impl FnMut(&PublicObject),
+    ) -> AppResult<PublicScanSummary> {
+        let client = S3Service::create_s3_client(connection).await;
+        let mut unavailable = HashMap::new();
+
+        let block = Self::public_access_block(&client, bucket, &mut unavailable)
+            .await
+            .unwrap_or_default();
+
+        let policy = Self::section(
+            client.get_bucket_policy().bucket(bucket).send().await,
+            "policy",
+            &["NoSuchBucketPolicy"],
+            &mut unavailable,
+        )
+        .and_then(|o| o.policy().map(str::to_string))
+        .filter(|_| !block.restrict_public_buckets);
+
+        let bucket_acl_is_public = Self::section(
+            client.get_bucket_acl().bucket(bucket).send().await,
+            "acl",
+            &[],
+            &mut unavailable,
+        )
+        .is_some_and(|o| Self::acl_grants(o.grants()).iter().any(Self::grant_is_public))
+            && !block.ignore_public_acls;
+
+        let patterns = policy
+            .as_deref()
+            .map(|p| Self::public_read_patterns(p, bucket))
+            .unwrap_or_default();
+
+        let key_prefix = if prefix.is_empty() || prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{}/", prefix)
+        };
+        let listing =
+            S3Service::list_objects_within(connection, bucket, &key_prefix, budget).await?;
+        let objects: Vec<S3Object> = listing.into_iter().filter(|o| !o.is_directory).collect();
+
+        let mut summary = PublicScanSummary {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            policy_is_public: policy.as_deref().is_some_and(Self::policy_is_public),
+            bucket_acl_is_public,
+            scanned_count: objects.len() as u64,
+            sampled_count: 0,
+            public_count: 0,
+            stopped_early: budget.cutoff(),
+        };
+
+        let mut unmatched = Vec::new();
+        for object in objects {
+            // Policies name real keys, which include a jailed connection's root
+            let real_key = S3Service::scoped_key(connection, &object.key)?;
+            let covered = patterns
+                .iter()
+                .any(|p| Self::wildcard_match(p.as_bytes(), real_key.as_bytes()));
+
+            if covered {
+                summary.public_count += 1;
+                on_found(&PublicObject {
+                    key: object.key,
+                    source: ExposureSource::BucketPolicy,
+                });
+            } else {
+                unmatched.push(object.key);
+            }
+        }
+
+        if block.ignore_public_acls || sample_size == 0 {
+            return Ok(summary);
+        }
+
+        let step = unmatched.len().div_ceil(sample_size).max(1);
+        let sample: Vec<String> = unmatched.into_iter().step_by(step).collect();
+        summary.sampled_count = sample.len() as u64;
+
+        debug!(
+            "Checking ACLs on {} sampled objects in '{}/{}'",
+            sample.len(),
+            bucket,
+            prefix
+        );
+
+        let client = &client;
+        let mut results = stream::iter(sample)
+            .map(|key| async move {
+                S3Service::throttle(connection, RequestKind::Get).await;
+                let acl = S3Service::object_acl(client, connection, bucket, &key).await;
+                (key, acl.map(|acl| acl.grants))
+            })
+            .buffer_unordered(8);
+
+        while let Some((key, grants)) = results.next().await {
+            match grants {
+                Ok(grants) if grants.iter().any(Self::grant_is_public) => {
+                    summary.public_count += 1;
+                    on_found(&PublicObject {
+                        key,
+                        source: ExposureSource::ObjectAcl,
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => trace!("Could not read ACL for '{}': {}", key, e),
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Public policy or ACL that the public access block doesn't neutralize.
+    fn effectively_public(report: &BucketAuditReport) -> bool {
+        let block = report.public_access_block.unwrap_or_default();
+
+        (report.policy_is_public && !block.restrict_public_buckets)
+            || (report.acl_is_public && !block.ignore_public_acls)
+    }
+
+    fn findings(report: &BucketAuditReport) -> Vec<AuditFinding> {
+        let mut findings = Vec::new();
+        let mut add = |severity, code: &str, message: String| {
+            findings.push(AuditFinding {
+                severity,
+                code: code.to_string(),
+                message,
+            })
+        };
+
+        if report.is_public && report.policy_is_public {
+            add(
+                FindingSeverity::High,
+                "public_policy",
+                "Bucket policy allows access to everyone".into(),
+            );
+        }
+        if report.is_public && report.acl_is_public {
+            add(
+                FindingSeverity::High,
+                "public_acl",
+                "Bucket ACL grants access to all users".into(),
+            );
+        }
+        if !report.unavailable.contains_key("publicAccessBlock") {
+            let fully_blocked = report.public_access_block.is_some_and(|b| {
+                b.block_public_acls
+                    && b.ignore_public_acls
+                    && b.block_public_policy
+                    && b.restrict_public_buckets
+            });
+            if !fully_blocked {
+                add(
+                    FindingSeverity::Medium,
+                    "public_access_not_blocked",
+                    "Public access block is missing or only partially enabled".into(),
+                );
+            }
+        }
+        if !report.is_encrypted && !report.unavailable.contains_key("encryption") {
+            add(
+                FindingSeverity::Medium,
+                "unencrypted",
+                "No default encryption is configured".into(),
+            );
+        }
+        if report.versioning.as_deref() != Some("Enabled")
+            && !report.unavailable.contains_key("versioning")
+        {
+            add(
+                FindingSeverity::Low,
+                "versioning_disabled",
+                "Versioning is not enabled; overwritten or deleted objects can't be recovered"
+                    .into(),
+            );
+        }
+        if report.logging_target.is_none() && !report.unavailable.contains_key("logging") {
+            add(
+                FindingSeverity::Low,
+                "logging_disabled",
+                "Server access logging is disabled".into(),
+            );
+        }
+
+        findings.sort_by_key(|f| f.severity);
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, key: &str) -> bool {
+        AuditService::wildcard_match(pattern.as_bytes(), key.as_bytes())
+    }
+
+    #[test]
+    fn wildcards_match_like_iam() {
+        assert!(matches("public/*", "public/a/b.txt"));
+        assert!(matches("*", ""));
+        assert!(matches("*.jpg", "photos/cat.jpg"));
+        assert!(matches("photos/?at.jpg", "photos/cat.jpg"));
+        assert!(matches("a*b*c", "aXXbYYbZc"));
+        assert!(!matches("public/*", "private/a.txt"));
+        assert!(!matches("photos/?at.jpg", "photos/at.jpg"));
+        assert!(!matches("a*b*c", "aXXbYYbZ"));
+        assert!(!matches("", "a"));
+    }
+
+    #[test]
+    fn many_stars_on_a_long_key_finish_quickly() {
+        let pattern = "*a".repeat(30) + "b";
+        let key = "a".repeat(1024);
+        assert!(!matches(&pattern, &key));
+    }
+}
@@ -0,0 +1,261 @@
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client as S3Client;
+use chrono::Utc;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Bookmark, BucketBookmarks, BucketMeta, RequestKind, S3ConnectionWithSecret};
+use crate::services::{ConfigService, S3Service};
+
+/// Well-known key of the shared metadata object in each bucket.
+pub const BUCKET_META_KEY: &str = ".baul/meta.json";
+
+const BUCKET_META_VERSION: u32 = 1;
+
+/// Tombstones older than this are dropped on save.
+const TOMBSTONE_TTL_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Serializes read-modify-write of the local bookmarks file.
+static BOOKMARKS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Times a shared write is retried after another client changed the object
+/// between our read and write.
+const REMOTE_WRITE_ATTEMPTS: usize = 3;
+
+/// The shared bookmarks and the ETag they were read at; no ETag means the
+/// object doesn't exist yet.
+struct RemoteBookmarks {
+    bookmarks: Vec<Bookmark>,
+    etag: Option<String>,
+}
+
+pub struct BookmarkService;
+
+impl BookmarkService {
+    fn store_key(connection_id: &str, bucket: &str) -> String {
+        format!("{}/{}", connection_id, bucket)
+    }
+
+    /// Combine two bookmark lists, keeping the most recent edit of each
+    /// prefix, deletions included. Ties go to `ours`.
+    pub fn merge(ours: Vec<Bookmark>, theirs: Vec<Bookmark>) -> Vec<Bookmark> {
+        let mut merged: HashMap<String, Bookmark> = HashMap::new();
+        for bookmark in ours.into_iter().chain(theirs) {
+            match merged.get(&bookmark.prefix) {
+                Some(existing) if existing.updated_at >= bookmark.updated_at => {}
+                _ => {
+                    merged.insert(bookmark.prefix.clone(), bookmark);
+                }
+            }
+        }
+
+        let cutoff = Utc::now().timestamp() - TOMBSTONE_TTL_SECS;
+        let mut bookmarks: Vec<Bookmark> = merged
+            .into_values()
+            .filter(|b| !b.deleted || b.updated_at > cutoff)
+            .collect();
+        bookmarks.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+        bookmarks
+    }
+
+    /// Bookmarks stored in the bucket; a missing object means none yet.
+    async fn load_remote(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+    ) -> AppResult<RemoteBookmarks> {
+        S3Service::throttle(connection, RequestKind::Get).await;
+        let result = client
+            .get_object()
+            .bucket(bucket)
+            .key(S3Service::scoped_key(connection, BUCKET_META_KEY)?)
+            .send()
+            .await;
+        let result = match result {
+            Ok(result) => result,
+            Err(e) if e.code() == Some("NoSuchKey") => {
+                return Ok(RemoteBookmarks {
+                    bookmarks: Vec::new(),
+                    etag: None,
+                })
+            }
+            Err(e) => return Err(S3Service::api_error(e)),
+        };
+
+        let etag = result.e_tag().map(str::to_string);
+        let data = result
+            .body
+            .collect()
+            .await
+            .map_err(|e| AppError::S3Error(e.to_string()))?
+            .to_vec();
+        let meta: BucketMeta = serde_json::from_slice(&data)?;
+        Ok(RemoteBookmarks {
+            bookmarks: meta.bookmarks,
+            etag,
+        })
+    }
+
+    /// Write the shared bookmarks only if the object is still at `etag`, or
+    /// still absent without one. Returns `false` when another client got
+    /// there first.
+    async fn save_remote(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        bookmarks: &[Bookmark],
+        etag: Option<&str>,
+    ) -> AppResult<bool> {
+        let meta = BucketMeta {
+            version: BUCKET_META_VERSION,
+            bookmarks: bookmarks.to_vec(),
+        };
+        let data = serde_json::to_vec_pretty(&meta)?;
+
+        S3Service::throttle(connection, RequestKind::Put).await;
+        let written = client
+            .put_object()
+            .bucket(bucket)
+            .key(S3Service::scoped_key(connection, BUCKET_META_KEY)?)
+            .body(ByteStream::from(data))
+            .content_type("application/json")
+            .set_if_match(etag.map(str::to_string))
+            .set_if_none_match(etag.is_none().then(|| "*".to_string()))
+            .send()
+            .await;
+        match written {
+            Ok(_) => Ok(true),
+            Err(e)
+                if matches!(
+                    e.code(),
+                    Some("PreconditionFailed" | "ConditionalRequestConflict")
+                ) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(S3Service::api_error(e)),
+        }
+    }
+
+    /// Merge `bookmarks` with the bucket's copy and write the result back
+    /// when it adds anything, retrying from a fresh read if another client
+    /// writes in between. Returns the merged set.
+    async fn share(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        bookmarks: Vec<Bookmark>,
+    ) -> AppResult<Vec<Bookmark>> {
+        let client = S3Service::create_s3_client(connection).await;
+        let mut attempts = 0;
+        loop {
+            let remote = Self::load_remote(&client, connection, bucket).await?;
+            debug!(
+                "Merging {} local and {} shared bookmarks for '{}'",
+                bookmarks.len(),
+                remote.bookmarks.len(),
+                bucket
+            );
+            let merged = Self::merge(bookmarks.clone(), remote.bookmarks.clone());
+            // `merge` sorts by prefix, so equal sets compare equal
+            if merged == Self::merge(remote.bookmarks, Vec::new()) {
+                return Ok(merged);
+            }
+
+            attempts += 1;
+            let etag = remote.etag.as_deref();
+            if Self::save_remote(&client, connection, bucket, &merged, etag).await? {
+                return Ok(merged);
+            }
+            if attempts == REMOTE_WRITE_ATTEMPTS {
+                warn!(
+                    "Shared bookmarks in '{}' kept changing, keeping ours local",
+                    bucket
+                );
+                return Ok(merged);
+            }
+            debug!("Shared bookmarks in '{}' changed, merging again", bucket);
+        }
+    }
+
+    pub fn load_local(connection_id: &str, bucket: &str) -> AppResult<Vec<Bookmark>> {
+        let _guard = BOOKMARKS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut store = ConfigService::load_bookmarks()?;
+        Ok(store
+            .remove(&Self::store_key(connection_id, bucket))
+            .unwrap_or_default())
+    }
+
+    pub fn save_local(connection_id: &str, bucket: &str, bookmarks: &[Bookmark]) -> AppResult<()> {
+        let _guard = BOOKMARKS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut store = ConfigService::load_bookmarks()?;
+        store.insert(Self::store_key(connection_id, bucket), bookmarks.to_vec());
+        ConfigService::save_bookmarks(&store)
+    }
+
+    /// Every bucket's local bookmarks, tombstones included.
+    pub fn export_local() -> AppResult<Vec<BucketBookmarks>> {
+        let _guard = BOOKMARKS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let store = ConfigService::load_bookmarks()?;
+        Ok(store
+            .into_iter()
+            .filter_map(|(key, bookmarks)| {
+                let (connection_id, bucket) = key.split_once('/')?;
+                Some(BucketBookmarks {
+                    connection_id: connection_id.to_string(),
+                    bucket: bucket.to_string(),
+                    bookmarks,
+                })
+            })
+            .collect())
+    }
+
+    /// Merge imported bookmarks into a bucket's local ones.
+    pub fn import_local(
+        connection_id: &str,
+        bucket: &str,
+        bookmarks: Vec<Bookmark>,
+    ) -> AppResult<()> {
+        let _guard = BOOKMARKS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut store = ConfigService::load_bookmarks()?;
+        let key = Self::store_key(connection_id, bucket);
+        let ours = store.remove(&key).unwrap_or_default();
+        store.insert(key, Self::merge(ours, bookmarks));
+        ConfigService::save_bookmarks(&store)
+    }
+
+    /// Merge the local and (when shared) bucket copies after applying
+    /// `change`, write the result back to both, and return the live
+    /// bookmarks. A failure to reach the bucket falls back to local only.
+    pub async fn sync(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        shared: bool,
+        change: Option<Bookmark>,
+    ) -> AppResult<Vec<Bookmark>> {
+        let connection_id = connection.id.as_str();
+        let mut bookmarks = Self::load_local(connection_id, bucket)?;
+        // The caller's edit replaces the local copy outright, even if the
+        // clock hasn't moved since the last one
+        if let Some(change) = change {
+            bookmarks.retain(|b| b.prefix != change.prefix);
+            bookmarks.push(change);
+        }
+        let bookmarks = if shared {
+            match Self::share(connection, bucket, bookmarks.clone()).await {
+                Ok(merged) => merged,
+                Err(e) => {
+                    warn!("Failed to share bookmarks in '{}': {}", bucket, e);
+                    Self::merge(bookmarks, Vec::new())
+                }
+            }
+        } else {
+            Self::merge(bookmarks, Vec::new())
+        };
+
+        Self::save_local(connection_id, bucket, &bookmarks)?;
+        Ok(bookmarks.into_iter().filter(|b| !b.deleted).collect())
+    }
+}
@@ -0,0 +1,79 @@
+use aws_credential_types::Credentials;
+use aws_sdk_cloudfront::config::Region;
+use aws_sdk_cloudfront::types::{InvalidationBatch, Paths};
+use aws_sdk_cloudfront::Client as CloudFrontClient;
+use chrono::Utc;
+use log::{debug, trace};
+
+use crate::error::{AppError, AppResult};
+use crate::models::S3ConnectionWithSecret;
+
+pub struct CdnService;
+
+impl CdnService {
+    fn create_cloudfront_client(connection: &S3ConnectionWithSecret) -> CloudFrontClient {
+        trace!("Creating CloudFront client for connection: {}", connection.id);
+
+        let credentials = Credentials::new(
+            &connection.access_key,
+            &connection.secret_key,
+            None,
+            None,
+            "baul-s3-client",
+        );
+
+        // CloudFront is a global service served out of us-east-1
+        let config = aws_sdk_cloudfront::Config::builder()
+            .credentials_provider(credentials)
+            .region(Region::new("us-east-1"))
+            .build();
+
+        CloudFrontClient::from_conf(config)
+    }
+
+    /// Submit a CloudFront invalidation and return its id.
+    pub async fn invalidate_paths(
+        connection: &S3ConnectionWithSecret,
+        distribution_id: &str,
+        paths: Vec<String>,
+    ) -> AppResult<String> {
+        let client = Self::create_cloudfront_client(connection);
+
+        // CloudFront paths are absolute
+        let paths: Vec<String> = paths
+            .into_iter()
+            .map(|p| if p.starts_with('/') { p } else { format!("/{}", p) })
+            .collect();
+
+        debug!(
+            "Invalidating {} paths on distribution {}",
+            paths.len(),
+            distribution_id
+        );
+
+        let paths = Paths::builder()
+            .quantity(paths.len() as i32)
+            .set_items(Some(paths))
+            .build()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+        let batch = InvalidationBatch::builder()
+            .paths(paths)
+            .caller_reference(format!("baul-{}", Utc::now().timestamp_millis()))
+            .build()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+        let result = client
+            .create_invalidation()
+            .distribution_id(distribution_id)
+            .invalidation_batch(batch)
+            .send()
+            .await
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+        Ok(result
+            .invalidation()
+            .map(|i| i.id().to_string())
+            .unwrap_or_default())
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+use aws_credential_types::Credentials;
+use aws_sdk_s3::config::interceptors::BeforeDeserializationInterceptorContextRef;
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+use aws_sigv4::http_request::{
+    sign, PercentEncodingMode, SignableBody, SignableRequest, SigningSettings,
+    UriPathNormalizationMode,
+};
+use aws_sigv4::sign::v4;
+use aws_smithy_async::time::TimeSource;
+use chrono::{DateTime, Utc};
+use http::header::{AUTHORIZATION, DATE};
+use http::{Request, Response};
+use log::{debug, warn};
+use opendal::raw::{HttpBody, HttpClient, HttpFetch};
+use opendal::Buffer;
+
+use crate::models::{ClockSkewReport, S3ConnectionWithSecret, SignatureVersion};
+
+/// Offsets below this are latency and the `Date` header's one-second
+/// resolution rather than a wrong clock.
+const SKEW_THRESHOLD_SECS: i64 = 60;
+
+#[derive(Debug, Default)]
+struct SkewState {
+    compensate: bool,
+    /// Last significant offset seen per connection id.
+    offsets: HashMap<String, ClockSkewReport>,
+}
+
+static SKEW: LazyLock<Mutex<SkewState>> = LazyLock::new(|| Mutex::new(SkewState::default()));
+
+pub struct ClockSkew;
+
+impl ClockSkew {
+    pub fn set_compensation(enabled: bool) {
+        debug!(
+            "Clock skew compensation {}",
+            if enabled { "on" } else { "off" }
+        );
+        SKEW.lock().unwrap_or_else(|e| e.into_inner()).compensate = enabled;
+    }
+
+    /// Record the server time from a response. Offsets under the threshold
+    /// clear an earlier measurement.
+    pub fn record(connection_id: &str, server_time: DateTime<Utc>) {
+        let offset = server_time.timestamp() - Utc::now().timestamp();
+        let mut state = SKEW.lock().unwrap_or_else(|e| e.into_inner());
+
+        if offset.abs() < SKEW_THRESHOLD_SECS {
+            if state.offsets.remove(connection_id).is_some() {
+                debug!("Clock skew for connection '{}' is gone", connection_id);
+            }
+            return;
+        }
+
+        let compensated = state.compensate;
+        let previous = state.offsets.insert(
+            connection_id.to_string(),
+            ClockSkewReport {
+                offset_seconds: offset,
+                measured_at: Utc::now().timestamp(),
+                compensated,
+            },
+        );
+        if previous.is_none() {
+            warn!(
+                "This computer's clock is {}s {} the server's for connection '{}'{}",
+                offset.abs(),
+                if offset > 0 { "behind" } else { "ahead of" },
+                connection_id,
+                if compensated {
+                    "; signing with the server's time"
+                } else {
+                    "; requests may fail with RequestTimeTooSkewed"
+                }
+            );
+        }
+    }
+
+    pub fn report(connection_id: &str) -> Option<ClockSkewReport> {
+        let state = SKEW.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .offsets
+            .get(connection_id)
+            .map(|report| ClockSkewReport {
+                compensated: state.compensate,
+                ..report.clone()
+            })
+    }
+
+    /// Offset to sign with, when compensation is on and skew was measured.
+    pub fn compensation(connection_id: &str) -> Option<i64> {
+        let state = SKEW.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.compensate {
+            return None;
+        }
+        state.offsets.get(connection_id).map(|r| r.offset_seconds)
+    }
+
+    pub fn reset(connection_id: &str) {
+        SKEW.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .offsets
+            .remove(connection_id);
+    }
+}
+
+/// Reads the `Date` header of every response, so skew is known before a
+/// request gets rejected as RequestTimeTooSkewed.
+#[derive(Debug)]
+pub struct ClockSkewInterceptor {
+    connection_id: String,
+}
+
+impl ClockSkewInterceptor {
+    pub fn new(connection_id: &str) -> Self {
+        Self {
+            connection_id: connection_id.to_string(),
+        }
+    }
+}
+
+impl Intercept for ClockSkewInterceptor {
+    fn name(&self) -> &'static str {
+        "ClockSkewInterceptor"
+    }
+
+    fn read_after_transmit(
+        &self,
+        context: &BeforeDeserializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let server_time = context
+            .response()
+            .headers()
+            .get("date")
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok());
+        if let Some(server_time) = server_time {
+            ClockSkew::record(&self.connection_id, server_time.with_timezone(&Utc));
+        }
+        Ok(())
+    }
+}
+
+/// System time shifted by a measured offset, for signing requests.
+#[derive(Debug)]
+pub struct SkewedTimeSource {
+    offset_seconds: i64,
+}
+
+impl SkewedTimeSource {
+    pub fn new(offset_seconds: i64) -> Self {
+        Self { offset_seconds }
+    }
+}
+
+impl TimeSource for SkewedTimeSource {
+    fn now(&self) -> SystemTime {
+        let offset = Duration::from_secs(self.offset_seconds.unsigned_abs());
+        if self.offset_seconds >= 0 {
+            SystemTime::now() + offset
+        } else {
+            SystemTime::now() - offset
+        }
+    }
+}
+
+/// The OpenDAL side of `ClockSkewInterceptor` and `SkewedTimeSource`: reads
+/// the `Date` header of every response and, while compensation applies,
+/// signs SigV4 requests again with the server's time before they go out.
+/// SigV2 requests are left to `SigV2Fetcher`, which dates them itself.
+pub struct ClockSkewFetcher {
+    inner: HttpClient,
+    connection_id: String,
+    access_key: String,
+    secret_key: String,
+    region: String,
+    sigv4: bool,
+}
+
+impl ClockSkewFetcher {
+    /// Wrap `inner`, which actually sends the requests.
+    pub fn new(connection: &S3ConnectionWithSecret, inner: HttpClient) -> Self {
+        Self {
+            inner,
+            connection_id: connection.id.clone(),
+            access_key: connection.access_key.clone(),
+            secret_key: connection.secret_key.clone(),
+            region: connection.region.clone(),
+            sigv4: connection.signature_version == SignatureVersion::V4,
+        }
+    }
+
+    /// Replace OpenDAL's SigV4 signature with one dated `offset_seconds`
+    /// from now, keeping the payload hash it already computed.
+    fn resign(&self, request: &mut Request<Buffer>, offset_seconds: i64) -> Result<(), BoxError> {
+        let headers = request.headers_mut();
+        headers.remove(AUTHORIZATION);
+        headers.remove("x-amz-date");
+        let payload_hash = headers
+            .get("x-amz-content-sha256")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("UNSIGNED-PAYLOAD")
+            .to_string();
+
+        // S3 signs the path as sent, encoded once
+        let mut settings = SigningSettings::default();
+        settings.percent_encoding_mode = PercentEncodingMode::Single;
+        settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+
+        let identity =
+            Credentials::new(&self.access_key, &self.secret_key, None, None, "connection").into();
+        let params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(&self.region)
+            .name("s3")
+            .time(SkewedTimeSource::new(offset_seconds).now())
+            .settings(settings)
+            .build()?
+            .into();
+
+        let uri = request.uri().to_string();
+        let headers: Vec<(&str, &str)> = request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+            .collect();
+        let signable = SignableRequest::new(
+            request.method().as_str(),
+            uri,
+            headers.into_iter(),
+            SignableBody::Precomputed(payload_hash),
+        )?;
+        let (instructions, _) = sign(signable, &params)?.into_parts();
+
+        instructions.apply_to_request_http1x(request);
+        Ok(())
+    }
+}
+
+impl HttpFetch for ClockSkewFetcher {
+    async fn fetch(&self, mut request: Request<Buffer>) -> opendal::Result<Response<HttpBody>> {
+        let offset = ClockSkew::compensation(&self.connection_id).filter(|_| self.sigv4);
+        if let Some(offset) = offset {
+            if let Err(e) = self.resign(&mut request, offset) {
+                warn!("Failed to sign request with the server's time: {}", e);
+            }
+        }
+
+        let response = self.inner.fetch(request).await?;
+        let server_time = response
+            .headers()
+            .get(DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok());
+        if let Some(server_time) = server_time {
+            ClockSkew::record(&self.connection_id, server_time.with_timezone(&Utc));
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{CollisionStrategy, S3Object};
+
+/// Where an incoming object goes once collisions are resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placement {
+    Write(String),
+    Skip,
+}
+
+/// The object about to be written, as far as collision strategies care.
+#[derive(Debug, Clone, Copy)]
+pub struct Incoming<'a> {
+    pub size: u64,
+    /// Unix seconds.
+    pub modified: i64,
+    /// MD5 or ETag of the content, needed by `SuffixHash`.
+    pub digest: Option<&'a str>,
+}
+
+/// Keys already at a destination. Keys handed out by `place` are added as
+/// they go, so two objects in one batch never resolve to the same key.
+#[derive(Debug, Default)]
+pub struct DestinationKeys {
+    existing: HashMap<String, S3Object>,
+}
+
+/// Characters of the content hash `SuffixHash` appends.
+const HASH_SUFFIX_LEN: usize = 8;
+
+impl DestinationKeys {
+    pub fn extend(&mut self, objects: impl IntoIterator<Item = S3Object>) {
+        self.existing
+            .extend(objects.into_iter().map(|o| (o.key.clone(), o)));
+    }
+
+    /// `key` without its extension. Every key a suffix strategy can pick
+    /// for `key` starts with it, so listing it is enough to resolve one.
+    pub fn stem(key: &str) -> &str {
+        let name_start = key.rfind('/').map_or(0, |i| i + 1);
+        match key[name_start..].rfind('.') {
+            Some(dot) if dot > 0 => &key[..name_start + dot],
+            _ => key,
+        }
+    }
+
+    /// `key` with `-suffix` inserted before its extension.
+    pub fn suffixed(key: &str, suffix: &str) -> String {
+        let stem = Self::stem(key);
+        format!("{}-{}{}", stem, suffix, &key[stem.len()..])
+    }
+
+    /// Normalize a digest or ETag for comparison: no quotes, lower case.
+    fn normalize(digest: &str) -> String {
+        digest.trim_matches('"').to_lowercase()
+    }
+
+    /// Decide which key `incoming` is written to in place of `key`, or that
+    /// it is skipped. `Fail` turns a taken key into `AlreadyExists`.
+    pub fn place(
+        &mut self,
+        strategy: CollisionStrategy,
+        key: &str,
+        incoming: Incoming,
+    ) -> AppResult<Placement> {
+        let placement = self.resolve(strategy, key, incoming)?;
+        if let Placement::Write(key) = &placement {
+            self.existing.insert(
+                key.clone(),
+                S3Object {
+                    key: key.clone(),
+                    size: incoming.size,
+                    last_modified: incoming.modified,
+                    etag: incoming.digest.map(str::to_string),
+                    is_directory: key.ends_with('/'),
+                    content_type: None,
+                    child_count: None,
+                },
+            );
+        }
+        Ok(placement)
+    }
+
+    fn resolve(
+        &self,
+        strategy: CollisionStrategy,
+        key: &str,
+        incoming: Incoming,
+    ) -> AppResult<Placement> {
+        let Some(existing) = self.existing.get(key) else {
+            return Ok(Placement::Write(key.to_string()));
+        };
+
+        match strategy {
+            CollisionStrategy::Overwrite => Ok(Placement::Write(key.to_string())),
+            CollisionStrategy::Skip => Ok(Placement::Skip),
+            CollisionStrategy::Fail => Err(AppError::AlreadyExists(key.to_string())),
+            CollisionStrategy::KeepNewer if incoming.modified > existing.last_modified => {
+                Ok(Placement::Write(key.to_string()))
+            }
+            CollisionStrategy::KeepNewer => Ok(Placement::Skip),
+            // A folder that already exists needs no second marker
+            CollisionStrategy::SuffixCounter | CollisionStrategy::SuffixHash
+                if key.ends_with('/') =>
+            {
+                Ok(Placement::Skip)
+            }
+            CollisionStrategy::SuffixCounter => {
+                let mut counter = 1;
+                loop {
+                    let candidate = Self::suffixed(key, &counter.to_string());
+                    if !self.existing.contains_key(&candidate) {
+                        return Ok(Placement::Write(candidate));
+                    }
+                    counter += 1;
+                }
+            }
+            CollisionStrategy::SuffixHash => {
+                let digest = incoming.digest.map(Self::normalize).ok_or_else(|| {
+                    AppError::ConfigError(format!("No content hash to suffix '{}' with", key))
+                })?;
+                if existing.etag.as_deref().map(Self::normalize) == Some(digest.clone()) {
+                    return Ok(Placement::Skip);
+                }
+
+                let short: String = digest
+                    .chars()
+                    .filter(char::is_ascii_alphanumeric)
+                    .take(HASH_SUFFIX_LEN)
+                    .collect();
+                let candidate = Self::suffixed(key, &short);
+                // The same hash suffix means the same content is already there
+                if self.existing.contains_key(&candidate) {
+                    Ok(Placement::Skip)
+                } else {
+                    Ok(Placement::Write(candidate))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use aws_runtime::auth::PayloadSigningOverride;
+use aws_sdk_s3::config::interceptors::{
+    BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextMut,
+};
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+
+use crate::models::CompatibilityOptions;
+
+/// Applies per-connection request tweaks for S3-compatible providers that
+/// reject parts of the default AWS SDK request shape.
+#[derive(Debug)]
+pub struct CompatibilityInterceptor {
+    options: CompatibilityOptions,
+}
+
+impl CompatibilityInterceptor {
+    pub fn new(options: CompatibilityOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl Intercept for CompatibilityInterceptor {
+    fn name(&self) -> &'static str {
+        "CompatibilityInterceptor"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if self.options.unsigned_payload {
+            cfg.interceptor_state()
+                .store_put(PayloadSigningOverride::unsigned_payload());
+        }
+        Ok(())
+    }
+
+    fn modify_before_transmit(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if self.options.disable_expect_continue {
+            context.request_mut().headers_mut().remove("expect");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{CompatCheckResult, CompatFeature, CompatReport, S3ConnectionWithSecret};
+use crate::services::S3Service;
+
+/// Smallest part size S3 accepts for every part but the last.
+const MULTIPART_PART_SIZE: usize = 5 * 1024 * 1024;
+
+const PRESIGNED_TTL: Duration = Duration::from_secs(300);
+
+pub struct CompatCheckService;
+
+impl CompatCheckService {
+    async fn check<F>(checks: &mut Vec<CompatCheckResult>, feature: CompatFeature, run: F) -> bool
+    where
+        F: Future<Output = AppResult<()>>,
+    {
+        let started = Instant::now();
+        let result = run.await;
+        if let Err(e) = &result {
+            warn!("Compatibility check {:?} failed: {}", feature, e);
+        }
+        let ok = result.is_ok();
+        checks.push(CompatCheckResult {
+            feature,
+            ok,
+            error: result.err().map(|e| e.to_string()),
+            duration_ms: started.elapsed().as_millis() as u64,
+        });
+        ok
+    }
+
+    fn expect_bytes(what: &str, actual: &[u8], expected: &[u8]) -> AppResult<()> {
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(AppError::IntegrityError(format!(
+                "{} returned {} bytes, expected {}",
+                what,
+                actual.len(),
+                expected.len()
+            )))
+        }
+    }
+
+    /// Exercise the S3 calls Baul depends on against `bucket`, using
+    /// throwaway objects under `<prefix>compat-<uuid>/` that are removed
+    /// afterwards.
+    pub async fn run(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+    ) -> AppResult<CompatReport> {
+        let operator = S3Service::create_operator(connection, bucket)?;
+        let scratch = format!("{}compat-{}/", prefix, Uuid::new_v4());
+        let key = format!("{}object.txt", scratch);
+        let copy_key = format!("{}copy.txt", scratch);
+        let multipart_key = format!("{}multipart.bin", scratch);
+        let payload = format!(
+            "baul compatibility check {}",
+            chrono::Utc::now().to_rfc3339()
+        )
+        .into_bytes();
+
+        debug!("Running compatibility check in '{}/{}'", bucket, scratch);
+        let mut checks = Vec::new();
+
+        let put = Self::check(&mut checks, CompatFeature::PutObject, async {
+            operator.write(&key, payload.clone()).await?;
+            Ok(())
+        })
+        .await;
+
+        if put {
+            Self::check(&mut checks, CompatFeature::HeadObject, async {
+                let meta = operator.stat(&key).await?;
+                if meta.content_length() != payload.len() as u64 {
+                    return Err(AppError::IntegrityError(format!(
+                        "stat reported {} bytes, wrote {}",
+                        meta.content_length(),
+                        payload.len()
+                    )));
+                }
+                Ok(())
+            })
+            .await;
+
+            Self::check(&mut checks, CompatFeature::GetObject, async {
+                let data = operator.read(&key).await?;
+                Self::expect_bytes("read", &data.to_vec(), &payload)
+            })
+            .await;
+
+            Self::check(&mut checks, CompatFeature::RangeRead, async {
+                if !S3Service::supports_range(connection, bucket, &key).await? {
+                    return Err(AppError::NotSupported(
+                        "range request answered without Content-Range".to_string(),
+                    ));
+                }
+                let data = S3Service::read_range(&operator, &key, 0..4).await?;
+                Self::expect_bytes("range read", &data, &payload[..4])
+            })
+            .await;
+
+            Self::check(&mut checks, CompatFeature::ListObjects, async {
+                let listing = S3Service::list_all_objects(&operator, &scratch).await?;
+                if !listing.objects.iter().any(|o| o.key == key) {
+                    return Err(AppError::NotFound(format!(
+                        "'{}' missing from the listing of '{}'",
+                        key, scratch
+                    )));
+                }
+                Ok(())
+            })
+            .await;
+
+            Self::check(&mut checks, CompatFeature::CopyObject, async {
+                S3Service::copy_object(connection, bucket, &key, None, bucket, &copy_key).await?;
+                let data = operator.read(&copy_key).await?;
+                Self::expect_bytes("copy", &data.to_vec(), &payload)
+            })
+            .await;
+
+            Self::check(&mut checks, CompatFeature::MultipartUpload, async {
+                let data = vec![0x62u8; MULTIPART_PART_SIZE + 1];
+                let mut writer = operator
+                    .writer_with(&multipart_key)
+                    .chunk(MULTIPART_PART_SIZE)
+                    .await?;
+                if let Err(e) = writer.write(data.clone()).await {
+                    let _ = writer.abort().await;
+                    return Err(e.into());
+                }
+                writer.close().await?;
+                let meta = operator.stat(&multipart_key).await?;
+                if meta.content_length() != data.len() as u64 {
+                    return Err(AppError::IntegrityError(format!(
+                        "multipart object is {} bytes, wrote {}",
+                        meta.content_length(),
+                        data.len()
+                    )));
+                }
+                Ok(())
+            })
+            .await;
+
+            Self::check(&mut checks, CompatFeature::ObjectTagging, async {
+                let tags = [("baul".to_string(), "compat".to_string())].into();
+                S3Service::put_object_tags(connection, bucket, &key, &tags).await
+            })
+            .await;
+
+            Self::check(&mut checks, CompatFeature::PresignedUrl, async {
+                if let Some(reason) = S3Service::presign_unavailable(connection) {
+                    return Err(AppError::NotSupported(reason.to_string()));
+                }
+                let url = S3Service::get_presigned_url(
+                    connection,
+                    bucket,
+                    &key,
+                    PRESIGNED_TTL.as_secs(),
+                    None,
+                )
+                .await?;
+                let client = reqwest::Client::builder()
+                    .timeout(Duration::from_secs(30))
+                    .build()
+                    .map_err(|e| AppError::S3Error(e.to_string()))?;
+                let response = client
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| AppError::S3Error(format!("presigned GET failed: {}", e)))?;
+                let data = response
+                    .bytes()
+                    .await
+                    .map_err(|e| AppError::S3Error(e.to_string()))?;
+                Self::expect_bytes("presigned GET", &data, &payload)
+            })
+            .await;
+        }
+
+        Self::check(&mut checks, CompatFeature::Versioning, async {
+            S3Service::get_bucket_versioning(connection, bucket).await?;
+            Ok(())
+        })
+        .await;
+
+        if put {
+            Self::check(&mut checks, CompatFeature::BatchDelete, async {
+                let result = S3Service::delete_keys(
+                    connection,
+                    bucket,
+                    vec![key.clone(), copy_key.clone(), multipart_key.clone()],
+                )
+                .await?;
+                match result.failed.first() {
+                    Some(failure) => Err(AppError::S3Error(format!(
+                        "{} objects not deleted, first: {}",
+                        result.failed.len(),
+                        failure.message
+                    ))),
+                    None => Ok(()),
+                }
+            })
+            .await;
+        }
+
+        let passed = checks.iter().filter(|c| c.ok).count();
+        let failed = checks.len() - passed;
+        Ok(CompatReport {
+            bucket: bucket.to_string(),
+            endpoint: connection.endpoint.clone(),
+            checks,
+            passed,
+            failed,
+        })
+    }
+}
@@ -0,0 +1,93 @@
+use std::num::NonZeroU32;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::error::{AppError, AppResult};
+use crate::models::EncryptedSecret;
+
+/// Bundle format written by this build.
+pub const CONFIG_BUNDLE_VERSION: u32 = 1;
+
+/// OWASP's current recommendation for PBKDF2-HMAC-SHA256.
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+const SALT_LEN: usize = 16;
+
+/// Seals and opens the connection secrets in a config bundle with a key
+/// derived from the user's passphrase.
+pub struct ConfigBundleService;
+
+impl ConfigBundleService {
+    fn random_bytes<const N: usize>() -> AppResult<[u8; N]> {
+        let mut bytes = [0u8; N];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| AppError::ConfigError("System random source unavailable".into()))?;
+        Ok(bytes)
+    }
+
+    fn decode(field: &str, value: &str) -> AppResult<Vec<u8>> {
+        BASE64
+            .decode(value)
+            .map_err(|e| AppError::ConfigError(format!("Invalid {} in bundle: {}", field, e)))
+    }
+
+    /// A fresh salt for a bundle, base64.
+    pub fn new_salt() -> AppResult<String> {
+        Ok(BASE64.encode(Self::random_bytes::<SALT_LEN>()?))
+    }
+
+    /// Stretch `passphrase` into an AES-256-GCM key. Slow on purpose; derive
+    /// once per bundle, not per secret.
+    pub fn derive_key(passphrase: &str, salt: &str) -> AppResult<LessSafeKey> {
+        if passphrase.is_empty() {
+            return Err(AppError::ConfigError("Passphrase is empty".into()));
+        }
+        let salt = Self::decode("salt", salt)?;
+
+        let mut key = [0u8; 32];
+        let rounds = NonZeroU32::new(PBKDF2_ROUNDS).expect("PBKDF2_ROUNDS is non-zero");
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            rounds,
+            &salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+
+        let key = UnboundKey::new(&AES_256_GCM, &key)
+            .map_err(|_| AppError::ConfigError("Invalid bundle key".into()))?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    pub fn seal(key: &LessSafeKey, secret: &str) -> AppResult<EncryptedSecret> {
+        let nonce = Self::random_bytes::<NONCE_LEN>()?;
+        let mut data = secret.as_bytes().to_vec();
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| AppError::ConfigError("Failed to encrypt secret".into()))?;
+
+        Ok(EncryptedSecret {
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(data),
+        })
+    }
+
+    /// Decrypt a sealed secret. A wrong passphrase and a tampered bundle
+    /// look the same here.
+    pub fn open(key: &LessSafeKey, sealed: &EncryptedSecret) -> AppResult<String> {
+        let nonce: [u8; NONCE_LEN] = Self::decode("nonce", &sealed.nonce)?
+            .try_into()
+            .map_err(|_| AppError::ConfigError("Invalid nonce in bundle".into()))?;
+        let mut data = Self::decode("secret", &sealed.ciphertext)?;
+
+        let plain = key
+            .open_in_place(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| AppError::ConfigError("Wrong passphrase or corrupted bundle".into()))?;
+        String::from_utf8(plain.to_vec())
+            .map_err(|_| AppError::ConfigError("Decrypted secret is not UTF-8".into()))
+    }
+}
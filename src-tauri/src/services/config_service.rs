@@ -1,13 +1,36 @@
 use directories::ProjectDirs;
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{S3Connection, S3ConnectionWithSecret};
+use crate::models::{
+    AppSettings, Bookmark, KeyringRegistry, LocalCopy, ProfileConfig, S3Connection,
+    S3ConnectionWithSecret, DELETE_BATCH_SIZES,
+};
 
 const CONFIG_FILE: &str = "connections.json";
+const SETTINGS_FILE: &str = "settings.json";
+const KEYRING_FILE: &str = "keyring.json";
+const PROFILES_FILE: &str = "profiles.json";
+const PROFILES_DIR: &str = "profiles";
+const JOURNALS_DIR: &str = "journals";
+const INDEXES_DIR: &str = "indexes";
+const DOWNLOADS_FILE: &str = "downloads.json";
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+/// The profile that lives directly in the config directory, so installs
+/// from before profiles existed keep their connections.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Active profile name, loaded from disk on first use.
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Serializes read-modify-write of the connections file, so concurrent
+/// create/update/delete commands can't drop each other's changes.
+static CONNECTIONS_LOCK: Mutex<()> = Mutex::new(());
 
 pub struct ConfigService;
 
@@ -27,14 +50,124 @@ impl ConfigService {
         Ok(config_dir)
     }
 
-    fn get_config_path() -> AppResult<PathBuf> {
+    /// Directory holding the active profile's connections and keyring registry.
+    fn get_profile_dir() -> AppResult<PathBuf> {
+        let profile = Self::active_profile();
         let config_dir = Self::get_config_dir()?;
-        Ok(config_dir.join(CONFIG_FILE))
+
+        if profile == DEFAULT_PROFILE {
+            return Ok(config_dir);
+        }
+
+        let profile_dir = config_dir.join(PROFILES_DIR).join(&profile);
+        if !profile_dir.exists() {
+            debug!("Creating profile directory: {:?}", profile_dir);
+            fs::create_dir_all(&profile_dir)?;
+        }
+        Ok(profile_dir)
+    }
+
+    fn get_config_path() -> AppResult<PathBuf> {
+        let profile_dir = Self::get_profile_dir()?;
+        Ok(profile_dir.join(CONFIG_FILE))
+    }
+
+    /// Replace `path` with `content` via a temporary file and rename, so a
+    /// reader never sees a half-written config file.
+    fn write_atomic(path: &Path, content: &str) -> AppResult<()> {
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content)?;
+        if let Err(e) = fs::rename(&tmp_path, path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    pub fn active_profile() -> String {
+        if let Some(profile) = ACTIVE_PROFILE.read().unwrap_or_else(|e| e.into_inner()).clone() {
+            return profile;
+        }
+
+        let profile = Self::get_config_dir()
+            .and_then(|dir| {
+                let path = dir.join(PROFILES_FILE);
+                if !path.exists() {
+                    return Ok(ProfileConfig::default());
+                }
+                let content = fs::read_to_string(path)?;
+                Ok(serde_json::from_str::<ProfileConfig>(&content)?)
+            })
+            .unwrap_or_else(|e| {
+                error!("Failed to read profile config: {}", e);
+                ProfileConfig::default()
+            })
+            .active_profile
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+
+        *ACTIVE_PROFILE.write().unwrap_or_else(|e| e.into_inner()) = Some(profile.clone());
+        profile
+    }
+
+    pub fn list_profiles() -> AppResult<Vec<String>> {
+        let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+
+        let profiles_dir = Self::get_config_dir()?.join(PROFILES_DIR);
+        if profiles_dir.exists() {
+            for entry in fs::read_dir(&profiles_dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    profiles.push(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+        }
+
+        profiles[1..].sort();
+        Ok(profiles)
+    }
+
+    /// Make `profile` active for this run, creating it if needed. It is
+    /// only remembered across launches by `save_active_profile`, so callers
+    /// can load its connections first and back out if that fails.
+    pub fn select_profile(profile: &str) -> AppResult<()> {
+        let valid = !profile.is_empty()
+            && profile.len() <= 64
+            && profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ' '))
+            && profile.trim() == profile;
+        if !valid {
+            return Err(AppError::ConfigError(format!(
+                "Invalid profile name '{}': use letters, digits, spaces, '-' or '_'",
+                profile
+            )));
+        }
+
+        *ACTIVE_PROFILE.write().unwrap_or_else(|e| e.into_inner()) = Some(profile.to_string());
+
+        // Create the profile directory up front so it shows in list_profiles
+        Self::get_profile_dir()?;
+        Ok(())
+    }
+
+    /// Remember the active profile for the next launch.
+    pub fn save_active_profile() -> AppResult<()> {
+        let profile = Self::active_profile();
+        let config = ProfileConfig {
+            active_profile: Some(profile.clone()),
+        };
+        let content = serde_json::to_string_pretty(&config)?;
+        Self::write_atomic(&Self::get_config_dir()?.join(PROFILES_FILE), &content)?;
+
+        info!("Switched to profile '{}'", profile);
+        Ok(())
     }
 
     pub fn load_connections() -> AppResult<HashMap<String, S3Connection>> {
-        let config_path = Self::get_config_path()?;
+        Self::load_connections_from(&Self::get_config_path()?)
+    }
 
+    fn load_connections_from(config_path: &Path) -> AppResult<HashMap<String, S3Connection>> {
         if !config_path.exists() {
             debug!("Config file does not exist: {:?}", config_path);
             return Ok(HashMap::new());
@@ -42,7 +175,7 @@ impl ConfigService {
 
         debug!("Loading connections from: {:?}", config_path);
 
-        let content = match fs::read_to_string(&config_path) {
+        let content = match fs::read_to_string(config_path) {
             Ok(c) => c,
             Err(e) => {
                 error!("Failed to read config file: {}", e);
@@ -50,7 +183,7 @@ impl ConfigService {
             }
         };
 
-        let connections: HashMap<String, S3Connection> = match serde_json::from_str(&content) {
+        let mut connections: HashMap<String, S3Connection> = match serde_json::from_str(&content) {
             Ok(c) => c,
             Err(e) => {
                 error!("Failed to parse config file: {}", e);
@@ -58,35 +191,263 @@ impl ConfigService {
             }
         };
 
+        // A hand-edited batch size of 0 would stall every bulk delete
+        for connection in connections.values_mut() {
+            if let Some(size) = connection
+                .delete_batch_size
+                .filter(|s| !DELETE_BATCH_SIZES.contains(s))
+            {
+                warn!(
+                    "Ignoring delete batch size {} of connection '{}'",
+                    size, connection.name
+                );
+                connection.delete_batch_size = None;
+            }
+        }
+
         debug!("Loaded {} connections from config", connections.len());
         Ok(connections)
     }
 
-    pub fn save_connections(connections: &HashMap<String, S3Connection>) -> AppResult<()> {
-        let config_path = Self::get_config_path()?;
-
+    fn save_connections_to(
+        config_path: &Path,
+        connections: &HashMap<String, S3Connection>,
+    ) -> AppResult<()> {
         trace!("Saving {} connections to: {:?}", connections.len(), config_path);
 
         let content = serde_json::to_string_pretty(connections)?;
-        fs::write(&config_path, content)?;
+        Self::write_atomic(config_path, &content)?;
 
         debug!("Saved {} connections to config", connections.len());
         Ok(())
     }
 
+    /// Run `f` against the stored connections and persist the result,
+    /// holding the connections lock for the whole load-modify-save.
+    pub fn update_connections<T>(
+        f: impl FnOnce(&mut HashMap<String, S3Connection>) -> T,
+    ) -> AppResult<T> {
+        // Resolved once, so a profile switch midway can't split the load
+        // and the save across two files
+        let config_path = Self::get_config_path()?;
+        Self::update_connections_at(&config_path, f)
+    }
+
+    fn update_connections_at<T>(
+        config_path: &Path,
+        f: impl FnOnce(&mut HashMap<String, S3Connection>) -> T,
+    ) -> AppResult<T> {
+        let _guard = CONNECTIONS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut connections = Self::load_connections_from(config_path)?;
+        let result = f(&mut connections);
+        Self::save_connections_to(config_path, &connections)?;
+        Ok(result)
+    }
+
     pub fn save_connection(connection: &S3ConnectionWithSecret) -> AppResult<()> {
         info!("Saving connection '{}' to config", connection.name);
 
-        let mut connections = Self::load_connections()?;
-        connections.insert(connection.id.clone(), connection.clone().into());
-        Self::save_connections(&connections)
+        Self::update_connections(|connections| {
+            connections.insert(connection.id.clone(), connection.clone().into());
+        })
     }
 
     pub fn delete_connection(connection_id: &str) -> AppResult<()> {
         info!("Deleting connection '{}' from config", connection_id);
 
-        let mut connections = Self::load_connections()?;
-        connections.remove(connection_id);
-        Self::save_connections(&connections)
+        Self::update_connections(|connections| {
+            connections.remove(connection_id);
+        })
+    }
+
+    pub fn load_settings() -> AppResult<AppSettings> {
+        let settings_path = Self::get_config_dir()?.join(SETTINGS_FILE);
+
+        if !settings_path.exists() {
+            debug!("Settings file does not exist, using defaults: {:?}", settings_path);
+            return Ok(AppSettings::default());
+        }
+
+        let content = fs::read_to_string(&settings_path)?;
+        let mut settings: AppSettings = serde_json::from_str(&content).map_err(|e| {
+            error!("Failed to parse settings file: {}", e);
+            e
+        })?;
+
+        for preset in &mut settings.provider_presets {
+            if let Some(size) = preset
+                .delete_batch_size
+                .filter(|s| !DELETE_BATCH_SIZES.contains(s))
+            {
+                warn!(
+                    "Ignoring delete batch size {} of preset '{}'",
+                    size, preset.name
+                );
+                preset.delete_batch_size = None;
+            }
+        }
+
+        debug!("Loaded settings from: {:?}", settings_path);
+        Ok(settings)
+    }
+
+    pub fn save_settings(settings: &AppSettings) -> AppResult<()> {
+        let settings_path = Self::get_config_dir()?.join(SETTINGS_FILE);
+
+        let content = serde_json::to_string_pretty(settings)?;
+        Self::write_atomic(&settings_path, &content)?;
+
+        debug!("Saved settings to: {:?}", settings_path);
+        Ok(())
+    }
+
+    /// Where job journals are written.
+    pub fn journal_dir() -> AppResult<PathBuf> {
+        let journal_dir = Self::get_config_dir()?.join(JOURNALS_DIR);
+        if !journal_dir.exists() {
+            fs::create_dir_all(&journal_dir)?;
+        }
+        Ok(journal_dir)
+    }
+
+    /// Where a connection's bucket indexes are kept.
+    pub fn index_dir(connection_id: &str) -> AppResult<PathBuf> {
+        let index_dir = Self::get_config_dir()?
+            .join(INDEXES_DIR)
+            .join(connection_id);
+        if !index_dir.exists() {
+            fs::create_dir_all(&index_dir)?;
+        }
+        Ok(index_dir)
+    }
+
+    pub fn load_download_history() -> AppResult<Vec<LocalCopy>> {
+        let history_path = Self::get_profile_dir()?.join(DOWNLOADS_FILE);
+
+        if !history_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&history_path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save_download_history(history: &[LocalCopy]) -> AppResult<()> {
+        let history_path = Self::get_profile_dir()?.join(DOWNLOADS_FILE);
+
+        let content = serde_json::to_string_pretty(history)?;
+        Self::write_atomic(&history_path, &content)?;
+
+        trace!("Saved download history to: {:?}", history_path);
+        Ok(())
+    }
+
+    /// Bookmarks keyed by `<connection id>/<bucket>`.
+    pub fn load_bookmarks() -> AppResult<HashMap<String, Vec<Bookmark>>> {
+        let bookmarks_path = Self::get_profile_dir()?.join(BOOKMARKS_FILE);
+
+        if !bookmarks_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = fs::read_to_string(&bookmarks_path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save_bookmarks(bookmarks: &HashMap<String, Vec<Bookmark>>) -> AppResult<()> {
+        let bookmarks_path = Self::get_profile_dir()?.join(BOOKMARKS_FILE);
+
+        let content = serde_json::to_string_pretty(bookmarks)?;
+        Self::write_atomic(&bookmarks_path, &content)?;
+
+        trace!("Saved bookmarks to: {:?}", bookmarks_path);
+        Ok(())
+    }
+
+    pub fn load_keyring_registry() -> AppResult<KeyringRegistry> {
+        let registry_path = Self::get_profile_dir()?.join(KEYRING_FILE);
+
+        if !registry_path.exists() {
+            return Ok(KeyringRegistry::default());
+        }
+
+        let content = fs::read_to_string(&registry_path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save_keyring_registry(registry: &KeyringRegistry) -> AppResult<()> {
+        let registry_path = Self::get_profile_dir()?.join(KEYRING_FILE);
+
+        let content = serde_json::to_string_pretty(registry)?;
+        Self::write_atomic(&registry_path, &content)?;
+
+        trace!("Saved keyring registry to: {:?}", registry_path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+
+    fn connection(id: &str) -> S3Connection {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "provider": "custom",
+            "endpoint": "http://localhost:9000",
+            "region": "us-east-1",
+            "accessKey": "key",
+            "useSsl": false,
+            "usePathStyle": true,
+            "createdAt": 0,
+            "updatedAt": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn racing_updates_keep_both_changes() {
+        let dir = std::env::temp_dir().join(format!("baul-config-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE);
+        ConfigService::update_connections_at(&path, |c| {
+            c.insert("existing".into(), connection("existing"));
+        })
+        .unwrap();
+
+        // Both updates load at the same moment and hold their copy a while,
+        // which is where an unserialized load-modify-save loses one
+        let start = Arc::new(Barrier::new(2));
+        let writers: Vec<_> = ["first", "second"]
+            .into_iter()
+            .map(|id| {
+                let start = start.clone();
+                let path = path.clone();
+                thread::spawn(move || {
+                    start.wait();
+                    ConfigService::update_connections_at(&path, |c| {
+                        thread::sleep(Duration::from_millis(50));
+                        c.insert(id.into(), connection(id));
+                    })
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+
+        let mut ids: Vec<String> = ConfigService::load_connections_from(&path)
+            .unwrap()
+            .into_keys()
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["existing", "first", "second"]);
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,26 +1,94 @@
 use keyring::Entry;
-use log::{debug, error, trace, warn};
+use log::{debug, error, info, trace, warn};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use uuid::Uuid;
 
 use crate::error::{AppError, AppResult};
+use crate::models::{KeyringRegistry, OrphanReason, OrphanedSecret};
+use crate::services::ConfigService;
 
 const SERVICE_NAME: &str = "dev.codycody31.baul";
 
+/// Serializes read-modify-write of the keyring registry; secrets are loaded
+/// concurrently at startup.
+static REGISTRY_LOCK: Mutex<()> = Mutex::new(());
+
 pub struct CredentialService;
 
 impl CredentialService {
-    fn get_entry(connection_id: &str) -> AppResult<Entry> {
-        trace!("Creating keyring entry for connection: {}", connection_id);
-        Entry::new(SERVICE_NAME, connection_id)
+    fn entry_for(account: &str) -> AppResult<Entry> {
+        trace!("Creating keyring entry for account: {}", account);
+        Entry::new(SERVICE_NAME, account)
             .map_err(|e| {
                 error!("Failed to create keyring entry: {}", e);
                 AppError::KeyringError(e.to_string())
             })
     }
 
+    /// Map a keychain failure, telling a denied or cancelled access prompt
+    /// apart from other errors so the connection can be retried later.
+    fn keyring_error(e: keyring::Error) -> AppError {
+        let denied = match &e {
+            keyring::Error::NoStorageAccess(_) => true,
+            keyring::Error::PlatformFailure(inner) => {
+                let message = inner.to_string().to_lowercase();
+                // macOS: errSecUserCanceled, errSecAuthFailed,
+                // errSecInteractionNotAllowed
+                ["-128", "-25293", "-25308", "user canceled", "denied"]
+                    .iter()
+                    .any(|needle| message.contains(needle))
+            }
+            _ => false,
+        };
+
+        if denied {
+            AppError::KeychainLocked(e.to_string())
+        } else {
+            AppError::KeyringError(e.to_string())
+        }
+    }
+
+    /// Run `f` against the registry, creating this install's namespace on
+    /// first use, and persist the result.
+    fn with_registry<T>(f: impl FnOnce(&mut KeyringRegistry) -> T) -> AppResult<T> {
+        let _guard = REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut registry = ConfigService::load_keyring_registry()?;
+        if registry.namespace.is_empty() {
+            registry.namespace = Uuid::new_v4().simple().to_string()[..12].to_string();
+            info!("Created keyring namespace '{}'", registry.namespace);
+        }
+
+        let result = f(&mut registry);
+        ConfigService::save_keyring_registry(&registry)?;
+        Ok(result)
+    }
+
+    fn account_name(namespace: &str, connection_id: &str) -> String {
+        format!("{}:{}", namespace, connection_id)
+    }
+
+    /// Namespaced account for a connection, recording it in the registry.
+    fn register_account(connection_id: &str) -> AppResult<String> {
+        Self::with_registry(|registry| {
+            let account = Self::account_name(&registry.namespace, connection_id);
+            if !registry.accounts.contains(&account) {
+                registry.accounts.push(account.clone());
+            }
+            account
+        })
+    }
+
+    fn unregister_account(account: &str) -> AppResult<()> {
+        Self::with_registry(|registry| registry.accounts.retain(|a| a != account))
+    }
+
     pub fn store_secret(connection_id: &str, secret_key: &str) -> AppResult<()> {
         debug!("Storing secret in keyring for connection: {}", connection_id);
 
-        let entry = Self::get_entry(connection_id)?;
+        let account = Self::register_account(connection_id)?;
+        let entry = Self::entry_for(&account)?;
         entry
             .set_password(secret_key)
             .map_err(|e| {
@@ -32,30 +100,106 @@ impl CredentialService {
         Ok(())
     }
 
+    /// Read a connection's secret. Entries written before namespacing (keyed
+    /// by the bare connection id) are moved into the namespace on first read.
+    /// A denied keychain prompt is a `KeychainLocked` error and a missing
+    /// entry is `NotFound`.
     pub fn get_secret(connection_id: &str) -> AppResult<String> {
         trace!("Retrieving secret from keyring for connection: {}", connection_id);
 
-        let entry = Self::get_entry(connection_id)?;
-        entry
-            .get_password()
-            .map_err(|e| {
+        let account = Self::register_account(connection_id)?;
+        match Self::entry_for(&account)?.get_password() {
+            Ok(secret) => return Ok(secret),
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => {
                 warn!("Failed to retrieve secret from keyring: {}", e);
-                AppError::KeyringError(e.to_string())
-            })
+                return Err(Self::keyring_error(e));
+            }
+        }
+
+        let legacy = Self::entry_for(connection_id)?;
+        let secret = legacy.get_password().map_err(|e| match e {
+            keyring::Error::NoEntry => AppError::NotFound(format!(
+                "No secret in the keychain for connection {}",
+                connection_id
+            )),
+            e => {
+                warn!("Failed to retrieve secret from keyring: {}", e);
+                Self::keyring_error(e)
+            }
+        })?;
+
+        info!("Migrating legacy keyring entry for connection: {}", connection_id);
+        Self::store_secret(connection_id, &secret)?;
+        if let Err(e) = legacy.delete_credential() {
+            warn!("Failed to remove legacy keyring entry: {}", e);
+        }
+
+        Ok(secret)
     }
 
     pub fn delete_secret(connection_id: &str) -> AppResult<()> {
         debug!("Deleting secret from keyring for connection: {}", connection_id);
 
-        let entry = Self::get_entry(connection_id)?;
-        entry
-            .delete_credential()
-            .map_err(|e| {
-                warn!("Failed to delete secret from keyring: {}", e);
-                AppError::KeyringError(e.to_string())
-            })?;
+        let account =
+            Self::with_registry(|registry| Self::account_name(&registry.namespace, connection_id))?;
+        Self::delete_account(&account)?;
 
         debug!("Successfully deleted secret from keyring");
         Ok(())
     }
+
+    /// Remove one keychain account and forget it in the registry. An entry
+    /// that is already gone counts as removed.
+    pub fn delete_account(account: &str) -> AppResult<()> {
+        match Self::entry_for(account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => {
+                warn!("Failed to delete secret from keyring: {}", e);
+                return Err(AppError::KeyringError(e.to_string()));
+            }
+        }
+
+        Self::unregister_account(account)
+    }
+
+    /// Check the keychain can be reached by looking up an account that
+    /// never exists; anything but "no entry" means it's unusable.
+    pub fn check_store() -> AppResult<()> {
+        match Self::entry_for("baul-availability-check")?.get_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => {
+                warn!("Keychain is unavailable: {}", e);
+                Err(AppError::KeyringError(e.to_string()))
+            }
+        }
+    }
+
+    /// Registered accounts that belong to no current connection or to another
+    /// install's namespace.
+    pub fn list_orphaned_secrets(known_ids: &HashSet<String>) -> AppResult<Vec<OrphanedSecret>> {
+        Self::with_registry(|registry| {
+            registry
+                .accounts
+                .iter()
+                .filter_map(|account| {
+                    let (namespace, connection_id) = account.split_once(':')?;
+
+                    let reason = if namespace != registry.namespace {
+                        OrphanReason::ForeignNamespace
+                    } else if !known_ids.contains(connection_id) {
+                        OrphanReason::UnknownConnection
+                    } else {
+                        return None;
+                    };
+
+                    Some(OrphanedSecret {
+                        account: account.clone(),
+                        connection_id: connection_id.to_string(),
+                        reason,
+                    })
+                })
+                .collect()
+        })
+    }
 }
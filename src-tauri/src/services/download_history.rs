@@ -0,0 +1,89 @@
+use chrono::Utc;
+use log::debug;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::AppResult;
+use crate::models::LocalCopy;
+use crate::services::ConfigService;
+
+/// Oldest entries are dropped past this many recorded downloads.
+const MAX_HISTORY: usize = 2000;
+
+/// Serializes read-modify-write of the history file; downloads finish
+/// concurrently.
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct DownloadHistory;
+
+impl DownloadHistory {
+    /// Remember that `key` was saved to `path`, replacing any earlier record
+    /// for the same file.
+    pub fn record(
+        connection_id: &str,
+        bucket: &str,
+        key: &str,
+        path: &Path,
+        etag: Option<String>,
+        size: u64,
+    ) -> AppResult<()> {
+        let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let path = path.to_string_lossy().to_string();
+        let mut history = ConfigService::load_download_history()?;
+        history.retain(|copy| copy.path != path);
+        history.push(LocalCopy {
+            connection_id: connection_id.to_string(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            path,
+            downloaded_at: Utc::now().timestamp(),
+            etag,
+            size,
+            exists: true,
+            up_to_date: true,
+        });
+
+        if history.len() > MAX_HISTORY {
+            let excess = history.len() - MAX_HISTORY;
+            history.drain(..excess);
+        }
+
+        ConfigService::save_download_history(&history)
+    }
+
+    /// Recorded copies of an object, newest first, checked against the disk
+    /// and the object's current ETag.
+    pub fn copies_of(
+        connection_id: &str,
+        bucket: &str,
+        key: &str,
+        remote_etag: Option<&str>,
+    ) -> AppResult<Vec<LocalCopy>> {
+        let history = {
+            let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            ConfigService::load_download_history()?
+        };
+
+        let mut copies: Vec<LocalCopy> = history
+            .into_iter()
+            .filter(|c| c.connection_id == connection_id && c.bucket == bucket && c.key == key)
+            .map(|mut copy| {
+                copy.exists = std::fs::metadata(&copy.path)
+                    .is_ok_and(|meta| meta.is_file() && meta.len() == copy.size);
+                copy.up_to_date =
+                    copy.exists && copy.etag.is_some() && copy.etag.as_deref() == remote_etag;
+                copy
+            })
+            .collect();
+        copies.reverse();
+
+        debug!(
+            "Found {} local copies of '{}/{}'",
+            copies.len(),
+            bucket,
+            key
+        );
+        Ok(copies)
+    }
+}
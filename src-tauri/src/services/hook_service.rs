@@ -0,0 +1,102 @@
+use chrono::Utc;
+use log::{debug, warn};
+
+use crate::error::AppResult;
+use crate::models::{
+    PostUploadAction, PostUploadHook, PostUploadResult, S3ConnectionWithSecret, UploadNotification,
+};
+use crate::services::{S3Service, WebhookService};
+
+pub struct HookService;
+
+impl HookService {
+    /// Hooks that apply to an upload of `key` into `bucket`.
+    pub fn matching<'a>(
+        hooks: &'a [PostUploadHook],
+        bucket: &str,
+        key: &str,
+    ) -> Vec<&'a PostUploadHook> {
+        hooks
+            .iter()
+            .filter(|h| h.bucket.as_deref().is_none_or(|b| b == bucket))
+            .filter(|h| key.starts_with(h.prefix.trim_start_matches('/')))
+            .collect()
+    }
+
+    async fn run_action(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        size: u64,
+        action: &PostUploadAction,
+    ) -> AppResult<Option<String>> {
+        match action {
+            PostUploadAction::CopyPublicUrl => {
+                S3Service::public_url(connection, bucket, key).map(Some)
+            }
+            PostUploadAction::CopyPresignedUrl { expires_in_secs } => {
+                S3Service::get_presigned_url(connection, bucket, key, *expires_in_secs, None)
+                    .await
+                    .map(Some)
+            }
+            PostUploadAction::SetTags { tags } => {
+                S3Service::put_object_tags(connection, bucket, key, tags).await?;
+                Ok(None)
+            }
+            PostUploadAction::Webhook { url } => {
+                let notification = UploadNotification {
+                    event: "upload".to_string(),
+                    bucket: bucket.to_string(),
+                    key: key.to_string(),
+                    size,
+                    uploaded_at: Utc::now().timestamp(),
+                };
+                WebhookService::post_json(url, &notification, None).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Run every matching hook for an uploaded object, in order. A failed
+    /// action is reported and doesn't stop the ones after it.
+    pub async fn run_post_upload(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        size: u64,
+        hooks: &[PostUploadHook],
+    ) -> Vec<PostUploadResult> {
+        let mut results = Vec::new();
+
+        for hook in Self::matching(hooks, bucket, key) {
+            let action = hook.action.name();
+            debug!("Running post-upload '{}' for '{}/{}'", action, bucket, key);
+
+            let result = match Self::run_action(connection, bucket, key, size, &hook.action).await {
+                Ok(output) => PostUploadResult {
+                    key: key.to_string(),
+                    action: action.to_string(),
+                    ok: true,
+                    output,
+                    error: None,
+                },
+                Err(e) => {
+                    warn!(
+                        "Post-upload '{}' failed for '{}/{}': {}",
+                        action, bucket, key, e
+                    );
+                    PostUploadResult {
+                        key: key.to_string(),
+                        action: action.to_string(),
+                        ok: false,
+                        output: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            results.push(result);
+        }
+
+        results
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use aws_sdk_s3::Client as S3Client;
+use chrono::Utc;
+use futures::TryStreamExt;
+use log::{debug, info};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    IndexQuery, IndexQueryResult, IndexStats, IndexSummary, ListingSort, S3ConnectionWithSecret,
+    S3Object, SizeBreakdown,
+};
+use crate::services::{ConfigService, KeyMatcher, S3Service};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS objects (
+        key TEXT PRIMARY KEY,
+        size INTEGER NOT NULL,
+        etag TEXT,
+        last_modified INTEGER NOT NULL,
+        content_type TEXT,
+        generation INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS objects_size ON objects (size);
+    CREATE INDEX IF NOT EXISTS objects_last_modified ON objects (last_modified);
+    CREATE TABLE IF NOT EXISTS meta (name TEXT PRIMARY KEY, value INTEGER NOT NULL);
+";
+
+/// Entries in `IndexStats::largest`.
+const LARGEST_OBJECTS: usize = 20;
+
+/// Extensions listed in `IndexStats`; the rest are left out.
+const MAX_EXTENSIONS: usize = 50;
+
+/// Index files with a crawl running, so two can't write the same one.
+static CRAWLING: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Marks an index as being crawled until dropped.
+struct CrawlGuard(PathBuf);
+
+impl CrawlGuard {
+    fn acquire(path: &Path) -> AppResult<Self> {
+        let mut crawling = CRAWLING.lock().unwrap_or_else(|e| e.into_inner());
+        if !crawling.insert(path.to_path_buf()) {
+            return Err(AppError::Conflict(
+                "This bucket is already being indexed".to_string(),
+            ));
+        }
+        Ok(Self(path.to_path_buf()))
+    }
+}
+
+impl Drop for CrawlGuard {
+    fn drop(&mut self) {
+        CRAWLING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.0);
+    }
+}
+
+/// Local SQLite copies of bucket listings, for search and size analytics
+/// without listing the bucket again.
+pub struct IndexService;
+
+impl IndexService {
+    fn sqlite_error(e: rusqlite::Error) -> AppError {
+        AppError::IndexError(e.to_string())
+    }
+
+    fn path(connection_id: &str, bucket: &str) -> AppResult<PathBuf> {
+        Ok(ConfigService::index_dir(connection_id)?.join(format!("{}.sqlite", bucket)))
+    }
+
+    fn open(path: &Path) -> AppResult<Connection> {
+        let db = Connection::open(path).map_err(Self::sqlite_error)?;
+        // Lets searches read while a refresh writes
+        db.pragma_update(None, "journal_mode", "WAL")
+            .map_err(Self::sqlite_error)?;
+        db.pragma_update(None, "synchronous", "NORMAL")
+            .map_err(Self::sqlite_error)?;
+        db.execute_batch(SCHEMA).map_err(Self::sqlite_error)?;
+        Ok(db)
+    }
+
+    /// Open an existing index, failing when the bucket was never indexed.
+    fn open_existing(connection_id: &str, bucket: &str) -> AppResult<Connection> {
+        let path = Self::path(connection_id, bucket)?;
+        if !path.exists() {
+            return Err(AppError::NotFound(format!(
+                "No index for '{}'; build one first",
+                bucket
+            )));
+        }
+        Self::open(&path)
+    }
+
+    fn meta(db: &Connection, name: &str) -> AppResult<Option<i64>> {
+        db.query_row("SELECT value FROM meta WHERE name = ?1", [name], |row| {
+            row.get(0)
+        })
+        .optional()
+        .map_err(Self::sqlite_error)
+    }
+
+    fn set_meta(db: &Connection, name: &str, value: i64) -> AppResult<()> {
+        db.execute(
+            "INSERT INTO meta (name, value) VALUES (?1, ?2)
+             ON CONFLICT (name) DO UPDATE SET value = excluded.value",
+            params![name, value],
+        )
+        .map_err(Self::sqlite_error)?;
+        Ok(())
+    }
+
+    fn insert_page(db: &mut Connection, page: &[S3Object], generation: i64) -> AppResult<()> {
+        let tx = db.transaction().map_err(Self::sqlite_error)?;
+        {
+            let mut insert = tx
+                .prepare_cached(
+                    "INSERT INTO objects (key, size, etag, last_modified, content_type, generation)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT (key) DO UPDATE SET
+                        size = excluded.size,
+                        etag = excluded.etag,
+                        last_modified = excluded.last_modified,
+                        content_type = excluded.content_type,
+                        generation = excluded.generation",
+                )
+                .map_err(Self::sqlite_error)?;
+            for object in page.iter().filter(|o| !o.is_directory) {
+                // Listings don't carry content types; guess like uploads do
+                let content_type = mime_guess::from_path(&object.key)
+                    .first()
+                    .map(|m| m.to_string());
+                insert
+                    .execute(params![
+                        object.key,
+                        object.size as i64,
+                        object.etag,
+                        object.last_modified,
+                        content_type,
+                        generation
+                    ])
+                    .map_err(Self::sqlite_error)?;
+            }
+        }
+        tx.commit().map_err(Self::sqlite_error)
+    }
+
+    /// Walk the whole bucket into its index. `rebuild` starts from an empty
+    /// file; otherwise rows are updated in place and ones whose objects are
+    /// gone are dropped at the end. A cancelled crawl keeps what it wrote.
+    pub async fn crawl(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        rebuild: bool,
+        job_id: &str,
+        cancelled: &AtomicBool,
+        mut progress: impl FnMut(u64),
+    ) -> AppResult<IndexSummary> {
+        let path = Self::path(&connection.id, bucket)?;
+        let _guard = CrawlGuard::acquire(&path)?;
+        if rebuild {
+            for suffix in ["", "-wal", "-shm"] {
+                let file = PathBuf::from(format!("{}{}", path.display(), suffix));
+                if file.exists() {
+                    std::fs::remove_file(&file)?;
+                }
+            }
+        }
+        info!(
+            "{} index of '{}' at {:?}",
+            if rebuild { "Building" } else { "Refreshing" },
+            bucket,
+            path
+        );
+
+        let mut db = Self::open(&path)?;
+        let generation = Self::meta(&db, "generation")?.unwrap_or(0) + 1;
+        Self::set_meta(&db, "generation", generation)?;
+
+        let mut pages = std::pin::pin!(S3Service::list_object_pages(
+            client, connection, bucket, ""
+        )?);
+        let mut indexed: u64 = 0;
+        let mut finished = true;
+
+        while let Some(page) = pages.try_next().await? {
+            if cancelled.load(Ordering::Relaxed) {
+                finished = false;
+                break;
+            }
+            indexed += page.len() as u64;
+            db = tokio::task::spawn_blocking(move || {
+                Self::insert_page(&mut db, &page, generation)?;
+                Ok::<_, AppError>(db)
+            })
+            .await
+            .map_err(|e| AppError::IoError(std::io::Error::other(e)))??;
+            progress(indexed);
+        }
+
+        let indexed_at = finished.then(|| Utc::now().timestamp());
+        let removed = match indexed_at {
+            Some(indexed_at) => {
+                let removed = db
+                    .execute("DELETE FROM objects WHERE generation != ?1", [generation])
+                    .map_err(Self::sqlite_error)?;
+                Self::set_meta(&db, "indexed_at", indexed_at)?;
+                removed as u64
+            }
+            None => 0,
+        };
+
+        let (object_count, total_size): (i64, i64) = db
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM objects",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(Self::sqlite_error)?;
+        debug!(
+            "Index of '{}' holds {} objects ({} removed)",
+            bucket, object_count, removed
+        );
+
+        Ok(IndexSummary {
+            job_id: job_id.to_string(),
+            object_count: object_count as u64,
+            total_size: total_size as u64,
+            removed,
+            indexed_at,
+        })
+    }
+
+    /// `key >= start AND key < end` bounds selecting everything under
+    /// `prefix`; an empty end means no upper bound.
+    fn prefix_range(prefix: &str) -> (String, String) {
+        if prefix.is_empty() {
+            (String::new(), String::new())
+        } else {
+            (prefix.to_string(), format!("{}{}", prefix, char::MAX))
+        }
+    }
+
+    fn object_from_row(row: &rusqlite::Row) -> rusqlite::Result<S3Object> {
+        Ok(S3Object {
+            key: row.get(0)?,
+            size: row.get::<_, i64>(1)? as u64,
+            etag: row.get(2)?,
+            last_modified: row.get(3)?,
+            content_type: row.get(4)?,
+            is_directory: false,
+            child_count: None,
+        })
+    }
+
+    /// Search the index. Size and date bounds and the sort order run in
+    /// SQLite; key patterns and extensions are checked on the rows it
+    /// returns.
+    pub fn query(
+        connection_id: &str,
+        bucket: &str,
+        query: &IndexQuery,
+    ) -> AppResult<IndexQueryResult> {
+        let db = Self::open_existing(connection_id, bucket)?;
+        let matcher = query
+            .search
+            .as_deref()
+            .filter(|s| !s.is_empty())
+            .map(|s| KeyMatcher::new(s, &query.search_options))
+            .transpose()?;
+
+        let filter = &query.filter;
+        let order = match (filter.sort, filter.descending) {
+            (ListingSort::Name, false) => "key ASC",
+            (ListingSort::Name, true) => "key DESC",
+            (ListingSort::Size, false) => "size ASC, key ASC",
+            (ListingSort::Size, true) => "size DESC, key DESC",
+            (ListingSort::LastModified, false) => "last_modified ASC, key ASC",
+            (ListingSort::LastModified, true) => "last_modified DESC, key DESC",
+        };
+        let mut stmt = db
+            .prepare(&format!(
+                "SELECT key, size, etag, last_modified, content_type FROM objects
+                 WHERE key >= ?1 AND (?2 = '' OR key < ?2)
+                   AND (?3 IS NULL OR size >= ?3) AND (?4 IS NULL OR size <= ?4)
+                   AND (?5 IS NULL OR last_modified >= ?5)
+                   AND (?6 IS NULL OR last_modified < ?6)
+                 ORDER BY {}",
+                order
+            ))
+            .map_err(Self::sqlite_error)?;
+
+        let (start, end) = Self::prefix_range(&query.prefix);
+        let rows = stmt
+            .query_map(
+                params![
+                    start,
+                    end,
+                    filter.min_size.map(|s| s as i64),
+                    filter.max_size.map(|s| s as i64),
+                    filter.modified_after,
+                    filter.modified_before
+                ],
+                Self::object_from_row,
+            )
+            .map_err(Self::sqlite_error)?;
+
+        let mut objects = Vec::new();
+        let mut skipped = 0;
+        let mut has_more = false;
+        for row in rows {
+            let object = row.map_err(Self::sqlite_error)?;
+            if !filter.matches(&object) || !matcher.as_ref().is_none_or(|m| m.matches(&object.key))
+            {
+                continue;
+            }
+            if skipped < query.offset {
+                skipped += 1;
+                continue;
+            }
+            if objects.len() == query.limit {
+                has_more = true;
+                break;
+            }
+            objects.push(object);
+        }
+
+        Ok(IndexQueryResult {
+            objects,
+            has_more,
+            indexed_at: Self::meta(&db, "indexed_at")?,
+        })
+    }
+
+    /// Object counts and sizes under `prefix`, by sub-folder and extension.
+    pub fn stats(connection_id: &str, bucket: &str, prefix: &str) -> AppResult<IndexStats> {
+        let db = Self::open_existing(connection_id, bucket)?;
+        let (start, end) = Self::prefix_range(prefix);
+        let range = "key >= ?1 AND (?2 = '' OR key < ?2)";
+
+        let mut folders: HashMap<String, SizeBreakdown> = HashMap::new();
+        let mut extensions: HashMap<String, SizeBreakdown> = HashMap::new();
+        let mut object_count = 0;
+        let mut total_size = 0;
+        let add = |groups: &mut HashMap<String, SizeBreakdown>, name: &str, size: u64| {
+            let group = groups
+                .entry(name.to_string())
+                .or_insert_with(|| SizeBreakdown {
+                    name: name.to_string(),
+                    object_count: 0,
+                    total_size: 0,
+                });
+            group.object_count += 1;
+            group.total_size += size;
+        };
+
+        let mut stmt = db
+            .prepare(&format!("SELECT key, size FROM objects WHERE {}", range))
+            .map_err(Self::sqlite_error)?;
+        let rows = stmt
+            .query_map(params![start, end], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })
+            .map_err(Self::sqlite_error)?;
+        for row in rows {
+            let (key, size) = row.map_err(Self::sqlite_error)?;
+            let relative = &key[prefix.len()..];
+            let folder = relative
+                .split_once('/')
+                .map(|(folder, _)| format!("{}/", folder))
+                .unwrap_or_default();
+            let name = relative.rsplit('/').next().unwrap_or_default();
+            let extension = name
+                .rsplit_once('.')
+                .map(|(_, ext)| ext.to_lowercase())
+                .unwrap_or_default();
+
+            add(&mut folders, &folder, size);
+            add(&mut extensions, &extension, size);
+            object_count += 1;
+            total_size += size;
+        }
+
+        let largest = db
+            .prepare(&format!(
+                "SELECT key, size, etag, last_modified, content_type FROM objects
+                 WHERE {} ORDER BY size DESC LIMIT ?3",
+                range
+            ))
+            .map_err(Self::sqlite_error)?
+            .query_map(
+                params![start, end, LARGEST_OBJECTS as i64],
+                Self::object_from_row,
+            )
+            .map_err(Self::sqlite_error)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(Self::sqlite_error)?;
+
+        let by_size = |groups: HashMap<String, SizeBreakdown>| {
+            let mut groups: Vec<SizeBreakdown> = groups.into_values().collect();
+            groups.sort_by_key(|g| std::cmp::Reverse(g.total_size));
+            groups
+        };
+        let mut extensions = by_size(extensions);
+        extensions.truncate(MAX_EXTENSIONS);
+
+        Ok(IndexStats {
+            prefix: prefix.to_string(),
+            object_count,
+            total_size,
+            folders: by_size(folders),
+            extensions,
+            largest,
+            indexed_at: Self::meta(&db, "indexed_at")?,
+        })
+    }
+
+    /// Delete every index kept for a connection.
+    pub fn remove_all(connection_id: &str) -> AppResult<()> {
+        let dir = ConfigService::index_dir(connection_id)?;
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
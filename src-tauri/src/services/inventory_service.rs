@@ -0,0 +1,389 @@
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use aws_sdk_s3::types::InventoryFormat;
+use aws_sdk_s3::Client as S3Client;
+use chrono::DateTime;
+use flate2::read::GzDecoder;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use log::{debug, info, warn};
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{InventorySnapshot, RequestKind, S3ConnectionWithSecret, S3Object};
+use crate::services::{RateLimiter, S3Service};
+
+/// The parts of an inventory `manifest.json` we read.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InventoryManifest {
+    file_format: String,
+    file_schema: String,
+    /// Milliseconds since the epoch, as a string.
+    creation_timestamp: String,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Deserialize)]
+struct ManifestFile {
+    key: String,
+}
+
+/// Positions of the columns we use in a report's CSV rows.
+#[derive(Clone, Copy)]
+struct InventoryColumns {
+    key: usize,
+    size: Option<usize>,
+    last_modified: Option<usize>,
+    etag: Option<usize>,
+    is_latest: Option<usize>,
+    is_delete_marker: Option<usize>,
+}
+
+impl InventoryColumns {
+    fn from_schema(schema: &[String]) -> AppResult<Self> {
+        let column = |name: &str| schema.iter().position(|c| c == name);
+        Ok(Self {
+            key: column("Key").ok_or_else(|| {
+                AppError::NotSupported("Inventory report has no Key column".to_string())
+            })?,
+            size: column("Size"),
+            last_modified: column("LastModifiedDate"),
+            etag: column("ETag"),
+            is_latest: column("IsLatest"),
+            is_delete_marker: column("IsDeleteMarker"),
+        })
+    }
+}
+
+pub struct InventoryService;
+
+impl InventoryService {
+    /// The newest CSV inventory report for `bucket`, if the bucket has an
+    /// enabled inventory configuration that has delivered one.
+    pub async fn latest_snapshot(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+    ) -> AppResult<Option<InventorySnapshot>> {
+        let mut newest: Option<InventorySnapshot> = None;
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            S3Service::throttle(connection, RequestKind::List).await;
+            let result = client
+                .list_bucket_inventory_configurations()
+                .bucket(bucket)
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(S3Service::api_error)?;
+
+            for config in result.inventory_configuration_list() {
+                let Some(destination) =
+                    config.destination().and_then(|d| d.s3_bucket_destination())
+                else {
+                    continue;
+                };
+                if !config.is_enabled() || *destination.format() != InventoryFormat::Csv {
+                    debug!("Skipping inventory '{}': disabled or not CSV", config.id());
+                    continue;
+                }
+
+                let destination_bucket = destination
+                    .bucket()
+                    .rsplit(':')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                let base = match destination.prefix().map(|p| p.trim_end_matches('/')) {
+                    Some(prefix) if !prefix.is_empty() => {
+                        format!("{}/{}/{}/", prefix, bucket, config.id())
+                    }
+                    _ => format!("{}/{}/", bucket, config.id()),
+                };
+                let Some(manifest_key) =
+                    Self::newest_manifest(client, connection, &destination_bucket, &base).await?
+                else {
+                    continue;
+                };
+
+                let snapshot = Self::read_manifest(
+                    client,
+                    connection,
+                    config.id(),
+                    &destination_bucket,
+                    &manifest_key,
+                    config.filter().map(|f| f.prefix().to_string()),
+                )
+                .await?;
+                if newest
+                    .as_ref()
+                    .is_none_or(|n| n.created_at < snapshot.created_at)
+                {
+                    newest = Some(snapshot);
+                }
+            }
+
+            if result.is_truncated() == Some(true) {
+                continuation_token = result.next_continuation_token().map(|s| s.to_string());
+            } else {
+                break;
+            }
+        }
+
+        if let Some(snapshot) = &newest {
+            info!(
+                "Using inventory '{}' for '{}' from {} ({} files)",
+                snapshot.configuration_id,
+                bucket,
+                snapshot.created_at,
+                snapshot.files.len()
+            );
+        }
+        Ok(newest)
+    }
+
+    /// Key of the newest `manifest.json` under a configuration's
+    /// destination. Report folders are named by UTC timestamp
+    /// (`2024-01-31T01-00Z/`), so the largest name is the newest.
+    async fn newest_manifest(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        base: &str,
+    ) -> AppResult<Option<String>> {
+        let mut newest: Option<String> = None;
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            S3Service::throttle(connection, RequestKind::List).await;
+            let result = client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(base)
+                .delimiter("/")
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(S3Service::api_error)?;
+
+            let folders = result
+                .common_prefixes()
+                .iter()
+                .filter_map(|p| p.prefix())
+                // Skips the `hive/` symlink tree delivered alongside
+                .filter(|p| p[base.len()..].starts_with(|c: char| c.is_ascii_digit()));
+            for folder in folders {
+                if newest.as_deref().is_none_or(|n| folder > n) {
+                    newest = Some(folder.to_string());
+                }
+            }
+
+            if result.is_truncated() == Some(true) {
+                continuation_token = result.next_continuation_token().map(|s| s.to_string());
+            } else {
+                break;
+            }
+        }
+
+        Ok(newest.map(|folder| format!("{}manifest.json", folder)))
+    }
+
+    async fn read_manifest(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        configuration_id: &str,
+        bucket: &str,
+        key: &str,
+        filter_prefix: Option<String>,
+    ) -> AppResult<InventorySnapshot> {
+        S3Service::throttle(connection, RequestKind::Get).await;
+        let data = Self::download(client, bucket, key).await?;
+        let manifest: InventoryManifest = serde_json::from_slice(&data)?;
+        if !manifest.file_format.eq_ignore_ascii_case("CSV") {
+            return Err(AppError::NotSupported(format!(
+                "Inventory report '{}' is {}, only CSV is read",
+                key, manifest.file_format
+            )));
+        }
+
+        Ok(InventorySnapshot {
+            configuration_id: configuration_id.to_string(),
+            destination_bucket: bucket.to_string(),
+            manifest_key: key.to_string(),
+            created_at: manifest.creation_timestamp.parse::<i64>().unwrap_or(0) / 1000,
+            filter_prefix: filter_prefix.filter(|p| !p.is_empty()),
+            schema: manifest
+                .file_schema
+                .split(',')
+                .map(|c| c.trim().to_string())
+                .collect(),
+            files: manifest.files.into_iter().map(|f| f.key).collect(),
+        })
+    }
+
+    async fn download(client: &S3Client, bucket: &str, key: &str) -> AppResult<Vec<u8>> {
+        let result = client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(S3Service::api_error)?;
+        let data = result
+            .body
+            .collect()
+            .await
+            .map_err(|e| AppError::S3Error(e.to_string()))?
+            .to_vec();
+        RateLimiter::acquire_bandwidth(data.len() as u64).await;
+        Ok(data)
+    }
+
+    /// Objects under `prefix` as recorded in `snapshot`, one page per report
+    /// data file, shaped like `S3Service::list_object_pages`.
+    pub fn object_pages<'a>(
+        client: &'a S3Client,
+        connection: &'a S3ConnectionWithSecret,
+        snapshot: InventorySnapshot,
+        prefix: &str,
+    ) -> AppResult<BoxStream<'a, AppResult<Vec<S3Object>>>> {
+        let scoped_prefix = S3Service::scoped_key(connection, prefix)?;
+        if let Some(filter) = &snapshot.filter_prefix {
+            if !scoped_prefix.starts_with(filter.as_str()) {
+                return Err(AppError::NotSupported(format!(
+                    "Inventory '{}' only covers '{}'",
+                    snapshot.configuration_id, filter
+                )));
+            }
+        }
+        let columns = InventoryColumns::from_schema(&snapshot.schema)?;
+        let root = connection.root_prefix.clone().unwrap_or_default();
+        let snapshot = Arc::new(snapshot);
+
+        Ok(stream::iter(snapshot.files.clone())
+            .then(move |file| {
+                let snapshot = snapshot.clone();
+                let scoped_prefix = scoped_prefix.clone();
+                let root = root.clone();
+                async move {
+                    S3Service::throttle(connection, RequestKind::Get).await;
+                    let data = Self::download(client, &snapshot.destination_bucket, &file).await?;
+                    // Decompressing millions of rows would stall the runtime
+                    tokio::task::spawn_blocking(move || {
+                        Self::parse_data_file(&data, columns, &scoped_prefix, &root)
+                    })
+                    .await
+                    .map_err(|e| AppError::IoError(std::io::Error::other(e)))?
+                }
+            })
+            .boxed())
+    }
+
+    /// Rows of one gzipped CSV data file whose key falls under `prefix`,
+    /// skipping noncurrent versions and delete markers.
+    fn parse_data_file(
+        data: &[u8],
+        columns: InventoryColumns,
+        prefix: &str,
+        root: &str,
+    ) -> AppResult<Vec<S3Object>> {
+        let mut objects = Vec::new();
+
+        for line in BufReader::new(GzDecoder::new(data)).lines() {
+            let fields = Self::csv_fields(&line?);
+            let field = |index: Option<usize>| index.and_then(|i| fields.get(i));
+            let Some(key) = field(Some(columns.key)).map(|k| Self::decode_key(k)) else {
+                continue;
+            };
+            if !key.starts_with(prefix)
+                || field(columns.is_latest).is_some_and(|v| v == "false")
+                || field(columns.is_delete_marker).is_some_and(|v| v == "true")
+            {
+                continue;
+            }
+
+            let key = key.strip_prefix(root).unwrap_or(&key).to_string();
+            objects.push(S3Object {
+                is_directory: key.ends_with('/'),
+                key,
+                size: field(columns.size)
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0),
+                last_modified: field(columns.last_modified)
+                    .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+                    .map(|d| d.timestamp())
+                    .unwrap_or(0),
+                // Listings quote ETags; match them so comparisons line up
+                etag: field(columns.etag)
+                    .filter(|e| !e.is_empty())
+                    .map(|e| format!("\"{}\"", e)),
+                content_type: None,
+                child_count: None,
+            });
+        }
+
+        Ok(objects)
+    }
+
+    /// Split a CSV row; inventory quotes every field.
+    fn csv_fields(line: &str) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(std::mem::take(&mut field)),
+                c => field.push(c),
+            }
+        }
+        fields.push(field);
+        fields
+    }
+
+    /// CSV reports URL-encode keys, form style with `+` for spaces.
+    fn decode_key(key: &str) -> String {
+        percent_decode_str(&key.replace('+', " "))
+            .decode_utf8_lossy()
+            .into_owned()
+    }
+
+    /// Listing pages for `prefix`, from the newest inventory report when
+    /// `use_inventory` is set and one covers the prefix, otherwise from live
+    /// LIST calls. Inventory is a day or more behind, so only planning that
+    /// tolerates stale data should ask for it.
+    pub async fn planning_pages<'a>(
+        client: &'a S3Client,
+        connection: &'a S3ConnectionWithSecret,
+        bucket: &'a str,
+        prefix: &str,
+        use_inventory: bool,
+    ) -> AppResult<BoxStream<'a, AppResult<Vec<S3Object>>>> {
+        if use_inventory {
+            let pages = match Self::latest_snapshot(client, connection, bucket).await {
+                Ok(Some(snapshot)) => Self::object_pages(client, connection, snapshot, prefix),
+                Ok(None) => Err(AppError::NotFound(format!(
+                    "No CSV inventory report for '{}'",
+                    bucket
+                ))),
+                Err(e) => Err(e),
+            };
+            match pages {
+                Ok(pages) => return Ok(pages),
+                Err(e) => warn!("Listing '{}/{}' live instead: {}", bucket, prefix, e),
+            }
+        }
+
+        Ok(S3Service::list_object_pages(client, connection, bucket, prefix)?.boxed())
+    }
+}
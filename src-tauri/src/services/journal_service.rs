@@ -0,0 +1,227 @@
+use chrono::Utc;
+use log::{debug, warn};
+use opendal::Operator;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    BatchResult, JournalAction, JournalEntry, JournalFinish, JournalRecord, JournalStart,
+    S3ConnectionWithSecret,
+};
+use crate::services::{ConfigService, S3Service};
+
+/// An open journal for one job run. Entries are flushed as they are
+/// recorded, so a crashed run still leaves a usable trail. A journal dropped
+/// without `finish` is closed with an error, however the job exits.
+pub struct JobJournal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    succeeded: u64,
+    failed: u64,
+    closed: bool,
+}
+
+impl JobJournal {
+    pub fn create(start: JournalStart) -> AppResult<Self> {
+        let file_name = format!("{}-{}.jsonl", start.started_at, start.job_id);
+        let path = ConfigService::journal_dir()?.join(file_name);
+        debug!("Writing job journal to {:?}", path);
+
+        let mut journal = Self {
+            writer: BufWriter::new(File::create(&path)?),
+            path,
+            succeeded: 0,
+            failed: 0,
+            closed: false,
+        };
+        journal.write(&JournalRecord::Start(start))?;
+        Ok(journal)
+    }
+
+    fn write(&mut self, record: &JournalRecord) -> AppResult<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn record(
+        &mut self,
+        action: JournalAction,
+        key: &str,
+        local_path: Option<&Path>,
+        size: u64,
+        checksum: Option<String>,
+        error: Option<&AppError>,
+    ) {
+        if error.is_some() {
+            self.failed += 1;
+        } else {
+            self.succeeded += 1;
+        }
+
+        let entry = JournalEntry {
+            key: key.to_string(),
+            local_path: local_path.map(|p| p.to_string_lossy().to_string()),
+            action,
+            size,
+            checksum,
+            error: error.map(|e| e.to_string()),
+            timestamp: Utc::now().timestamp(),
+        };
+
+        // A journal problem shouldn't abort the transfer it describes
+        if let Err(e) = self.write(&JournalRecord::Entry(entry)) {
+            warn!("Failed to write journal entry for '{}': {}", key, e);
+        }
+    }
+
+    /// Note a file the job deliberately left alone. Skips count as neither
+    /// succeeded nor failed.
+    pub fn skip(&mut self, key: &str, local_path: Option<&Path>) {
+        let entry = JournalEntry {
+            key: key.to_string(),
+            local_path: local_path.map(|p| p.to_string_lossy().to_string()),
+            action: JournalAction::Skip,
+            size: 0,
+            checksum: None,
+            error: None,
+            timestamp: Utc::now().timestamp(),
+        };
+
+        if let Err(e) = self.write(&JournalRecord::Entry(entry)) {
+            warn!("Failed to write journal entry for '{}': {}", key, e);
+        }
+    }
+
+    /// Close the journal and return its path.
+    pub fn finish(mut self) -> AppResult<PathBuf> {
+        self.close(None)?;
+        Ok(std::mem::take(&mut self.path))
+    }
+
+    /// Close the journal for a job that stopped on `error`.
+    pub fn fail(mut self, error: &AppError) {
+        if let Err(e) = self.close(Some(error.to_string())) {
+            warn!("Failed to close journal {:?}: {}", self.path, e);
+        }
+    }
+
+    fn close(&mut self, error: Option<String>) -> AppResult<()> {
+        self.closed = true;
+        let finish = JournalFinish {
+            finished_at: Utc::now().timestamp(),
+            succeeded: self.succeeded,
+            failed: self.failed,
+            error,
+        };
+        self.write(&JournalRecord::Finish(finish))
+    }
+}
+
+impl Drop for JobJournal {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        if let Err(e) = self.close(Some("the job stopped before finishing".to_string())) {
+            warn!("Failed to close journal {:?}: {}", self.path, e);
+        }
+    }
+}
+
+pub struct JournalService;
+
+impl JournalService {
+    pub fn read(path: &Path) -> AppResult<Vec<JournalRecord>> {
+        let reader = BufReader::new(File::open(path)?);
+
+        let mut records = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str(&line)?);
+        }
+        Ok(records)
+    }
+
+    /// Store a finished journal alongside the data it describes.
+    pub async fn upload(operator: &Operator, path: &Path, key: &str) -> AppResult<()> {
+        let data = fs::read(path)?;
+        S3Service::upload_object_with(
+            operator,
+            key,
+            data,
+            Some("application/x-ndjson"),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Re-check every successful upload in a journal against the bucket:
+    /// the object must exist with the recorded size and, where the ETag is a
+    /// plain MD5, the recorded checksum.
+    pub async fn verify(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        operator: &Operator,
+        path: &Path,
+    ) -> AppResult<BatchResult> {
+        let mut result = BatchResult::default();
+
+        for record in Self::read(path)? {
+            let JournalRecord::Entry(entry) = record else {
+                continue;
+            };
+            if entry.action != JournalAction::Upload || entry.error.is_some() {
+                continue;
+            }
+
+            let object = match S3Service::get_object_details(operator, &entry.key).await {
+                Ok(object) => object,
+                Err(e) => {
+                    result.fail(entry.key, &e);
+                    continue;
+                }
+            };
+
+            if object.size != entry.size {
+                let e = AppError::IntegrityError(format!(
+                    "'{}' is {} bytes, journal recorded {}",
+                    entry.key, object.size, entry.size
+                ));
+                result.fail(entry.key, &e);
+                continue;
+            }
+
+            let Some(checksum) = entry.checksum.as_deref() else {
+                result.succeed(entry.key);
+                continue;
+            };
+            let etag = match S3Service::content_md5(connection, bucket, &object).await {
+                Ok(etag) => etag,
+                Err(e) => {
+                    result.fail(entry.key, &e);
+                    continue;
+                }
+            };
+            match etag {
+                Some(etag) if !etag.eq_ignore_ascii_case(checksum) => {
+                    let e = AppError::IntegrityError(format!(
+                        "'{}' ETag {} doesn't match journal checksum {}",
+                        entry.key, etag, checksum
+                    ));
+                    result.fail(entry.key, &e);
+                }
+                _ => result.succeed(entry.key),
+            }
+        }
+
+        Ok(result)
+    }
+}
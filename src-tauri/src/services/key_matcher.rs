@@ -0,0 +1,68 @@
+use regex_lite::{Regex, RegexBuilder};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{KeyMatchMode, SearchOptions};
+use crate::services::LocalService;
+
+/// A `search_objects` query compiled once for a whole bucket walk.
+pub enum KeyMatcher {
+    Substring {
+        needle: String,
+        case_sensitive: bool,
+    },
+    Glob {
+        pattern: String,
+        case_sensitive: bool,
+    },
+    Regex(Regex),
+}
+
+impl KeyMatcher {
+    pub fn new(query: &str, options: &SearchOptions) -> AppResult<Self> {
+        if query.is_empty() {
+            return Err(AppError::ConfigError("Search query is empty".to_string()));
+        }
+
+        let fold = |s: &str| {
+            if options.case_sensitive {
+                s.to_string()
+            } else {
+                s.to_lowercase()
+            }
+        };
+        Ok(match options.mode {
+            KeyMatchMode::Substring => Self::Substring {
+                needle: fold(query),
+                case_sensitive: options.case_sensitive,
+            },
+            KeyMatchMode::Glob => Self::Glob {
+                pattern: fold(query),
+                case_sensitive: options.case_sensitive,
+            },
+            KeyMatchMode::Regex => Self::Regex(
+                RegexBuilder::new(query)
+                    .case_insensitive(!options.case_sensitive)
+                    .build()
+                    .map_err(|e| {
+                        AppError::ConfigError(format!("Invalid search pattern '{}': {}", query, e))
+                    })?,
+            ),
+        })
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        match self {
+            Self::Substring {
+                needle,
+                case_sensitive: true,
+            } => key.contains(needle.as_str()),
+            Self::Substring { needle, .. } => key.to_lowercase().contains(needle.as_str()),
+            Self::Glob {
+                pattern,
+                case_sensitive: true,
+            } => LocalService::glob_matches(pattern, key),
+            Self::Glob { pattern, .. } => LocalService::glob_matches(pattern, &key.to_lowercase()),
+            Self::Regex(regex) => regex.is_match(key),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::error::{AppError, AppResult};
+use crate::models::KeyTemplate;
+
+/// Placeholders a key template may use.
+const PLACEHOLDERS: &[&str] = &[
+    "date", "yyyy", "mm", "dd", "uuid", "filename", "stem", "ext", "path",
+];
+
+pub struct KeyTemplates;
+
+impl KeyTemplates {
+    fn normalize_prefix(prefix: &str) -> String {
+        let prefix = prefix.trim_matches('/');
+        if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", prefix)
+        }
+    }
+
+    /// Reject templates with unknown placeholders, unbalanced braces or
+    /// segments that would climb out of the prefix.
+    pub fn validate(template: &str) -> AppResult<()> {
+        let invalid = |reason: &str| {
+            AppError::ConfigError(format!("Invalid key template '{}': {}", template, reason))
+        };
+
+        if template.trim_matches('/').is_empty() {
+            return Err(invalid("template is empty"));
+        }
+        if template.split('/').any(|segment| segment == "..") {
+            return Err(invalid("'..' segments are not allowed"));
+        }
+
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| invalid("unclosed '{'"))?;
+            let name = &rest[open + 1..open + close];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(invalid(&format!("unknown placeholder '{{{}}}'", name)));
+            }
+            rest = &rest[open + close + 1..];
+        }
+        if rest.contains('}') {
+            return Err(invalid("unmatched '}'"));
+        }
+
+        Ok(())
+    }
+
+    /// The template configured for uploads directly into `prefix`.
+    pub fn find<'a>(
+        templates: &'a [KeyTemplate],
+        bucket: &str,
+        prefix: &str,
+    ) -> Option<&'a KeyTemplate> {
+        let prefix = Self::normalize_prefix(prefix);
+        templates
+            .iter()
+            .find(|t| t.bucket == bucket && Self::normalize_prefix(&t.prefix) == prefix)
+    }
+
+    /// Expand `template` for a file at `relative_path` (just the file name
+    /// for single uploads). `{filename}` is the last segment and `{path}`
+    /// the whole relative path.
+    pub fn render(template: &str, relative_path: &str, now: DateTime<Utc>) -> String {
+        let filename = relative_path.rsplit('/').next().unwrap_or(relative_path);
+        let (stem, ext) = match filename.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, ext),
+            _ => (filename, ""),
+        };
+
+        let rendered = template
+            .replace("{date}", &now.format("%Y-%m-%d").to_string())
+            .replace("{yyyy}", &now.format("%Y").to_string())
+            .replace("{mm}", &now.format("%m").to_string())
+            .replace("{dd}", &now.format("%d").to_string())
+            .replace("{uuid}", &Uuid::new_v4().to_string())
+            .replace("{filename}", filename)
+            .replace("{stem}", stem)
+            .replace("{ext}", ext)
+            .replace("{path}", relative_path);
+
+        // Empty placeholders shouldn't leave empty path segments behind
+        rendered
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Key for an upload of `relative_path` into `prefix` under the
+    /// matching template, or `None` when the prefix has no template.
+    pub fn apply(
+        templates: &[KeyTemplate],
+        bucket: &str,
+        prefix: &str,
+        relative_path: &str,
+    ) -> Option<String> {
+        let template = Self::find(templates, bucket, prefix)?;
+        Some(format!(
+            "{}{}",
+            Self::normalize_prefix(prefix),
+            Self::render(&template.template, relative_path, Utc::now())
+        ))
+    }
+}
@@ -0,0 +1,524 @@
+use log::{debug, trace};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    pattern: Vec<char>,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+/// Gitignore-style exclude rules. Later rules take precedence, so a `!`
+/// pattern can re-include something excluded by an earlier one.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let rules = patterns
+            .into_iter()
+            .filter_map(|p| Self::parse_rule(p.as_ref()))
+            .collect();
+
+        Self { rules }
+    }
+
+    fn parse_rule(line: &str) -> Option<IgnoreRule> {
+        let mut pattern = line.trim();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            return None;
+        }
+
+        let negated = pattern.starts_with('!');
+        if negated {
+            pattern = &pattern[1..];
+        }
+
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+
+        // A slash anywhere but the end anchors the pattern to the walk root
+        let anchored = pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/');
+
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(IgnoreRule {
+            pattern: pattern.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Check a `/`-separated path relative to the walk root.
+    pub fn is_ignored(&self, relative_path: &str, is_dir: bool) -> bool {
+        let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+        let path: Vec<char> = relative_path.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let target = if rule.anchored { &path } else { &name };
+            if glob_match(&rule.pattern, target) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+
+    /// Check a file path the way a walk would reach it: an ignored ancestor
+    /// directory hides everything beneath it, even files a later `!` rule
+    /// would re-include.
+    pub fn is_path_ignored(&self, relative_path: &str) -> bool {
+        let ancestor_ignored = relative_path
+            .match_indices('/')
+            .any(|(end, _)| self.is_ignored(&relative_path[..end], true));
+        ancestor_ignored || self.is_ignored(relative_path, false)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GlobToken {
+    Char(char),
+    /// `?`: one character other than `/`.
+    Any,
+    /// `*`: any run within one path segment.
+    Star,
+    /// `**`, with a following `/` folded in: nothing, or a run ending at a
+    /// segment boundary. At the end of a pattern it matches everything.
+    DoubleStar,
+}
+
+fn glob_tokens(pattern: &[char]) -> Vec<GlobToken> {
+    let mut tokens = Vec::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i] {
+            '*' if pattern.get(i + 1) == Some(&'*') => {
+                tokens.push(GlobToken::DoubleStar);
+                i += 2;
+                if pattern.get(i) == Some(&'/') {
+                    i += 1;
+                }
+            }
+            '*' => {
+                tokens.push(GlobToken::Star);
+                i += 1;
+            }
+            '?' => {
+                tokens.push(GlobToken::Any);
+                i += 1;
+            }
+            c => {
+                tokens.push(GlobToken::Char(c));
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/// Match `text` against a glob one pattern token at a time, tracking which
+/// prefixes of `text` the pattern so far can match. Quadratic at worst, so
+/// patterns full of stars can't blow up on long keys.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let tokens = glob_tokens(pattern);
+    // `matched[j]`: the tokens so far match `text[..j]`
+    let mut matched = vec![false; text.len() + 1];
+    matched[0] = true;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let mut next = vec![false; text.len() + 1];
+        match token {
+            GlobToken::Char(c) => {
+                for j in 0..text.len() {
+                    next[j + 1] = matched[j] && text[j] == *c;
+                }
+            }
+            GlobToken::Any => {
+                for j in 0..text.len() {
+                    next[j + 1] = matched[j] && text[j] != '/';
+                }
+            }
+            GlobToken::Star => {
+                next[0] = matched[0];
+                for j in 1..=text.len() {
+                    next[j] = matched[j] || (next[j - 1] && text[j - 1] != '/');
+                }
+            }
+            GlobToken::DoubleStar => {
+                let trailing = index == tokens.len() - 1;
+                let mut reachable = false;
+                for j in 0..=text.len() {
+                    next[j] = matched[j] || (reachable && (trailing || text[j - 1] == '/'));
+                    reachable |= matched[j];
+                }
+            }
+        }
+        matched = next;
+        if !matched.contains(&true) {
+            return false;
+        }
+    }
+    matched[text.len()]
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalFile {
+    pub path: PathBuf,
+    /// Path relative to the walk root using `/` separators.
+    pub relative_path: String,
+}
+
+#[derive(Debug, Default)]
+pub struct LocalWalk {
+    pub files: Vec<LocalFile>,
+    /// Relative paths matched by the ignore rules; directories end in `/`
+    /// and stand for everything under them.
+    pub ignored: Vec<String>,
+}
+
+pub struct LocalService;
+
+impl LocalService {
+    /// Recursively collect files under `root`, skipping anything matched by
+    /// `rules`. Ignored directories are not descended into.
+    pub fn collect_files(root: &Path, rules: &IgnoreRules) -> AppResult<LocalWalk> {
+        debug!("Walking local folder: {:?}", root);
+
+        let mut walk = LocalWalk::default();
+        let mut pending = vec![(root.to_path_buf(), String::new())];
+
+        while let Some((dir, relative_dir)) = pending.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                let name = entry.file_name().to_string_lossy().to_string();
+
+                let relative_path = if relative_dir.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", relative_dir, name)
+                };
+
+                if rules.is_ignored(&relative_path, file_type.is_dir()) {
+                    trace!("Ignoring '{}'", relative_path);
+                    if file_type.is_dir() {
+                        walk.ignored.push(format!("{}/", relative_path));
+                    } else {
+                        walk.ignored.push(relative_path);
+                    }
+                    continue;
+                }
+
+                if file_type.is_dir() {
+                    pending.push((entry.path(), relative_path));
+                } else if file_type.is_file() {
+                    walk.files.push(LocalFile {
+                        path: entry.path(),
+                        relative_path,
+                    });
+                }
+            }
+        }
+
+        walk.files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        debug!(
+            "Found {} files under {:?} ({} ignored)",
+            walk.files.len(),
+            root,
+            walk.ignored.len()
+        );
+        Ok(walk)
+    }
+
+    /// Local path for an object key relative to `root`. Keys with `.` or
+    /// `..` segments, or characters the platform treats as path syntax, are
+    /// refused so a download can't escape `root`.
+    pub fn local_path_for_key(root: &Path, relative_key: &str) -> AppResult<PathBuf> {
+        let mut path = root.to_path_buf();
+        for segment in relative_key.split('/').filter(|s| !s.is_empty()) {
+            let unsafe_segment = segment == "."
+                || segment == ".."
+                || (cfg!(windows) && segment.contains(['\\', ':']));
+            if unsafe_segment {
+                return Err(AppError::DestinationNotWritable(format!(
+                    "key '{}' doesn't map to a path under '{}'",
+                    relative_key,
+                    root.display()
+                )));
+            }
+            path.push(segment);
+        }
+        Ok(path)
+    }
+
+    /// File name for a temporary copy of `key`, taken from its last segment.
+    /// Characters that are path or shell syntax on some platform become `_`
+    /// and a name of only dots falls back to `object`, so joining it onto a
+    /// scratch directory can't land anywhere else.
+    pub fn temp_file_name(key: &str) -> String {
+        let name = key.rsplit('/').find(|s| !s.is_empty()).unwrap_or_default();
+        let name: String = name
+            .chars()
+            .map(|c| match c {
+                '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+        // Windows drops trailing dots and spaces, which turns `..` into `.`
+        let name = name.trim_end_matches(['.', ' ']);
+        if name.is_empty() {
+            "object".to_string()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Make sure the parent directory of a download destination exists and
+    /// is usable, creating it when `create_dirs` is set.
+    pub async fn prepare_destination(destination: &Path, create_dirs: bool) -> AppResult<()> {
+        let Some(parent) = destination.parent().filter(|p| !p.as_os_str().is_empty()) else {
+            return Ok(());
+        };
+
+        match tokio::fs::metadata(parent).await {
+            Ok(meta) if !meta.is_dir() => Err(AppError::DestinationNotWritable(format!(
+                "'{}' is not a directory",
+                parent.display()
+            ))),
+            Ok(meta) if meta.permissions().readonly() => Err(AppError::DestinationNotWritable(
+                format!("'{}' is read-only", parent.display()),
+            )),
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                if !create_dirs {
+                    return Err(AppError::DestinationNotWritable(format!(
+                        "directory '{}' does not exist",
+                        parent.display()
+                    )));
+                }
+
+                debug!("Creating destination directory: {:?}", parent);
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| Self::destination_error(parent, e))
+            }
+            Err(e) => Err(Self::destination_error(parent, e)),
+        }
+    }
+
+    /// Turn permission failures on a destination path into a readable error.
+    pub fn destination_error(path: &Path, e: io::Error) -> AppError {
+        match e.kind() {
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+                AppError::DestinationNotWritable(format!("{}: {}", path.display(), e))
+            }
+            _ => AppError::IoError(e),
+        }
+    }
+
+    /// Match a gitignore-style glob against a `/`-separated key. Patterns
+    /// without a `/` only look at the last path segment.
+    pub fn glob_matches(pattern: &str, key: &str) -> bool {
+        let target = if pattern.contains('/') {
+            key
+        } else {
+            key.rsplit('/').next().unwrap_or(key)
+        };
+
+        let pattern: Vec<char> = pattern.trim_start_matches('/').chars().collect();
+        let target: Vec<char> = target.chars().collect();
+        glob_match(&pattern, &target)
+    }
+
+    /// Managed scratch directory for Baul's temporary files.
+    pub fn temp_dir(subdir: &str) -> PathBuf {
+        std::env::temp_dir().join("baul").join(subdir)
+    }
+
+    pub fn modified_millis(meta: &fs::Metadata) -> i64 {
+        meta.modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    }
+
+    /// Open a local file with the operating system's default application.
+    pub fn open_with_default_app(path: &Path) -> AppResult<()> {
+        debug!("Opening {:?} with the default application", path);
+
+        // Goes through ShellExecuteW, `open` or `xdg-open` with the path as a
+        // single argument, never through a command interpreter
+        tauri_plugin_opener::open_path(path, None::<&str>)
+            .map_err(|e| AppError::IoError(io::Error::other(e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        glob_match(&pattern, &text)
+    }
+
+    #[test]
+    fn globs_match_within_and_across_segments() {
+        assert!(matches("*.log", "debug.log"));
+        assert!(!matches("*.log", "logs/debug.log"));
+        assert!(matches("file?.txt", "file1.txt"));
+        assert!(!matches("file?.txt", "file/.txt"));
+        assert!(matches("**/cache", "cache"));
+        assert!(matches("**/cache", "a/b/cache"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "a/xb"));
+        assert!(matches("build/**", "build/a/b.js"));
+        assert!(!matches("build/**", "builder/a.js"));
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+    }
+
+    #[test]
+    fn many_stars_on_a_long_key_finish_quickly() {
+        let pattern = "*a".repeat(30) + "b";
+        let key = "a".repeat(1024);
+        assert!(!matches(&pattern, &key));
+        let pattern = "**/".repeat(30) + "b";
+        let key = "a/".repeat(512);
+        assert!(!matches(&pattern, &key));
+    }
+
+    #[test]
+    fn ignore_rules_skip_comments_and_blank_lines() {
+        let rules = IgnoreRules::new(["# *.log", "", "   ", "*.tmp"]);
+        assert!(!rules.is_ignored("debug.log", false));
+        assert!(!rules.is_ignored("# *.log", false));
+        assert!(rules.is_ignored("scratch.tmp", false));
+    }
+
+    #[test]
+    fn negated_ignore_rules_re_include_later_matches() {
+        let rules = IgnoreRules::new(["*.log", "!keep.log"]);
+        assert!(rules.is_ignored("debug.log", false));
+        assert!(!rules.is_ignored("keep.log", false));
+        assert!(!rules.is_ignored("logs/keep.log", false));
+
+        // The last matching rule wins
+        let rules = IgnoreRules::new(["!keep.log", "*.log"]);
+        assert!(rules.is_ignored("keep.log", false));
+    }
+
+    #[test]
+    fn dir_only_ignore_rules_skip_files() {
+        let rules = IgnoreRules::new(["build/"]);
+        assert!(rules.is_ignored("build", true));
+        assert!(rules.is_ignored("src/build", true));
+        assert!(!rules.is_ignored("build", false));
+    }
+
+    #[test]
+    fn anchored_ignore_rules_match_from_the_root() {
+        let rules = IgnoreRules::new(["/dist", "docs/*.md"]);
+        assert!(rules.is_ignored("dist", true));
+        assert!(!rules.is_ignored("web/dist", true));
+        assert!(rules.is_ignored("docs/intro.md", false));
+        assert!(!rules.is_ignored("site/docs/intro.md", false));
+
+        let rules = IgnoreRules::new(["dist"]);
+        assert!(rules.is_ignored("dist", true));
+        assert!(rules.is_ignored("web/dist", true));
+    }
+
+    #[test]
+    fn double_star_ignore_rules_span_directories() {
+        let rules = IgnoreRules::new(["**/cache", "assets/**/*.map"]);
+        assert!(rules.is_ignored("cache", true));
+        assert!(rules.is_ignored("a/b/cache", true));
+        assert!(rules.is_ignored("assets/app.map", false));
+        assert!(rules.is_ignored("assets/js/vendor/app.map", false));
+        assert!(!rules.is_ignored("public/app.map", false));
+    }
+
+    #[test]
+    fn path_checks_apply_directory_rules_to_ancestors() {
+        let rules = IgnoreRules::new(["node_modules/", ".git/", "build"]);
+        assert!(rules.is_path_ignored("node_modules/react/index.js"));
+        assert!(rules.is_path_ignored("web/node_modules/react/index.js"));
+        assert!(rules.is_path_ignored(".git/HEAD"));
+        assert!(rules.is_path_ignored("build/app.js"));
+        assert!(rules.is_path_ignored("build"));
+        assert!(!rules.is_path_ignored("node_modules"));
+        assert!(!rules.is_path_ignored("src/builder.js"));
+    }
+
+    #[test]
+    fn path_checks_respect_anchors_and_re_includes() {
+        let rules = IgnoreRules::new(["/dist/", "*.log", "!keep.log"]);
+        assert!(rules.is_path_ignored("dist/app.js"));
+        assert!(!rules.is_path_ignored("web/dist/app.js"));
+        assert!(rules.is_path_ignored("logs/debug.log"));
+        assert!(!rules.is_path_ignored("logs/keep.log"));
+
+        // A re-include can't reach into an ignored directory
+        let rules = IgnoreRules::new(["vendor/", "!vendor/keep.js"]);
+        assert!(rules.is_path_ignored("vendor/keep.js"));
+    }
+
+    #[test]
+    fn temp_file_names_stay_inside_the_scratch_directory() {
+        assert_eq!(
+            LocalService::temp_file_name("docs/report.pdf"),
+            "report.pdf"
+        );
+        assert_eq!(LocalService::temp_file_name("docs/"), "docs");
+        assert_eq!(LocalService::temp_file_name("a/.."), "object");
+        assert_eq!(LocalService::temp_file_name(""), "object");
+        assert_eq!(
+            LocalService::temp_file_name("..\\..\\boot.ini"),
+            ".._.._boot.ini"
+        );
+        assert_eq!(LocalService::temp_file_name("C:evil.txt"), "C_evil.txt");
+        assert_eq!(
+            LocalService::temp_file_name("x\" & calc & \".txt"),
+            "x_ & calc & _.txt"
+        );
+    }
+
+    #[test]
+    fn key_globs_without_a_slash_match_the_last_segment() {
+        assert!(LocalService::glob_matches("*.png", "images/logo.png"));
+        assert!(LocalService::glob_matches(
+            "/images/*.png",
+            "images/logo.png"
+        ));
+        assert!(!LocalService::glob_matches(
+            "images/*.png",
+            "assets/images/logo.png"
+        ));
+    }
+}
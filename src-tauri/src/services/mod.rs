@@ -1,7 +1,55 @@
+pub mod audit_service;
+pub mod bookmark_service;
+pub mod cdn_service;
+pub mod clock_skew;
+pub mod collision;
+pub mod compat;
+pub mod compat_check;
+pub mod config_bundle;
 pub mod config_service;
 pub mod credential_service;
+pub mod download_history;
+pub mod hook_service;
+pub mod index_service;
+pub mod inventory_service;
+pub mod journal_service;
+pub mod key_matcher;
+pub mod key_template;
+pub mod local_service;
+pub mod preview_service;
+pub mod rate_limiter;
 pub mod s3_service;
+pub mod sigv2;
+pub mod site_service;
+pub mod sts_service;
+pub mod sync_service;
+pub mod transfer_tuner;
+pub mod webhook_service;
 
+pub use audit_service::*;
+pub use bookmark_service::*;
+pub use cdn_service::*;
+pub use clock_skew::*;
+pub use collision::*;
+pub use compat::*;
+pub use compat_check::*;
+pub use config_bundle::*;
 pub use config_service::*;
 pub use credential_service::*;
+pub use download_history::*;
+pub use hook_service::*;
+pub use index_service::*;
+pub use inventory_service::*;
+pub use journal_service::*;
+pub use key_matcher::*;
+pub use key_template::*;
+pub use local_service::*;
+pub use preview_service::*;
+pub use rate_limiter::*;
 pub use s3_service::*;
+pub use sigv2::*;
+pub use site_service::*;
+pub use sts_service::*;
+pub use sync_service::*;
+pub use transfer_tuner::*;
+pub use webhook_service::*;
@@ -0,0 +1,494 @@
+use log::debug;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::process::Command;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    ConfigFormat, ConfigParseError, ConfigPreview, PreviewLimits, PreviewMode, PreviewPlan,
+    S3Object, SqliteTable,
+};
+
+/// Bytes read from the start of an object to recognize its type.
+pub const SNIFF_BYTES: u64 = 512;
+
+/// Leading signatures of common binary formats.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"BZh", "application/x-bzip2"),
+    (b"\xfd7zXZ\x00", "application/x-xz"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (b"PAR1", "application/vnd.apache.parquet"),
+    (b"\x7fELF", "application/x-elf"),
+    (b"\x00asm", "application/wasm"),
+    (b"OggS", "audio/ogg"),
+    (b"ID3", "audio/mpeg"),
+    (b"fLaC", "audio/flac"),
+];
+
+/// Office formats converted to PDF before rendering.
+const OFFICE_EXTENSIONS: &[&str] = &[
+    "doc", "docx", "odt", "rtf", "xls", "xlsx", "ods", "ppt", "pptx", "odp",
+];
+
+/// Extensions rendered by the structured viewer.
+const STRUCTURED_EXTENSIONS: &[&str] = &[
+    "json", "jsonl", "ndjson", "yaml", "yml", "toml", "csv", "tsv", "xml", "ini",
+];
+
+pub struct PreviewService;
+
+impl PreviewService {
+    /// Recognize a format from its leading bytes.
+    pub fn sniff(head: &[u8]) -> Option<&'static str> {
+        if let Some((_, mime)) = SIGNATURES.iter().find(|(sig, _)| head.starts_with(sig)) {
+            return Some(mime);
+        }
+
+        // Container formats carry their brand a few bytes in
+        match head.get(..12) {
+            Some([b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P']) => {
+                Some("image/webp")
+            }
+            Some([b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E']) => Some("audio/wav"),
+            Some([_, _, _, _, b'f', b't', b'y', b'p', brand @ ..]) => match brand {
+                b"avif" | b"avis" => Some("image/avif"),
+                b"heic" | b"heix" | b"mif1" => Some("image/heic"),
+                b"M4A " => Some("audio/mp4"),
+                b"qt  " => Some("video/quicktime"),
+                _ => Some("video/mp4"),
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether the bytes look like text: valid UTF-8 (allowing a character
+    /// cut off at the end) with no NUL bytes.
+    pub fn looks_like_text(head: &[u8]) -> bool {
+        if head.contains(&0) {
+            return false;
+        }
+
+        match std::str::from_utf8(head) {
+            Ok(_) => true,
+            Err(e) => e.error_len().is_none() && head.len() - e.valid_up_to() < 4,
+        }
+    }
+
+    fn is_structured(key: &str, content_type: Option<&str>) -> bool {
+        let by_extension = key.rsplit_once('.').is_some_and(|(_, ext)| {
+            STRUCTURED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+        });
+        let by_type = content_type.is_some_and(|t| {
+            let essence = t.split(';').next().unwrap_or_default().trim();
+            essence.ends_with("json")
+                || essence.ends_with("yaml")
+                || essence.ends_with("xml")
+                || essence == "text/csv"
+                || essence == "application/toml"
+        });
+
+        by_extension || by_type
+    }
+
+    pub fn is_office_document(key: &str) -> bool {
+        key.rsplit_once('.')
+            .is_some_and(|(_, ext)| OFFICE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    }
+
+    /// For a linearized PDF, the byte offset where the first page ends,
+    /// taken from the `/E` entry of the linearization dictionary.
+    pub fn linearized_first_page_end(head: &[u8]) -> Option<u64> {
+        let text = String::from_utf8_lossy(head);
+        let dict = &text[text.find("/Linearized")?..];
+        let dict = &dict[..dict.find(">>")?];
+
+        let value = dict.split("/E").nth(1)?.trim_start();
+        let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().ok()
+    }
+
+    /// Convert an office document to PDF with LibreOffice, returning the
+    /// path of the converted file.
+    pub async fn convert_to_pdf(input: &Path, out_dir: &Path) -> AppResult<PathBuf> {
+        debug!("Converting {:?} to PDF", input);
+
+        let output = Command::new("soffice")
+            .args(["--headless", "--convert-to", "pdf", "--outdir"])
+            .arg(out_dir)
+            .arg(input)
+            .output()
+            .await
+            .map_err(|e| Self::missing_tool("soffice (LibreOffice)", e))?;
+
+        let stem = input.file_stem().unwrap_or_default();
+        let pdf = out_dir.join(stem).with_extension("pdf");
+        if !output.status.success() || !fs::try_exists(&pdf).await? {
+            return Err(AppError::S3Error(format!(
+                "Document conversion failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(pdf)
+    }
+
+    /// Render the first page of a PDF to PNG with poppler's `pdftoppm`.
+    pub async fn render_first_page(pdf: &Path, width: u32) -> AppResult<Vec<u8>> {
+        debug!("Rendering first page of {:?} at {}px", pdf, width);
+
+        let prefix = pdf.with_extension("page");
+        let output = Command::new("pdftoppm")
+            .args(["-png", "-singlefile", "-f", "1", "-l", "1", "-scale-to-x"])
+            .arg(width.to_string())
+            .args(["-scale-to-y", "-1"])
+            .arg(pdf)
+            .arg(&prefix)
+            .output()
+            .await
+            .map_err(|e| Self::missing_tool("pdftoppm (poppler)", e))?;
+
+        if !output.status.success() {
+            return Err(AppError::S3Error(format!(
+                "Failed to render document: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(fs::read(prefix.with_extension("png")).await?)
+    }
+
+    /// List the tables and views in a SQLite database with a few rows of
+    /// each. The file is opened read-only, so nothing is written beside it.
+    pub fn inspect_sqlite(path: &Path, sample_rows: usize) -> AppResult<Vec<SqliteTable>> {
+        let db = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(Self::sqlite_error)?;
+
+        let mut stmt = db
+            .prepare(
+                "SELECT name, type FROM sqlite_master \
+                 WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' \
+                 ORDER BY name",
+            )
+            .map_err(Self::sqlite_error)?;
+        let entries = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(Self::sqlite_error)?;
+
+        let mut tables = Vec::new();
+        for (name, kind) in entries {
+            let quoted = format!("\"{}\"", name.replace('"', "\"\""));
+
+            let row_count = if kind == "table" {
+                db.query_row(&format!("SELECT COUNT(*) FROM {}", quoted), [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .map(|count| count as u64)
+                .ok()
+            } else {
+                None
+            };
+
+            // Views over missing tables or functions fail here; keep going
+            let (columns, sample_rows) = match Self::sample_rows(&db, &quoted, sample_rows) {
+                Ok(sample) => sample,
+                Err(e) => {
+                    debug!("Failed to sample '{}': {}", name, e);
+                    (Vec::new(), Vec::new())
+                }
+            };
+
+            tables.push(SqliteTable {
+                name,
+                kind,
+                row_count,
+                columns,
+                sample_rows,
+            });
+        }
+
+        Ok(tables)
+    }
+
+    fn sample_rows(
+        db: &Connection,
+        quoted: &str,
+        limit: usize,
+    ) -> rusqlite::Result<(Vec<String>, Vec<Vec<serde_json::Value>>)> {
+        let mut stmt = db.prepare(&format!("SELECT * FROM {} LIMIT {}", quoted, limit))?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+
+        let mut rows = Vec::new();
+        let mut query = stmt.query([])?;
+        while let Some(row) = query.next()? {
+            let values = (0..columns.len())
+                .map(|i| row.get_ref(i).map(Self::sqlite_value))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows.push(values);
+        }
+
+        Ok((columns, rows))
+    }
+
+    fn sqlite_value(value: ValueRef<'_>) -> serde_json::Value {
+        match value {
+            ValueRef::Null => serde_json::Value::Null,
+            ValueRef::Integer(i) => i.into(),
+            ValueRef::Real(f) => f.into(),
+            ValueRef::Text(text) => String::from_utf8_lossy(text).into(),
+            ValueRef::Blob(blob) => format!("<blob, {} bytes>", blob.len()).into(),
+        }
+    }
+
+    fn sqlite_error(e: rusqlite::Error) -> AppError {
+        AppError::PreviewError(format!("not a readable SQLite database: {}", e))
+    }
+
+    /// Work out a config format from the key's extension or content type.
+    pub fn config_format(key: &str, content_type: Option<&str>) -> Option<ConfigFormat> {
+        let ext = key
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "json" => return Some(ConfigFormat::Json),
+            "yaml" | "yml" => return Some(ConfigFormat::Yaml),
+            "toml" => return Some(ConfigFormat::Toml),
+            _ => {}
+        }
+
+        let essence = content_type?.split(';').next()?.trim();
+        if essence.ends_with("json") {
+            Some(ConfigFormat::Json)
+        } else if essence.ends_with("yaml") {
+            Some(ConfigFormat::Yaml)
+        } else if essence == "application/toml" {
+            Some(ConfigFormat::Toml)
+        } else {
+            None
+        }
+    }
+
+    /// Parse a config document, returning its normalized pretty form with
+    /// structure stats, or where it failed to parse.
+    pub fn parse_config(key: String, format: ConfigFormat, text: &str) -> ConfigPreview {
+        let parsed = match format {
+            ConfigFormat::Json => serde_json::from_str::<serde_json::Value>(text)
+                .map_err(|e| ConfigParseError {
+                    message: e.to_string(),
+                    line: Some(e.line()),
+                    column: Some(e.column()),
+                })
+                .and_then(|value| {
+                    let pretty = serde_json::to_string_pretty(&value)
+                        .map_err(|e| Self::parse_error(e.to_string()))?;
+                    Ok((pretty, value))
+                }),
+            ConfigFormat::Yaml => serde_yaml::from_str::<serde_yaml::Value>(text)
+                .map_err(|e| ConfigParseError {
+                    message: e.to_string(),
+                    line: e.location().map(|l| l.line()),
+                    column: e.location().map(|l| l.column()),
+                })
+                .and_then(|value| {
+                    let pretty = serde_yaml::to_string(&value)
+                        .map_err(|e| Self::parse_error(e.to_string()))?;
+                    // Keys that aren't strings (e.g. `1: a`) can't be
+                    // converted; they only cost us the stats
+                    let value = serde_json::to_value(&value).unwrap_or_default();
+                    Ok((pretty, value))
+                }),
+            ConfigFormat::Toml => text
+                .parse::<toml::Table>()
+                .map_err(|e| {
+                    let position = e.span().map(|span| Self::line_column(text, span.start));
+                    ConfigParseError {
+                        message: e.message().to_string(),
+                        line: position.map(|(line, _)| line),
+                        column: position.map(|(_, column)| column),
+                    }
+                })
+                .and_then(|table| {
+                    let pretty = toml::to_string_pretty(&table)
+                        .map_err(|e| Self::parse_error(e.to_string()))?;
+                    let value = serde_json::to_value(&table).unwrap_or_default();
+                    Ok((pretty, value))
+                }),
+        };
+
+        match parsed {
+            Ok((pretty, value)) => {
+                let (key_count, depth) = Self::structure(&value);
+                ConfigPreview {
+                    key,
+                    format,
+                    pretty: Some(pretty),
+                    key_count,
+                    depth,
+                    error: None,
+                }
+            }
+            Err(error) => ConfigPreview {
+                key,
+                format,
+                pretty: None,
+                key_count: 0,
+                depth: 0,
+                error: Some(error),
+            },
+        }
+    }
+
+    /// 1-based line and column of a byte offset.
+    fn line_column(text: &str, offset: usize) -> (usize, usize) {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (before.matches('\n').count() + 1, offset - line_start + 1)
+    }
+
+    fn parse_error(message: String) -> ConfigParseError {
+        ConfigParseError {
+            message,
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Total key count and nesting depth of a document.
+    fn structure(value: &serde_json::Value) -> (usize, usize) {
+        match value {
+            serde_json::Value::Object(map) => map.values().map(Self::structure).fold(
+                (map.len(), 1),
+                |(keys, depth), (child_keys, child_depth)| {
+                    (keys + child_keys, depth.max(child_depth + 1))
+                },
+            ),
+            serde_json::Value::Array(items) => items.iter().map(Self::structure).fold(
+                (0, 1),
+                |(keys, depth), (child_keys, child_depth)| {
+                    (keys + child_keys, depth.max(child_depth + 1))
+                },
+            ),
+            _ => (0, 0),
+        }
+    }
+
+    /// Whether `tool` is an executable somewhere on `PATH`.
+    pub fn tool_available(tool: &str) -> bool {
+        let Some(paths) = std::env::var_os("PATH") else {
+            return false;
+        };
+        std::env::split_paths(&paths).any(|dir| {
+            dir.join(tool).is_file()
+                || (cfg!(windows) && dir.join(format!("{}.exe", tool)).is_file())
+        })
+    }
+
+    fn missing_tool(tool: &str, e: std::io::Error) -> AppError {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            AppError::NotSupported(format!("document previews need {} installed", tool))
+        } else {
+            AppError::IoError(e)
+        }
+    }
+
+    /// Decide how to preview `object` given its first bytes.
+    pub fn plan(object: &S3Object, head: &[u8], limits: &PreviewLimits) -> PreviewPlan {
+        let content_type = object.content_type.clone();
+        let sniffed = Self::sniff(head);
+        let declared_essence = content_type
+            .as_deref()
+            .map(|t| t.split(';').next().unwrap_or_default().trim().to_string());
+
+        let detected_type = sniffed
+            .filter(|mime| declared_essence.as_deref() != Some(*mime))
+            .map(str::to_string);
+
+        let effective_type = sniffed
+            .map(str::to_string)
+            .or(declared_essence)
+            .unwrap_or_else(|| {
+                mime_guess::from_path(&object.key)
+                    .first_or_octet_stream()
+                    .essence_str()
+                    .to_string()
+            });
+
+        let size = object.size;
+        let is_text = sniffed.is_none() && Self::looks_like_text(head);
+
+        let too_large = |limit: u64| {
+            (
+                PreviewMode::External,
+                limit,
+                Some(format!(
+                    "{} bytes exceeds the {} byte preview limit",
+                    size, limit
+                )),
+            )
+        };
+
+        let (mode, max_bytes, reason) = if effective_type.starts_with("image/") {
+            if size <= limits.image {
+                (PreviewMode::Image, size, None)
+            } else {
+                too_large(limits.image)
+            }
+        } else if is_text && Self::is_structured(&object.key, content_type.as_deref()) {
+            if size <= limits.structured {
+                (PreviewMode::Structured, size, None)
+            } else if size <= limits.text {
+                (PreviewMode::Text, size, None)
+            } else {
+                too_large(limits.structured)
+            }
+        } else if is_text {
+            if size <= limits.text {
+                (PreviewMode::Text, size, None)
+            } else {
+                // Still useful to peek at the start of a huge log
+                (
+                    PreviewMode::Text,
+                    limits.text,
+                    Some(format!("showing the first {} bytes", limits.text)),
+                )
+            }
+        } else if effective_type.starts_with("video/")
+            || effective_type.starts_with("audio/")
+            || effective_type == "application/pdf"
+        {
+            (
+                PreviewMode::External,
+                0,
+                Some(format!("{} is previewed by another viewer", effective_type)),
+            )
+        } else {
+            (PreviewMode::Hex, size.min(limits.hex), None)
+        };
+
+        PreviewPlan {
+            key: object.key.clone(),
+            mode,
+            size,
+            content_type,
+            detected_type,
+            max_bytes,
+            reason,
+        }
+    }
+}
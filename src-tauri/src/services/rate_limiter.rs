@@ -0,0 +1,130 @@
+use chrono::Local;
+use log::{debug, trace};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::models::{BandwidthSchedule, RateLimits, RequestKind};
+
+/// Token buckets keyed by (connection id, request kind), shared by every
+/// command so concurrent jobs on one connection draw from the same budget.
+static BUCKETS: LazyLock<Mutex<HashMap<(String, RequestKind), TokenBucket>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Byte budget shared by every transfer, following the bandwidth schedule.
+static BANDWIDTH: LazyLock<Mutex<BandwidthState>> =
+    LazyLock::new(|| Mutex::new(BandwidthState::default()));
+
+#[derive(Debug, Default)]
+struct BandwidthState {
+    schedule: BandwidthSchedule,
+    bucket: Option<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate.max(1.0),
+            updated_at: Instant::now(),
+        }
+    }
+
+    /// Take `amount` tokens, returning how long the caller must wait for them.
+    fn take(&mut self, amount: f64) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        // Allow up to one second worth of burst
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate.max(1.0));
+        self.updated_at = now;
+
+        self.tokens -= amount;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+pub struct RateLimiter;
+
+impl RateLimiter {
+    /// Wait until a request of `kind` is allowed for the connection. Returns
+    /// immediately when no limit is configured.
+    pub async fn acquire(connection_id: &str, limits: &RateLimits, kind: RequestKind) {
+        let Some(rate) = limits.for_kind(kind).filter(|r| *r > 0.0) else {
+            return;
+        };
+
+        let wait = {
+            let mut buckets = BUCKETS.lock().unwrap_or_else(|e| e.into_inner());
+            let bucket = buckets
+                .entry((connection_id.to_string(), kind))
+                .or_insert_with(|| TokenBucket::new(rate));
+
+            // Pick up edits to the connection's limits
+            if bucket.rate != rate {
+                *bucket = TokenBucket::new(rate);
+            }
+            bucket.take(1.0)
+        };
+
+        if !wait.is_zero() {
+            trace!("Throttling {:?} request for {:?}", kind, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub fn set_bandwidth_schedule(schedule: BandwidthSchedule) {
+        debug!("Bandwidth schedule updated: {:?}", schedule);
+        let mut state = BANDWIDTH.lock().unwrap_or_else(|e| e.into_inner());
+        state.schedule = schedule;
+        state.bucket = None;
+    }
+
+    /// Account for `bytes` transferred, waiting as long as the limit in
+    /// effect right now requires. Large single transfers pay their debt up
+    /// front, so the average rate still holds.
+    pub async fn acquire_bandwidth(bytes: u64) {
+        let wait = {
+            let mut state = BANDWIDTH.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(limit) = state
+                .schedule
+                .limit_at(Local::now().time())
+                .filter(|l| *l > 0)
+            else {
+                return;
+            };
+
+            let rate = limit as f64;
+            // Entering a different window changes the rate
+            if state.bucket.as_ref().is_none_or(|b| b.rate != rate) {
+                state.bucket = Some(TokenBucket::new(rate));
+            }
+            state
+                .bucket
+                .as_mut()
+                .map_or(Duration::ZERO, |b| b.take(bytes as f64))
+        };
+
+        if !wait.is_zero() {
+            trace!("Throttling {} byte transfer for {:?}", bytes, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub fn reset(connection_id: &str) {
+        BUCKETS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(id, _), _| id != connection_id);
+    }
+}
@@ -1,43 +1,139 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use aws_credential_types::Credentials;
-use aws_sdk_s3::config::Region;
+use aws_sdk_s3::config::{Region, RequestChecksumCalculation, ResponseChecksumValidation};
+use aws_sdk_s3::error::ProvideErrorMetadata;
 use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{
+    BucketCannedAcl, Checksum, ChecksumAlgorithm as SdkChecksumAlgorithm, ChecksumMode,
+    CompletedMultipartUpload, CompletedPart, Delete, DeleteMarkerEntry,
+    IntelligentTieringAccessTier, IntelligentTieringConfiguration, IntelligentTieringFilter,
+    IntelligentTieringStatus, MetadataDirective, Object as SdkObject,
+    ObjectAttributes as SdkObjectAttributes, ObjectCannedAcl, ObjectIdentifier,
+    ObjectLockLegalHoldStatus, ObjectVersion as SdkObjectVersion, ServerSideEncryption,
+    ServerSideEncryptionByDefault, ServerSideEncryptionConfiguration, ServerSideEncryptionRule,
+    StorageClass, Tag, Tagging, Tiering,
+};
 use aws_sdk_s3::Client as S3Client;
-use futures::TryStreamExt;
-use log::{debug, trace};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use log::{debug, trace, warn};
+use md5::{Digest, Md5};
+use opendal::raw::HttpClient;
 use opendal::services::S3;
-use opendal::{Entry, Operator};
+use opendal::{Entry, Metadata, Operator};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 
 use crate::error::{AppError, AppResult};
-use crate::models::{BucketInfo, BucketStats, ListObjectsResult, ObjectMetadata, S3ConnectionWithSecret, S3Object, S3Provider};
-use std::collections::HashMap;
+use crate::models::{
+    AccessControlList, BatchProgress, BatchResult, BucketEncryptionConfig, BucketInfo, BucketStats,
+    CannedAcl, ChecksumAlgorithm, DeleteCheck, DeleteOutcome, DeleteProtectionReport, DeleteTarget,
+    IntelligentTieringConfig, ListObjectVersionsResult, ListObjectsResult, ListingQuery,
+    MetadataUpdate, ObjectAttributes, ObjectChecksum, ObjectMetadata, ObjectVersion,
+    PrefixEstimate, PresignedUpload, ProbeStep, ProbeStepResult, ProviderCapabilities,
+    ProviderFeature, RequestKind, S3ConnectionWithSecret, S3Object, S3Provider, ScanBudget,
+    ScanLimits, SignatureVersion, SseAlgorithm, TagFilter, TaggedObject, TieringRule, TransferPlan,
+    UploadOptions,
+};
+use crate::services::{
+    AuditService, ClockSkew, ClockSkewFetcher, ClockSkewInterceptor, CompatibilityInterceptor,
+    RateLimiter, SigV2Fetcher, SigV2Interceptor, SiteService, SkewedTimeSource, TransferTuner,
+};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Suffix Hadoop's S3 connectors use for folder placeholder objects.
+const HADOOP_FOLDER_SUFFIX: &str = "_$folder$";
+
+/// Upper bound on a requested listing page size. Providers cap each
+/// response on their own (1000 keys on AWS), so pages may come back smaller.
+pub const MAX_LIST_PAGE_SIZE: u32 = 50_000;
+
+/// Keys per request while scanning for a sorted or filtered listing; the
+/// most S3 returns at once.
+const SCAN_PAGE_SIZE: usize = 1000;
+
+/// Entries a sorted listing scans before giving up on the rest of the level;
+/// a tenth of this in low-memory mode.
+const MAX_SORTED_ENTRIES: usize = 100_000;
+
+/// First-level prefixes walked at once by `list_all_objects_recursive`.
+const LIST_CONCURRENCY: usize = 8;
+
+/// DeleteObjects requests in flight at once unless a caller overrides it.
+pub const DELETE_CONCURRENCY: usize = 4;
+
+/// CopyObject requests in flight at once unless a caller overrides it.
+pub const COPY_CONCURRENCY: usize = 8;
+
+/// Largest object a single CopyObject request can copy.
+const MAX_COPY_OBJECT_SIZE: i64 = 5 * 1024 * 1024 * 1024;
+
+/// Smallest part used when copying an object too large for CopyObject.
+const COPY_PART_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Most parts a multipart upload can have.
+const MAX_UPLOAD_PARTS: u64 = 10_000;
+
+/// Connections whose provider doesn't implement GetObjectAttributes.
+static NO_OBJECT_ATTRIBUTES: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Characters escaped in the key of a copy source; `/` stays a separator.
+const COPY_SOURCE_KEY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Longest object key S3 accepts, in UTF-8 bytes.
+const MAX_KEY_LENGTH: usize = 1024;
 
 pub struct S3Service;
 
 impl S3Service {
+    /// OpenDAL operator for `bucket`. OpenDAL signs with SigV4, so SigV2
+    /// connections send their requests through `SigV2Fetcher`, which signs
+    /// them again.
     pub fn create_operator(
         connection: &S3ConnectionWithSecret,
         bucket: &str,
     ) -> AppResult<Operator> {
+        let endpoint = connection.endpoint_for_region(&connection.region);
+
         trace!(
             "Creating OpenDAL operator for bucket '{}' at {}",
             bucket,
-            connection.endpoint
+            endpoint
         );
 
         let mut builder = S3::default()
             .bucket(bucket)
-            .endpoint(&connection.endpoint)
+            .endpoint(&endpoint)
             .region(&connection.region)
             .access_key_id(&connection.access_key)
             .secret_access_key(&connection.secret_key);
 
+        // Jailed connections see the root prefix as the top of the bucket
+        if let Some(root) = connection.root_prefix.as_deref() {
+            debug!("Scoping operator to root prefix '{}'", root);
+            builder = builder.root(&format!("/{}", root));
+        }
+
         // Provider-specific configuration
         match connection.provider {
             S3Provider::CloudflareR2 => {
-                debug!("Configuring for Cloudflare R2 (delete_max_size=700)");
-                builder = builder.delete_max_size(700);
+                let delete_max_size = connection.max_delete_batch();
+                debug!(
+                    "Configuring for Cloudflare R2 (delete_max_size={})",
+                    delete_max_size
+                );
+                builder = builder.delete_max_size(delete_max_size);
             }
             S3Provider::Minio => {
                 if !connection.use_path_style {
@@ -53,15 +149,122 @@ impl S3Service {
             }
         }
 
+        let transport = if connection.signature_version == SignatureVersion::V2 {
+            debug!("Signing operator requests with SigV2 for legacy gateway");
+            HttpClient::with(SigV2Fetcher::new(connection)?)
+        } else {
+            HttpClient::new()?
+        };
+
+        // Same skew tracking and compensation as the SDK client
+        let fetcher = ClockSkewFetcher::new(connection, transport);
+        builder = builder.http_client(HttpClient::with(fetcher));
+
         let op = Operator::new(builder)?.finish();
 
         Ok(op)
     }
 
-    async fn create_s3_client(connection: &S3ConnectionWithSecret) -> S3Client {
+    /// Map errors from APIs that gateways may not implement, so callers can
+    /// tell "unsupported" apart from a real failure.
+    fn map_optional_api_error<E>(e: E) -> AppError
+    where
+        E: ProvideErrorMetadata + std::fmt::Display,
+    {
+        match e.code() {
+            Some("NotImplemented") | Some("NotSupported") => {
+                AppError::NotSupported(e.message().unwrap_or("not implemented").to_string())
+            }
+            _ => Self::api_error(e),
+        }
+    }
+
+    /// Map an S3 API error, turning the codes users commonly trip over into
+    /// a `ProviderError` that says what to fix.
+    pub fn api_error<E>(e: E) -> AppError
+    where
+        E: ProvideErrorMetadata + std::fmt::Display,
+    {
+        e.code()
+            .and_then(|code| AppError::from_provider(code, e.message().unwrap_or_default()))
+            .unwrap_or_else(|| AppError::S3Error(e.to_string()))
+    }
+
+    /// Canonical form of a root prefix: no leading slash, one trailing slash,
+    /// `None` when empty.
+    pub fn normalize_root_prefix(root_prefix: Option<String>) -> Option<String> {
+        let root = root_prefix?;
+        let root = root.trim_matches('/');
+        if root.is_empty() {
+            None
+        } else {
+            Some(format!("{}/", root))
+        }
+    }
+
+    /// Map a key as seen by the user to the real key in the bucket, refusing
+    /// anything that would step outside a jailed connection's root.
+    pub fn scoped_key(connection: &S3ConnectionWithSecret, key: &str) -> AppResult<String> {
+        let Some(root) = connection.root_prefix.as_deref() else {
+            return Ok(key.to_string());
+        };
+
+        if key.split('/').any(|segment| segment == "..") {
+            return Err(AppError::ConfigError(format!(
+                "Key '{}' escapes the connection's root prefix",
+                key
+            )));
+        }
+
+        Ok(format!("{}{}", root, key.trim_start_matches('/')))
+    }
+
+    /// Whether a probe request shows the API exists, `None` when the answer
+    /// says nothing either way: credentials that may not read it, or a
+    /// server error. Other service errors (missing config) still mean it
+    /// does.
+    fn probe_supported<T, E>(result: Result<T, E>) -> AppResult<Option<bool>>
+    where
+        E: ProvideErrorMetadata + std::fmt::Display,
+    {
+        match result {
+            Ok(_) => Ok(Some(true)),
+            Err(e) => match e.code() {
+                Some("NotImplemented") | Some("NotSupported") | Some("MethodNotAllowed") => {
+                    Ok(Some(false))
+                }
+                Some(
+                    "AccessDenied" | "AllAccessDisabled" | "AccountProblem" | "InternalError"
+                    | "ServiceUnavailable" | "SlowDown" | "RequestTimeout",
+                ) => {
+                    debug!("Probe was inconclusive: {}", e);
+                    Ok(None)
+                }
+                Some(_) => Ok(Some(true)),
+                None => Err(AppError::S3Error(e.to_string())),
+            },
+        }
+    }
+
+    /// Wait for the connection's rate limit, if any, before issuing a request.
+    pub async fn throttle(connection: &S3ConnectionWithSecret, kind: RequestKind) {
+        RateLimiter::acquire(&connection.id, &connection.rate_limits, kind).await;
+    }
+
+    pub async fn create_s3_client(connection: &S3ConnectionWithSecret) -> S3Client {
+        Self::create_s3_client_for_region(connection, &connection.region).await
+    }
+
+    async fn create_s3_client_for_region(
+        connection: &S3ConnectionWithSecret,
+        region: &str,
+    ) -> S3Client {
+        let endpoint = connection.endpoint_for_region(region);
+
         trace!(
-            "Creating AWS SDK S3 client for endpoint: {}",
-            connection.endpoint
+            "Creating AWS SDK S3 client for endpoint: {} (region: {})",
+            endpoint,
+            region
         );
 
         let credentials = Credentials::new(
@@ -74,12 +277,43 @@ impl S3Service {
 
         let mut config_builder = aws_sdk_s3::Config::builder()
             .credentials_provider(credentials)
-            .region(Region::new(connection.region.clone()))
+            .region(Region::new(region.to_string()))
             .force_path_style(connection.use_path_style);
 
         // Set endpoint URL
-        if !connection.endpoint.is_empty() {
-            config_builder = config_builder.endpoint_url(&connection.endpoint);
+        if !endpoint.is_empty() {
+            config_builder = config_builder.endpoint_url(&endpoint);
+        }
+
+        let compat = connection.compatibility;
+        if compat.disable_request_checksums {
+            debug!("Disabling request checksums for compatibility");
+            config_builder = config_builder
+                .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+                .response_checksum_validation(ResponseChecksumValidation::WhenRequired);
+        }
+        if compat.unsigned_payload || compat.disable_expect_continue {
+            debug!(
+                "Applying compatibility options (unsigned_payload: {}, disable_expect_continue: {})",
+                compat.unsigned_payload, compat.disable_expect_continue
+            );
+            config_builder = config_builder.interceptor(CompatibilityInterceptor::new(compat));
+        }
+
+        if connection.signature_version == SignatureVersion::V2 {
+            debug!("Signing requests with SigV2 for legacy gateway");
+            config_builder = config_builder.interceptor(SigV2Interceptor::new(
+                &connection.access_key,
+                &connection.secret_key,
+                &endpoint,
+                connection.use_path_style,
+            ));
+        }
+
+        config_builder = config_builder.interceptor(ClockSkewInterceptor::new(&connection.id));
+        if let Some(offset) = ClockSkew::compensation(&connection.id) {
+            debug!("Signing with the server's time, {}s off local", offset);
+            config_builder = config_builder.time_source(SkewedTimeSource::new(offset));
         }
 
         let config = config_builder.build();
@@ -88,12 +322,13 @@ impl S3Service {
 
     pub async fn list_buckets(connection: &S3ConnectionWithSecret) -> AppResult<Vec<BucketInfo>> {
         let client = Self::create_s3_client(connection).await;
+        Self::throttle(connection, RequestKind::List).await;
 
         let result = client
             .list_buckets()
             .send()
             .await
-            .map_err(|e| AppError::S3Error(e.to_string()))?;
+            .map_err(Self::api_error)?;
 
         let buckets = result
             .buckets()
@@ -108,14 +343,18 @@ impl S3Service {
         Ok(buckets)
     }
 
+    /// List one page of up to `limit` entries directly under `prefix` with
+    /// ListObjectsV2. `continuation_token` resumes the previous page;
+    /// `start_after` jumps to a key when starting fresh.
     pub async fn list_objects(
-        operator: &Operator,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
         prefix: &str,
-        max_keys: Option<u32>,
+        limit: usize,
+        continuation_token: Option<String>,
+        start_after: Option<&str>,
+        show_folder_markers: bool,
     ) -> AppResult<ListObjectsResult> {
-        let mut objects = Vec::new();
-        let mut prefixes = Vec::new();
-
         let prefix_with_delimiter = if prefix.is_empty() {
             "".to_string()
         } else if prefix.ends_with('/') {
@@ -124,69 +363,236 @@ impl S3Service {
             format!("{}/", prefix)
         };
 
-        // Default to 500 items per page, max 1000
-        let limit = max_keys.unwrap_or(500).min(1000) as usize;
-        let mut count = 0;
+        let client = Self::create_s3_client(connection).await;
+        let scoped_prefix = Self::scoped_key(connection, &prefix_with_delimiter)?;
+        let scoped_start_after = start_after
+            .map(|key| Self::scoped_key(connection, key))
+            .transpose()?;
+        let root = connection.root_prefix.as_deref().unwrap_or_default();
+        let user_key = |key: Option<&str>| {
+            let key = key.unwrap_or_default();
+            key.strip_prefix(root).unwrap_or(key).to_string()
+        };
 
-        let mut lister = operator.lister_with(&prefix_with_delimiter).await?;
+        let result = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(&scoped_prefix)
+            .delimiter("/")
+            .max_keys(limit.min(i32::MAX as usize) as i32)
+            .set_continuation_token(continuation_token)
+            .set_start_after(scoped_start_after)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
 
-        while let Some(entry) = lister.try_next().await? {
-            if count >= limit {
-                // We've reached the limit, indicate there's more data
-                return Ok(ListObjectsResult {
-                    objects,
-                    prefixes,
-                    continuation_token: Some(format!("offset:{}", count)),
-                    is_truncated: true,
-                });
-            }
+        let objects = result
+            .contents()
+            .iter()
+            .map(|o| S3Object {
+                key: user_key(o.key()),
+                size: o.size().unwrap_or(0).max(0) as u64,
+                last_modified: o.last_modified().map(|t| t.secs()).unwrap_or(0),
+                etag: o.e_tag().map(|s| s.to_string()),
+                content_type: None,
+                is_directory: false,
+                child_count: None,
+            })
+            .collect();
+        let folders = result
+            .common_prefixes()
+            .iter()
+            .map(|p| S3Object {
+                key: user_key(p.prefix()),
+                size: 0,
+                last_modified: 0,
+                etag: None,
+                content_type: None,
+                is_directory: true,
+                child_count: None,
+            })
+            .collect();
 
-            let entry: Entry = entry;
-            let path = entry.path().to_string();
-            let meta = entry.metadata();
+        let is_truncated = result.is_truncated() == Some(true);
+        let mut result = ListObjectsResult {
+            objects,
+            folders,
+            prefixes: Vec::new(),
+            continuation_token: is_truncated
+                .then(|| result.next_continuation_token().map(|t| t.to_string()))
+                .flatten(),
+            is_truncated,
+        };
+        Self::normalize_folder_markers(&mut result, &prefix_with_delimiter, show_folder_markers);
 
-            if meta.is_dir() || path.ends_with('/') {
-                // It's a directory/prefix
-                prefixes.push(path);
-            } else {
-                // It's an object
-                objects.push(S3Object {
-                    key: path,
-                    size: meta.content_length(),
-                    last_modified: meta.last_modified().map(|t| t.timestamp()).unwrap_or(0),
-                    etag: meta.etag().map(|s| s.to_string()),
-                    content_type: meta.content_type().map(|s| s.to_string()),
-                    is_directory: false,
-                });
-            }
-            count += 1;
+        Ok(result)
+    }
+
+    /// `list_objects` with sorting and filtering applied. Name order pages
+    /// from the server, skipping ahead until `limit` objects match; other
+    /// orders scan the whole level (up to `MAX_SORTED_ENTRIES`) and come back
+    /// as one page.
+    pub async fn list_objects_matching(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        limit: usize,
+        mut continuation_token: Option<String>,
+        start_after: Option<&str>,
+        show_folder_markers: bool,
+        query: &ListingQuery,
+    ) -> AppResult<ListObjectsResult> {
+        let full_listing = query.needs_full_listing();
+        let max_entries = if TransferTuner::low_memory() {
+            MAX_SORTED_ENTRIES / 10
+        } else {
+            MAX_SORTED_ENTRIES
+        };
+        if full_listing {
+            continuation_token = None;
         }
 
-        Ok(ListObjectsResult {
-            objects,
-            prefixes,
+        let mut matched = ListObjectsResult {
+            objects: Vec::new(),
+            folders: Vec::new(),
+            prefixes: Vec::new(),
             continuation_token: None,
             is_truncated: false,
-        })
+        };
+        let mut scanned = 0;
+
+        loop {
+            Self::throttle(connection, RequestKind::List).await;
+            let page = Self::list_objects(
+                connection,
+                bucket,
+                prefix,
+                // Filtering thins pages out, so fetch full ones
+                SCAN_PAGE_SIZE,
+                continuation_token.take(),
+                start_after.filter(|_| scanned == 0),
+                show_folder_markers,
+            )
+            .await?;
+            scanned += page.objects.len() + page.folders.len();
+
+            matched
+                .objects
+                .extend(page.objects.into_iter().filter(|o| query.matches(o)));
+            matched.folders.extend(page.folders);
+            matched.continuation_token = page.continuation_token;
+            matched.is_truncated = page.is_truncated;
+
+            let Some(token) = &matched.continuation_token else {
+                break;
+            };
+            if !full_listing && matched.objects.len() + matched.folders.len() >= limit {
+                break;
+            }
+            if full_listing && scanned >= max_entries {
+                warn!(
+                    "Sorted listing of '{}/{}' stopped after {} entries",
+                    bucket, prefix, scanned
+                );
+                // The rest can't be merged into this order, so there's no
+                // page to continue with
+                matched.continuation_token = None;
+                break;
+            }
+            continuation_token = Some(token.clone());
+        }
+
+        if full_listing {
+            query.sort(&mut matched.objects);
+            query.sort(&mut matched.folders);
+        }
+        matched.prefixes = matched.folders.iter().map(|f| f.key.clone()).collect();
+        debug!(
+            "Query over '{}/{}' kept {} of {} scanned entries",
+            bucket,
+            prefix,
+            matched.objects.len() + matched.folders.len(),
+            scanned
+        );
+
+        Ok(matched)
     }
 
-    /// List all objects without pagination (for operations that need full listing)
-    pub async fn list_all_objects(
-        operator: &Operator,
+    /// Providers disagree on how folder placeholders show up: AWS returns the
+    /// listed folder's own `prefix/` marker, MinIO hides it, and Hadoop-style
+    /// tools write `name_$folder$` keys. Fold them all into folder entries.
+    fn normalize_folder_markers(
+        result: &mut ListObjectsResult,
         prefix: &str,
-    ) -> AppResult<ListObjectsResult> {
-        let mut objects = Vec::new();
-        let mut prefixes = Vec::new();
+        show_folder_markers: bool,
+    ) {
+        let (markers, objects): (Vec<S3Object>, Vec<S3Object>) =
+            std::mem::take(&mut result.objects)
+                .into_iter()
+                .partition(|o| o.key.ends_with(HADOOP_FOLDER_SUFFIX) || o.key.ends_with('/'));
+        result.objects = objects;
+
+        for mut marker in markers {
+            if let Some(name) = marker.key.strip_suffix(HADOOP_FOLDER_SUFFIX) {
+                marker.key = format!("{}/", name);
+            }
+            marker.is_directory = true;
+            marker.size = 0;
+            marker.content_type = None;
+            result.folders.push(marker);
+        }
 
-        let prefix_with_delimiter = if prefix.is_empty() {
-            "".to_string()
-        } else if prefix.ends_with('/') {
-            prefix.to_string()
-        } else {
-            format!("{}/", prefix)
-        };
+        // Merge duplicates, keeping whichever entry carries a marker timestamp
+        result.folders.sort_by(|a, b| {
+            a.key
+                .cmp(&b.key)
+                .then(b.last_modified.cmp(&a.last_modified))
+        });
+        result.folders.dedup_by(|a, b| a.key == b.key);
+
+        if !show_folder_markers {
+            result.folders.retain(|f| f.key != prefix);
+        }
+        result.prefixes = result.folders.iter().map(|f| f.key.clone()).collect();
+    }
+
+    fn object_entry(path: String, meta: &Metadata) -> S3Object {
+        S3Object {
+            key: path,
+            size: meta.content_length(),
+            last_modified: meta.last_modified().map(|t| t.timestamp()).unwrap_or(0),
+            etag: meta.etag().map(|s| s.to_string()),
+            content_type: meta.content_type().map(|s| s.to_string()),
+            is_directory: false,
+            child_count: None,
+        }
+    }
+
+    /// Folder entry for a common prefix or zero-byte marker; markers carry
+    /// their own timestamp, bare prefixes don't have one.
+    fn folder_entry(path: String, meta: &Metadata) -> S3Object {
+        S3Object {
+            key: path,
+            size: 0,
+            last_modified: meta.last_modified().map(|t| t.timestamp()).unwrap_or(0),
+            etag: meta.etag().map(|s| s.to_string()),
+            content_type: None,
+            is_directory: true,
+            child_count: None,
+        }
+    }
+
+    /// Objects and folders under `path`, split the same way `list_objects`
+    /// does.
+    async fn list_entries(
+        operator: &Operator,
+        path: &str,
+        recursive: bool,
+    ) -> AppResult<(Vec<S3Object>, Vec<S3Object>)> {
+        let mut objects = Vec::new();
+        let mut folders = Vec::new();
 
-        let mut lister = operator.lister_with(&prefix_with_delimiter).await?;
+        let mut lister = operator.lister_with(path).recursive(recursive).await?;
 
         while let Some(entry) = lister.try_next().await? {
             let entry: Entry = entry;
@@ -194,327 +600,3434 @@ impl S3Service {
             let meta = entry.metadata();
 
             if meta.is_dir() || path.ends_with('/') {
-                prefixes.push(path);
+                folders.push(Self::folder_entry(path, meta));
             } else {
-                objects.push(S3Object {
-                    key: path,
-                    size: meta.content_length(),
-                    last_modified: meta.last_modified().map(|t| t.timestamp()).unwrap_or(0),
-                    etag: meta.etag().map(|s| s.to_string()),
-                    content_type: meta.content_type().map(|s| s.to_string()),
-                    is_directory: false,
-                });
+                objects.push(Self::object_entry(path, meta));
             }
         }
 
-        Ok(ListObjectsResult {
+        Ok((objects, folders))
+    }
+
+    /// Combine the (objects, folders) listings of parts of the tree under
+    /// `prefix`, which finish in any order and may overlap, into one listing
+    /// in key order with every key once.
+    fn merge_entries(
+        parts: Vec<(Vec<S3Object>, Vec<S3Object>)>,
+        prefix: &str,
+    ) -> ListObjectsResult {
+        let mut objects = Vec::new();
+        let mut folders = Vec::new();
+        for (part_objects, part_folders) in parts {
+            objects.extend(part_objects);
+            folders.extend(part_folders);
+        }
+
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        objects.dedup_by(|a, b| a.key == b.key);
+
+        let mut result = ListObjectsResult {
             objects,
-            prefixes,
+            folders,
+            prefixes: Vec::new(),
             continuation_token: None,
             is_truncated: false,
-        })
+        };
+        Self::normalize_folder_markers(&mut result, prefix, false);
+        result
     }
 
-    pub async fn upload_object(operator: &Operator, key: &str, data: Vec<u8>) -> AppResult<()> {
-        operator.write(key, data).await?;
-        Ok(())
+    /// `prefix` with the trailing slash a delimited listing needs.
+    fn listing_prefix(prefix: &str) -> String {
+        if prefix.is_empty() || prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{}/", prefix)
+        }
     }
 
-    pub async fn download_object(operator: &Operator, key: &str) -> AppResult<Vec<u8>> {
-        let data = operator.read(key).await?;
-        Ok(data.to_vec())
+    /// List all objects without pagination (for operations that need full listing)
+    pub async fn list_all_objects(
+        operator: &Operator,
+        prefix: &str,
+    ) -> AppResult<ListObjectsResult> {
+        let prefix_with_delimiter = Self::listing_prefix(prefix);
+        let top = Self::list_entries(operator, &prefix_with_delimiter, false).await?;
+        Ok(Self::merge_entries(vec![top], &prefix_with_delimiter))
     }
 
-    pub async fn delete_object(operator: &Operator, key: &str) -> AppResult<()> {
-        operator.delete(key).await?;
-        Ok(())
-    }
+    /// Every object at any depth under `prefix`, without pagination, with
+    /// folder totals filled in.
+    pub async fn list_all_objects_recursive(
+        operator: &Operator,
+        prefix: &str,
+    ) -> AppResult<ListObjectsResult> {
+        let prefix_with_delimiter = Self::listing_prefix(prefix);
+
+        // One delimited listing of the top level, then each first-level
+        // folder is walked recursively on its own so large trees list in
+        // parallel rather than as a single serial pagination
+        let top = Self::list_entries(operator, &prefix_with_delimiter, false).await?;
+        let children: Vec<String> = top
+            .1
+            .iter()
+            .map(|f| f.key.clone())
+            .filter(|key| *key != prefix_with_delimiter)
+            .collect();
 
-    pub async fn get_object_details(operator: &Operator, key: &str) -> AppResult<S3Object> {
-        let meta = operator.stat(key).await?;
+        let mut parts = vec![top];
+        let mut subtrees = stream::iter(children)
+            .map(|child| async move { Self::list_entries(operator, &child, true).await })
+            .buffer_unordered(TransferTuner::concurrency(LIST_CONCURRENCY));
+        while let Some(subtree) = subtrees.next().await {
+            parts.push(subtree?);
+        }
 
-        Ok(S3Object {
-            key: key.to_string(),
-            size: meta.content_length(),
-            last_modified: meta.last_modified().map(|t| t.timestamp()).unwrap_or(0),
-            etag: meta.etag().map(|s| s.to_string()),
-            content_type: meta.content_type().map(|s| s.to_string()),
-            is_directory: meta.is_dir(),
-        })
+        let mut result = Self::merge_entries(parts, &prefix_with_delimiter);
+
+        // A recursive listing has seen everything, so folder totals are free:
+        // charge each object to every folder between the prefix and its name
+        let mut totals: HashMap<&str, (u64, u64)> = HashMap::new();
+        for object in &result.objects {
+            let relative = object
+                .key
+                .strip_prefix(&prefix_with_delimiter)
+                .unwrap_or(&object.key);
+            let base = object.key.len() - relative.len();
+            for (i, _) in relative.match_indices('/') {
+                let total = totals.entry(&object.key[..base + i + 1]).or_default();
+                total.0 += 1;
+                total.1 += object.size;
+            }
+        }
+        for folder in &mut result.folders {
+            let (count, size) = totals.get(folder.key.as_str()).copied().unwrap_or_default();
+            folder.child_count = Some(count);
+            folder.size = size;
+        }
+
+        Ok(result)
     }
 
-    pub async fn create_folder(operator: &Operator, path: &str) -> AppResult<()> {
-        let folder_path = if path.ends_with('/') {
-            path.to_string()
-        } else {
-            format!("{}/", path)
-        };
+    pub async fn upload_object_with(
+        operator: &Operator,
+        key: &str,
+        data: Vec<u8>,
+        content_type: Option<&str>,
+        cache_control: Option<&str>,
+        content_encoding: Option<&str>,
+    ) -> AppResult<()> {
+        RateLimiter::acquire_bandwidth(data.len() as u64).await;
 
-        // Create an empty object with trailing slash to represent a folder
-        operator.write(&folder_path, Vec::<u8>::new()).await?;
+        let mut write = operator.write_with(key, data);
+        if let Some(content_type) = content_type {
+            write = write.content_type(content_type);
+        }
+        if let Some(cache_control) = cache_control {
+            write = write.cache_control(cache_control);
+        }
+        if let Some(content_encoding) = content_encoding {
+            write = write.content_encoding(content_encoding);
+        }
+        write.await?;
         Ok(())
     }
 
-    pub async fn get_presigned_url(
+    /// Stream the file at `path` to `key` in parts sized and uploaded in
+    /// parallel as `plan` says, calling `on_progress` with the running byte
+    /// count after each part. Failures reading the file come back as
+    /// `IoError`.
+    pub async fn upload_file_chunked(
+        operator: &Operator,
+        key: &str,
+        path: &Path,
+        plan: &TransferPlan,
+        content_type: Option<&str>,
+        cache_control: Option<&str>,
+        mut on_progress: impl FnMut(u64),
+    ) -> AppResult<u64> {
+        let mut file = tokio::fs::File::open(path).await?;
+
+        let part_size = plan.part_size as usize;
+        let mut write = operator
+            .writer_with(key)
+            .chunk(part_size)
+            .concurrent(plan.concurrency);
+        if let Some(content_type) = content_type {
+            write = write.content_type(content_type);
+        }
+        if let Some(cache_control) = cache_control {
+            write = write.cache_control(cache_control);
+        }
+        let mut writer = write.await?;
+
+        let mut uploaded: u64 = 0;
+        loop {
+            let mut chunk = Vec::with_capacity(part_size);
+            let read = match (&mut file)
+                .take(plan.part_size)
+                .read_to_end(&mut chunk)
+                .await
+            {
+                Ok(read) => read,
+                Err(e) => {
+                    let _ = writer.abort().await;
+                    return Err(e.into());
+                }
+            };
+            if read == 0 {
+                break;
+            }
+
+            RateLimiter::acquire_bandwidth(read as u64).await;
+            if let Err(e) = writer.write(chunk).await {
+                let _ = writer.abort().await;
+                return Err(e.into());
+            }
+            uploaded += read as u64;
+            on_progress(uploaded);
+        }
+
+        // A failed close leaves the multipart upload open; abort it so its
+        // parts don't linger and bill
+        if let Err(e) = writer.close().await {
+            if let Err(abort) = writer.abort().await {
+                warn!("Failed to abort upload of '{}': {}", key, abort);
+            }
+            return Err(e.into());
+        }
+        Ok(uploaded)
+    }
+
+    /// Lower-cased user metadata names without the `x-amz-meta-` prefix,
+    /// rejecting names that can't go in a header.
+    fn metadata_headers(metadata: &HashMap<String, String>) -> AppResult<HashMap<String, String>> {
+        let mut headers = HashMap::new();
+        for (name, value) in metadata {
+            let name = name.trim().to_lowercase();
+            let name = name.strip_prefix("x-amz-meta-").unwrap_or(&name);
+            let valid = !name.is_empty()
+                && name
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b));
+            if !valid {
+                return Err(AppError::ConfigError(format!(
+                    "Invalid metadata name '{}'",
+                    name
+                )));
+            }
+            if !value.is_ascii() {
+                return Err(AppError::ConfigError(format!(
+                    "Metadata '{}' must be ASCII",
+                    name
+                )));
+            }
+            headers.insert(name.to_string(), value.clone());
+        }
+        Ok(headers)
+    }
+
+    /// Write `data` to `key` in one PutObject through the SDK, with the
+    /// headers, metadata, storage class and checksum in `options`.
+    pub async fn put_object_with_options(
         connection: &S3ConnectionWithSecret,
         bucket: &str,
         key: &str,
-        expires_in_secs: u64,
-    ) -> AppResult<String> {
+        data: Vec<u8>,
+        options: &UploadOptions,
+    ) -> AppResult<u64> {
         let client = Self::create_s3_client(connection).await;
+        let size = data.len() as u64;
 
-        let presigning_config = PresigningConfig::builder()
-            .expires_in(Duration::from_secs(expires_in_secs))
-            .build()
-            .map_err(|e| AppError::S3Error(e.to_string()))?;
-
-        let presigned_request = client
-            .get_object()
+        RateLimiter::acquire_bandwidth(size).await;
+        Self::throttle(connection, RequestKind::Put).await;
+        client
+            .put_object()
             .bucket(bucket)
-            .key(key)
-            .presigned(presigning_config)
+            .key(Self::scoped_key(connection, key)?)
+            .body(ByteStream::from(data))
+            .set_content_type(options.content_type.clone())
+            .set_cache_control(options.cache_control.clone())
+            .set_content_encoding(options.content_encoding.clone())
+            .set_metadata(
+                Some(Self::metadata_headers(&options.metadata)?).filter(|m| !m.is_empty()),
+            )
+            .set_storage_class(options.storage_class.as_deref().map(StorageClass::from))
+            .set_checksum_algorithm(
+                options
+                    .checksum_algorithm
+                    .map(|a| SdkChecksumAlgorithm::from(a.as_str())),
+            )
+            .send()
             .await
-            .map_err(|e| AppError::S3Error(e.to_string()))?;
-
-        Ok(presigned_request.uri().to_string())
+            .map_err(Self::api_error)?;
+        Ok(size)
     }
 
-    pub async fn get_object_content_as_text(
-        operator: &Operator,
+    /// Upload the file at `path` through the SDK so user metadata and the
+    /// storage class can be set: one PutObject for files that fit in a
+    /// part, otherwise a multipart upload split up as `plan` says. Calls
+    /// `on_progress` with the running byte count like `upload_file_chunked`.
+    pub async fn upload_file_with_options(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
         key: &str,
-        max_size: u64,
-    ) -> AppResult<String> {
-        let meta = operator.stat(key).await?;
-        let size = meta.content_length();
-
-        if size > max_size {
-            return Err(AppError::S3Error(format!(
-                "File too large for text preview: {} bytes (max: {} bytes)",
-                size, max_size
-            )));
+        path: &Path,
+        plan: &TransferPlan,
+        options: &UploadOptions,
+        mut on_progress: impl FnMut(u64),
+    ) -> AppResult<u64> {
+        let client = Self::create_s3_client(connection).await;
+        let real_key = Self::scoped_key(connection, key)?;
+        let metadata = Some(Self::metadata_headers(&options.metadata)?).filter(|m| !m.is_empty());
+        let storage_class = options.storage_class.as_deref().map(StorageClass::from);
+        let checksum_algorithm = options
+            .checksum_algorithm
+            .map(|a| SdkChecksumAlgorithm::from(a.as_str()));
+
+        let mut file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+
+        if size <= plan.part_size {
+            let mut data = Vec::with_capacity(size as usize);
+            file.read_to_end(&mut data).await?;
+            let size =
+                Self::put_object_with_options(connection, bucket, key, data, options).await?;
+            on_progress(size);
+            return Ok(size);
         }
 
-        let data = operator.read(key).await?;
-        let text = String::from_utf8(data.to_vec())
-            .map_err(|e| AppError::S3Error(format!("Not a valid UTF-8 text file: {}", e)))?;
+        Self::throttle(connection, RequestKind::Put).await;
+        let created = client
+            .create_multipart_upload()
+            .bucket(bucket)
+            .key(&real_key)
+            .set_content_type(options.content_type.clone())
+            .set_cache_control(options.cache_control.clone())
+            .set_content_encoding(options.content_encoding.clone())
+            .set_metadata(metadata)
+            .set_storage_class(storage_class)
+            .set_checksum_algorithm(checksum_algorithm.clone())
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+        let upload_id = created
+            .upload_id()
+            .ok_or_else(|| AppError::S3Error("CreateMultipartUpload returned no upload id".into()))?
+            .to_string();
+
+        let part_size = plan.part_size;
+        let parts = async {
+            let chunks = stream::try_unfold((file, 1), move |(mut file, part_number)| async move {
+                let mut chunk = Vec::with_capacity(part_size as usize);
+                let read = (&mut file).take(part_size).read_to_end(&mut chunk).await?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                Ok::<_, AppError>(Some(((part_number, chunk), (file, part_number + 1))))
+            });
+
+            // Parts finish out of order; CompleteMultipartUpload wants them sorted
+            let uploads = chunks
+                .map_ok(|(part_number, chunk)| {
+                    let (client, real_key, upload_id) = (&client, &real_key, &upload_id);
+                    let checksum_algorithm = checksum_algorithm.clone();
+                    async move {
+                        let read = chunk.len() as u64;
+                        RateLimiter::acquire_bandwidth(read).await;
+                        Self::throttle(connection, RequestKind::Put).await;
+                        let part = client
+                            .upload_part()
+                            .bucket(bucket)
+                            .key(real_key)
+                            .upload_id(upload_id)
+                            .part_number(part_number)
+                            .body(ByteStream::from(chunk))
+                            .set_checksum_algorithm(checksum_algorithm)
+                            .send()
+                            .await
+                            .map_err(Self::api_error)?;
+                        // Parts of an upload with a checksum must be
+                        // completed with theirs
+                        let completed = CompletedPart::builder()
+                            .set_e_tag(part.e_tag().map(str::to_string))
+                            .set_checksum_crc32(part.checksum_crc32().map(str::to_string))
+                            .set_checksum_crc32_c(part.checksum_crc32_c().map(str::to_string))
+                            .set_checksum_sha1(part.checksum_sha1().map(str::to_string))
+                            .set_checksum_sha256(part.checksum_sha256().map(str::to_string))
+                            .part_number(part_number)
+                            .build();
+                        Ok::<_, AppError>((completed, read))
+                    }
+                })
+                .try_buffer_unordered(plan.concurrency.max(1));
+            let mut uploads = std::pin::pin!(uploads);
+
+            let mut parts = Vec::new();
+            let mut uploaded: u64 = 0;
+            while let Some((part, read)) = uploads.try_next().await? {
+                parts.push(part);
+                uploaded += read;
+                on_progress(uploaded);
+            }
+            parts.sort_by_key(|p| p.part_number());
+            Ok::<_, AppError>((parts, uploaded))
+        }
+        .await;
+
+        let completed = match parts {
+            Ok((parts, uploaded)) => client
+                .complete_multipart_upload()
+                .bucket(bucket)
+                .key(&real_key)
+                .upload_id(&upload_id)
+                .multipart_upload(
+                    CompletedMultipartUpload::builder()
+                        .set_parts(Some(parts))
+                        .build(),
+                )
+                .send()
+                .await
+                .map(|_| uploaded)
+                .map_err(Self::api_error),
+            Err(e) => Err(e),
+        };
 
-        Ok(text)
+        if completed.is_err() {
+            let aborted = client
+                .abort_multipart_upload()
+                .bucket(bucket)
+                .key(&real_key)
+                .upload_id(&upload_id)
+                .send()
+                .await;
+            if let Err(e) = aborted {
+                warn!("Failed to abort multipart upload of '{}': {}", key, e);
+            }
+        }
+        completed
     }
 
-    // Bucket operations using AWS SDK
-    pub async fn create_bucket(
+    pub async fn download_object(operator: &Operator, key: &str) -> AppResult<Vec<u8>> {
+        let data = operator.read(key).await?;
+        RateLimiter::acquire_bandwidth(data.len() as u64).await;
+        Ok(data.to_vec())
+    }
+
+    /// Stream an object into `path` without holding it in memory, fetching
+    /// ranges as `plan` says and calling `on_progress` with the running byte
+    /// count. Returns the bytes written and their MD5. Failures writing the
+    /// file come back as `IoError`.
+    pub async fn download_object_to_file(
+        operator: &Operator,
+        key: &str,
+        path: &Path,
+        plan: &TransferPlan,
+        mut on_progress: impl FnMut(u64),
+    ) -> AppResult<(u64, String)> {
+        let reader = operator
+            .reader_with(key)
+            .chunk(plan.part_size as usize)
+            .concurrent(plan.concurrency)
+            .await?;
+        let mut stream = reader.into_bytes_stream(..).await?;
+
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut hasher = Md5::new();
+        let mut written: u64 = 0;
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk
+                .map_err(|e| AppError::S3Error(format!("Download of '{}' failed: {}", key, e)))?;
+            RateLimiter::acquire_bandwidth(chunk.len() as u64).await;
+
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+            on_progress(written);
+        }
+
+        file.flush().await?;
+        Ok((written, format!("{:x}", hasher.finalize())))
+    }
+
+    /// Stream the first `length` bytes of an object into the file at `path`
+    /// without holding them in memory. Returns the bytes written.
+    pub async fn download_head_to_file(
+        operator: &Operator,
+        key: &str,
+        length: u64,
+        path: &Path,
+    ) -> AppResult<u64> {
+        let reader = operator.reader(key).await?;
+        let mut stream = reader.into_bytes_stream(0..length).await?;
+
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut written: u64 = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk
+                .map_err(|e| AppError::S3Error(format!("Download of '{}' failed: {}", key, e)))?;
+            RateLimiter::acquire_bandwidth(chunk.len() as u64).await;
+
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+
+        file.flush().await?;
+        Ok(written)
+    }
+
+    /// Read part of an object, e.g. its leading bytes for type sniffing.
+    pub async fn read_range(
+        operator: &Operator,
+        key: &str,
+        range: std::ops::Range<u64>,
+    ) -> AppResult<Vec<u8>> {
+        let reader = operator.reader(key).await?;
+        let data = reader.read(range).await?;
+        RateLimiter::acquire_bandwidth(data.len() as u64).await;
+        Ok(data.to_vec())
+    }
+
+    /// The object's ETag if it is the MD5 of its content. Multipart ETags
+    /// ("<hash>-<parts>") never are, and neither are those of objects
+    /// encrypted with SSE-KMS or SSE-C, which takes a HEAD request to tell.
+    pub async fn content_md5(
         connection: &S3ConnectionWithSecret,
-        bucket_name: &str,
-        region: Option<&str>,
+        bucket: &str,
+        object: &S3Object,
+    ) -> AppResult<Option<String>> {
+        let etag = object
+            .etag
+            .as_deref()
+            .map(|e| e.trim_matches('"'))
+            .filter(|e| !e.contains('-'));
+        let Some(etag) = etag else {
+            return Ok(None);
+        };
+
+        let client = Self::create_s3_client(connection).await;
+        Self::throttle(connection, RequestKind::Get).await;
+        let head = client
+            .head_object()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, &object.key)?)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        let kms = head
+            .server_side_encryption()
+            .is_some_and(|sse| *sse != ServerSideEncryption::Aes256);
+        if kms || head.sse_customer_algorithm().is_some() {
+            debug!(
+                "'{}' is encrypted with a customer or KMS key, its ETag isn't an MD5",
+                object.key
+            );
+            return Ok(None);
+        }
+        Ok(Some(etag.to_string()))
+    }
+
+    /// Check a download's size and, when `md5` is known (see
+    /// `content_md5`), the digest that `digest` produces against it.
+    pub fn verify_digest(
+        expected: &S3Object,
+        size: u64,
+        md5: Option<&str>,
+        digest: impl FnOnce() -> String,
     ) -> AppResult<()> {
+        if size != expected.size {
+            return Err(AppError::IntegrityError(format!(
+                "'{}' size mismatch: expected {} bytes, got {}",
+                expected.key, expected.size, size
+            )));
+        }
+
+        if let Some(md5) = md5 {
+            let digest = digest();
+            if !digest.eq_ignore_ascii_case(md5) {
+                return Err(AppError::IntegrityError(format!(
+                    "'{}' checksum mismatch: expected ETag {}, got {}",
+                    expected.key, md5, digest
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hex MD5 of `data`, comparable with single-part ETags.
+    pub fn content_digest(data: &[u8]) -> String {
+        format!("{:x}", Md5::digest(data))
+    }
+
+    /// Index objects under `prefix` by (size, MD5 ETag) so identical local
+    /// content can be found regardless of key. Multipart objects are left out
+    /// since their ETag isn't a content digest. Stops early, leaving the
+    /// index partial, once `budget` runs out.
+    pub async fn content_index(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        budget: &mut ScanBudget,
+    ) -> AppResult<HashMap<(u64, String), String>> {
+        let prefix = if prefix.is_empty() || prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{}/", prefix)
+        };
         let client = Self::create_s3_client(connection).await;
+        let mut pages = std::pin::pin!(Self::list_object_pages(
+            &client, connection, bucket, &prefix
+        )?);
+
+        let mut index = HashMap::new();
+        while let Some(page) = pages.try_next().await? {
+            if let Some(cutoff) = budget.exhausted() {
+                warn!(
+                    "Stopped indexing '{}/{}' after {} objects ({:?})",
+                    bucket,
+                    prefix,
+                    budget.scanned(),
+                    cutoff
+                );
+                break;
+            }
+            budget.charge(page.len());
+            index.extend(page.into_iter().filter_map(|object| {
+                let etag = object.etag.as_deref()?.trim_matches('"').to_lowercase();
+                if etag.contains('-') || object.size == 0 {
+                    return None;
+                }
+                Some(((object.size, etag), object.key))
+            }));
+        }
 
-        let region_str = region.unwrap_or(&connection.region);
+        Ok(index)
+    }
 
-        // For us-east-1, don't specify LocationConstraint
-        let result = if region_str == "us-east-1" {
-            client.create_bucket().bucket(bucket_name).send().await
+    pub async fn delete_object(operator: &Operator, key: &str) -> AppResult<()> {
+        operator.delete(key).await?;
+        Ok(())
+    }
+
+    /// Delete `keys` with batched DeleteObjects calls sized for the
+    /// provider, reporting each key's outcome. Gateways without the batch
+    /// API fall back to one request per key.
+    pub async fn delete_keys(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        keys: Vec<String>,
+    ) -> AppResult<BatchResult> {
+        Self::delete_keys_with(
+            connection,
+            bucket,
+            keys,
+            DELETE_CONCURRENCY,
+            &AtomicBool::new(false),
+            |_, _| {},
+        )
+        .await
+    }
+
+    /// `delete_keys` with `concurrency` batches in flight, calling
+    /// `on_batch` with the running result and progress as each one ends.
+    /// Batches not yet started once `cancelled` is set are skipped.
+    pub async fn delete_keys_with(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        keys: Vec<String>,
+        concurrency: usize,
+        cancelled: &AtomicBool,
+        mut on_batch: impl FnMut(&BatchResult, BatchProgress),
+    ) -> AppResult<BatchResult> {
+        let client = Self::create_s3_client(connection).await;
+        let mut result = BatchResult::default();
+        let total = keys.len() as u64;
+
+        let mut scoped: Vec<(String, String)> = Vec::with_capacity(keys.len());
+        for key in keys {
+            match Self::scoped_key(connection, &key) {
+                Ok(real_key) => scoped.push((key, real_key)),
+                Err(e) => result.fail(key, &e),
+            }
+        }
+
+        let batch_supported = AtomicBool::new(true);
+        let batches: Vec<&[(String, String)]> = scoped
+            .chunks(connection.max_delete_batch())
+            .collect();
+        let batch_count = batches.len();
+
+        let mut deletes = stream::iter(batches)
+            .map(|batch| {
+                let client = &client;
+                let batch_supported = &batch_supported;
+                async move {
+                    if cancelled.load(Ordering::Relaxed) {
+                        let mut skipped = BatchResult::default();
+                        for (key, _) in batch {
+                            skipped.skip(key.clone());
+                        }
+                        return skipped;
+                    }
+                    Self::delete_chunk(client, connection, bucket, batch, batch_supported).await
+                }
+            })
+            .buffer_unordered(concurrency.max(1));
+
+        let mut batches_completed = 0;
+        while let Some(batch_result) = deletes.next().await {
+            result.merge(batch_result);
+            batches_completed += 1;
+            on_batch(
+                &result,
+                BatchProgress {
+                    total,
+                    batches_completed,
+                    batch_count,
+                },
+            );
+        }
+
+        Ok(result)
+    }
+
+    /// Delete one batch, switching every later batch to single deletes the
+    /// first time the provider turns out not to support DeleteObjects.
+    async fn delete_chunk(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        batch: &[(String, String)],
+        batch_supported: &AtomicBool,
+    ) -> BatchResult {
+        let mut result = BatchResult::default();
+
+        if batch_supported.load(Ordering::Relaxed) {
+            match Self::delete_batch(client, connection, bucket, batch).await {
+                Ok(failures) => {
+                    for (key, real_key) in batch {
+                        match failures.get(real_key) {
+                            Some(e) => result.fail(key.clone(), e),
+                            None => result.succeed(key.clone()),
+                        }
+                    }
+                    return result;
+                }
+                Err(AppError::NotSupported(_)) => {
+                    if batch_supported.swap(false, Ordering::Relaxed) {
+                        debug!(
+                            "'{}' has no DeleteObjects, deleting one key at a time",
+                            bucket
+                        );
+                    }
+                }
+                Err(e) => {
+                    for (key, _) in batch {
+                        result.fail(key.clone(), &e);
+                    }
+                    return result;
+                }
+            }
+        }
+
+        for (key, real_key) in batch {
+            Self::throttle(connection, RequestKind::Delete).await;
+            match client
+                .delete_object()
+                .bucket(bucket)
+                .key(real_key)
+                .send()
+                .await
+            {
+                Ok(_) => result.succeed(key.clone()),
+                Err(e) => result.fail(key.clone(), &Self::api_error(e)),
+            }
+        }
+
+        result
+    }
+
+    async fn delete_batch(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        batch: &[(String, String)],
+    ) -> AppResult<HashMap<String, AppError>> {
+        let objects = batch
+            .iter()
+            .map(|(_, real_key)| ObjectIdentifier::builder().key(real_key).build())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+        let delete = Delete::builder()
+            .set_objects(Some(objects))
+            .quiet(true)
+            .build()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+        Self::throttle(connection, RequestKind::Delete).await;
+        let output = client
+            .delete_objects()
+            .bucket(bucket)
+            .delete(delete)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
+        Ok(output
+            .errors()
+            .iter()
+            .map(|e| {
+                let message = format!(
+                    "{}: {}",
+                    e.code().unwrap_or("Error"),
+                    e.message().unwrap_or("delete failed")
+                );
+                (
+                    e.key().unwrap_or_default().to_string(),
+                    AppError::S3Error(message),
+                )
+            })
+            .collect())
+    }
+
+    /// Delete specific versions, given as (key, version id), with batched
+    /// DeleteObjects calls sized for the provider. Gateways without the
+    /// batch API fall back to one request per version. Outcomes are
+    /// reported as `key?versionId=...`.
+    pub async fn delete_versions(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        versions: &[(String, String)],
+    ) -> BatchResult {
+        let client = Self::create_s3_client(connection).await;
+        let label = |key: &str, version_id: &str| format!("{}?versionId={}", key, version_id);
+        let mut result = BatchResult::default();
+        let mut batch_supported = true;
+
+        for batch in versions.chunks(connection.max_delete_batch()) {
+            if batch_supported {
+                match Self::delete_version_batch(&client, connection, bucket, batch).await {
+                    Ok(failures) => {
+                        for (key, version_id) in batch {
+                            match failures.get(&(key.clone(), version_id.clone())) {
+                                Some(e) => result.fail(label(key, version_id), e),
+                                None => result.succeed(label(key, version_id)),
+                            }
+                        }
+                        continue;
+                    }
+                    Err(AppError::NotSupported(_)) => {
+                        debug!(
+                            "'{}' has no DeleteObjects, deleting one version at a time",
+                            bucket
+                        );
+                        batch_supported = false;
+                    }
+                    Err(e) => {
+                        for (key, version_id) in batch {
+                            result.fail(label(key, version_id), &e);
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            for (key, version_id) in batch {
+                match Self::delete_object_version(connection, bucket, key, version_id).await {
+                    Ok(()) => result.succeed(label(key, version_id)),
+                    Err(e) => result.fail(label(key, version_id), &e),
+                }
+            }
+        }
+
+        result
+    }
+
+    /// One DeleteObjects call for `batch`, returning the failures keyed by
+    /// (key, version id) as given.
+    async fn delete_version_batch(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        batch: &[(String, String)],
+    ) -> AppResult<HashMap<(String, String), AppError>> {
+        // The response names the scoped key, so map it back
+        let mut keys = HashMap::new();
+        let mut objects = Vec::with_capacity(batch.len());
+        for (key, version_id) in batch {
+            let real_key = Self::scoped_key(connection, key)?;
+            objects.push(
+                ObjectIdentifier::builder()
+                    .key(&real_key)
+                    .version_id(version_id)
+                    .build()
+                    .map_err(|e| AppError::S3Error(e.to_string()))?,
+            );
+            keys.insert(real_key, key.clone());
+        }
+        let delete = Delete::builder()
+            .set_objects(Some(objects))
+            .quiet(true)
+            .build()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+        Self::throttle(connection, RequestKind::Delete).await;
+        let output = client
+            .delete_objects()
+            .bucket(bucket)
+            .delete(delete)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
+        Ok(output
+            .errors()
+            .iter()
+            .map(|e| {
+                let real_key = e.key().unwrap_or_default();
+                let key = keys
+                    .get(real_key)
+                    .cloned()
+                    .unwrap_or_else(|| real_key.to_string());
+                let message = format!(
+                    "{}: {}",
+                    e.code().unwrap_or("Error"),
+                    e.message().unwrap_or("delete failed")
+                );
+                (
+                    (key, e.version_id().unwrap_or("null").to_string()),
+                    AppError::S3Error(message),
+                )
+            })
+            .collect())
+    }
+
+    /// Every object under `prefix`, folder markers included, one
+    /// ListObjectsV2 page (up to 1000 keys) at a time. Callers that only
+    /// need to look at each object once should use this rather than
+    /// `list_all_objects_recursive`, so huge buckets never sit in memory whole.
+    pub fn list_object_pages<'a>(
+        client: &'a S3Client,
+        connection: &'a S3ConnectionWithSecret,
+        bucket: &'a str,
+        prefix: &str,
+    ) -> AppResult<impl Stream<Item = AppResult<Vec<S3Object>>> + 'a> {
+        let scoped_prefix = Self::scoped_key(connection, prefix)?;
+        let root = connection.root_prefix.as_deref().unwrap_or_default();
+
+        // `None` once the last page has been read
+        let start: Option<Option<String>> = Some(None);
+        Ok(stream::try_unfold(start, move |token| {
+            let scoped_prefix = scoped_prefix.clone();
+            async move {
+                let Some(token) = token else {
+                    return Ok(None);
+                };
+
+                Self::throttle(connection, RequestKind::List).await;
+                let result = client
+                    .list_objects_v2()
+                    .bucket(bucket)
+                    .prefix(scoped_prefix)
+                    .set_continuation_token(token)
+                    .send()
+                    .await
+                    .map_err(Self::api_error)?;
+
+                let page = result
+                    .contents()
+                    .iter()
+                    .map(|object| Self::listed_object(object, root))
+                    .collect::<Vec<_>>();
+
+                let next = (result.is_truncated() == Some(true))
+                    .then(|| {
+                        result
+                            .next_continuation_token()
+                            .map(|t| Some(t.to_string()))
+                    })
+                    .flatten();
+                Ok(Some((page, next)))
+            }
+        }))
+    }
+
+    /// Every entry under `prefix`, folder markers included, read a page at a
+    /// time until the listing ends or `budget` runs out. A cut-short listing
+    /// leaves the cap that stopped it in `budget.cutoff()`.
+    pub async fn list_objects_within(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        budget: &mut ScanBudget,
+    ) -> AppResult<Vec<S3Object>> {
+        let client = Self::create_s3_client(connection).await;
+        let mut pages = std::pin::pin!(Self::list_object_pages(
+            &client, connection, bucket, prefix
+        )?);
+
+        let mut objects = Vec::new();
+        while let Some(page) = pages.try_next().await? {
+            if let Some(cutoff) = budget.exhausted() {
+                warn!(
+                    "Stopped listing '{}/{}' after {} objects ({:?})",
+                    bucket,
+                    prefix,
+                    budget.scanned(),
+                    cutoff
+                );
+                break;
+            }
+            budget.charge(page.len());
+            objects.extend(page);
+        }
+        Ok(objects)
+    }
+
+    /// A ListObjectsV2 entry as seen through the connection's root prefix.
+    fn listed_object(object: &SdkObject, root: &str) -> S3Object {
+        let key = object.key().unwrap_or_default();
+        let key = key.strip_prefix(root).unwrap_or(key).to_string();
+        S3Object {
+            is_directory: key.ends_with('/'),
+            key,
+            size: object.size().unwrap_or(0) as u64,
+            last_modified: object.last_modified().map(|d| d.secs()).unwrap_or(0),
+            etag: object.e_tag().map(|s| s.to_string()),
+            content_type: None,
+            child_count: None,
+        }
+    }
+
+    /// Objects whose keys start with `stem` at the same folder level, i.e.
+    /// without descending into subfolders that happen to share it.
+    pub async fn list_siblings(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        stem: &str,
+    ) -> AppResult<Vec<S3Object>> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_stem = Self::scoped_key(connection, stem)?;
+        let root = connection.root_prefix.as_deref().unwrap_or_default();
+
+        let mut siblings = Vec::new();
+        let mut token = None;
+        loop {
+            Self::throttle(connection, RequestKind::List).await;
+            let result = client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(&scoped_stem)
+                .delimiter("/")
+                .set_continuation_token(token)
+                .send()
+                .await
+                .map_err(Self::api_error)?;
+
+            siblings.extend(
+                result
+                    .contents()
+                    .iter()
+                    .map(|object| Self::listed_object(object, root)),
+            );
+
+            token = result
+                .next_continuation_token()
+                .filter(|_| result.is_truncated() == Some(true))
+                .map(str::to_string);
+            if token.is_none() {
+                return Ok(siblings);
+            }
+        }
+    }
+
+    /// Delete every object under `prefix`, folder placeholders included,
+    /// in DeleteObjects batches as `delete_keys_with` does. Batches start
+    /// as the listing pages arrive, so the prefix is never held in memory
+    /// whole and `total` in the progress counts the keys listed so far.
+    pub async fn delete_prefix(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        concurrency: usize,
+        cancelled: &AtomicBool,
+        mut on_batch: impl FnMut(&BatchResult, BatchProgress),
+    ) -> AppResult<BatchResult> {
+        let prefix = prefix.trim_start_matches('/');
+        if prefix.trim_matches('/').is_empty() {
+            return Err(AppError::ConfigError(
+                "Refusing to delete the whole bucket as a prefix".into(),
+            ));
+        }
+        let prefix = if prefix.ends_with('/') {
+            prefix.to_string()
         } else {
-            use aws_sdk_s3::types::{BucketLocationConstraint, CreateBucketConfiguration};
+            format!("{}/", prefix)
+        };
+        debug!("Deleting everything under '{}/{}'", bucket, prefix);
 
-            let constraint = BucketLocationConstraint::from(region_str);
-            let cfg = CreateBucketConfiguration::builder()
-                .location_constraint(constraint)
-                .build();
+        let client = Self::create_s3_client(connection).await;
+        let batch_size = connection.max_delete_batch();
+        let batch_supported = AtomicBool::new(true);
+        let mut result = BatchResult::default();
+        // Bumped as batches are scheduled, read as they complete
+        let listed = Cell::new(0u64);
+        let batch_count = Cell::new(0usize);
+
+        let batches = Self::list_object_pages(&client, connection, bucket, &prefix)?
+            .map_ok(|page| {
+                let batches: Vec<AppResult<Vec<S3Object>>> =
+                    page.chunks(batch_size).map(|b| Ok(b.to_vec())).collect();
+                stream::iter(batches)
+            })
+            .try_flatten()
+            // Stop listing once cancelled; batches already queued are skipped
+            .take_while(|_| std::future::ready(!cancelled.load(Ordering::Relaxed)));
+
+        let deletes = batches
+            .map(|batch| {
+                if let Ok(batch) = &batch {
+                    listed.set(listed.get() + batch.len() as u64);
+                    batch_count.set(batch_count.get() + 1);
+                }
+                let client = &client;
+                let batch_supported = &batch_supported;
+                async move {
+                    let mut scoped = Vec::new();
+                    for object in batch? {
+                        let real_key = Self::scoped_key(connection, &object.key)?;
+                        scoped.push((object.key, real_key));
+                    }
+                    if cancelled.load(Ordering::Relaxed) {
+                        let mut skipped = BatchResult::default();
+                        for (key, _) in scoped {
+                            skipped.skip(key);
+                        }
+                        return Ok(skipped);
+                    }
+                    Ok::<_, AppError>(
+                        Self::delete_chunk(client, connection, bucket, &scoped, batch_supported)
+                            .await,
+                    )
+                }
+            })
+            .buffer_unordered(concurrency.max(1));
+        let mut deletes = std::pin::pin!(deletes);
+
+        let mut batches_completed = 0;
+        while let Some(batch_result) = deletes.next().await {
+            result.merge(batch_result?);
+            batches_completed += 1;
+            on_batch(
+                &result,
+                BatchProgress {
+                    total: listed.get(),
+                    batches_completed,
+                    batch_count: batch_count.get(),
+                },
+            );
+        }
+
+        Ok(result)
+    }
+
+    pub async fn get_object_details(operator: &Operator, key: &str) -> AppResult<S3Object> {
+        let meta = operator.stat(key).await?;
+
+        Ok(S3Object {
+            key: key.to_string(),
+            size: meta.content_length(),
+            last_modified: meta.last_modified().map(|t| t.timestamp()).unwrap_or(0),
+            etag: meta.etag().map(|s| s.to_string()),
+            content_type: meta.content_type().map(|s| s.to_string()),
+            is_directory: meta.is_dir(),
+            child_count: None,
+        })
+    }
+
+    /// Check `path` is usable as a folder and return its key with the
+    /// trailing slash. Rejects empty, `.` and `..` segments and control
+    /// characters, which S3 accepts but most tools choke on.
+    pub fn folder_key(path: &str) -> AppResult<String> {
+        let trimmed = path.trim_end_matches('/');
+        let invalid = |reason: &str| {
+            AppError::ConfigError(format!("Invalid folder name '{}': {}", path, reason))
+        };
+
+        if trimmed.is_empty() {
+            return Err(invalid("name is empty"));
+        }
+        if trimmed.starts_with('/') {
+            return Err(invalid("must not start with '/'"));
+        }
+        if trimmed.chars().any(char::is_control) {
+            return Err(invalid("contains control characters"));
+        }
+        for segment in trimmed.split('/') {
+            match segment {
+                "" => return Err(invalid("contains an empty path segment")),
+                "." | ".." => return Err(invalid("'.' and '..' are not allowed")),
+                _ => {}
+            }
+        }
+
+        let key = format!("{}/", trimmed);
+        if key.len() > MAX_KEY_LENGTH {
+            return Err(invalid("longer than 1024 bytes"));
+        }
+        Ok(key)
+    }
+
+    /// Create a folder marker at `path`. Fails with `AlreadyExists` when the
+    /// folder is already there, marker or not, and with `Conflict` when an
+    /// object has the folder's name.
+    pub async fn create_folder(operator: &Operator, path: &str) -> AppResult<()> {
+        let folder_path = Self::folder_key(path)?;
+        let object_path = folder_path.trim_end_matches('/');
+
+        if operator.exists(object_path).await? {
+            return Err(AppError::Conflict(format!(
+                "an object named '{}' already exists",
+                object_path
+            )));
+        }
+
+        // Anything under the prefix means the folder already shows up
+        let mut lister = operator.lister_with(&folder_path).limit(1).await?;
+        if lister.try_next().await?.is_some() {
+            return Err(AppError::AlreadyExists(folder_path));
+        }
+
+        // Create an empty object with trailing slash to represent a folder
+        operator.write(&folder_path, Vec::<u8>::new()).await?;
+        Ok(())
+    }
+
+    pub async fn get_presigned_url(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        expires_in_secs: u64,
+        response_content_type: Option<&str>,
+    ) -> AppResult<String> {
+        let client = Self::create_s3_client(connection).await;
+
+        let presigning_config = PresigningConfig::builder()
+            .expires_in(Duration::from_secs(expires_in_secs))
+            .build()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+        let presigned_request = client
+            .get_object()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .set_response_content_type(response_content_type.map(str::to_string))
+            .presigned(presigning_config)
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(presigned_request.uri().to_string())
+    }
+
+    /// Presign a PutObject for `key`. A content type or length, when given,
+    /// is signed too, so the upload has to match it.
+    pub async fn get_presigned_upload_url(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        expires_in_secs: u64,
+        content_type: Option<&str>,
+        content_length: Option<u64>,
+    ) -> AppResult<PresignedUpload> {
+        let client = Self::create_s3_client(connection).await;
+
+        let presigning_config = PresigningConfig::builder()
+            .expires_in(Duration::from_secs(expires_in_secs))
+            .build()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+        let presigned_request = client
+            .put_object()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .set_content_type(content_type.map(str::to_string))
+            .set_content_length(content_length.map(|l| l as i64))
+            .presigned(presigning_config)
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(PresignedUpload {
+            url: presigned_request.uri().to_string(),
+            method: presigned_request.method().to_string(),
+            headers: presigned_request
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            expires_at: chrono::Utc::now().timestamp() + expires_in_secs as i64,
+        })
+    }
+
+    /// Presigned GET URLs for many keys, signed with one client.
+    pub async fn get_presigned_urls(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        keys: &[String],
+        expires_in_secs: u64,
+    ) -> AppResult<Vec<String>> {
+        let client = Self::create_s3_client(connection).await;
+
+        let mut urls = Vec::with_capacity(keys.len());
+        for key in keys {
+            let presigning_config = PresigningConfig::builder()
+                .expires_in(Duration::from_secs(expires_in_secs))
+                .build()
+                .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+            let presigned_request = client
+                .get_object()
+                .bucket(bucket)
+                .key(Self::scoped_key(connection, key)?)
+                .presigned(presigning_config)
+                .await
+                .map_err(Self::api_error)?;
+            urls.push(presigned_request.uri().to_string());
+        }
+
+        Ok(urls)
+    }
+
+    /// Replace an object's tag set.
+    pub async fn put_object_tags(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        tags: &HashMap<String, String>,
+    ) -> AppResult<()> {
+        let client = Self::create_s3_client(connection).await;
+
+        let tag_set = tags
+            .iter()
+            .map(|(k, v)| Tag::builder().key(k).value(v).build())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+        let tagging = Tagging::builder()
+            .set_tag_set(Some(tag_set))
+            .build()
+            .map_err(|e| AppError::S3Error(e.to_string()))?;
+
+        Self::throttle(connection, RequestKind::Put).await;
+        client
+            .put_object_tagging()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .tagging(tagging)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
+        Ok(())
+    }
+
+    /// Unsigned HTTPS URL for an object, which only works when the object
+    /// is publicly readable.
+    pub fn public_url(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+    ) -> AppResult<String> {
+        let path = SiteService::href(&Self::scoped_key(connection, key)?);
+        let endpoint = connection.endpoint_for_region(&connection.region);
+
+        if endpoint.is_empty() {
+            return Ok(format!(
+                "https://{}.s3.{}.amazonaws.com/{}",
+                bucket, connection.region, path
+            ));
+        }
+
+        let (scheme, host) = match endpoint.split_once("://") {
+            Some((scheme, host)) => (scheme, host),
+            None if connection.use_ssl => ("https", endpoint.as_str()),
+            None => ("http", endpoint.as_str()),
+        };
+        let host = host.trim_end_matches('/');
+
+        if connection.use_path_style {
+            Ok(format!("{}://{}/{}/{}", scheme, host, bucket, path))
+        } else {
+            Ok(format!("{}://{}.{}/{}", scheme, bucket, host, path))
+        }
+    }
+
+    /// Why a URL for this connection can't be presigned, if it can't.
+    pub fn presign_unavailable(connection: &S3ConnectionWithSecret) -> Option<&'static str> {
+        if connection.access_key.is_empty() {
+            Some("anonymous connections have no credentials to sign with")
+        } else if connection.signature_version == SignatureVersion::V2 {
+            Some("presigned URLs are SigV4-only and this connection uses SigV2")
+        } else {
+            None
+        }
+    }
+
+    /// Ask for the first byte of an object and check the server answers
+    /// with a partial response.
+    pub async fn supports_range(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+    ) -> AppResult<bool> {
+        let client = Self::create_s3_client(connection).await;
+
+        let result = client
+            .get_object()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .range("bytes=0-0")
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(result.content_range().is_some())
+    }
+
+    pub async fn get_object_content_as_text(
+        operator: &Operator,
+        key: &str,
+        max_size: u64,
+    ) -> AppResult<String> {
+        let meta = operator.stat(key).await?;
+        let size = meta.content_length();
+
+        if size > max_size {
+            return Err(AppError::S3Error(format!(
+                "File too large for text preview: {} bytes (max: {} bytes)",
+                size, max_size
+            )));
+        }
+
+        let data = operator.read(key).await?;
+        let text = String::from_utf8(data.to_vec())
+            .map_err(|e| AppError::S3Error(format!("Not a valid UTF-8 text file: {}", e)))?;
+
+        Ok(text)
+    }
+
+    // Bucket operations using AWS SDK
+    pub async fn create_bucket(
+        connection: &S3ConnectionWithSecret,
+        bucket_name: &str,
+        region: Option<&str>,
+    ) -> AppResult<()> {
+        let region_str = region.unwrap_or(&connection.region);
+
+        // Templated endpoints embed the region, so target the bucket's region directly
+        let client = Self::create_s3_client_for_region(connection, region_str).await;
+
+        // For us-east-1, don't specify LocationConstraint
+        let result = if region_str == "us-east-1" {
+            client.create_bucket().bucket(bucket_name).send().await
+        } else {
+            use aws_sdk_s3::types::{BucketLocationConstraint, CreateBucketConfiguration};
+
+            let constraint = BucketLocationConstraint::from(region_str);
+            let cfg = CreateBucketConfiguration::builder()
+                .location_constraint(constraint)
+                .build();
+
+            client
+                .create_bucket()
+                .bucket(bucket_name)
+                .create_bucket_configuration(cfg)
+                .send()
+                .await
+        };
+
+        result.map_err(Self::api_error)?;
+        Ok(())
+    }
+
+    pub async fn delete_bucket(
+        connection: &S3ConnectionWithSecret,
+        bucket_name: &str,
+    ) -> AppResult<()> {
+        let client = Self::create_s3_client(connection).await;
+
+        client
+            .delete_bucket()
+            .bucket(bucket_name)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(())
+    }
+
+    pub async fn get_bucket_location(
+        connection: &S3ConnectionWithSecret,
+        bucket_name: &str,
+    ) -> AppResult<Option<String>> {
+        let client = Self::create_s3_client(connection).await;
+
+        let result = client
+            .get_bucket_location()
+            .bucket(bucket_name)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
+        Ok(result.location_constraint().map(|l| l.as_str().to_string()))
+    }
+
+    /// Resolve the region a bucket lives in. Only AWS and templated endpoints
+    /// route by region; everything else keeps the connection's region.
+    pub async fn resolve_bucket_region(
+        connection: &S3ConnectionWithSecret,
+        bucket_name: &str,
+    ) -> String {
+        if connection.provider != S3Provider::Aws && connection.endpoint_template.is_none() {
+            return connection.region.clone();
+        }
+        if !connection.provider.supports(ProviderFeature::BucketLocation) {
+            return connection.region.clone();
+        }
+
+        match Self::get_bucket_location(connection, bucket_name).await {
+            // Legacy alias still returned for old eu-west-1 buckets
+            Ok(Some(location)) if location == "EU" => "eu-west-1".to_string(),
+            Ok(Some(location)) if !location.is_empty() => location,
+            Ok(_) if connection.provider == S3Provider::Aws => "us-east-1".to_string(),
+            Ok(_) => connection.region.clone(),
+            Err(e) => {
+                debug!(
+                    "Could not resolve region for bucket '{}', using '{}': {}",
+                    bucket_name, connection.region, e
+                );
+                connection.region.clone()
+            }
+        }
+    }
+
+    /// `x-amz-copy-source` value for a scoped key, percent-encoded so keys
+    /// with spaces, `+`, `?` or non-ASCII characters copy the right object.
+    fn copy_source(bucket: &str, scoped_key: &str, version_id: Option<&str>) -> String {
+        let source = format!(
+            "{}/{}",
+            bucket,
+            utf8_percent_encode(scoped_key, COPY_SOURCE_KEY)
+        );
+        match version_id {
+            Some(version_id) => format!(
+                "{}?versionId={}",
+                source,
+                utf8_percent_encode(version_id, NON_ALPHANUMERIC)
+            ),
+            None => source,
+        }
+    }
+
+    /// Server-side copy of one object. `size`, when the caller already
+    /// knows it, saves a HEAD; objects over 5 GiB are copied in parts.
+    pub async fn copy_object(
+        connection: &S3ConnectionWithSecret,
+        source_bucket: &str,
+        source_key: &str,
+        size: Option<u64>,
+        dest_bucket: &str,
+        dest_key: &str,
+    ) -> AppResult<()> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_source = Self::scoped_key(connection, source_key)?;
+        let scoped_dest = Self::scoped_key(connection, dest_key)?;
+
+        let size = match size {
+            Some(size) => size,
+            None => {
+                Self::throttle(connection, RequestKind::Get).await;
+                let head = client
+                    .head_object()
+                    .bucket(source_bucket)
+                    .key(&scoped_source)
+                    .send()
+                    .await
+                    .map_err(Self::api_error)?;
+                head.content_length().unwrap_or(0).max(0) as u64
+            }
+        };
+        if size > MAX_COPY_OBJECT_SIZE as u64 {
+            return Self::copy_object_in_parts(
+                &client,
+                connection,
+                (source_bucket, &scoped_source),
+                (dest_bucket, &scoped_dest),
+                None,
+            )
+            .await;
+        }
+
+        Self::throttle(connection, RequestKind::Put).await;
+        client
+            .copy_object()
+            .copy_source(Self::copy_source(source_bucket, &scoped_source, None))
+            .bucket(dest_bucket)
+            .key(&scoped_dest)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(())
+    }
+
+    /// Server-side copy within `bucket` that gives the copy its own content
+    /// type and cache control, as uploading the same file would, rather
+    /// than the source's headers and metadata.
+    pub async fn copy_object_as(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        source_key: &str,
+        size: u64,
+        dest_key: &str,
+        content_type: &str,
+        cache_control: Option<&str>,
+    ) -> AppResult<()> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_source = Self::scoped_key(connection, source_key)?;
+        let scoped_dest = Self::scoped_key(connection, dest_key)?;
+
+        if size > MAX_COPY_OBJECT_SIZE as u64 {
+            return Self::copy_object_in_parts(
+                &client,
+                connection,
+                (bucket, &scoped_source),
+                (bucket, &scoped_dest),
+                Some((content_type, cache_control)),
+            )
+            .await;
+        }
+
+        Self::throttle(connection, RequestKind::Put).await;
+        client
+            .copy_object()
+            .copy_source(Self::copy_source(bucket, &scoped_source, None))
+            .bucket(bucket)
+            .key(&scoped_dest)
+            .metadata_directive(MetadataDirective::Replace)
+            .content_type(content_type)
+            .set_cache_control(cache_control.map(str::to_string))
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(())
+    }
+
+    /// Copy an object too large for CopyObject with UploadPartCopy, carrying
+    /// its headers and metadata over the way CopyObject would, or replacing
+    /// them with `headers` (content type, cache control). The upload is
+    /// aborted if any part fails.
+    async fn copy_object_in_parts(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        (source_bucket, source_key): (&str, &str),
+        (dest_bucket, dest_key): (&str, &str),
+        headers: Option<(&str, Option<&str>)>,
+    ) -> AppResult<()> {
+        Self::throttle(connection, RequestKind::Get).await;
+        let source = client
+            .head_object()
+            .bucket(source_bucket)
+            .key(source_key)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+        let size = source.content_length().unwrap_or(0).max(0) as u64;
+        let part_size = COPY_PART_SIZE.max(size.div_ceil(MAX_UPLOAD_PARTS));
+        debug!(
+            "Copying '{}/{}' ({} bytes) in {} byte parts",
+            source_bucket, source_key, size, part_size
+        );
+
+        let create = client
+            .create_multipart_upload()
+            .bucket(dest_bucket)
+            .key(dest_key)
+            .set_storage_class(source.storage_class().cloned());
+        let create = match headers {
+            Some((content_type, cache_control)) => create
+                .content_type(content_type)
+                .set_cache_control(cache_control.map(str::to_string)),
+            None => create
+                .set_content_type(source.content_type().map(str::to_string))
+                .set_content_encoding(source.content_encoding().map(str::to_string))
+                .set_content_disposition(source.content_disposition().map(str::to_string))
+                .set_content_language(source.content_language().map(str::to_string))
+                .set_cache_control(source.cache_control().map(str::to_string))
+                .set_metadata(source.metadata().cloned()),
+        };
+        Self::throttle(connection, RequestKind::Put).await;
+        let created = create.send().await.map_err(Self::api_error)?;
+        let upload_id = created
+            .upload_id()
+            .ok_or_else(|| AppError::S3Error("CreateMultipartUpload returned no upload id".into()))?
+            .to_string();
+
+        let copy_source = Self::copy_source(source_bucket, source_key, None);
+        let ranges = (0..size.div_ceil(part_size)).map(|i| {
+            let start = i * part_size;
+            (i as i32 + 1, start, (start + part_size).min(size) - 1)
+        });
+        let parts = stream::iter(ranges)
+            .map(|(part_number, start, end)| {
+                let (copy_source, upload_id) = (&copy_source, &upload_id);
+                async move {
+                    Self::throttle(connection, RequestKind::Put).await;
+                    let part = client
+                        .upload_part_copy()
+                        .bucket(dest_bucket)
+                        .key(dest_key)
+                        .upload_id(upload_id)
+                        .part_number(part_number)
+                        .copy_source(copy_source)
+                        .copy_source_range(format!("bytes={}-{}", start, end))
+                        .send()
+                        .await
+                        .map_err(Self::api_error)?;
+                    Ok::<_, AppError>(
+                        CompletedPart::builder()
+                            .set_e_tag(
+                                part.copy_part_result()
+                                    .and_then(|r| r.e_tag())
+                                    .map(str::to_string),
+                            )
+                            .part_number(part_number)
+                            .build(),
+                    )
+                }
+            })
+            .buffer_unordered(TransferTuner::concurrency(COPY_CONCURRENCY))
+            .try_collect::<Vec<_>>()
+            .await;
+
+        let completed = match parts {
+            Ok(mut parts) => {
+                parts.sort_by_key(|p| p.part_number());
+                client
+                    .complete_multipart_upload()
+                    .bucket(dest_bucket)
+                    .key(dest_key)
+                    .upload_id(&upload_id)
+                    .multipart_upload(
+                        CompletedMultipartUpload::builder()
+                            .set_parts(Some(parts))
+                            .build(),
+                    )
+                    .send()
+                    .await
+                    .map(|_| ())
+                    .map_err(Self::api_error)
+            }
+            Err(e) => Err(e),
+        };
+
+        if completed.is_err() {
+            let aborted = client
+                .abort_multipart_upload()
+                .bucket(dest_bucket)
+                .key(dest_key)
+                .upload_id(&upload_id)
+                .send()
+                .await;
+            if let Err(e) = aborted {
+                warn!("Failed to abort multipart copy to '{}': {}", dest_key, e);
+            }
+        }
+        completed
+    }
+
+    pub async fn rename_object(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        old_key: &str,
+        new_key: &str,
+    ) -> AppResult<()> {
+        // Copy to new location, then delete old
+        Self::copy_object(connection, bucket, old_key, None, bucket, new_key).await?;
+
+        // Through the SDK like the copy, so SigV2 connections can rename too
+        let client = Self::create_s3_client(connection).await;
+        Self::throttle(connection, RequestKind::Delete).await;
+        client
+            .delete_object()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, old_key)?)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(())
+    }
+
+    pub async fn head_bucket(
+        connection: &S3ConnectionWithSecret,
+        bucket_name: &str,
+    ) -> AppResult<bool> {
+        let client = Self::create_s3_client(connection).await;
+
+        match client.head_bucket().bucket(bucket_name).send().await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let err_str = e.to_string();
+                if err_str.contains("404") || err_str.contains("NotFound") {
+                    Ok(false)
+                } else {
+                    Err(AppError::S3Error(err_str))
+                }
+            }
+        }
+    }
+
+    pub async fn get_bucket_versioning(
+        connection: &S3ConnectionWithSecret,
+        bucket_name: &str,
+    ) -> AppResult<Option<String>> {
+        let client = Self::create_s3_client(connection).await;
+
+        let result = client
+            .get_bucket_versioning()
+            .bucket(bucket_name)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
+        Ok(result.status().map(|s| s.as_str().to_string()))
+    }
+
+    /// Predict what deleting `targets` will do. Current objects in a
+    /// versioned bucket only get a delete marker; specific versions under
+    /// a legal hold or unexpired retention can't be deleted at all.
+    pub async fn check_delete_protection(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        targets: Vec<DeleteTarget>,
+    ) -> AppResult<DeleteProtectionReport> {
+        let client = Self::create_s3_client(connection).await;
+
+        let object_lock_enabled = match client
+            .get_object_lock_configuration()
+            .bucket(bucket)
+            .send()
+            .await
+        {
+            Ok(result) => result
+                .object_lock_configuration()
+                .and_then(|c| c.object_lock_enabled())
+                .is_some(),
+            Err(e) if e.code() == Some("ObjectLockConfigurationNotFoundError") => false,
+            Err(e) => match Self::map_optional_api_error(e) {
+                AppError::NotSupported(_) => false,
+                e => return Err(e),
+            },
+        };
+
+        let versioning = if connection
+            .provider
+            .supports(ProviderFeature::BucketVersioning)
+        {
+            match Self::get_bucket_versioning(connection, bucket).await {
+                Ok(status) => status,
+                Err(AppError::NotSupported(_)) => None,
+                Err(e) => return Err(e),
+            }
+        } else {
+            None
+        };
+        let versioned = matches!(versioning.as_deref(), Some("Enabled" | "Suspended"));
+        let now = chrono::Utc::now().timestamp();
+
+        let checks: Vec<DeleteCheck> = stream::iter(targets)
+            .map(|target| {
+                let client = &client;
+                async move {
+                    let mut check = DeleteCheck {
+                        outcome: match (&target.version_id, versioned) {
+                            (None, true) => DeleteOutcome::DeleteMarker,
+                            _ => DeleteOutcome::Deleted,
+                        },
+                        key: target.key,
+                        version_id: target.version_id,
+                        retention_mode: None,
+                        retain_until: None,
+                        legal_hold: false,
+                        reason: None,
+                    };
+                    if !object_lock_enabled {
+                        return check;
+                    }
+
+                    Self::throttle(connection, RequestKind::Get).await;
+                    let head = match Self::scoped_key(connection, &check.key) {
+                        Ok(real_key) => client
+                            .head_object()
+                            .bucket(bucket)
+                            .key(real_key)
+                            .set_version_id(check.version_id.clone())
+                            .send()
+                            .await
+                            .map_err(Self::api_error),
+                        Err(e) => Err(e),
+                    };
+                    let head = match head {
+                        Ok(head) => head,
+                        Err(e) => {
+                            check.outcome = DeleteOutcome::Unknown;
+                            check.reason = Some(e.to_string());
+                            return check;
+                        }
+                    };
+
+                    check.retention_mode = head.object_lock_mode().map(|m| m.as_str().to_string());
+                    check.retain_until = head.object_lock_retain_until_date().map(|d| d.secs());
+                    check.legal_hold = head.object_lock_legal_hold_status()
+                        == Some(&ObjectLockLegalHoldStatus::On);
+
+                    // Only permanent deletes of a version are refused
+                    if check.version_id.is_some() {
+                        if check.legal_hold {
+                            check.outcome = DeleteOutcome::Blocked;
+                            check.reason = Some("under legal hold".to_string());
+                        } else if let Some(until) = check.retain_until.filter(|u| *u > now) {
+                            check.outcome = DeleteOutcome::Blocked;
+                            check.reason = Some(format!(
+                                "{} retention until {}",
+                                check.retention_mode.as_deref().unwrap_or("Object Lock"),
+                                chrono::DateTime::from_timestamp(until, 0)
+                                    .map(|d| d.to_rfc3339())
+                                    .unwrap_or_else(|| until.to_string())
+                            ));
+                        }
+                    }
+                    check
+                }
+            })
+            .buffer_unordered(8)
+            .collect()
+            .await;
+
+        let count = |outcome| checks.iter().filter(|c| c.outcome == outcome).count();
+        Ok(DeleteProtectionReport {
+            bucket: bucket.to_string(),
+            object_lock_enabled,
+            versioning,
+            blocked: count(DeleteOutcome::Blocked),
+            delete_markers: count(DeleteOutcome::DeleteMarker),
+            checks,
+        })
+    }
+
+    pub async fn probe_capabilities(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+    ) -> AppResult<ProviderCapabilities> {
+        let client = Self::create_s3_client(connection).await;
+        let provider = &connection.provider;
+
+        let versioning = if provider.supports(ProviderFeature::BucketVersioning) {
+            Self::probe_supported(client.get_bucket_versioning().bucket(bucket).send().await)?
+        } else {
+            Some(false)
+        };
+        let tagging =
+            Self::probe_supported(client.get_bucket_tagging().bucket(bucket).send().await)?;
+        let lifecycle = Self::probe_supported(
+            client
+                .get_bucket_lifecycle_configuration()
+                .bucket(bucket)
+                .send()
+                .await,
+        )?;
+        let object_lock = Self::probe_supported(
+            client
+                .get_object_lock_configuration()
+                .bucket(bucket)
+                .send()
+                .await,
+        )?;
+
+        // Transfer acceleration is AWS-only and S3 Select has no cheap probe
+        let acceleration = if *provider == S3Provider::Aws {
+            Self::probe_supported(
+                client
+                    .get_bucket_accelerate_configuration()
+                    .bucket(bucket)
+                    .send()
+                    .await,
+            )?
+        } else {
+            Some(false)
+        };
+        let select = matches!(provider, S3Provider::Aws | S3Provider::Minio);
+
+        Ok(ProviderCapabilities {
+            versioning,
+            tagging,
+            lifecycle,
+            object_lock,
+            select,
+            acceleration,
+            multipart: provider.multipart_limits(),
+        })
+    }
+
+    /// Put `key`, read it back and delete it, timing each step.
+    pub async fn write_probe(operator: &Operator, key: &str) -> Vec<ProbeStepResult> {
+        let payload = format!("baul write probe {}", chrono::Utc::now().to_rfc3339()).into_bytes();
+        let mut steps = Vec::new();
+
+        let started = Instant::now();
+        let put = operator.write(key, payload.clone()).await.map(|_| ());
+        let put_ok = put.is_ok();
+        steps.push(Self::probe_step(ProbeStep::Put, started, put));
+        if !put_ok {
+            return steps;
+        }
+
+        let started = Instant::now();
+        let read = match operator.read(key).await {
+            Ok(data) if data.to_vec() == payload => Ok(()),
+            Ok(data) => Err(AppError::IntegrityError(format!(
+                "read back {} bytes, wrote {}",
+                data.len(),
+                payload.len()
+            ))),
+            Err(e) => Err(e.into()),
+        };
+        steps.push(Self::probe_step(ProbeStep::Read, started, read));
+
+        let started = Instant::now();
+        let delete = operator.delete(key).await;
+        steps.push(Self::probe_step(ProbeStep::Delete, started, delete));
+
+        steps
+    }
+
+    fn probe_step<E: std::fmt::Display>(
+        step: ProbeStep,
+        started: Instant,
+        result: Result<(), E>,
+    ) -> ProbeStepResult {
+        ProbeStepResult {
+            step,
+            ok: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// Count and total the bucket's objects, stopping early with partial
+    /// totals when `limits` are reached.
+    pub async fn get_bucket_stats(
+        connection: &S3ConnectionWithSecret,
+        bucket_name: &str,
+        limits: ScanLimits,
+    ) -> AppResult<BucketStats> {
+        let client = Self::create_s3_client(connection).await;
+
+        let mut budget = limits.start();
+        let mut object_count: u64 = 0;
+        let mut total_size: u64 = 0;
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            Self::throttle(connection, RequestKind::List).await;
+            let mut request = client
+                .list_objects_v2()
+                .bucket(bucket_name)
+                .set_prefix(connection.root_prefix.clone());
+
+            if let Some(token) = continuation_token.take() {
+                request = request.continuation_token(token);
+            }
+
+            let result = request
+                .send()
+                .await
+                .map_err(Self::api_error)?;
+
+            for object in result.contents() {
+                object_count += 1;
+                total_size += object.size().unwrap_or(0) as u64;
+            }
+
+            budget.charge(result.contents().len());
+            if result.is_truncated() != Some(true) {
+                break;
+            }
+            if let Some(cutoff) = budget.exhausted() {
+                warn!(
+                    "Stopped counting '{}' after {} objects ({:?})",
+                    bucket_name, object_count, cutoff
+                );
+                break;
+            }
+            continuation_token = result.next_continuation_token().map(|s| s.to_string());
+        }
+
+        Ok(BucketStats {
+            name: bucket_name.to_string(),
+            object_count,
+            total_size,
+            stopped_early: budget.cutoff(),
+        })
+    }
+
+    /// Objects under `prefix` modified at or after `since` (unix seconds),
+    /// newest first. Uses listing timestamps only, no per-object requests,
+    /// and stops early once `budget` runs out.
+    pub async fn list_modified_since(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        since: i64,
+        budget: &mut ScanBudget,
+    ) -> AppResult<Vec<S3Object>> {
+        let listing = Self::list_objects_within(connection, bucket, prefix, budget).await?;
+
+        let mut changed: Vec<S3Object> = listing
+            .into_iter()
+            .filter(|o| !o.is_directory && o.last_modified >= since)
+            .collect();
+        changed.sort_by_key(|o| std::cmp::Reverse(o.last_modified));
+
+        Ok(changed)
+    }
+
+    /// Count objects under a prefix, giving up after `max_objects` so bulk
+    /// confirmations stay fast on huge prefixes.
+    pub async fn estimate_prefix(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        max_objects: u64,
+    ) -> AppResult<PrefixEstimate> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_prefix = Self::scoped_key(connection, prefix)?;
+
+        let mut estimate = PrefixEstimate {
+            prefix: prefix.to_string(),
+            object_count: 0,
+            total_size: 0,
+            is_complete: true,
+        };
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            Self::throttle(connection, RequestKind::List).await;
+            let mut request = client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(&scoped_prefix);
+
+            if let Some(token) = continuation_token.take() {
+                request = request.continuation_token(token);
+            }
+
+            let result = request
+                .send()
+                .await
+                .map_err(Self::api_error)?;
+
+            for object in result.contents() {
+                estimate.object_count += 1;
+                estimate.total_size += object.size().unwrap_or(0) as u64;
+            }
+
+            if result.is_truncated() != Some(true) {
+                break;
+            }
+            if estimate.object_count >= max_objects {
+                estimate.is_complete = false;
+                break;
+            }
+            continuation_token = result.next_continuation_token().map(|s| s.to_string());
+        }
+
+        Ok(estimate)
+    }
+
+    pub async fn get_object_metadata(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+    ) -> AppResult<ObjectMetadata> {
+        let client = Self::create_s3_client(connection).await;
+        Self::throttle(connection, RequestKind::Get).await;
+
+        let result = client
+            .head_object()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        let mut custom_metadata = HashMap::new();
+        if let Some(metadata) = result.metadata() {
+            for (k, v) in metadata {
+                custom_metadata.insert(k.clone(), v.clone());
+            }
+        }
+
+        // HEAD only returns checksums of single-part uploads, so ask
+        // GetObjectAttributes, which plenty of gateways don't implement
+        let checksum = match Self::get_object_checksum(connection, bucket, key).await {
+            Ok(checksum) => checksum,
+            Err(e) => {
+                debug!("No checksum for '{}/{}': {}", bucket, key, e);
+                None
+            }
+        };
+
+        Ok(ObjectMetadata {
+            key: key.to_string(),
+            size: result.content_length().unwrap_or(0) as u64,
+            last_modified: result.last_modified().map(|d| d.secs()),
+            etag: result.e_tag().map(|s| s.to_string()),
+            content_type: result.content_type().map(|s| s.to_string()),
+            content_encoding: result.content_encoding().map(|s| s.to_string()),
+            content_disposition: result.content_disposition().map(|s| s.to_string()),
+            content_language: result.content_language().map(|s| s.to_string()),
+            cache_control: result.cache_control().map(|s| s.to_string()),
+            storage_class: result.storage_class().map(|s| s.as_str().to_string()),
+            archive_status: result.archive_status().map(|s| s.as_str().to_string()),
+            restore: result.restore().map(|s| s.to_string()),
+            version_id: result.version_id().map(|s| s.to_string()),
+            custom_metadata,
+            checksum,
+        })
+    }
+
+    /// The checksum stored with an object, if it was uploaded with one.
+    pub async fn get_object_checksum(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+    ) -> AppResult<Option<ObjectChecksum>> {
+        if Self::lacks_object_attributes(&connection.id) {
+            return Err(AppError::NotSupported(
+                "GetObjectAttributes isn't implemented by this provider".to_string(),
+            ));
+        }
+        let client = Self::create_s3_client(connection).await;
+        Self::throttle(connection, RequestKind::Get).await;
+
+        let result = client
+            .get_object_attributes()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .object_attributes(SdkObjectAttributes::Checksum)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)
+            .inspect_err(|e| Self::note_object_attributes_error(&connection.id, e))?;
+
+        Ok(result.checksum().and_then(Self::object_checksum))
+    }
+
+    /// Size, checksum, part count and storage class in one
+    /// GetObjectAttributes call, falling back to HEAD on providers that
+    /// don't implement it.
+    pub async fn get_object_attributes(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+    ) -> AppResult<ObjectAttributes> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_key = Self::scoped_key(connection, key)?;
+
+        if !Self::lacks_object_attributes(&connection.id) {
+            Self::throttle(connection, RequestKind::Get).await;
+            let attributes = client
+                .get_object_attributes()
+                .bucket(bucket)
+                .key(&scoped_key)
+                .object_attributes(SdkObjectAttributes::Etag)
+                .object_attributes(SdkObjectAttributes::Checksum)
+                .object_attributes(SdkObjectAttributes::ObjectParts)
+                .object_attributes(SdkObjectAttributes::StorageClass)
+                .object_attributes(SdkObjectAttributes::ObjectSize)
+                .send()
+                .await;
+
+            match attributes {
+                Ok(result) => {
+                    return Ok(ObjectAttributes {
+                        key: key.to_string(),
+                        size: result.object_size().unwrap_or(0) as u64,
+                        last_modified: result.last_modified().map(|d| d.secs()),
+                        // Unlike HEAD, GetObjectAttributes leaves the quotes off
+                        etag: result
+                            .e_tag()
+                            .map(|e| format!("\"{}\"", e.trim_matches('"'))),
+                        storage_class: result.storage_class().map(|s| s.as_str().to_string()),
+                        checksum: result.checksum().and_then(Self::object_checksum),
+                        parts_count: result
+                            .object_parts()
+                            .and_then(|p| p.total_parts_count())
+                            .map(|n| n as u32),
+                        version_id: result.version_id().map(|s| s.to_string()),
+                        from_head: false,
+                    });
+                }
+                // Gateways answer the unknown subresource in all sorts of
+                // ways; a real problem with the key shows up again on the HEAD
+                Err(e) => {
+                    let e = Self::map_optional_api_error(e);
+                    debug!(
+                        "GetObjectAttributes failed for '{}/{}', using HEAD: {}",
+                        bucket, key, e
+                    );
+                    Self::note_object_attributes_error(&connection.id, &e);
+                }
+            }
+        }
+
+        Self::throttle(connection, RequestKind::Get).await;
+        let result = client
+            .head_object()
+            .bucket(bucket)
+            .key(&scoped_key)
+            .checksum_mode(ChecksumMode::Enabled)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        let checksum = Checksum::builder()
+            .set_checksum_crc32(result.checksum_crc32().map(str::to_string))
+            .set_checksum_crc32_c(result.checksum_crc32_c().map(str::to_string))
+            .set_checksum_sha1(result.checksum_sha1().map(str::to_string))
+            .set_checksum_sha256(result.checksum_sha256().map(str::to_string))
+            .set_checksum_type(result.checksum_type().cloned())
+            .build();
+
+        Ok(ObjectAttributes {
+            key: key.to_string(),
+            size: result.content_length().unwrap_or(0) as u64,
+            last_modified: result.last_modified().map(|d| d.secs()),
+            etag: result.e_tag().map(|s| s.to_string()),
+            storage_class: result.storage_class().map(|s| s.as_str().to_string()),
+            checksum: Self::object_checksum(&checksum),
+            parts_count: None,
+            version_id: result.version_id().map(|s| s.to_string()),
+            from_head: true,
+        })
+    }
+
+    /// Whether the connection's provider has already said it doesn't
+    /// implement GetObjectAttributes.
+    fn lacks_object_attributes(connection_id: &str) -> bool {
+        NO_OBJECT_ATTRIBUTES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(connection_id)
+    }
+
+    /// Remember a connection whose provider doesn't implement
+    /// GetObjectAttributes, so later lookups skip straight to HEAD. Other
+    /// errors may be specific to the key and aren't remembered.
+    fn note_object_attributes_error(connection_id: &str, error: &AppError) {
+        if matches!(error, AppError::NotSupported(_)) {
+            debug!(
+                "Not asking connection '{}' for object attributes again",
+                connection_id
+            );
+            NO_OBJECT_ATTRIBUTES
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(connection_id.to_string());
+        }
+    }
+
+    /// The strongest checksum present, as S3 only stores one per object.
+    fn object_checksum(checksum: &Checksum) -> Option<ObjectChecksum> {
+        let (algorithm, value) = [
+            (ChecksumAlgorithm::Sha256, checksum.checksum_sha256()),
+            (ChecksumAlgorithm::Sha1, checksum.checksum_sha1()),
+            (ChecksumAlgorithm::Crc32c, checksum.checksum_crc32_c()),
+            (ChecksumAlgorithm::Crc32, checksum.checksum_crc32()),
+        ]
+        .into_iter()
+        .find_map(|(algorithm, value)| Some((algorithm, value?)))?;
+
+        Some(ObjectChecksum {
+            algorithm,
+            value: value.to_string(),
+            checksum_type: checksum.checksum_type().map(|t| t.as_str().to_string()),
+        })
+    }
+
+    /// Rewrite an object's headers and custom metadata by copying it onto
+    /// itself with `MetadataDirective=REPLACE`. Whatever the update leaves
+    /// alone, including the storage class and KMS key, is carried over from
+    /// the current object; tags are copied as with any CopyObject.
+    pub async fn update_object_metadata(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        update: &MetadataUpdate,
+    ) -> AppResult<ObjectMetadata> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_key = Self::scoped_key(connection, key)?;
+
+        Self::throttle(connection, RequestKind::Get).await;
+        let current = client
+            .head_object()
+            .bucket(bucket)
+            .key(&scoped_key)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        if current.content_length().unwrap_or(0) > MAX_COPY_OBJECT_SIZE {
+            return Err(AppError::NotSupported(format!(
+                "'{}' is larger than the 5 GiB a metadata update can copy",
+                key
+            )));
+        }
+
+        let merge = |change: &Option<String>, current: Option<&str>| match change {
+            Some(value) if value.is_empty() => None,
+            Some(value) => Some(value.clone()),
+            None => current.map(str::to_string),
+        };
+        let metadata = match &update.metadata {
+            Some(metadata) => Self::metadata_headers(metadata)?,
+            None => current.metadata().cloned().unwrap_or_default(),
+        };
+
+        Self::throttle(connection, RequestKind::Put).await;
+        client
+            .copy_object()
+            .copy_source(Self::copy_source(bucket, &scoped_key, None))
+            .bucket(bucket)
+            .key(&scoped_key)
+            .metadata_directive(MetadataDirective::Replace)
+            .set_content_type(merge(&update.content_type, current.content_type()))
+            .set_cache_control(merge(&update.cache_control, current.cache_control()))
+            .set_content_disposition(merge(
+                &update.content_disposition,
+                current.content_disposition(),
+            ))
+            .set_content_encoding(current.content_encoding().map(str::to_string))
+            .set_content_language(current.content_language().map(str::to_string))
+            .set_metadata(Some(metadata))
+            .set_storage_class(current.storage_class().cloned())
+            .set_server_side_encryption(current.server_side_encryption().cloned())
+            .set_ssekms_key_id(current.ssekms_key_id().map(str::to_string))
+            .set_bucket_key_enabled(current.bucket_key_enabled())
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        Self::get_object_metadata(connection, bucket, key).await
+    }
+
+    /// Every version and delete marker under `prefix`, with keys as the
+    /// user sees them.
+    pub async fn list_versions(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+    ) -> AppResult<Vec<ObjectVersion>> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_prefix = Self::scoped_key(connection, prefix)?;
+        let root = connection.root_prefix.as_deref().unwrap_or_default();
+        let user_key = |key: Option<&str>| {
+            let key = key.unwrap_or_default();
+            key.strip_prefix(root).unwrap_or(key).to_string()
+        };
+
+        let mut versions = Vec::new();
+        let mut key_marker: Option<String> = None;
+        let mut version_id_marker: Option<String> = None;
+
+        loop {
+            Self::throttle(connection, RequestKind::List).await;
+            let result = client
+                .list_object_versions()
+                .bucket(bucket)
+                .prefix(&scoped_prefix)
+                .set_key_marker(key_marker.take())
+                .set_version_id_marker(version_id_marker.take())
+                .send()
+                .await
+                .map_err(Self::map_optional_api_error)?;
+
+            for version in result.versions() {
+                versions.push(Self::version_entry(version, user_key(version.key())));
+            }
+            for marker in result.delete_markers() {
+                versions.push(Self::delete_marker_entry(marker, user_key(marker.key())));
+            }
+
+            if result.is_truncated() == Some(true) {
+                key_marker = result.next_key_marker().map(|s| s.to_string());
+                version_id_marker = result.next_version_id_marker().map(|s| s.to_string());
+            } else {
+                break;
+            }
+        }
+
+        Ok(versions)
+    }
+
+    fn version_entry(version: &SdkObjectVersion, key: String) -> ObjectVersion {
+        ObjectVersion {
+            key,
+            version_id: version.version_id().map(|s| s.to_string()),
+            size: version.size().unwrap_or(0) as u64,
+            last_modified: version.last_modified().map(|d| d.secs()),
+            etag: version.e_tag().map(|s| s.to_string()),
+            storage_class: version.storage_class().map(|s| s.as_str().to_string()),
+            owner: version.owner().and_then(Self::owner_name),
+            is_latest: version.is_latest().unwrap_or(false),
+            is_delete_marker: false,
+        }
+    }
+
+    fn delete_marker_entry(marker: &DeleteMarkerEntry, key: String) -> ObjectVersion {
+        ObjectVersion {
+            key,
+            version_id: marker.version_id().map(|s| s.to_string()),
+            size: 0,
+            last_modified: marker.last_modified().map(|d| d.secs()),
+            etag: None,
+            storage_class: None,
+            owner: marker.owner().and_then(Self::owner_name),
+            is_latest: marker.is_latest().unwrap_or(false),
+            is_delete_marker: true,
+        }
+    }
+
+    /// One page of versions and delete markers directly under `prefix`,
+    /// with deeper keys rolled up into folders. Pass the returned markers
+    /// back to continue.
+    pub async fn list_object_versions(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        key_marker: Option<String>,
+        version_id_marker: Option<String>,
+        max_keys: i32,
+    ) -> AppResult<ListObjectVersionsResult> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_prefix = Self::scoped_key(connection, prefix)?;
+        let root = connection.root_prefix.as_deref().unwrap_or_default();
+        let user_key = |key: Option<&str>| {
+            let key = key.unwrap_or_default();
+            key.strip_prefix(root).unwrap_or(key).to_string()
+        };
+        let scoped_marker = key_marker
+            .map(|marker| Self::scoped_key(connection, &marker))
+            .transpose()?;
+
+        Self::throttle(connection, RequestKind::List).await;
+        let result = client
+            .list_object_versions()
+            .bucket(bucket)
+            .prefix(&scoped_prefix)
+            .delimiter("/")
+            .max_keys(max_keys)
+            .set_key_marker(scoped_marker)
+            .set_version_id_marker(version_id_marker)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
+        let mut versions: Vec<ObjectVersion> = result
+            .versions()
+            .iter()
+            .map(|v| Self::version_entry(v, user_key(v.key())))
+            .chain(
+                result
+                    .delete_markers()
+                    .iter()
+                    .map(|m| Self::delete_marker_entry(m, user_key(m.key()))),
+            )
+            .collect();
+        // Versions and delete markers come back in separate lists
+        versions.sort_by(|a, b| {
+            a.key
+                .cmp(&b.key)
+                .then_with(|| b.last_modified.cmp(&a.last_modified))
+        });
+
+        let is_truncated = result.is_truncated() == Some(true);
+        Ok(ListObjectVersionsResult {
+            versions,
+            folders: result
+                .common_prefixes()
+                .iter()
+                .map(|p| user_key(p.prefix()))
+                .collect(),
+            next_key_marker: is_truncated
+                .then(|| result.next_key_marker().map(|k| user_key(Some(k))))
+                .flatten(),
+            next_version_id_marker: is_truncated
+                .then(|| result.next_version_id_marker().map(|s| s.to_string()))
+                .flatten(),
+            is_truncated,
+        })
+    }
+
+    pub async fn delete_object_version(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> AppResult<()> {
+        let client = Self::create_s3_client(connection).await;
+
+        Self::throttle(connection, RequestKind::Delete).await;
+        client
+            .delete_object()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .version_id(version_id)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(())
+    }
+
+    /// Stream one version of an object to `path`, calling `on_progress`
+    /// with the running byte count. Returns the bytes written.
+    pub async fn download_object_version_to_file(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        path: &Path,
+        mut on_progress: impl FnMut(u64, u64),
+    ) -> AppResult<u64> {
+        let client = Self::create_s3_client(connection).await;
+
+        Self::throttle(connection, RequestKind::Get).await;
+        let mut result = client
+            .get_object()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .version_id(version_id)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+        let total = result.content_length().unwrap_or(0).max(0) as u64;
+
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut written: u64 = 0;
+
+        while let Some(chunk) = result.body.next().await {
+            let chunk = chunk.map_err(|e| {
+                AppError::S3Error(format!(
+                    "Download of '{}' version '{}' failed: {}",
+                    key, version_id, e
+                ))
+            })?;
+            RateLimiter::acquire_bandwidth(chunk.len() as u64).await;
+
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+            on_progress(written, total);
+        }
+        file.flush().await?;
+
+        if written != total {
+            return Err(AppError::IntegrityError(format!(
+                "'{}' version '{}' size mismatch: expected {} bytes, got {}",
+                key, version_id, total, written
+            )));
+        }
+        Ok(written)
+    }
+
+    /// Make an older version the latest again by copying it over the key.
+    /// The versions in between are kept.
+    pub async fn restore_object_version(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> AppResult<()> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_key = Self::scoped_key(connection, key)?;
+
+        Self::throttle(connection, RequestKind::Put).await;
+
+        client
+            .copy_object()
+            .copy_source(Self::copy_source(bucket, &scoped_key, Some(version_id)))
+            .bucket(bucket)
+            .key(&scoped_key)
+            .send()
+            .await
+            .map_err(Self::api_error)?;
+
+        Ok(())
+    }
+
+    /// Every version and delete marker of a single key, oldest first.
+    pub async fn get_object_history(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+    ) -> AppResult<Vec<ObjectVersion>> {
+        let client = Self::create_s3_client(connection).await;
+        let scoped_key = Self::scoped_key(connection, key)?;
+
+        let mut history = Vec::new();
+        let mut key_marker: Option<String> = None;
+        let mut version_id_marker: Option<String> = None;
+
+        loop {
+            Self::throttle(connection, RequestKind::List).await;
+            let mut request = client
+                .list_object_versions()
+                .bucket(bucket)
+                .prefix(&scoped_key);
+
+            if let Some(marker) = key_marker.take() {
+                request = request.key_marker(marker);
+            }
+            if let Some(marker) = version_id_marker.take() {
+                request = request.version_id_marker(marker);
+            }
+
+            let result = request
+                .send()
+                .await
+                .map_err(Self::api_error)?;
+
+            // The prefix also matches longer keys, so keep exact matches only
+            for version in result
+                .versions()
+                .iter()
+                .filter(|v| v.key() == Some(scoped_key.as_str()))
+            {
+                history.push(ObjectVersion {
+                    key: key.to_string(),
+                    version_id: version.version_id().map(|s| s.to_string()),
+                    size: version.size().unwrap_or(0) as u64,
+                    last_modified: version.last_modified().map(|d| d.secs()),
+                    etag: version.e_tag().map(|s| s.to_string()),
+                    storage_class: version.storage_class().map(|s| s.as_str().to_string()),
+                    owner: version.owner().and_then(Self::owner_name),
+                    is_latest: version.is_latest().unwrap_or(false),
+                    is_delete_marker: false,
+                });
+            }
+
+            for marker in result
+                .delete_markers()
+                .iter()
+                .filter(|m| m.key() == Some(scoped_key.as_str()))
+            {
+                history.push(ObjectVersion {
+                    key: key.to_string(),
+                    version_id: marker.version_id().map(|s| s.to_string()),
+                    size: 0,
+                    last_modified: marker.last_modified().map(|d| d.secs()),
+                    etag: None,
+                    storage_class: None,
+                    owner: marker.owner().and_then(Self::owner_name),
+                    is_latest: marker.is_latest().unwrap_or(false),
+                    is_delete_marker: true,
+                });
+            }
+
+            // Versions come back grouped by key, so once we've moved past ours we're done
+            let past_key = result
+                .next_key_marker()
+                .is_some_and(|next| next != scoped_key);
+
+            if result.is_truncated() == Some(true) && !past_key {
+                key_marker = result.next_key_marker().map(|s| s.to_string());
+                version_id_marker = result.next_version_id_marker().map(|s| s.to_string());
+            } else {
+                break;
+            }
+        }
+
+        history.sort_by_key(|v| v.last_modified);
+
+        Ok(history)
+    }
+
+    pub async fn list_intelligent_tiering_configs(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+    ) -> AppResult<Vec<IntelligentTieringConfig>> {
+        let client = Self::create_s3_client(connection).await;
+
+        let mut configs = Vec::new();
+        let mut continuation_token: Option<String> = None;
+
+        loop {
+            let result = client
+                .list_bucket_intelligent_tiering_configurations()
+                .bucket(bucket)
+                .set_continuation_token(continuation_token.take())
+                .send()
+                .await
+                .map_err(Self::map_optional_api_error)?;
+
+            for config in result.intelligent_tiering_configuration_list() {
+                let filter = config.filter();
+                configs.push(IntelligentTieringConfig {
+                    id: config.id().to_string(),
+                    enabled: *config.status() == IntelligentTieringStatus::Enabled,
+                    prefix: filter
+                        .and_then(|f| f.prefix().or_else(|| f.and().and_then(|a| a.prefix())))
+                        .map(|p| p.to_string()),
+                    tierings: config
+                        .tierings()
+                        .iter()
+                        .map(|t| TieringRule {
+                            access_tier: t.access_tier().as_str().to_string(),
+                            days: t.days(),
+                        })
+                        .collect(),
+                });
+            }
+
+            if result.is_truncated() == Some(true) {
+                continuation_token = result.next_continuation_token().map(|s| s.to_string());
+            } else {
+                break;
+            }
+        }
+
+        Ok(configs)
+    }
+
+    pub async fn put_intelligent_tiering_config(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        config: &IntelligentTieringConfig,
+    ) -> AppResult<()> {
+        let client = Self::create_s3_client(connection).await;
 
-            client
-                .create_bucket()
-                .bucket(bucket_name)
-                .create_bucket_configuration(cfg)
-                .send()
-                .await
+        let tierings = config
+            .tierings
+            .iter()
+            .map(|t| {
+                Tiering::builder()
+                    .access_tier(IntelligentTieringAccessTier::from(t.access_tier.as_str()))
+                    .days(t.days)
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::ConfigError(e.to_string()))?;
+
+        let filter = config
+            .prefix
+            .as_deref()
+            .filter(|p| !p.is_empty())
+            .map(|p| IntelligentTieringFilter::builder().prefix(p).build());
+        let status = if config.enabled {
+            IntelligentTieringStatus::Enabled
+        } else {
+            IntelligentTieringStatus::Disabled
         };
 
-        result.map_err(|e| AppError::S3Error(e.to_string()))?;
+        let configuration = IntelligentTieringConfiguration::builder()
+            .id(&config.id)
+            .status(status)
+            .set_filter(filter)
+            .set_tierings(Some(tierings))
+            .build()
+            .map_err(|e| AppError::ConfigError(e.to_string()))?;
+
+        client
+            .put_bucket_intelligent_tiering_configuration()
+            .bucket(bucket)
+            .id(&config.id)
+            .intelligent_tiering_configuration(configuration)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
         Ok(())
     }
 
-    pub async fn delete_bucket(
+    pub async fn delete_intelligent_tiering_config(
         connection: &S3ConnectionWithSecret,
-        bucket_name: &str,
+        bucket: &str,
+        id: &str,
     ) -> AppResult<()> {
         let client = Self::create_s3_client(connection).await;
 
         client
-            .delete_bucket()
-            .bucket(bucket_name)
+            .delete_bucket_intelligent_tiering_configuration()
+            .bucket(bucket)
+            .id(id)
             .send()
             .await
-            .map_err(|e| AppError::S3Error(e.to_string()))?;
+            .map_err(Self::map_optional_api_error)?;
 
         Ok(())
     }
 
-    pub async fn get_bucket_location(
+    /// Syntax-check a bucket policy and return it pretty-printed, keys in
+    /// the order they were written. Only the document's shape is checked;
+    /// the provider has the final say.
+    pub fn validate_bucket_policy(policy: &str) -> AppResult<String> {
+        let invalid = |reason: String| AppError::ConfigError(format!("Invalid policy: {}", reason));
+
+        let document: serde_json::Value =
+            serde_json::from_str(policy).map_err(|e| invalid(e.to_string()))?;
+        let statements = match document.get("Statement") {
+            Some(serde_json::Value::Array(statements)) => statements.iter().collect(),
+            Some(statement @ serde_json::Value::Object(_)) => vec![statement],
+            Some(_) => return Err(invalid("'Statement' must be an object or array".into())),
+            None if document.is_object() => {
+                return Err(invalid("missing 'Statement'".into()));
+            }
+            None => return Err(invalid("the policy must be a JSON object".into())),
+        };
+        if statements.is_empty() {
+            return Err(invalid("'Statement' is empty".into()));
+        }
+
+        for (index, statement) in statements.iter().enumerate() {
+            match statement.get("Effect").and_then(|e| e.as_str()) {
+                Some("Allow") | Some("Deny") => {}
+                _ => {
+                    return Err(invalid(format!(
+                        "statement {} needs an 'Effect' of \"Allow\" or \"Deny\"",
+                        index + 1
+                    )))
+                }
+            }
+            if statement.get("Action").is_none() && statement.get("NotAction").is_none() {
+                return Err(invalid(format!("statement {} has no 'Action'", index + 1)));
+            }
+        }
+
+        Ok(serde_json::to_string_pretty(&document)?)
+    }
+
+    /// The bucket's policy document, or `None` when it has none.
+    pub async fn get_bucket_policy(
         connection: &S3ConnectionWithSecret,
-        bucket_name: &str,
+        bucket: &str,
     ) -> AppResult<Option<String>> {
         let client = Self::create_s3_client(connection).await;
 
-        let result = client
-            .get_bucket_location()
-            .bucket(bucket_name)
-            .send()
-            .await
-            .map_err(|e| AppError::S3Error(e.to_string()))?;
-
-        Ok(result.location_constraint().map(|l| l.as_str().to_string()))
+        match client.get_bucket_policy().bucket(bucket).send().await {
+            Ok(result) => Ok(result.policy().map(str::to_string)),
+            Err(e) if e.code() == Some("NoSuchBucketPolicy") => Ok(None),
+            Err(e) => Err(Self::map_optional_api_error(e)),
+        }
     }
 
-    pub async fn copy_object(
+    pub async fn put_bucket_policy(
         connection: &S3ConnectionWithSecret,
-        source_bucket: &str,
-        source_key: &str,
-        dest_bucket: &str,
-        dest_key: &str,
+        bucket: &str,
+        policy: &str,
     ) -> AppResult<()> {
         let client = Self::create_s3_client(connection).await;
 
-        let copy_source = format!("{}/{}", source_bucket, source_key);
-
         client
-            .copy_object()
-            .copy_source(&copy_source)
-            .bucket(dest_bucket)
-            .key(dest_key)
+            .put_bucket_policy()
+            .bucket(bucket)
+            .policy(policy)
             .send()
             .await
-            .map_err(|e| AppError::S3Error(e.to_string()))?;
+            .map_err(Self::map_optional_api_error)?;
 
         Ok(())
     }
 
-    pub async fn rename_object(
+    pub async fn delete_bucket_policy(
         connection: &S3ConnectionWithSecret,
         bucket: &str,
-        old_key: &str,
-        new_key: &str,
     ) -> AppResult<()> {
-        // Copy to new location, then delete old
-        Self::copy_object(connection, bucket, old_key, bucket, new_key).await?;
+        let client = Self::create_s3_client(connection).await;
 
-        let operator = Self::create_operator(connection, bucket)?;
-        Self::delete_object(&operator, old_key).await?;
+        client
+            .delete_bucket_policy()
+            .bucket(bucket)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
 
         Ok(())
     }
 
-    pub async fn head_bucket(
+    /// The bucket's default encryption, or `None` when it has none.
+    pub async fn get_bucket_encryption(
         connection: &S3ConnectionWithSecret,
-        bucket_name: &str,
-    ) -> AppResult<bool> {
+        bucket: &str,
+    ) -> AppResult<Option<BucketEncryptionConfig>> {
         let client = Self::create_s3_client(connection).await;
 
-        match client.head_bucket().bucket(bucket_name).send().await {
-            Ok(_) => Ok(true),
-            Err(e) => {
-                let err_str = e.to_string();
-                if err_str.contains("404") || err_str.contains("NotFound") {
-                    Ok(false)
-                } else {
-                    Err(AppError::S3Error(err_str))
-                }
+        let result = match client.get_bucket_encryption().bucket(bucket).send().await {
+            Ok(result) => result,
+            Err(e) if e.code() == Some("ServerSideEncryptionConfigurationNotFoundError") => {
+                return Ok(None)
             }
-        }
+            Err(e) => return Err(Self::map_optional_api_error(e)),
+        };
+
+        let Some(rule) = result
+            .server_side_encryption_configuration()
+            .and_then(|c| c.rules().first())
+        else {
+            return Ok(None);
+        };
+        let Some(default) = rule.apply_server_side_encryption_by_default() else {
+            return Ok(None);
+        };
+
+        let algorithm = match default.sse_algorithm() {
+            ServerSideEncryption::Aes256 => SseAlgorithm::SseS3,
+            ServerSideEncryption::AwsKms => SseAlgorithm::SseKms,
+            other => {
+                return Err(AppError::NotSupported(format!(
+                    "encryption algorithm '{}'",
+                    other.as_str()
+                )))
+            }
+        };
+
+        Ok(Some(BucketEncryptionConfig {
+            algorithm,
+            kms_key_id: default.kms_master_key_id().map(str::to_string),
+            bucket_key_enabled: rule.bucket_key_enabled().unwrap_or(false),
+        }))
     }
 
-    pub async fn get_bucket_versioning(
+    pub async fn put_bucket_encryption(
         connection: &S3ConnectionWithSecret,
-        bucket_name: &str,
-    ) -> AppResult<Option<String>> {
+        bucket: &str,
+        config: &BucketEncryptionConfig,
+    ) -> AppResult<()> {
+        let kms_key_id = config
+            .kms_key_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|k| !k.is_empty());
+
+        let algorithm = match config.algorithm {
+            SseAlgorithm::SseS3 if kms_key_id.is_some() => {
+                return Err(AppError::ConfigError(
+                    "A KMS key can only be set with SSE-KMS".into(),
+                ))
+            }
+            SseAlgorithm::SseS3 => ServerSideEncryption::Aes256,
+            SseAlgorithm::SseKms => ServerSideEncryption::AwsKms,
+        };
+
+        let default = ServerSideEncryptionByDefault::builder()
+            .sse_algorithm(algorithm)
+            .set_kms_master_key_id(kms_key_id.map(str::to_string))
+            .build()
+            .map_err(|e| AppError::ConfigError(e.to_string()))?;
+        let rule = ServerSideEncryptionRule::builder()
+            .apply_server_side_encryption_by_default(default)
+            .bucket_key_enabled(config.bucket_key_enabled)
+            .build();
+        let configuration = ServerSideEncryptionConfiguration::builder()
+            .rules(rule)
+            .build()
+            .map_err(|e| AppError::ConfigError(e.to_string()))?;
+
         let client = Self::create_s3_client(connection).await;
 
-        let result = client
-            .get_bucket_versioning()
-            .bucket(bucket_name)
+        client
+            .put_bucket_encryption()
+            .bucket(bucket)
+            .server_side_encryption_configuration(configuration)
             .send()
             .await
-            .map_err(|e| AppError::S3Error(e.to_string()))?;
+            .map_err(Self::map_optional_api_error)?;
 
-        Ok(result.status().map(|s| s.as_str().to_string()))
+        Ok(())
     }
 
-    pub async fn get_bucket_stats(
+    fn owner_name(owner: &aws_sdk_s3::types::Owner) -> Option<String> {
+        owner.display_name().or(owner.id()).map(|s| s.to_string())
+    }
+
+    pub async fn search_by_tag(
         connection: &S3ConnectionWithSecret,
-        bucket_name: &str,
-    ) -> AppResult<BucketStats> {
+        bucket: &str,
+        prefix: &str,
+        filters: &[TagFilter],
+        concurrency: usize,
+        budget: &mut ScanBudget,
+    ) -> AppResult<Vec<TaggedObject>> {
+        let prefix = if prefix.is_empty() || prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{}/", prefix)
+        };
+        debug!("Checking tags on objects under '{}/{}'", bucket, prefix);
+
         let client = Self::create_s3_client(connection).await;
 
-        let mut object_count: u64 = 0;
-        let mut total_size: u64 = 0;
-        let mut continuation_token: Option<String> = None;
+        // Tag lookups start as listing pages arrive; only matches are kept
+        let pages = Self::list_object_pages(&client, connection, bucket, &prefix)?;
+        let tagged: Vec<TaggedObject> = pages
+            .try_take_while(|page| {
+                let more = match budget.exhausted() {
+                    Some(cutoff) => {
+                        warn!(
+                            "Stopped checking tags under '{}/{}' after {} objects ({:?})",
+                            bucket,
+                            prefix,
+                            budget.scanned(),
+                            cutoff
+                        );
+                        false
+                    }
+                    None => {
+                        budget.charge(page.len());
+                        true
+                    }
+                };
+                std::future::ready(Ok(more))
+            })
+            .map_ok(|page| stream::iter(page.into_iter().map(Ok::<_, AppError>)))
+            .try_flatten()
+            .try_filter(|object| std::future::ready(!object.is_directory))
+            .map(|object| {
+                let client = client.clone();
+                async move {
+                    let object = object?;
+                    Self::throttle(connection, RequestKind::Get).await;
+                    let result = async {
+                        client
+                            .get_object_tagging()
+                            .bucket(bucket)
+                            .key(Self::scoped_key(connection, &object.key)?)
+                            .send()
+                            .await
+                            .map_err(Self::api_error)
+                    }
+                    .await;
+
+                    // One unreadable object shouldn't end the whole search
+                    let result = match result {
+                        Ok(result) => result,
+                        Err(e) => {
+                            warn!("Skipping '{}/{}' in tag search: {}", bucket, object.key, e);
+                            return Ok(None);
+                        }
+                    };
+
+                    let tags: HashMap<String, String> = result
+                        .tag_set()
+                        .iter()
+                        .map(|t| (t.key().to_string(), t.value().to_string()))
+                        .collect();
+
+                    Ok::<_, AppError>(Some(TaggedObject {
+                        key: object.key,
+                        tags,
+                    }))
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .try_filter_map(|object| {
+                let matched =
+                    object.filter(|object| filters.iter().all(|f| f.matches(&object.tags)));
+                async move { Ok(matched) }
+            })
+            .try_collect()
+            .await?;
 
-        loop {
-            let mut request = client.list_objects_v2().bucket(bucket_name);
+        Ok(tagged)
+    }
 
-            if let Some(token) = continuation_token.take() {
-                request = request.continuation_token(token);
-            }
+    pub async fn get_object_acl(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+    ) -> AppResult<AccessControlList> {
+        let client = Self::create_s3_client(connection).await;
+        Self::object_acl(&client, connection, bucket, key).await
+    }
 
-            let result = request
-                .send()
-                .await
-                .map_err(|e| AppError::S3Error(e.to_string()))?;
+    /// `get_object_acl` with a client the caller already has, for checking
+    /// many objects in a row.
+    pub async fn object_acl(
+        client: &S3Client,
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+    ) -> AppResult<AccessControlList> {
+        let result = client
+            .get_object_acl()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
 
-            for object in result.contents() {
-                object_count += 1;
-                total_size += object.size().unwrap_or(0) as u64;
-            }
+        Ok(AuditService::access_control_list(
+            result.owner(),
+            result.grants(),
+        ))
+    }
 
-            if result.is_truncated() == Some(true) {
-                continuation_token = result.next_continuation_token().map(|s| s.to_string());
-            } else {
-                break;
-            }
-        }
+    /// Replace an object's ACL with a canned one.
+    pub async fn put_object_acl(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key: &str,
+        acl: CannedAcl,
+    ) -> AppResult<()> {
+        let client = Self::create_s3_client(connection).await;
 
-        Ok(BucketStats {
-            name: bucket_name.to_string(),
-            object_count,
-            total_size,
-        })
+        Self::throttle(connection, RequestKind::Put).await;
+        client
+            .put_object_acl()
+            .bucket(bucket)
+            .key(Self::scoped_key(connection, key)?)
+            .acl(ObjectCannedAcl::from(acl.as_str()))
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
+        Ok(())
     }
 
-    pub async fn get_object_metadata(
+    pub async fn get_bucket_acl(
         connection: &S3ConnectionWithSecret,
         bucket: &str,
-        key: &str,
-    ) -> AppResult<ObjectMetadata> {
+    ) -> AppResult<AccessControlList> {
         let client = Self::create_s3_client(connection).await;
 
         let result = client
-            .head_object()
+            .get_bucket_acl()
             .bucket(bucket)
-            .key(key)
             .send()
             .await
-            .map_err(|e| AppError::S3Error(e.to_string()))?;
+            .map_err(Self::map_optional_api_error)?;
 
-        let mut custom_metadata = HashMap::new();
-        if let Some(metadata) = result.metadata() {
-            for (k, v) in metadata {
-                custom_metadata.insert(k.clone(), v.clone());
-            }
+        Ok(AuditService::access_control_list(
+            result.owner(),
+            result.grants(),
+        ))
+    }
+
+    /// Replace a bucket's ACL with a canned one. Object-only ACLs are
+    /// rejected before anything is sent.
+    pub async fn put_bucket_acl(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        acl: CannedAcl,
+    ) -> AppResult<()> {
+        if !acl.applies_to_buckets() {
+            return Err(AppError::ConfigError(format!(
+                "'{}' can only be applied to objects",
+                acl.as_str()
+            )));
         }
 
-        Ok(ObjectMetadata {
+        let client = Self::create_s3_client(connection).await;
+
+        client
+            .put_bucket_acl()
+            .bucket(bucket)
+            .acl(BucketCannedAcl::from(acl.as_str()))
+            .send()
+            .await
+            .map_err(Self::map_optional_api_error)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, size: u64, last_modified: i64, is_directory: bool) -> S3Object {
+        S3Object {
             key: key.to_string(),
-            size: result.content_length().unwrap_or(0) as u64,
-            last_modified: result.last_modified().map(|d| d.secs()),
-            etag: result.e_tag().map(|s| s.to_string()),
-            content_type: result.content_type().map(|s| s.to_string()),
-            content_encoding: result.content_encoding().map(|s| s.to_string()),
-            content_disposition: result.content_disposition().map(|s| s.to_string()),
-            content_language: result.content_language().map(|s| s.to_string()),
-            cache_control: result.cache_control().map(|s| s.to_string()),
-            storage_class: result.storage_class().map(|s| s.as_str().to_string()),
-            version_id: result.version_id().map(|s| s.to_string()),
-            custom_metadata,
-        })
+            size,
+            last_modified,
+            etag: None,
+            content_type: Some("application/x-directory".to_string()),
+            is_directory,
+            child_count: None,
+        }
+    }
+
+    fn listing(objects: Vec<S3Object>, folders: Vec<S3Object>) -> ListObjectsResult {
+        ListObjectsResult {
+            objects,
+            folders,
+            prefixes: Vec::new(),
+            continuation_token: None,
+            is_truncated: false,
+        }
+    }
+
+    fn keys(entries: &[S3Object]) -> Vec<&str> {
+        entries.iter().map(|e| e.key.as_str()).collect()
+    }
+
+    #[test]
+    fn aws_marker_of_the_listed_folder_is_hidden() {
+        // AWS returns `photos/` itself among the contents of `photos/`
+        let mut result = listing(
+            vec![
+                entry("photos/", 0, 100, false),
+                entry("photos/a.jpg", 10, 200, false),
+            ],
+            vec![entry("photos/2024/", 0, 0, true)],
+        );
+
+        S3Service::normalize_folder_markers(&mut result, "photos/", false);
+
+        assert_eq!(keys(&result.objects), ["photos/a.jpg"]);
+        assert_eq!(keys(&result.folders), ["photos/2024/"]);
+        assert_eq!(result.prefixes, ["photos/2024/"]);
+    }
+
+    #[test]
+    fn aws_marker_of_the_listed_folder_can_be_shown() {
+        let mut result = listing(
+            vec![
+                entry("photos/", 0, 100, false),
+                entry("photos/a.jpg", 10, 200, false),
+            ],
+            Vec::new(),
+        );
+
+        S3Service::normalize_folder_markers(&mut result, "photos/", true);
+
+        assert_eq!(keys(&result.objects), ["photos/a.jpg"]);
+        assert_eq!(keys(&result.folders), ["photos/"]);
+        let marker = &result.folders[0];
+        assert!(marker.is_directory);
+        assert_eq!(marker.content_type, None);
+        assert_eq!(marker.last_modified, 100);
+    }
+
+    #[test]
+    fn minio_listing_without_markers_is_unchanged() {
+        let mut result = listing(
+            vec![entry("photos/a.jpg", 10, 200, false)],
+            vec![
+                entry("photos/2025/", 0, 0, true),
+                entry("photos/2024/", 0, 0, true),
+            ],
+        );
+
+        S3Service::normalize_folder_markers(&mut result, "photos/", false);
+
+        assert_eq!(keys(&result.objects), ["photos/a.jpg"]);
+        assert_eq!(keys(&result.folders), ["photos/2024/", "photos/2025/"]);
+    }
+
+    #[test]
+    fn marker_merges_with_its_prefix_keeping_the_timestamp() {
+        // Recursive listings see both the common prefix and its marker
+        let mut result = listing(
+            vec![entry("photos/2024/", 0, 300, false)],
+            vec![entry("photos/2024/", 0, 0, true)],
+        );
+
+        S3Service::normalize_folder_markers(&mut result, "photos/", false);
+
+        assert!(result.objects.is_empty());
+        assert_eq!(keys(&result.folders), ["photos/2024/"]);
+        assert_eq!(result.folders[0].last_modified, 300);
+    }
+
+    #[test]
+    fn hadoop_folder_markers_become_folders() {
+        let mut result = listing(
+            vec![
+                entry("photos/raw_$folder$", 0, 400, false),
+                entry("photos/raw.txt", 5, 400, false),
+            ],
+            vec![entry("photos/raw/", 0, 0, true)],
+        );
+
+        S3Service::normalize_folder_markers(&mut result, "photos/", false);
+
+        assert_eq!(keys(&result.objects), ["photos/raw.txt"]);
+        assert_eq!(keys(&result.folders), ["photos/raw/"]);
+        assert_eq!(result.folders[0].last_modified, 400);
+    }
+
+    #[test]
+    fn merged_subtrees_come_back_in_key_order() {
+        // Subtrees finish in whatever order their listings complete
+        let parts = vec![
+            (
+                vec![entry("docs/z.txt", 1, 0, false)],
+                vec![entry("docs/b/", 0, 0, true), entry("docs/a/", 0, 0, true)],
+            ),
+            (
+                vec![
+                    entry("docs/b/2.txt", 2, 0, false),
+                    entry("docs/b/1.txt", 1, 0, false),
+                ],
+                Vec::new(),
+            ),
+            (
+                vec![entry("docs/a/1.txt", 1, 0, false)],
+                vec![entry("docs/a/deep/", 0, 0, true)],
+            ),
+        ];
+
+        let result = S3Service::merge_entries(parts, "docs/");
+
+        assert_eq!(
+            keys(&result.objects),
+            ["docs/a/1.txt", "docs/b/1.txt", "docs/b/2.txt", "docs/z.txt"]
+        );
+        assert_eq!(
+            keys(&result.folders),
+            ["docs/a/", "docs/a/deep/", "docs/b/"]
+        );
+        assert!(!result.is_truncated);
+        assert_eq!(result.continuation_token, None);
+    }
+
+    #[test]
+    fn merged_subtrees_list_each_key_once() {
+        // The top-level listing and a subtree can both return a folder and
+        // its marker
+        let parts = vec![
+            (
+                vec![entry("docs/readme.md", 1, 0, false)],
+                vec![entry("docs/a/", 0, 0, true)],
+            ),
+            (
+                vec![
+                    entry("docs/a/", 0, 50, false),
+                    entry("docs/a/1.txt", 1, 0, false),
+                ],
+                vec![entry("docs/a/", 0, 0, true)],
+            ),
+            (vec![entry("docs/a/1.txt", 1, 0, false)], Vec::new()),
+        ];
+
+        let result = S3Service::merge_entries(parts, "docs/");
+
+        assert_eq!(keys(&result.objects), ["docs/a/1.txt", "docs/readme.md"]);
+        assert_eq!(keys(&result.folders), ["docs/a/"]);
+        assert_eq!(result.folders[0].last_modified, 50);
+        assert_eq!(result.prefixes, ["docs/a/"]);
     }
 }
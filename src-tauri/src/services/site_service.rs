@@ -0,0 +1,229 @@
+use chrono::{TimeZone, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::io::Write;
+
+use crate::error::AppResult;
+use crate::models::{ContentEncoding, ContentTypeRule, IndexMode, S3Object, ShareManifest};
+use crate::services::LocalService;
+
+/// Characters escaped in each path segment of a generated link.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+pub const INDEX_FILE: &str = "index.html";
+
+pub struct SiteService;
+
+impl SiteService {
+    /// Best-effort content type for a key, based on its extension.
+    pub fn content_type_for(key: &str) -> String {
+        let mime = mime_guess::from_path(key).first_or_octet_stream();
+        if mime.type_() == mime_guess::mime::TEXT {
+            format!("{}; charset=utf-8", mime.essence_str())
+        } else {
+            mime.essence_str().to_string()
+        }
+    }
+
+    /// Content type and cache-control for an upload, honouring the user's
+    /// override rules before falling back to the extension guess.
+    pub fn content_headers(rules: &[ContentTypeRule], key: &str) -> (String, Option<String>) {
+        let matching: Vec<&ContentTypeRule> = rules
+            .iter()
+            .filter(|rule| {
+                let pattern = &rule.pattern;
+                if pattern.contains(['*', '?', '/']) {
+                    LocalService::glob_matches(pattern, key)
+                } else {
+                    let extension = pattern.trim_start_matches('.');
+                    key.rsplit_once('.')
+                        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case(extension))
+                }
+            })
+            .collect();
+
+        let content_type = matching
+            .iter()
+            .find_map(|rule| rule.content_type.clone())
+            .unwrap_or_else(|| Self::content_type_for(key));
+        let cache_control = matching.iter().find_map(|rule| rule.cache_control.clone());
+
+        (content_type, cache_control)
+    }
+
+    /// Whether a content type is text-like enough to benefit from compression.
+    pub fn is_compressible(content_type: &str) -> bool {
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        essence.starts_with("text/")
+            || matches!(
+                essence,
+                "application/javascript"
+                    | "application/json"
+                    | "application/xml"
+                    | "application/wasm"
+                    | "image/svg+xml"
+            )
+    }
+
+    pub fn compress(data: &[u8], encoding: ContentEncoding) -> AppResult<Vec<u8>> {
+        match encoding {
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            ContentEncoding::Brotli => {
+                let mut output = Vec::new();
+                {
+                    let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 11, 22);
+                    encoder.write_all(data)?;
+                }
+                Ok(output)
+            }
+        }
+    }
+
+    pub fn is_image(key: &str) -> bool {
+        mime_guess::from_path(key)
+            .first()
+            .is_some_and(|m| m.type_() == mime_guess::mime::IMAGE)
+    }
+
+    /// Render a standalone index page for the objects under `prefix`. Links
+    /// are relative, so the page works wherever the prefix is served from.
+    pub fn render_index(
+        title: &str,
+        prefix: &str,
+        objects: &[S3Object],
+        mode: IndexMode,
+    ) -> String {
+        let mut entries: Vec<(&str, &S3Object)> = objects
+            .iter()
+            .filter_map(|o| o.key.strip_prefix(prefix).map(|rel| (rel, o)))
+            .filter(|(rel, _)| !rel.is_empty() && *rel != INDEX_FILE)
+            .filter(|(rel, _)| mode == IndexMode::Listing || Self::is_image(rel))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let body = match mode {
+            IndexMode::Listing => {
+                let rows: String = entries
+                    .iter()
+                    .map(|(rel, object)| {
+                        format!(
+                            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                            Self::href(rel),
+                            escape_html(rel),
+                            object.size,
+                            format_timestamp(object.last_modified)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "<table>\n<tr><th>Name</th><th>Size</th><th>Last modified</th></tr>\n{}</table>",
+                    rows
+                )
+            }
+            IndexMode::Gallery => {
+                let tiles: String = entries
+                    .iter()
+                    .map(|(rel, _)| {
+                        format!(
+                            "<a href=\"{0}\"><img src=\"{0}\" alt=\"{1}\" loading=\"lazy\"></a>\n",
+                            Self::href(rel),
+                            escape_html(rel)
+                        )
+                    })
+                    .collect();
+                format!("<div class=\"gallery\">\n{}</div>", tiles)
+            }
+        };
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{title}</title>\n<style>\n{style}</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n{body}\n<p class=\"count\">{count} items</p>\n</body>\n</html>\n",
+            title = escape_html(title),
+            style = STYLE,
+            body = body,
+            count = entries.len()
+        )
+    }
+
+    /// Render a share manifest as a standalone page of download links.
+    pub fn render_share_page(manifest: &ShareManifest) -> String {
+        let title = if manifest.prefix.is_empty() {
+            manifest.bucket.clone()
+        } else {
+            format!("{}/{}", manifest.bucket, manifest.prefix)
+        };
+        let rows: String = manifest
+            .links
+            .iter()
+            .map(|link| {
+                format!(
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td></tr>\n",
+                    escape_html(&link.url),
+                    escape_html(&link.name),
+                    link.size
+                )
+            })
+            .collect();
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{title}</title>\n<style>\n{style}</style>\n</head>\n<body>\n\
+             <h1>{title}</h1>\n<table>\n<tr><th>Name</th><th>Size</th></tr>\n{rows}</table>\n\
+             <p class=\"count\">{count} items, links expire {expires} UTC</p>\n</body>\n</html>\n",
+            title = escape_html(&title),
+            style = STYLE,
+            rows = rows,
+            count = manifest.links.len(),
+            expires = format_timestamp(manifest.expires_at)
+        )
+    }
+
+    /// Percent-encode a key for use in a URL path, keeping its slashes.
+    pub fn href(relative_key: &str) -> String {
+        relative_key
+            .split('/')
+            .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+const STYLE: &str = "body { font-family: system-ui, sans-serif; margin: 2rem; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.3rem 0.6rem; border-bottom: 1px solid #ddd; }
+.gallery { display: grid; grid-template-columns: repeat(auto-fill, minmax(180px, 1fr)); gap: 0.5rem; }
+.gallery img { width: 100%; height: 180px; object-fit: cover; }
+.count { color: #888; }
+";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_timestamp(secs: i64) -> String {
+    Utc.timestamp_opt(secs, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
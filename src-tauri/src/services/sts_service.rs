@@ -0,0 +1,225 @@
+use aws_credential_types::Credentials;
+use aws_sdk_sts::config::Region;
+use aws_sdk_sts::Client as StsClient;
+use chrono::Utc;
+use log::{debug, info, trace};
+use serde_json::{json, Value};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    S3ConnectionWithSecret, ScopedAccess, ScopedCredentials, ScopedCredentialsOptions,
+};
+use crate::services::S3Service;
+
+const DEFAULT_DURATION_SECS: i32 = 3600;
+/// STS refuses shorter sessions.
+const MIN_DURATION_SECS: i32 = 900;
+/// GetFederationToken's ceiling. AssumeRole is further capped by the
+/// role's own maximum session duration.
+const MAX_DURATION_SECS: i32 = 129_600;
+/// Federated user names are limited to 32 characters.
+const MAX_SESSION_NAME_LEN: usize = 32;
+
+pub struct StsService;
+
+impl StsService {
+    fn create_sts_client(connection: &S3ConnectionWithSecret) -> StsClient {
+        trace!("Creating STS client for connection: {}", connection.id);
+
+        let credentials = Credentials::new(
+            &connection.access_key,
+            &connection.secret_key,
+            None,
+            None,
+            "baul-s3-client",
+        );
+        let region = if connection.region.is_empty() {
+            "us-east-1"
+        } else {
+            &connection.region
+        };
+
+        let config = aws_sdk_sts::Config::builder()
+            .credentials_provider(credentials)
+            .region(Region::new(region.to_string()))
+            .build();
+
+        StsClient::from_conf(config)
+    }
+
+    /// `prefix` as a folder, so `reports` can't also grant `reports-old/`,
+    /// with the characters IAM treats as wildcards or variables escaped.
+    fn policy_prefix(prefix: &str) -> String {
+        let mut escaped = String::with_capacity(prefix.len() + 1);
+        for c in prefix.chars() {
+            match c {
+                '*' | '?' | '$' => {
+                    escaped.push_str("${");
+                    escaped.push(c);
+                    escaped.push('}');
+                }
+                c => escaped.push(c),
+            }
+        }
+        if !escaped.is_empty() && !escaped.ends_with('/') {
+            escaped.push('/');
+        }
+        escaped
+    }
+
+    /// Session policy allowing `access` to keys under `prefix` in `bucket`.
+    /// Session policies only narrow what the caller may do, so the result
+    /// never exceeds the connection's own permissions.
+    pub fn session_policy(region: &str, bucket: &str, prefix: &str, access: ScopedAccess) -> Value {
+        let prefix = Self::policy_prefix(prefix);
+        let partition = if region.starts_with("cn-") {
+            "aws-cn"
+        } else if region.starts_with("us-gov-") {
+            "aws-us-gov"
+        } else {
+            "aws"
+        };
+        let bucket_arn = format!("arn:{}:s3:::{}", partition, bucket);
+
+        let mut list = json!({
+            "Effect": "Allow",
+            "Action": "s3:ListBucket",
+            "Resource": bucket_arn,
+        });
+        if !prefix.is_empty() {
+            list["Condition"] = json!({ "StringLike": { "s3:prefix": [format!("{}*", prefix)] } });
+        }
+
+        let mut bucket_actions = vec!["s3:GetBucketLocation"];
+        let mut object_actions = vec!["s3:GetObject", "s3:GetObjectVersion", "s3:GetObjectTagging"];
+        if access == ScopedAccess::ReadWrite {
+            bucket_actions.push("s3:ListBucketMultipartUploads");
+            object_actions.extend([
+                "s3:PutObject",
+                "s3:PutObjectTagging",
+                "s3:DeleteObject",
+                "s3:AbortMultipartUpload",
+                "s3:ListMultipartUploadParts",
+            ]);
+        }
+
+        json!({
+            "Version": "2012-10-17",
+            "Statement": [
+                list,
+                {
+                    "Effect": "Allow",
+                    "Action": bucket_actions,
+                    "Resource": bucket_arn,
+                },
+                {
+                    "Effect": "Allow",
+                    "Action": object_actions,
+                    "Resource": format!("{}/{}*", bucket_arn, prefix),
+                },
+            ],
+        })
+    }
+
+    /// STS accepts `[\w+=,.@-]` in session and federated user names.
+    fn session_name(requested: Option<&str>) -> String {
+        let name: String = requested
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("baul-{}", Utc::now().timestamp()))
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "_+=,.@-".contains(c) {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .take(MAX_SESSION_NAME_LEN)
+            .collect();
+        // Names must be at least two characters long
+        if name.len() < 2 {
+            format!("baul-{}", name)
+        } else {
+            name
+        }
+    }
+
+    /// Issue temporary credentials restricted to `prefix` in `bucket`, via
+    /// AssumeRole when a role is given and GetFederationToken otherwise.
+    pub async fn scoped_credentials(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        prefix: &str,
+        options: &ScopedCredentialsOptions,
+    ) -> AppResult<ScopedCredentials> {
+        let duration = options.duration_seconds.unwrap_or(DEFAULT_DURATION_SECS);
+        if !(MIN_DURATION_SECS..=MAX_DURATION_SECS).contains(&duration) {
+            return Err(AppError::ConfigError(format!(
+                "Credential lifetime must be between {} and {} seconds",
+                MIN_DURATION_SECS, MAX_DURATION_SECS
+            )));
+        }
+
+        let prefix = if prefix.is_empty() || prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{}/", prefix)
+        };
+        let policy =
+            Self::session_policy(&connection.region, bucket, &prefix, options.access).to_string();
+        let name = Self::session_name(options.session_name.as_deref());
+        let client = Self::create_sts_client(connection);
+        debug!(
+            "Requesting {:?} credentials for '{}/{}' as '{}' ({}s)",
+            options.access, bucket, prefix, name, duration
+        );
+
+        let credentials = match options.role_arn.as_deref() {
+            Some(role_arn) => {
+                client
+                    .assume_role()
+                    .role_arn(role_arn)
+                    .role_session_name(&name)
+                    .policy(&policy)
+                    .duration_seconds(duration)
+                    .send()
+                    .await
+                    .map_err(S3Service::api_error)?
+                    .credentials
+            }
+            None => {
+                client
+                    .get_federation_token()
+                    .name(&name)
+                    .policy(&policy)
+                    .duration_seconds(duration)
+                    .send()
+                    .await
+                    .map_err(S3Service::api_error)?
+                    .credentials
+            }
+        }
+        .ok_or_else(|| AppError::S3Error("STS returned no credentials".into()))?;
+
+        info!(
+            "Issued {:?} credentials for '{}/{}' as '{}', expiring at {}",
+            options.access,
+            bucket,
+            prefix,
+            name,
+            credentials.expiration.secs()
+        );
+
+        Ok(ScopedCredentials {
+            access_key_id: credentials.access_key_id,
+            secret_access_key: credentials.secret_access_key,
+            session_token: credentials.session_token,
+            expires_at: credentials.expiration.secs(),
+            region: connection.region.clone(),
+            bucket: bucket.to_string(),
+            prefix,
+            access: options.access,
+            policy,
+        })
+    }
+}
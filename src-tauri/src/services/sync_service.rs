@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use futures::TryStreamExt;
+use log::{debug, warn};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    S3ConnectionWithSecret, S3Object, SyncAction, SyncCompare, SyncDirection, SyncItem,
+    SyncOptions, SyncPlan, SyncReason,
+};
+use crate::services::{IgnoreRules, InventoryService, LocalService, S3Service};
+use crate::util::{hash_file_async, HashAlgorithm};
+
+/// A file on the local side of a sync.
+struct LocalEntry {
+    path: PathBuf,
+    size: u64,
+    /// Unix seconds, comparable with `S3Object::last_modified`.
+    modified: i64,
+}
+
+pub struct SyncService;
+
+impl SyncService {
+    /// Compare the files under `root` with the objects under `key_prefix`
+    /// and work out what a one-way sync in `options.direction` has to
+    /// transfer and, with `options.delete`, remove. Paths matching `rules`
+    /// are left alone on both sides.
+    pub async fn plan(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key_prefix: &str,
+        root: &Path,
+        rules: &IgnoreRules,
+        options: &SyncOptions,
+    ) -> AppResult<SyncPlan> {
+        let local = Self::local_files(root, rules, options.direction).await?;
+        let remote =
+            Self::remote_objects(connection, bucket, key_prefix, rules, options.use_inventory)
+                .await?;
+        debug!(
+            "Sync of {:?} with '{}/{}': {} local files, {} remote objects",
+            root,
+            bucket,
+            key_prefix,
+            local.len(),
+            remote.len()
+        );
+
+        let mut plan = SyncPlan::default();
+        match options.direction {
+            SyncDirection::Push => {
+                for (relative, file) in &local {
+                    let reason = match remote.get(relative) {
+                        None => Some(SyncReason::Missing),
+                        Some(object) => {
+                            Self::out_of_date(connection, bucket, file, object, options).await?
+                        }
+                    };
+                    let Some(reason) = reason else {
+                        plan.unchanged_count += 1;
+                        continue;
+                    };
+                    plan.total_bytes += file.size;
+                    plan.uploads.push(SyncItem {
+                        action: SyncAction::Upload,
+                        reason,
+                        key: format!("{}{}", key_prefix, relative),
+                        local_path: file.path.to_string_lossy().to_string(),
+                        size: file.size,
+                        modified: file.modified,
+                        etag: None,
+                    });
+                }
+
+            }
+            SyncDirection::Pull => {
+                for (relative, object) in &remote {
+                    let path = match LocalService::local_path_for_key(root, relative) {
+                        Ok(path) => path,
+                        Err(e) => {
+                            warn!("Not syncing '{}': {}", object.key, e);
+                            continue;
+                        }
+                    };
+                    let reason = match local.get(relative) {
+                        None => Some(SyncReason::Missing),
+                        Some(file) => {
+                            Self::out_of_date(connection, bucket, file, object, options).await?
+                        }
+                    };
+                    let Some(reason) = reason else {
+                        plan.unchanged_count += 1;
+                        continue;
+                    };
+                    plan.total_bytes += object.size;
+                    plan.downloads.push(SyncItem {
+                        action: SyncAction::Download,
+                        reason,
+                        key: object.key.clone(),
+                        local_path: path.to_string_lossy().to_string(),
+                        size: object.size,
+                        modified: object.last_modified,
+                        etag: object.etag.clone(),
+                    });
+                }
+
+            }
+        }
+
+        if options.delete {
+            Self::plan_deletions(
+                &mut plan,
+                options.direction,
+                root,
+                key_prefix,
+                &local,
+                &remote,
+            );
+        }
+        Ok(plan)
+    }
+
+    /// Files the destination has and the source doesn't. Both sides were
+    /// already filtered through the ignore rules, so ignored paths never
+    /// show up as missing from the source.
+    fn plan_deletions(
+        plan: &mut SyncPlan,
+        direction: SyncDirection,
+        root: &Path,
+        key_prefix: &str,
+        local: &BTreeMap<String, LocalEntry>,
+        remote: &BTreeMap<String, S3Object>,
+    ) {
+        match direction {
+            SyncDirection::Push => {
+                for (relative, object) in remote {
+                    if local.contains_key(relative) {
+                        continue;
+                    }
+                    plan.deletions.push(SyncItem {
+                        action: SyncAction::DeleteRemote,
+                        reason: SyncReason::Removed,
+                        key: object.key.clone(),
+                        local_path: root.join(relative).to_string_lossy().to_string(),
+                        size: object.size,
+                        modified: object.last_modified,
+                        etag: object.etag.clone(),
+                    });
+                }
+            }
+            SyncDirection::Pull => {
+                for (relative, file) in local {
+                    if remote.contains_key(relative) {
+                        continue;
+                    }
+                    plan.deletions.push(SyncItem {
+                        action: SyncAction::DeleteLocal,
+                        reason: SyncReason::Removed,
+                        key: format!("{}{}", key_prefix, relative),
+                        local_path: file.path.to_string_lossy().to_string(),
+                        size: file.size,
+                        modified: file.modified,
+                        etag: None,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Local files keyed by their `/`-separated path below `root`. A pull
+    /// into a folder that doesn't exist yet starts from nothing.
+    async fn local_files(
+        root: &Path,
+        rules: &IgnoreRules,
+        direction: SyncDirection,
+    ) -> AppResult<BTreeMap<String, LocalEntry>> {
+        if direction == SyncDirection::Pull && !root.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let root = root.to_path_buf();
+        let rules = rules.clone();
+        tokio::task::spawn_blocking(move || {
+            let walk = LocalService::collect_files(&root, &rules)?;
+            walk.files
+                .into_iter()
+                .map(|file| {
+                    let meta = fs::metadata(&file.path)?;
+                    let entry = LocalEntry {
+                        size: meta.len(),
+                        modified: LocalService::modified_millis(&meta) / 1000,
+                        path: file.path,
+                    };
+                    Ok((file.relative_path, entry))
+                })
+                .collect()
+        })
+        .await
+        .map_err(|e| AppError::IoError(std::io::Error::other(e)))?
+    }
+
+    /// Remote objects keyed by their path below `key_prefix`, without
+    /// folder markers, from an inventory report when `use_inventory` is set
+    /// and one covers the prefix.
+    async fn remote_objects(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        key_prefix: &str,
+        rules: &IgnoreRules,
+        use_inventory: bool,
+    ) -> AppResult<BTreeMap<String, S3Object>> {
+        let client = S3Service::create_s3_client(connection).await;
+        let mut pages = InventoryService::planning_pages(
+            &client,
+            connection,
+            bucket,
+            key_prefix,
+            use_inventory,
+        )
+        .await?;
+
+        let mut remote = BTreeMap::new();
+        while let Some(page) = pages.try_next().await? {
+            Self::index_remote(&mut remote, page, key_prefix, rules);
+        }
+        Ok(remote)
+    }
+
+    /// Add a listing page to `remote`, skipping folder markers and keys an
+    /// ignore rule covers, including those inside an ignored directory.
+    fn index_remote(
+        remote: &mut BTreeMap<String, S3Object>,
+        page: Vec<S3Object>,
+        key_prefix: &str,
+        rules: &IgnoreRules,
+    ) {
+        for object in page {
+            if object.is_directory {
+                continue;
+            }
+            let Some(relative) = object.key.strip_prefix(key_prefix) else {
+                continue;
+            };
+            if relative.is_empty() || rules.is_path_ignored(relative) {
+                continue;
+            }
+            remote.insert(relative.to_string(), object);
+        }
+    }
+
+    /// Whether the destination copy of a file present on both sides needs
+    /// replacing, and why.
+    async fn out_of_date(
+        connection: &S3ConnectionWithSecret,
+        bucket: &str,
+        file: &LocalEntry,
+        object: &S3Object,
+        options: &SyncOptions,
+    ) -> AppResult<Option<SyncReason>> {
+        if file.size != object.size {
+            return Ok(Some(SyncReason::SizeChanged));
+        }
+
+        let source_newer = match options.direction {
+            SyncDirection::Push => file.modified > object.last_modified,
+            SyncDirection::Pull => object.last_modified > file.modified,
+        };
+        let newer = source_newer.then_some(SyncReason::Newer);
+
+        match options.compare {
+            SyncCompare::Size => Ok(None),
+            SyncCompare::SizeAndMtime => Ok(newer),
+            SyncCompare::Checksum => {
+                let Some(etag) = S3Service::content_md5(connection, bucket, object).await? else {
+                    return Ok(newer);
+                };
+                let digest = hash_file_async(file.path.clone(), HashAlgorithm::Md5).await?;
+                Ok((!digest.eq_ignore_ascii_case(&etag)).then_some(SyncReason::ChecksumChanged))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(key: &str) -> S3Object {
+        S3Object {
+            key: key.to_string(),
+            size: 1,
+            last_modified: 0,
+            etag: None,
+            content_type: None,
+            is_directory: false,
+            child_count: None,
+        }
+    }
+
+    #[test]
+    fn push_with_delete_leaves_ignored_remote_keys_alone() {
+        let rules = IgnoreRules::new(["node_modules/", ".git/"]);
+        let root = Path::new("/site");
+        let page = vec![
+            object("site/index.html"),
+            object("site/old.html"),
+            object("site/node_modules/react/index.js"),
+            object("site/web/node_modules/left-pad/index.js"),
+            object("site/.git/HEAD"),
+        ];
+        let mut remote = BTreeMap::new();
+        SyncService::index_remote(&mut remote, page, "site/", &rules);
+
+        let local = BTreeMap::from([(
+            "index.html".to_string(),
+            LocalEntry {
+                path: root.join("index.html"),
+                size: 1,
+                modified: 0,
+            },
+        )]);
+
+        let mut plan = SyncPlan::default();
+        SyncService::plan_deletions(
+            &mut plan,
+            SyncDirection::Push,
+            root,
+            "site/",
+            &local,
+            &remote,
+        );
+        let deleted: Vec<&str> = plan
+            .deletions
+            .iter()
+            .map(|item| item.key.as_str())
+            .collect();
+        assert_eq!(deleted, ["site/old.html"]);
+    }
+}
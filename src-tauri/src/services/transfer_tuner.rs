@@ -0,0 +1,162 @@
+use log::debug;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{S3ConnectionWithSecret, TransferPlan};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Smallest part we pick; fewer requests than the 5 MiB provider minimum.
+const BASE_PART_SIZE: u64 = 8 * MIB;
+
+/// Largest part S3 accepts.
+const MAX_PART_SIZE: u64 = 5 * 1024 * MIB;
+
+/// Throughput-driven growth stops here so progress stays granular; only
+/// the part cap pushes parts past it.
+const MAX_TUNED_PART_SIZE: u64 = 64 * MIB;
+
+/// How long one part should take on a single stream at the measured rate.
+const TARGET_PART_SECONDS: f64 = 4.0;
+
+/// Streams used before a connection has any measured throughput.
+const DEFAULT_CONCURRENCY: usize = 4;
+
+const MAX_CONCURRENCY: usize = 16;
+
+/// Below this rate the link is the bottleneck and extra streams only
+/// split it further.
+const SLOW_LINK: f64 = 2.0 * MIB as f64;
+
+/// Each multiple of this rate earns another stream above the default.
+const RATE_PER_STREAM: f64 = 16.0 * MIB as f64;
+
+/// Parts held in memory at once by one transfer.
+const MEMORY_BUDGET: u64 = 256 * MIB;
+
+/// Limits used instead of the ones above in low-memory mode.
+const LOW_MEMORY_BUDGET: u64 = 32 * MIB;
+const LOW_MEMORY_TUNED_PART_SIZE: u64 = 16 * MIB;
+const LOW_MEMORY_CONCURRENCY: usize = 2;
+
+/// Transfers smaller than this are dominated by request latency and would
+/// skew the measurement.
+const MIN_MEASURED_BYTES: u64 = 4 * MIB;
+
+/// Weight of the newest sample in the smoothed throughput.
+const SMOOTHING: f64 = 0.3;
+
+/// Smoothed bytes per second of recent transfers, keyed by connection id.
+static THROUGHPUT: LazyLock<Mutex<HashMap<String, f64>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static LOW_MEMORY: AtomicBool = AtomicBool::new(false);
+
+pub struct TransferTuner;
+
+impl TransferTuner {
+    pub fn set_low_memory(enabled: bool) {
+        debug!("Low-memory mode {}", if enabled { "on" } else { "off" });
+        LOW_MEMORY.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn low_memory() -> bool {
+        LOW_MEMORY.load(Ordering::Relaxed)
+    }
+
+    /// Parallelism for a batch of transfers, capped in low-memory mode.
+    pub fn concurrency(requested: usize) -> usize {
+        if Self::low_memory() {
+            requested.clamp(1, LOW_MEMORY_CONCURRENCY)
+        } else {
+            requested.max(1)
+        }
+    }
+
+    /// Measured throughput for the connection, if any transfer was big
+    /// enough to sample.
+    pub fn throughput(connection_id: &str) -> Option<f64> {
+        THROUGHPUT
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(connection_id)
+            .copied()
+    }
+
+    /// Fold a finished transfer into the connection's throughput.
+    pub fn record(connection_id: &str, bytes: u64, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if bytes < MIN_MEASURED_BYTES || secs <= 0.0 {
+            return;
+        }
+        let sample = bytes as f64 / secs;
+
+        let mut throughput = THROUGHPUT.lock().unwrap_or_else(|e| e.into_inner());
+        let smoothed = throughput
+            .entry(connection_id.to_string())
+            .and_modify(|rate| *rate += SMOOTHING * (sample - *rate))
+            .or_insert(sample);
+        debug!(
+            "Throughput for connection '{}' is now {:.0} B/s",
+            connection_id, smoothed
+        );
+    }
+
+    /// Part size and parallelism for moving `size` bytes over `connection`,
+    /// from the provider's multipart limits and the measured throughput.
+    pub fn plan(connection: &S3ConnectionWithSecret, size: u64) -> AppResult<TransferPlan> {
+        let limits = connection.provider.multipart_limits();
+        if size > limits.max_object_size {
+            return Err(AppError::NotSupported(format!(
+                "{} bytes exceeds the provider's {} byte object limit",
+                size, limits.max_object_size
+            )));
+        }
+        let measured = Self::throughput(&connection.id);
+        let (base_part_size, max_tuned_part_size, memory_budget) = if Self::low_memory() {
+            (
+                limits.min_part_size,
+                LOW_MEMORY_TUNED_PART_SIZE,
+                LOW_MEMORY_BUDGET,
+            )
+        } else {
+            (BASE_PART_SIZE, MAX_TUNED_PART_SIZE, MEMORY_BUDGET)
+        };
+
+        let concurrency = match measured {
+            None => DEFAULT_CONCURRENCY,
+            Some(rate) if rate < SLOW_LINK => 2,
+            Some(rate) => DEFAULT_CONCURRENCY + (rate / RATE_PER_STREAM) as usize,
+        }
+        .min(MAX_CONCURRENCY);
+
+        // Big enough that the part cap holds, then grown toward a few
+        // seconds per part so fast links aren't stuck on request overhead
+        let for_part_cap = size.div_ceil(limits.max_parts.max(1) as u64);
+        let for_rate = measured
+            .map(|rate| (rate / concurrency as f64 * TARGET_PART_SECONDS) as u64)
+            .unwrap_or(0)
+            .min(max_tuned_part_size);
+        let part_size = base_part_size
+            .max(limits.min_part_size)
+            .max(for_rate)
+            .max(for_part_cap)
+            .next_multiple_of(MIB)
+            .min(MAX_PART_SIZE);
+
+        let part_count = size.div_ceil(part_size).max(1);
+        let concurrency = concurrency
+            .min(part_count as usize)
+            .min((memory_budget / part_size).max(1) as usize);
+
+        Ok(TransferPlan {
+            part_size,
+            part_count,
+            concurrency,
+            measured_bytes_per_second: measured,
+        })
+    }
+}
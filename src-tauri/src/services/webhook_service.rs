@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use log::{debug, warn};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{JobEvent, WebhookConfig};
+
+/// How long a webhook endpoint gets to answer.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct WebhookService;
+
+impl WebhookService {
+    /// Hex HMAC-SHA256 of `body`, as sent in `X-Baul-Signature`.
+    pub fn signature(secret: &str, body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key size");
+        mac.update(body);
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+
+    /// POST `payload` as JSON to `url`, signed when a `secret` is given.
+    /// Any non-2xx answer is an error.
+    pub async fn post_json(
+        url: &str,
+        payload: &impl Serialize,
+        secret: Option<&str>,
+    ) -> AppResult<()> {
+        let body = serde_json::to_vec(payload)?;
+
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::WebhookError(e.to_string()))?;
+
+        let mut request = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = secret.filter(|s| !s.is_empty()) {
+            request = request.header(
+                "X-Baul-Signature",
+                format!("sha256={}", Self::signature(secret, &body)),
+            );
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| AppError::WebhookError(format!("{}: {}", url, e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AppError::WebhookError(format!(
+                "{} answered {}",
+                url, status
+            )));
+        }
+
+        debug!("Delivered webhook to {}", url);
+        Ok(())
+    }
+
+    /// Send `event` to every webhook subscribed to it. Delivery failures are
+    /// logged; a job's outcome never depends on them.
+    pub async fn deliver_job_event(webhooks: &[WebhookConfig], event: &JobEvent) {
+        let subscribed = webhooks
+            .iter()
+            .filter(|w| w.events.is_empty() || w.events.contains(&event.event));
+
+        for webhook in subscribed {
+            if let Err(e) = Self::post_json(&webhook.url, event, webhook.secret.as_deref()).await {
+                warn!(
+                    "Failed to notify webhook about {} job '{}': {}",
+                    event.job, event.job_id, e
+                );
+            }
+        }
+    }
+}
@@ -1,16 +1,462 @@
-use std::collections::HashMap;
-use tokio::sync::Mutex;
+use chrono::Utc;
+use futures::future::{BoxFuture, Shared};
+use log::{debug, warn};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
 
-use crate::models::S3ConnectionWithSecret;
+use crate::error::{AppError, AppResult};
+use crate::models::{
+    AppSettings, BackgroundJob, BucketInfo, ExternalEdit, JobEvent, JobEventKind,
+    ListObjectsResult, ProviderCapabilities, S3ConnectionWithSecret, WebhookConfig,
+};
+use crate::services::{ConfigService, CredentialService, S3Service, WebhookService};
+
+/// How long a cached bucket list is served before hitting the API again.
+const BUCKET_LIST_TTL: Duration = Duration::from_secs(300);
+
+/// The page after a continuation token, fetched before "load more" asked
+/// for it.
+pub struct PrefetchedPage {
+    pub started_at: Instant,
+    pub limit: usize,
+    /// Resolves to `None` if the fetch failed; the page is then listed again.
+    pub page: Shared<BoxFuture<'static, Option<ListObjectsResult>>>,
+}
+
+/// A connection behind its own lock, so edits to one never block another.
+pub type ConnectionEntry = Arc<RwLock<S3ConnectionWithSecret>>;
 
 pub struct AppState {
-    pub connections: Mutex<HashMap<String, S3ConnectionWithSecret>>,
+    /// Read-mostly map of connections; the outer lock is only written when
+    /// connections are added or removed.
+    pub connections: RwLock<HashMap<String, ConnectionEntry>>,
+    pub settings: Mutex<AppSettings>,
+    /// Keychain failures from startup, keyed by connection id.
+    pub credential_errors: Mutex<HashMap<String, String>>,
+    /// Connections whose keychain prompt was denied. They stay listed but
+    /// refuse to connect until `retry_unlock_connection` reads the secret.
+    pub locked_connections: Mutex<HashSet<String>>,
+    pub external_edits: Mutex<HashMap<String, ExternalEdit>>,
+    /// Where the last drag-out was staged, removed once the drag is over.
+    pub drag_staging: Mutex<Option<PathBuf>>,
+    /// Resolved bucket regions keyed by (connection id, bucket name).
+    pub bucket_regions: Mutex<HashMap<(String, String), String>>,
+    /// Bucket lists keyed by connection id, with the time they were fetched.
+    pub bucket_lists: Mutex<HashMap<String, (Instant, Vec<BucketInfo>)>>,
+    /// Continuation tokens of truncated listings, keyed by (connection, bucket, prefix).
+    pub listing_cursors: Mutex<HashMap<(String, String, String), String>>,
+    /// Background-fetched next pages keyed by (connection, bucket, token).
+    pub prefetched_pages: Mutex<HashMap<(String, String, String), PrefetchedPage>>,
+    /// Probed provider capabilities keyed by (connection id, bucket name).
+    pub capabilities: Mutex<HashMap<(String, String), ProviderCapabilities>>,
+    /// Set once startup has loaded connections and settings.
+    pub ready: AtomicBool,
+    /// Running jobs keyed by id, with the flag that cancels them. A std
+    /// mutex so `JobGuard` can deregister from `Drop`.
+    pub jobs: std::sync::Mutex<HashMap<String, (BackgroundJob, Arc<AtomicBool>)>>,
+    /// Scratch directories whose files the frontend still reads, removed
+    /// when the app exits. A std mutex so the exit handler can reach it
+    /// outside the async runtime.
+    pub temp_dirs: std::sync::Mutex<Vec<PathBuf>>,
+}
+
+/// Registration of a running job; the job is removed when this is dropped,
+/// however the command exits. Configured webhooks hear about the outcome:
+/// a guard dropped without `finish` counts as a failed job.
+pub struct JobGuard<'a> {
+    state: &'a AppState,
+    pub id: String,
+    job: BackgroundJob,
+    webhooks: Vec<WebhookConfig>,
+    outcome: Option<(u64, u64)>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobGuard<'_> {
+    /// Set once `cancel_job` has been called for this job; long-running
+    /// work checks it between steps.
+    pub fn cancelled(&self) -> &AtomicBool {
+        &self.cancelled
+    }
+
+    /// Record how many items the job handled before it goes away.
+    pub fn finish(mut self, succeeded: u64, failed: u64) {
+        self.outcome = Some((succeeded, failed));
+    }
+}
+
+impl Drop for JobGuard<'_> {
+    fn drop(&mut self) {
+        self.state
+            .jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.id);
+
+        if self.webhooks.is_empty() {
+            return;
+        }
+        let (succeeded, failed, event) = match self.outcome {
+            Some((succeeded, 0)) => (succeeded, 0, JobEventKind::JobCompleted),
+            Some((succeeded, failed)) => (succeeded, failed, JobEventKind::JobFailed),
+            None => (0, 0, JobEventKind::JobFailed),
+        };
+        let event = JobEvent {
+            event,
+            job_id: self.id.clone(),
+            job: self.job.kind.clone(),
+            bucket: self.job.bucket.clone(),
+            started_at: self.job.started_at,
+            finished_at: Utc::now().timestamp(),
+            succeeded,
+            failed,
+        };
+        let webhooks = std::mem::take(&mut self.webhooks);
+        tauri::async_runtime::spawn(async move {
+            WebhookService::deliver_job_event(&webhooks, &event).await;
+        });
+    }
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
-            connections: Mutex::new(HashMap::new()),
+            connections: RwLock::new(HashMap::new()),
+            settings: Mutex::new(AppSettings::default()),
+            credential_errors: Mutex::new(HashMap::new()),
+            locked_connections: Mutex::new(HashSet::new()),
+            external_edits: Mutex::new(HashMap::new()),
+            drag_staging: Mutex::new(None),
+            bucket_regions: Mutex::new(HashMap::new()),
+            bucket_lists: Mutex::new(HashMap::new()),
+            listing_cursors: Mutex::new(HashMap::new()),
+            prefetched_pages: Mutex::new(HashMap::new()),
+            capabilities: Mutex::new(HashMap::new()),
+            ready: AtomicBool::new(false),
+            jobs: std::sync::Mutex::new(HashMap::new()),
+            temp_dirs: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl AppState {
+    /// Remove `dir` and everything in it when the app exits.
+    pub fn track_temp_dir(&self, dir: PathBuf) {
+        self.temp_dirs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(dir);
+    }
+
+    /// Remove every tracked scratch directory.
+    pub fn remove_temp_dirs(&self) {
+        let dirs = std::mem::take(&mut *self.temp_dirs.lock().unwrap_or_else(|e| e.into_inner()));
+        for dir in dirs {
+            match std::fs::remove_dir_all(&dir) {
+                Ok(()) => debug!("Removed temp files in {:?}", dir),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to remove temp files in {:?}: {}", dir, e),
+            }
+        }
+    }
+
+    /// Replace the in-memory connections with the active profile's, fetching
+    /// each secret from the keychain. Returns how many were loaded.
+    pub async fn load_connections(&self) -> AppResult<usize> {
+        let connections = ConfigService::load_connections()?;
+        debug!("Found {} saved connections", connections.len());
+
+        // Keychain lookups block, so fetch every secret on its own
+        // thread rather than letting one slow entry stall the rest
+        let lookups = connections.into_iter().map(|(id, conn)| {
+            tokio::task::spawn_blocking(move || {
+                let secret = CredentialService::get_secret(&id);
+                (id, conn, secret)
+            })
+        });
+        let results = futures::future::join_all(lookups).await;
+
+        let mut state_connections: HashMap<String, ConnectionEntry> = HashMap::new();
+        let mut credential_errors: HashMap<String, String> = HashMap::new();
+        let mut locked_connections: HashSet<String> = HashSet::new();
+
+        for result in results {
+            let (id, conn, secret) = match result {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    warn!("Credential lookup task failed: {}", e);
+                    continue;
+                }
+            };
+
+            let secret_key = match secret {
+                Ok(secret_key) => {
+                    debug!("Loaded credentials for connection: {}", conn.name);
+                    secret_key
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to load credentials for connection '{}': {}",
+                        conn.name, e
+                    );
+                    if matches!(e, AppError::KeychainLocked(_)) {
+                        locked_connections.insert(id.clone());
+                    }
+                    credential_errors.insert(id.clone(), e.to_string());
+                    // Still add the connection but with empty secret
+                    String::new()
+                }
+            };
+
+            let connection = S3ConnectionWithSecret::from_connection(conn, secret_key);
+            state_connections.insert(id, Arc::new(RwLock::new(connection)));
+        }
+
+        // Legacy entries were migrated by get_secret; report what's left over
+        let known_ids = state_connections.keys().cloned().collect();
+        match CredentialService::list_orphaned_secrets(&known_ids) {
+            Ok(orphans) if !orphans.is_empty() => {
+                warn!("Found {} orphaned keychain entries", orphans.len());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to check for orphaned secrets: {}", e),
         }
+
+        let count = state_connections.len();
+        *self.connections.write().await = state_connections;
+        *self.credential_errors.lock().await = credential_errors;
+        *self.locked_connections.lock().await = locked_connections;
+
+        // Nothing cached for the previous set of connections applies any more
+        self.bucket_regions.lock().await.clear();
+        self.bucket_lists.lock().await.clear();
+        self.listing_cursors.lock().await.clear();
+        self.prefetched_pages.lock().await.clear();
+        self.capabilities.lock().await.clear();
+
+        Ok(count)
+    }
+
+    /// Read a connection's secret from the keychain again, e.g. after the
+    /// user denied the prompt at startup, and clear its error on success.
+    pub async fn unlock_connection(&self, connection_id: &str) -> AppResult<()> {
+        let entry = self.connection_entry(connection_id).await?;
+
+        let id = connection_id.to_string();
+        let secret = tokio::task::spawn_blocking(move || CredentialService::get_secret(&id))
+            .await
+            .map_err(|e| AppError::IoError(std::io::Error::other(e)))?;
+
+        match secret {
+            Ok(secret_key) => {
+                entry.write().await.secret_key = secret_key;
+                self.locked_connections.lock().await.remove(connection_id);
+                self.credential_errors.lock().await.remove(connection_id);
+                self.clear_connection_caches(connection_id).await;
+                Ok(())
+            }
+            Err(e) => {
+                let mut locked = self.locked_connections.lock().await;
+                if matches!(e, AppError::KeychainLocked(_)) {
+                    locked.insert(connection_id.to_string());
+                } else {
+                    locked.remove(connection_id);
+                }
+                self.credential_errors
+                    .lock()
+                    .await
+                    .insert(connection_id.to_string(), e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn connection_entry(&self, connection_id: &str) -> AppResult<ConnectionEntry> {
+        self.connections
+            .read()
+            .await
+            .get(connection_id)
+            .cloned()
+            .ok_or_else(|| AppError::ConnectionNotFound(connection_id.to_string()))
     }
-}
\ No newline at end of file
+
+    pub async fn connection(&self, connection_id: &str) -> AppResult<S3ConnectionWithSecret> {
+        if self.locked_connections.lock().await.contains(connection_id) {
+            return Err(AppError::KeychainLocked(format!(
+                "connection {} is locked until its secret is unlocked",
+                connection_id
+            )));
+        }
+        let entry = self.connection_entry(connection_id).await?;
+        let connection = entry.read().await.clone();
+        Ok(connection)
+    }
+
+    pub async fn all_connections(&self) -> Vec<S3ConnectionWithSecret> {
+        let entries: Vec<ConnectionEntry> =
+            self.connections.read().await.values().cloned().collect();
+
+        let mut connections = Vec::with_capacity(entries.len());
+        for entry in entries {
+            connections.push(entry.read().await.clone());
+        }
+        connections
+    }
+
+    pub async fn insert_connection(&self, connection: S3ConnectionWithSecret) {
+        self.connections
+            .write()
+            .await
+            .insert(connection.id.clone(), Arc::new(RwLock::new(connection)));
+    }
+
+    pub async fn remove_connection(&self, connection_id: &str) -> Option<ConnectionEntry> {
+        self.connections.write().await.remove(connection_id)
+    }
+
+    /// Clone a connection with its region pointed at the bucket's actual
+    /// region, resolving and caching it on first use.
+    pub async fn bucket_connection(
+        &self,
+        connection_id: &str,
+        bucket: &str,
+    ) -> AppResult<S3ConnectionWithSecret> {
+        let mut connection = self.connection(connection_id).await?;
+
+        let cache_key = (connection_id.to_string(), bucket.to_string());
+        let cached = self.bucket_regions.lock().await.get(&cache_key).cloned();
+
+        let region = match cached {
+            Some(region) => region,
+            None => {
+                let region = S3Service::resolve_bucket_region(&connection, bucket).await;
+                self.bucket_regions
+                    .lock()
+                    .await
+                    .insert(cache_key, region.clone());
+                region
+            }
+        };
+
+        connection.region = region;
+        Ok(connection)
+    }
+
+    pub async fn forget_bucket_region(&self, connection_id: &str, bucket: &str) {
+        self.bucket_regions
+            .lock()
+            .await
+            .remove(&(connection_id.to_string(), bucket.to_string()));
+    }
+
+    pub async fn forget_bucket_regions(&self, connection_id: &str) {
+        self.bucket_regions
+            .lock()
+            .await
+            .retain(|(id, _), _| id != connection_id);
+    }
+
+    pub async fn cached_buckets(&self, connection_id: &str) -> Option<Vec<BucketInfo>> {
+        self.bucket_lists
+            .lock()
+            .await
+            .get(connection_id)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < BUCKET_LIST_TTL)
+            .map(|(_, buckets)| buckets.clone())
+    }
+
+    pub async fn cache_buckets(&self, connection_id: &str, buckets: Vec<BucketInfo>) {
+        self.bucket_lists
+            .lock()
+            .await
+            .insert(connection_id.to_string(), (Instant::now(), buckets));
+    }
+
+    pub async fn invalidate_buckets(&self, connection_id: &str) {
+        self.bucket_lists.lock().await.remove(connection_id);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    /// Register a running job until the returned guard is dropped.
+    pub async fn start_job(&self, kind: &str, bucket: &str) -> JobGuard<'_> {
+        let webhooks = self.settings.lock().await.webhooks.clone();
+        let id = Uuid::new_v4().to_string();
+        let job = BackgroundJob {
+            id: id.clone(),
+            kind: kind.to_string(),
+            bucket: bucket.to_string(),
+            started_at: Utc::now().timestamp(),
+        };
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.clone(), (job.clone(), cancelled.clone()));
+        JobGuard {
+            state: self,
+            id,
+            job,
+            webhooks,
+            outcome: None,
+            cancelled,
+        }
+    }
+
+    /// Ask a running job to stop. Returns false when no such job is running.
+    pub fn cancel_job(&self, job_id: &str) -> bool {
+        match self
+            .jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(job_id)
+        {
+            Some((_, cancelled)) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn active_jobs(&self) -> Vec<BackgroundJob> {
+        let mut jobs: Vec<BackgroundJob> = self
+            .jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|(job, _)| job.clone())
+            .collect();
+        jobs.sort_by_key(|job| job.started_at);
+        jobs
+    }
+
+    /// Drop everything cached about a connection after it changes or goes away.
+    pub async fn clear_connection_caches(&self, connection_id: &str) {
+        self.forget_bucket_regions(connection_id).await;
+        self.invalidate_buckets(connection_id).await;
+        self.capabilities
+            .lock()
+            .await
+            .retain(|(id, _), _| id != connection_id);
+        self.listing_cursors
+            .lock()
+            .await
+            .retain(|(id, ..), _| id != connection_id);
+        self.prefetched_pages
+            .lock()
+            .await
+            .retain(|(id, ..), _| id != connection_id);
+    }
+}
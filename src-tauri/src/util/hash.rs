@@ -0,0 +1,74 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use md5::{Digest, Md5};
+use memmap2::Mmap;
+use sha2::Sha256;
+
+use crate::error::{AppError, AppResult};
+
+/// Files at least this big are memory-mapped rather than read in chunks.
+pub const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Buffer size for chunked hashing of smaller files.
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashAlgorithm {
+    /// Comparable with single-part ETags.
+    Md5,
+    Sha256,
+}
+
+/// Hex digest of everything `reader` yields, without holding more than one
+/// buffer of it in memory.
+pub fn hash_reader<D: Digest>(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = D::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Hex digest of a local file. Large files are memory-mapped so the OS pages
+/// them in and out instead of the whole file landing on the heap.
+pub fn hash_file<D: Digest>(path: &Path) -> io::Result<String> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    if size < MMAP_THRESHOLD {
+        return hash_reader::<D>(file);
+    }
+
+    // SAFETY: the map is read-only and dropped before returning. A file
+    // truncated underneath us can fault, the same risk any mmap reader
+    // takes; a concurrently modified one just hashes to a useless digest.
+    match unsafe { Mmap::map(&file) } {
+        Ok(map) => Ok(hex(&D::digest(&map[..]))),
+        // Some filesystems (FUSE, network shares) can't be mapped
+        Err(_) => hash_reader::<D>(file),
+    }
+}
+
+/// `hash_file` on the blocking pool, for async callers.
+pub async fn hash_file_async(path: PathBuf, algorithm: HashAlgorithm) -> AppResult<String> {
+    tokio::task::spawn_blocking(move || match algorithm {
+        HashAlgorithm::Md5 => hash_file::<Md5>(&path),
+        HashAlgorithm::Sha256 => hash_file::<Sha256>(&path),
+    })
+    .await
+    .map_err(|e| AppError::IoError(io::Error::other(e)))?
+    .map_err(AppError::from)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
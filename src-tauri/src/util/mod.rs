@@ -0,0 +1,3 @@
+pub mod hash;
+
+pub use hash::*;
@@ -0,0 +1,38 @@
+//! Runs Baul's compatibility checks against a live S3-compatible server:
+//!
+//! ```sh
+//! docker run -d -p 9000:9000 minio/minio server /data
+//! BAUL_COMPAT_ENDPOINT=http://localhost:9000 BAUL_COMPAT_BUCKET=baul \
+//!   BAUL_COMPAT_ACCESS_KEY=minioadmin BAUL_COMPAT_SECRET_KEY=minioadmin \
+//!   cargo test --features compat-tests --test compat
+//! ```
+//!
+//! See `compat_harness` for the variables it reads.
+#![cfg(feature = "compat-tests")]
+
+use baul_lib::compat_harness;
+
+#[tokio::test]
+async fn endpoint_supports_baul_features() {
+    let Some(report) = compat_harness::run_from_env().await else {
+        eprintln!("BAUL_COMPAT_ENDPOINT is not set, skipping");
+        return;
+    };
+    let report = report.expect("compatibility check could not run");
+
+    for check in report.checks.iter().filter(|c| !c.ok) {
+        eprintln!(
+            "{:?} failed: {}",
+            check.feature,
+            check.error.as_deref().unwrap_or_default()
+        );
+    }
+    assert_eq!(
+        report.failed,
+        0,
+        "{} of {} checks failed against {}",
+        report.failed,
+        report.checks.len(),
+        report.endpoint
+    );
+}
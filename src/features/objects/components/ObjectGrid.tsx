@@ -30,17 +30,19 @@ export function ObjectGrid({ objects, prefixes, hasMore, isFetchingMore, onLoadM
 
   const downloadMutation = useMutation({
     mutationFn: async ({ key, destination }: { key: string; destination: string }) => {
-      await commands.downloadFile(
+      return commands.downloadFile(
         activeConnectionId!,
         activeBucket!,
         key,
         destination
       );
     },
-    onSuccess: () => {
+    onSuccess: (outcome) => {
       toast({
         title: "Download complete",
-        description: "File downloaded successfully",
+        description: outcome.unchangedCopy
+          ? `An unchanged copy was already at ${outcome.unchangedCopy.path}`
+          : "File downloaded successfully",
       });
     },
     onError: (error) => {
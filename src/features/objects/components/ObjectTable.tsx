@@ -136,7 +136,7 @@ export function ObjectTable({ objects, prefixes, hasMore, isFetchingMore, onLoad
     });
 
     try {
-      await commands.downloadFile(
+      const outcome = await commands.downloadFile(
         activeConnectionId,
         activeBucket,
         key,
@@ -152,7 +152,9 @@ export function ObjectTable({ objects, prefixes, hasMore, isFetchingMore, onLoad
 
       toast({
         title: "Download complete",
-        description: `${fileName} downloaded successfully`,
+        description: outcome.unchangedCopy
+          ? `${fileName} downloaded; an unchanged copy was already at ${outcome.unchangedCopy.path}`
+          : `${fileName} downloaded successfully`,
       });
     } catch (error) {
       updateTransfer(transferId, {
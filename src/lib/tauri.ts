@@ -1,7 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { S3Connection, CreateConnectionInput } from "@/types/connection";
+import type { S3Connection, CreateConnectionInput, FeatureResult } from "@/types/connection";
 import type { BucketInfo, BucketStats } from "@/types/bucket";
-import type { S3Object, ListObjectsResult, ObjectMetadata } from "@/types/object";
+import type { S3Object, ListObjectsResult, ObjectMetadata, DownloadOutcome } from "@/types/object";
 
 async function invokeCommand<T>(
   command: string,
@@ -45,13 +45,13 @@ export const commands = {
     invokeCommand<void>("delete_bucket", { connectionId, bucketName }),
 
   getBucketLocation: (connectionId: string, bucketName: string) =>
-    invokeCommand<string | null>("get_bucket_location", { connectionId, bucketName }),
+    invokeCommand<FeatureResult<string | null>>("get_bucket_location", { connectionId, bucketName }),
 
   headBucket: (connectionId: string, bucketName: string) =>
     invokeCommand<boolean>("head_bucket", { connectionId, bucketName }),
 
   getBucketVersioning: (connectionId: string, bucketName: string) =>
-    invokeCommand<string | null>("get_bucket_versioning", { connectionId, bucketName }),
+    invokeCommand<FeatureResult<string | null>>("get_bucket_versioning", { connectionId, bucketName }),
 
   getBucketStats: (connectionId: string, bucketName: string) =>
     invokeCommand<BucketStats>("get_bucket_stats", { connectionId, bucketName }),
@@ -98,7 +98,7 @@ export const commands = {
     key: string,
     destination: string
   ) =>
-    invokeCommand<void>("download_file", {
+    invokeCommand<DownloadOutcome>("download_file", {
       connectionId,
       bucket,
       key,
@@ -144,7 +144,7 @@ export const commands = {
     destBucket: string,
     destKey: string
   ) =>
-    invokeCommand<void>("copy_object", {
+    invokeCommand<string | null>("copy_object", {
       connectionId,
       sourceBucket,
       sourceKey,
@@ -158,7 +158,7 @@ export const commands = {
     oldKey: string,
     newKey: string
   ) =>
-    invokeCommand<void>("rename_object", {
+    invokeCommand<string | null>("rename_object", {
       connectionId,
       bucket,
       oldKey,
@@ -31,6 +31,11 @@ export interface CreateConnectionInput {
   usePathStyle: boolean;
 }
 
+/** Result of a call the provider may not implement. */
+export type FeatureResult<T> =
+  | { status: "supported"; value: T }
+  | { status: "not_supported" };
+
 export const PROVIDER_PRESETS: Record<
   S3Provider,
   { name: string; endpoint: string; region: string; useSsl: boolean; usePathStyle: boolean }
@@ -5,15 +5,34 @@ export interface S3Object {
   etag: string | null;
   contentType: string | null;
   isDirectory: boolean;
+  childCount?: number;
 }
 
 export interface ListObjectsResult {
   objects: S3Object[];
+  folders: S3Object[];
   prefixes: string[];
   continuationToken: string | null;
   isTruncated: boolean;
 }
 
+export interface LocalCopy {
+  connectionId: string;
+  bucket: string;
+  key: string;
+  path: string;
+  downloadedAt: number;
+  etag: string | null;
+  size: number;
+  exists: boolean;
+  upToDate: boolean;
+}
+
+export interface DownloadOutcome {
+  size: number | null;
+  unchangedCopy: LocalCopy | null;
+}
+
 export interface UploadProgress {
   fileName: string;
   bytesUploaded: number;